@@ -0,0 +1,89 @@
+//! Micro-benchmarks for variable access patterns, run with `cargo bench`.
+//!
+//! Each case is a tight numeric loop dominated by one kind of variable lookup so that
+//! changes to the environment chain walk show up directly in the timings.
+
+use std::{
+    env,
+    fmt::Arguments,
+    fs,
+    time::{Duration, Instant},
+};
+
+use rlox::{run_file, Logger, Outcome};
+
+const ITERATIONS: usize = 200_000;
+const SAMPLES: usize = 5;
+
+struct NullLogger;
+impl Logger for NullLogger {
+    fn print(&mut self, _value: Arguments) {}
+}
+
+fn local_access() -> String {
+    format!(
+        "{{
+  var sum = 0;
+  for (var i = 0; i < {ITERATIONS}; i = i + 1) {{
+    sum = sum + i;
+  }}
+  print sum;
+}}"
+    )
+}
+
+fn global_access() -> String {
+    format!(
+        "var sum = 0;
+for (var i = 0; i < {ITERATIONS}; i = i + 1) {{
+  sum = sum + i;
+}}
+print sum;"
+    )
+}
+
+/// Reads and writes a variable captured `depth` function scopes away.
+fn upvalue_access(depth: usize) -> String {
+    let mut source = String::from("fun outer() {\n  var sum = 0;\n");
+    for level in 1..depth {
+        source.push_str(&format!("  fun level{level}() {{\n"));
+    }
+    source.push_str(&format!(
+        "  for (var i = 0; i < {ITERATIONS}; i = i + 1) {{\n    sum = sum + 1;\n  }}\n"
+    ));
+    for level in (1..depth).rev() {
+        source.push_str(&format!("  }}\n  level{level}();\n"));
+    }
+    source.push_str("  print sum;\n}\nouter();\n");
+    source
+}
+
+fn bench(name: &str, source: String) {
+    let path = env::temp_dir().join(format!("rlox_bench_{name}.lox"));
+    fs::write(&path, source).expect("bench file to be writable");
+    let path = path.to_str().expect("temp path to be valid UTF-8");
+
+    let mut samples: Vec<Duration> = (0..SAMPLES)
+        .map(|_| {
+            let start = Instant::now();
+            let outcome = run_file(path, Some(Box::new(NullLogger)));
+            assert_eq!(outcome, Outcome::Success);
+            start.elapsed()
+        })
+        .collect();
+    samples.sort();
+
+    println!(
+        "{name:<12} median {:>10.2?}  min {:>10.2?}",
+        samples[SAMPLES / 2],
+        samples[0]
+    );
+}
+
+fn main() {
+    bench("locals", local_access());
+    bench("globals", global_access());
+    for depth in 1..=5 {
+        bench(&format!("upvalue_{depth}"), upvalue_access(depth));
+    }
+}
@@ -0,0 +1,138 @@
+use std::{collections::HashMap, fmt::Display};
+
+/// A group of built-in natives that can be granted to, or withheld from, a module. Natives
+/// outside every group, such as `help`, are always available, and `net` is only available
+/// where it has been granted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NativeGroup {
+    Strings,
+    Time,
+    Io,
+    Env,
+    Net,
+}
+
+impl NativeGroup {
+    pub const ALL: [NativeGroup; 5] = [
+        NativeGroup::Strings,
+        NativeGroup::Time,
+        NativeGroup::Io,
+        NativeGroup::Env,
+        NativeGroup::Net,
+    ];
+
+    /// The groups granted unless the embedder says otherwise: all but `net`.
+    pub const DEFAULT: [NativeGroup; 4] = [
+        NativeGroup::Strings,
+        NativeGroup::Time,
+        NativeGroup::Io,
+        NativeGroup::Env,
+    ];
+
+    /// The group the built-in native `name` belongs to, if any.
+    pub fn of(name: &str) -> Option<NativeGroup> {
+        match name {
+            "split" | "join" | "substring" | "compareStrings" | "sortStrings" | "normalize"
+            | "caseFold" | "graphemeLength" | "graphemeReverse" => Some(NativeGroup::Strings),
+            "clock" | "clockMonotonic" | "dateParts" | "formatTime" | "parseTime"
+            | "setTimeout" | "setInterval" | "clearTimeout" | "clearInterval" => {
+                Some(NativeGroup::Time)
+            }
+            "readLine" | "readFile" | "writeFile" | "appendFile" | "fileExists" => {
+                Some(NativeGroup::Io)
+            }
+            "getenv" | "setenv" => Some(NativeGroup::Env),
+            "httpGet" | "httpPost" => Some(NativeGroup::Net),
+            _ => None,
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<NativeGroup> {
+        NativeGroup::ALL
+            .into_iter()
+            .find(|group| group.code() == code)
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            NativeGroup::Strings => "strings",
+            NativeGroup::Time => "time",
+            NativeGroup::Io => "io",
+            NativeGroup::Env => "env",
+            NativeGroup::Net => "net",
+        }
+    }
+}
+
+impl Display for NativeGroup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+/// Which groups of natives each module may use, checked when a module is resolved so a plugin
+/// that was never granted `io` can't even mention a file native. Modules are named as the
+/// import resolver names them, and the file given on the command line by its path. Modules
+/// without a grant of their own get the default grant, which is every group but `net`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Capabilities {
+    default: Vec<NativeGroup>,
+    grants: HashMap<String, Vec<NativeGroup>>,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Capabilities {
+            default: NativeGroup::DEFAULT.to_vec(),
+            grants: HashMap::new(),
+        }
+    }
+}
+
+impl Capabilities {
+    /// Limits the module named `module` to `groups`.
+    pub fn grant(mut self, module: &str, groups: &[NativeGroup]) -> Capabilities {
+        self.grants.insert(module.to_string(), groups.to_vec());
+        self
+    }
+
+    /// Limits modules without a grant of their own, and code outside any module, to `groups`.
+    pub fn grant_by_default(mut self, groups: &[NativeGroup]) -> Capabilities {
+        self.default = groups.to_vec();
+        self
+    }
+
+    pub fn allows(&self, module: Option<&str>, group: NativeGroup) -> bool {
+        module
+            .and_then(|module| self.grants.get(module))
+            .unwrap_or(&self.default)
+            .contains(&group)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn everything_but_the_network_is_granted_by_default() {
+        let capabilities = Capabilities::default();
+        for group in NativeGroup::DEFAULT {
+            assert!(capabilities.allows(None, group));
+            assert!(capabilities.allows(Some("plugin.lox"), group));
+        }
+        assert!(!capabilities.allows(None, NativeGroup::Net));
+    }
+
+    #[test]
+    fn grants_replace_the_default() {
+        let capabilities = Capabilities::default()
+            .grant_by_default(&[NativeGroup::Strings])
+            .grant("trusted.lox", &NativeGroup::ALL);
+
+        assert!(capabilities.allows(Some("plugin.lox"), NativeGroup::Strings));
+        assert!(!capabilities.allows(Some("plugin.lox"), NativeGroup::Io));
+        assert!(!capabilities.allows(None, NativeGroup::Time));
+        assert!(capabilities.allows(Some("trusted.lox"), NativeGroup::Io));
+    }
+}
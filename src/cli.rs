@@ -0,0 +1,566 @@
+use rlox::{DiagnosticOptions, ErrorFormat, Level};
+
+use crate::gen_tests;
+
+pub const USAGE: &str =
+    "Usage: rlox [options] [script [args...]]\n       rlox gen-tests --feature <name> [--dir <dir>]";
+
+const HELP: &str = "\
+Runs a Lox script, or starts a REPL when no script is given. Arguments after the script are
+passed to it, for it to read from ARGV.
+
+Options:
+  --ast <script>                  Print the syntax tree of a script instead of running it
+  --explain-resolution <script>   Print where each variable in a script resolves
+  --html <script>                 Print a script as HTML with its errors and warnings marked
+  -A <warning>                    Allow a warning, or every warning with `warnings`
+  -W <warning>                    Warn about a warning
+  -D <warning>                    Deny a warning, making it an error
+  --strict                        Deny every warning, like `-D warnings`
+  --optional-semicolons           Let a line break end a statement in place of `;`
+  --deterministic                 Run on a fake clock, so every run prints the same
+  --env-stats                     Print counts of environment allocations and lookups after running
+  --perf-hints                    Print property reads that saw many classes after running
+  --allow-net                     Let the script use the network natives, httpGet and httpPost
+  --time                          Print how long scanning, parsing, resolving and running took
+  --error-format=<format>         Print errors and warnings as `human` text, or as `json`,
+                                  one object per line, once the script has finished
+  -h, --help                      Print this help
+  -V, --version                   Print the version
+
+Commands:
+  gen-tests --feature <name>      Write a skeleton golden test for a feature area, such as
+                                  classes, for the integration tests to check
+    --dir <dir>                   Write it here rather than to test_files/golden";
+
+/// What the command line asked for.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// Run a script, or the REPL if there isn't one.
+    Run {
+        script: Option<String>,
+        /// The arguments after the script, for it to read from `ARGV`.
+        args: Vec<String>,
+        diagnostics: DiagnosticOptions,
+        optional_semicolons: bool,
+        deterministic: bool,
+        env_stats: bool,
+        perf_hints: bool,
+        allow_net: bool,
+        time: bool,
+        error_format: ErrorFormat,
+    },
+    Ast {
+        script: String,
+        optional_semicolons: bool,
+    },
+    ExplainResolution {
+        script: String,
+        optional_semicolons: bool,
+    },
+    /// Render a script and its diagnostics as HTML, with the warnings `diagnostics` reports.
+    Html {
+        script: String,
+        diagnostics: DiagnosticOptions,
+        optional_semicolons: bool,
+    },
+    /// Write the skeleton golden test for `feature` into `dir`.
+    GenTests {
+        feature: String,
+        dir: String,
+    },
+    Help,
+    Version,
+}
+
+pub fn help() -> String {
+    format!("{USAGE}\n\n{HELP}")
+}
+
+pub fn version() -> String {
+    format!("rlox {}", env!("CARGO_PKG_VERSION"))
+}
+
+/// What `--version` prints: the version, and the Cargo features this build was made with.
+pub fn version_and_features() -> String {
+    let features = [
+        ("bigint", cfg!(feature = "bigint")),
+        ("icu", cfg!(feature = "icu")),
+    ]
+    .into_iter()
+    .filter(|(_, enabled)| *enabled)
+    .map(|(feature, _)| feature)
+    .collect::<Vec<&str>>();
+    match features.is_empty() {
+        true => format!("{}\nfeatures: none", version()),
+        false => format!("{}\nfeatures: {}", version(), features.join(", ")),
+    }
+}
+
+/// Parses the arguments after the program name. Warning flags may be repeated, with later ones
+/// taking precedence, and `--help` or `--version` anywhere before the script wins over
+/// everything else. Everything after the script, flags included, is passed to it as its
+/// arguments.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let args = args.into_iter().collect::<Vec<String>>();
+    let options = match args.first() {
+        Some(arg) if arg == "gen-tests" => &args[..],
+        _ => before_script(&args),
+    };
+    if options.iter().any(|arg| arg == "-h" || arg == "--help") {
+        return Ok(Command::Help);
+    }
+    if options.iter().any(|arg| arg == "-V" || arg == "--version") {
+        return Ok(Command::Version);
+    }
+
+    if args.first().is_some_and(|arg| arg == "gen-tests") {
+        return parse_gen_tests(args.into_iter().skip(1));
+    }
+
+    let mut diagnostics = DiagnosticOptions::default();
+    let mut optional_semicolons = false;
+    let mut deterministic = false;
+    let mut env_stats = false;
+    let mut perf_hints = false;
+    let mut allow_net = false;
+    let mut time = false;
+    let mut error_format = ErrorFormat::Human;
+    let mut script = None;
+    let mut script_args = vec![];
+    let mut inspection = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let level = match arg.as_str() {
+            "--ast" | "--explain-resolution" | "--html" => {
+                let script = args
+                    .next()
+                    .ok_or_else(|| format!("{arg} expects a script."))?;
+                inspection = Some((arg, script));
+                continue;
+            }
+            "-A" => Level::Allow,
+            "-W" => Level::Warn,
+            "-D" => Level::Deny,
+            "--strict" => {
+                diagnostics.set("warnings", Level::Deny)?;
+                continue;
+            }
+            "--optional-semicolons" => {
+                optional_semicolons = true;
+                continue;
+            }
+            "--deterministic" => {
+                deterministic = true;
+                continue;
+            }
+            "--env-stats" => {
+                env_stats = true;
+                continue;
+            }
+            "--perf-hints" => {
+                perf_hints = true;
+                continue;
+            }
+            "--allow-net" => {
+                allow_net = true;
+                continue;
+            }
+            "--time" => {
+                time = true;
+                continue;
+            }
+            flag if flag.starts_with("--error-format=") => {
+                error_format = match &flag["--error-format=".len()..] {
+                    "human" => ErrorFormat::Human,
+                    "json" => ErrorFormat::Json,
+                    format => {
+                        return Err(format!(
+                            "Unknown error format '{format}'; expected human or json."
+                        ))
+                    }
+                };
+                continue;
+            }
+            flag if flag.starts_with('-') => return Err(format!("Unknown option '{flag}'.")),
+            _ if inspection.is_some() => {
+                return Err(String::from("Expected at most one script."));
+            }
+            _ => {
+                script = Some(arg);
+                script_args = args.by_ref().collect();
+                continue;
+            }
+        };
+
+        let code = args
+            .next()
+            .ok_or_else(|| format!("{arg} expects a warning."))?;
+        diagnostics.set(&code, level)?;
+    }
+
+    Ok(match inspection {
+        Some((flag, script)) if flag == "--ast" => Command::Ast {
+            script,
+            optional_semicolons,
+        },
+        Some((flag, script)) if flag == "--html" => Command::Html {
+            script,
+            diagnostics,
+            optional_semicolons,
+        },
+        Some((_, script)) => Command::ExplainResolution {
+            script,
+            optional_semicolons,
+        },
+        None => Command::Run {
+            script,
+            args: script_args,
+            diagnostics,
+            optional_semicolons,
+            deterministic,
+            env_stats,
+            perf_hints,
+            allow_net,
+            time,
+            error_format,
+        },
+    })
+}
+
+// the arguments up to the script to run, which are the interpreter's own
+fn before_script(args: &[String]) -> &[String] {
+    let mut index = 0;
+    while let Some(arg) = args.get(index) {
+        match arg.as_str() {
+            // these take a value, which isn't the script even if it looks like one
+            "--ast" | "--explain-resolution" | "--html" | "-A" | "-W" | "-D" => index += 2,
+            flag if flag.starts_with('-') => index += 1,
+            _ => return &args[..index],
+        }
+    }
+    args
+}
+
+fn parse_gen_tests(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut feature = None;
+    let mut dir = String::from(gen_tests::GOLDEN_DIR);
+    while let Some(arg) = args.next() {
+        let value = match arg.as_str() {
+            "--feature" | "--dir" => args
+                .next()
+                .ok_or_else(|| format!("{arg} expects a value."))?,
+            _ => return Err(format!("Unknown gen-tests option '{arg}'.")),
+        };
+        match arg.as_str() {
+            "--feature" => feature = Some(value),
+            _ => dir = value,
+        }
+    }
+
+    let feature = feature.ok_or_else(|| String::from("gen-tests expects --feature <name>."))?;
+    if gen_tests::skeleton(&feature).is_none() {
+        return Err(gen_tests::unknown_feature(&feature));
+    }
+    Ok(Command::GenTests { feature, dir })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &str) -> Result<Command, String> {
+        parse(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn scripts_and_warning_flags() {
+        let mut diagnostics = DiagnosticOptions::default();
+        diagnostics.set("warnings", Level::Deny).unwrap();
+        diagnostics.set("shadowing", Level::Allow).unwrap();
+
+        assert_eq!(
+            parse_args("-D warnings -A shadowing main.lox"),
+            Ok(Command::Run {
+                script: Some(String::from("main.lox")),
+                args: vec![],
+                diagnostics,
+                optional_semicolons: false,
+                deterministic: false,
+                env_stats: false,
+                perf_hints: false,
+                allow_net: false,
+                time: false,
+                error_format: ErrorFormat::Human,
+            })
+        );
+        assert_eq!(
+            parse_args("--strict"),
+            Ok(Command::Run {
+                script: None,
+                args: vec![],
+                diagnostics: DiagnosticOptions::default().deny_all(),
+                optional_semicolons: false,
+                deterministic: false,
+                env_stats: false,
+                perf_hints: false,
+                allow_net: false,
+                time: false,
+                error_format: ErrorFormat::Human,
+            })
+        );
+        assert_eq!(
+            parse_args("--optional-semicolons"),
+            Ok(Command::Run {
+                script: None,
+                args: vec![],
+                diagnostics: DiagnosticOptions::default(),
+                optional_semicolons: true,
+                deterministic: false,
+                env_stats: false,
+                perf_hints: false,
+                allow_net: false,
+                time: false,
+                error_format: ErrorFormat::Human,
+            })
+        );
+        assert_eq!(
+            parse_args("--deterministic main.lox"),
+            Ok(Command::Run {
+                script: Some(String::from("main.lox")),
+                args: vec![],
+                diagnostics: DiagnosticOptions::default(),
+                optional_semicolons: false,
+                deterministic: true,
+                env_stats: false,
+                perf_hints: false,
+                allow_net: false,
+                time: false,
+                error_format: ErrorFormat::Human,
+            })
+        );
+        assert_eq!(
+            parse_args("--env-stats main.lox"),
+            Ok(Command::Run {
+                script: Some(String::from("main.lox")),
+                args: vec![],
+                diagnostics: DiagnosticOptions::default(),
+                optional_semicolons: false,
+                deterministic: false,
+                env_stats: true,
+                perf_hints: false,
+                allow_net: false,
+                time: false,
+                error_format: ErrorFormat::Human,
+            })
+        );
+        assert_eq!(
+            parse_args("--perf-hints main.lox"),
+            Ok(Command::Run {
+                script: Some(String::from("main.lox")),
+                args: vec![],
+                diagnostics: DiagnosticOptions::default(),
+                optional_semicolons: false,
+                deterministic: false,
+                env_stats: false,
+                perf_hints: true,
+                allow_net: false,
+                time: false,
+                error_format: ErrorFormat::Human,
+            })
+        );
+        assert_eq!(
+            parse_args("--allow-net main.lox"),
+            Ok(Command::Run {
+                script: Some(String::from("main.lox")),
+                args: vec![],
+                diagnostics: DiagnosticOptions::default(),
+                optional_semicolons: false,
+                deterministic: false,
+                env_stats: false,
+                perf_hints: false,
+                allow_net: true,
+                time: false,
+                error_format: ErrorFormat::Human,
+            })
+        );
+        assert_eq!(
+            parse_args("--time main.lox"),
+            Ok(Command::Run {
+                script: Some(String::from("main.lox")),
+                args: vec![],
+                diagnostics: DiagnosticOptions::default(),
+                optional_semicolons: false,
+                deterministic: false,
+                env_stats: false,
+                perf_hints: false,
+                allow_net: false,
+                time: true,
+                error_format: ErrorFormat::Human,
+            })
+        );
+        assert_eq!(
+            parse_args("--error-format=json main.lox"),
+            Ok(Command::Run {
+                script: Some(String::from("main.lox")),
+                args: vec![],
+                diagnostics: DiagnosticOptions::default(),
+                optional_semicolons: false,
+                deterministic: false,
+                env_stats: false,
+                perf_hints: false,
+                allow_net: false,
+                time: false,
+                error_format: ErrorFormat::Json,
+            })
+        );
+        assert_eq!(
+            parse_args("--error-format=xml main.lox"),
+            Err(String::from(
+                "Unknown error format 'xml'; expected human or json."
+            ))
+        );
+        assert_eq!(
+            parse_args(""),
+            Ok(Command::Run {
+                script: None,
+                args: vec![],
+                diagnostics: DiagnosticOptions::default(),
+                optional_semicolons: false,
+                deterministic: false,
+                env_stats: false,
+                perf_hints: false,
+                allow_net: false,
+                time: false,
+                error_format: ErrorFormat::Human,
+            })
+        );
+    }
+
+    #[test]
+    fn arguments_after_the_script_are_passed_to_it() {
+        assert_eq!(
+            parse_args("--time main.lox first -D --time"),
+            Ok(Command::Run {
+                script: Some(String::from("main.lox")),
+                args: ["first", "-D", "--time"].map(String::from).to_vec(),
+                diagnostics: DiagnosticOptions::default(),
+                optional_semicolons: false,
+                deterministic: false,
+                env_stats: false,
+                perf_hints: false,
+                allow_net: false,
+                time: true,
+                error_format: ErrorFormat::Human,
+            })
+        );
+    }
+
+    #[test]
+    fn inspections_take_a_script() {
+        assert_eq!(
+            parse_args("--ast main.lox"),
+            Ok(Command::Ast {
+                script: String::from("main.lox"),
+                optional_semicolons: false,
+            })
+        );
+        assert_eq!(
+            parse_args("--optional-semicolons --ast main.lox"),
+            Ok(Command::Ast {
+                script: String::from("main.lox"),
+                optional_semicolons: true,
+            })
+        );
+        assert_eq!(
+            parse_args("--explain-resolution main.lox --optional-semicolons"),
+            Ok(Command::ExplainResolution {
+                script: String::from("main.lox"),
+                optional_semicolons: true,
+            })
+        );
+        assert_eq!(
+            parse_args("--html main.lox -D warnings"),
+            Ok(Command::Html {
+                script: String::from("main.lox"),
+                diagnostics: DiagnosticOptions::default().deny_all(),
+                optional_semicolons: false,
+            })
+        );
+        assert_eq!(
+            parse_args("--ast"),
+            Err(String::from("--ast expects a script."))
+        );
+        assert_eq!(
+            parse_args("--ast main.lox other.lox"),
+            Err(String::from("Expected at most one script."))
+        );
+    }
+
+    #[test]
+    fn gen_tests_takes_a_feature() {
+        assert_eq!(
+            parse_args("gen-tests --feature classes"),
+            Ok(Command::GenTests {
+                feature: String::from("classes"),
+                dir: String::from("test_files/golden"),
+            })
+        );
+        assert_eq!(
+            parse_args("gen-tests --dir tests/lox --feature control-flow"),
+            Ok(Command::GenTests {
+                feature: String::from("control-flow"),
+                dir: String::from("tests/lox"),
+            })
+        );
+        assert_eq!(
+            parse_args("gen-tests"),
+            Err(String::from("gen-tests expects --feature <name>."))
+        );
+        assert!(parse_args("gen-tests --feature macros")
+            .unwrap_err()
+            .starts_with("Unknown feature 'macros'"));
+        assert_eq!(
+            parse_args("gen-tests --feature"),
+            Err(String::from("--feature expects a value."))
+        );
+    }
+
+    #[test]
+    fn help_and_version_win() {
+        assert_eq!(parse_args("--bogus -h main.lox"), Ok(Command::Help));
+        assert_eq!(
+            parse_args("-D shadowing --help main.lox"),
+            Ok(Command::Help)
+        );
+        assert_eq!(
+            parse_args("gen-tests --feature x --help"),
+            Ok(Command::Help)
+        );
+        assert_eq!(
+            parse_args("--bogus"),
+            Err(String::from("Unknown option '--bogus'."))
+        );
+        assert_eq!(parse_args("-V"), Ok(Command::Version));
+    }
+
+    #[test]
+    fn version_lists_enabled_features() {
+        let printed = version_and_features();
+        assert!(printed.starts_with(&format!("{}\nfeatures: ", version())));
+        assert_eq!(printed.contains("bigint"), cfg!(feature = "bigint"));
+        assert_eq!(printed.contains("icu"), cfg!(feature = "icu"));
+    }
+
+    #[test]
+    fn help_and_version_after_the_script_are_its_arguments() {
+        for flag in ["--help", "-V"] {
+            let Ok(Command::Run { script, args, .. }) = parse_args(&format!("a.lox {flag}")) else {
+                panic!("Expected to run a.lox");
+            };
+            assert_eq!(script.as_deref(), Some("a.lox"));
+            assert_eq!(args, [flag]);
+        }
+    }
+}
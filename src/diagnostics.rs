@@ -0,0 +1,173 @@
+use std::fmt::Display;
+
+/// A warning the resolver can report about code that runs but is probably a mistake.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Warning {
+    UnusedVariable,
+    Shadowing,
+    UnreachableCode,
+    // a global function or class declared again, replacing the earlier one
+    Redefinition,
+    // an identifier mixing scripts, or that looks like another one but isn't, off by default
+    ConfusableIdentifier,
+}
+
+impl Warning {
+    pub const ALL: [Warning; 5] = [
+        Warning::UnusedVariable,
+        Warning::Shadowing,
+        Warning::UnreachableCode,
+        Warning::Redefinition,
+        Warning::ConfusableIdentifier,
+    ];
+
+    /// The name used to refer to this warning on the command line and in diagnostics.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Warning::UnusedVariable => "unused_variable",
+            Warning::Shadowing => "shadowing",
+            Warning::UnreachableCode => "unreachable_code",
+            Warning::Redefinition => "redefinition",
+            Warning::ConfusableIdentifier => "confusable_identifier",
+        }
+    }
+
+    /// The level of a warning nothing has been set for. Lints that are only worth it in some
+    /// codebases are allowed until they're asked for.
+    pub fn default_level(&self) -> Level {
+        match self {
+            Warning::ConfusableIdentifier => Level::Allow,
+            _ => Level::Warn,
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Warning> {
+        Warning::ALL
+            .into_iter()
+            .find(|warning| warning.code() == code)
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+/// What happens when a warning is found: nothing, a printed warning, or an error.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Target {
+    // every warning, written as `warnings` on the command line
+    All,
+    Only(Warning),
+}
+
+/// Decides the level of each warning, mirroring rustc's `-A`/`-W`/`-D` flags: settings apply in
+/// the order they were given, so `-D warnings -A shadowing` denies everything except shadowing.
+/// As in rustc, `warnings` only changes a warning that's allowed by default once it has been
+/// turned on by its own code.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DiagnosticOptions {
+    levels: Vec<(Target, Level)>,
+}
+
+impl DiagnosticOptions {
+    /// Sets the level of the warning named `code`, or of every warning for `warnings`.
+    pub fn set(&mut self, code: &str, level: Level) -> Result<(), String> {
+        let target = match code {
+            "warnings" => Target::All,
+            _ => match Warning::from_code(code) {
+                Some(warning) => Target::Only(warning),
+                None => return Err(format!("Unknown warning '{code}'.")),
+            },
+        };
+
+        self.levels.push((target, level));
+        Ok(())
+    }
+
+    pub fn allow(mut self, warning: Warning) -> DiagnosticOptions {
+        self.levels.push((Target::Only(warning), Level::Allow));
+        self
+    }
+
+    pub fn deny(mut self, warning: Warning) -> DiagnosticOptions {
+        self.levels.push((Target::Only(warning), Level::Deny));
+        self
+    }
+
+    /// Treats every warning as an error, like `-D warnings`.
+    pub fn deny_all(mut self) -> DiagnosticOptions {
+        self.levels.push((Target::All, Level::Deny));
+        self
+    }
+
+    pub fn level(&self, warning: Warning) -> Level {
+        let opt_in = warning.default_level() == Level::Allow;
+        self.levels.iter().fold(
+            warning.default_level(),
+            |current, (target, level)| match target {
+                Target::Only(only) if *only == warning => *level,
+                Target::All if !opt_in || current != Level::Allow => *level,
+                _ => current,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warnings_default_to_warn() {
+        let options = DiagnosticOptions::default();
+        for warning in Warning::ALL {
+            assert_eq!(options.level(warning), warning.default_level());
+        }
+        assert_eq!(options.level(Warning::Shadowing), Level::Warn);
+        assert_eq!(options.level(Warning::ConfusableIdentifier), Level::Allow);
+    }
+
+    #[test]
+    fn opt_in_warnings_need_their_own_code() {
+        let mut options = DiagnosticOptions::default().deny_all();
+        assert_eq!(options.level(Warning::ConfusableIdentifier), Level::Allow);
+
+        options.set("confusable_identifier", Level::Warn).unwrap();
+        assert_eq!(options.level(Warning::ConfusableIdentifier), Level::Warn);
+
+        options.set("warnings", Level::Deny).unwrap();
+        assert_eq!(options.level(Warning::ConfusableIdentifier), Level::Deny);
+    }
+
+    #[test]
+    fn later_settings_win() {
+        let mut options = DiagnosticOptions::default();
+        options.set("warnings", Level::Deny).unwrap();
+        options.set("shadowing", Level::Allow).unwrap();
+
+        assert_eq!(options.level(Warning::UnusedVariable), Level::Deny);
+        assert_eq!(options.level(Warning::Shadowing), Level::Allow);
+
+        options.set("warnings", Level::Warn).unwrap();
+        assert_eq!(options.level(Warning::Shadowing), Level::Warn);
+    }
+
+    #[test]
+    fn unknown_codes_are_rejected() {
+        let mut options = DiagnosticOptions::default();
+        assert_eq!(
+            options.set("unused", Level::Deny),
+            Err(String::from("Unknown warning 'unused'."))
+        );
+        assert_eq!(options, DiagnosticOptions::default());
+    }
+}
@@ -1,88 +1,361 @@
 use crate::{
+    pool,
     syntax::{token::Token, value::Value},
     Exception,
 };
-use std::{
-    cell::RefCell,
-    collections::{hash_map::Entry, HashMap},
-    rc::Rc,
-};
+use std::{cell::RefCell, collections::HashMap, fmt::Display, rc::Rc};
 
 pub type EnvRef = Rc<RefCell<Environment>>;
 
+/// A variable's value. Variables live in their own shared cell so closures can share them with
+/// the scope that declared them, except in the frames of calls the resolver found can't create
+/// a closure, which keep them inline and save an allocation per variable. An inline variable
+/// is moved into a cell if something captures it after all.
+#[derive(Debug)]
+enum Slot {
+    Inline(Value),
+    Shared(Rc<RefCell<Value>>),
+}
+
+impl Slot {
+    fn new(value: Value, shared: bool) -> Slot {
+        match shared {
+            true => Slot::Shared(Rc::new(RefCell::new(value))),
+            false => Slot::Inline(value),
+        }
+    }
+
+    fn get(&self) -> Value {
+        match self {
+            Slot::Inline(value) => value.clone(),
+            Slot::Shared(cell) => cell.borrow().clone(),
+        }
+    }
+
+    fn set(&mut self, value: &Value) {
+        match self {
+            Slot::Inline(inline) => *inline = value.clone(),
+            Slot::Shared(cell) => *cell.borrow_mut() = value.clone(),
+        }
+    }
+
+    // the cell a closure shares, moving an inline value into one first
+    fn share(&mut self) -> Rc<RefCell<Value>> {
+        if let Slot::Inline(value) = self {
+            *self = Slot::Shared(Rc::new(RefCell::new(value.clone())));
+        }
+
+        match self {
+            Slot::Shared(cell) => cell.clone(),
+            Slot::Inline(_) => unreachable!(),
+        }
+    }
+}
+
+/// A variable a closure captures from the environment it is created in, found `distance` hops up.
+#[derive(Clone, Debug)]
+pub struct Upvalue {
+    pub name: String,
+    pub distance: usize,
+}
+
+/// Counts of how environments were used while collection was on, to measure what a redesign
+/// of them would save on real programs. Collection is per thread, counting every interpreter
+/// running on it, and costs nothing but a check while it's off.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EnvStats {
+    /// Environments created, for blocks, calls and closures alike.
+    pub allocations: u64,
+    /// Variable map lookups and insertions.
+    pub probes: u64,
+    /// How many accesses of resolved local variables went each number of environments up the
+    /// chain, so `depths[0]` is the accesses of the innermost environment.
+    pub depths: Vec<u64>,
+    /// Accesses of variables by name, which are globals, searched for up the chain.
+    pub name_lookups: u64,
+}
+
+thread_local! {
+    static STATS: RefCell<Option<EnvStats>> = const { RefCell::new(None) };
+}
+
+impl EnvStats {
+    /// Starts counting on this thread, from zero.
+    pub fn start() {
+        STATS.with(|stats| *stats.borrow_mut() = Some(EnvStats::default()));
+    }
+
+    /// Stops counting on this thread, returning the counts if counting was on.
+    pub fn stop() -> Option<EnvStats> {
+        STATS.with(|stats| stats.borrow_mut().take())
+    }
+
+    /// Accesses of resolved local variables, at any depth.
+    pub fn local_accesses(&self) -> u64 {
+        self.depths.iter().sum()
+    }
+
+    fn record(update: impl FnOnce(&mut EnvStats)) {
+        STATS.with(|stats| {
+            if let Some(stats) = stats.borrow_mut().as_mut() {
+                update(stats);
+            }
+        });
+    }
+
+    fn allocation() {
+        EnvStats::record(|stats| stats.allocations += 1);
+    }
+
+    fn probes(count: u64) {
+        EnvStats::record(|stats| stats.probes += count);
+    }
+
+    fn name_lookup() {
+        EnvStats::record(|stats| stats.name_lookups += 1);
+    }
+
+    // an access of a resolved local found `depth` environments up
+    fn access(depth: usize) {
+        EnvStats::record(|stats| {
+            if stats.depths.len() <= depth {
+                stats.depths.resize(depth + 1, 0);
+            }
+            stats.depths[depth] += 1;
+        });
+    }
+}
+
+impl Display for EnvStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let accesses = self.local_accesses();
+        let hops = self
+            .depths
+            .iter()
+            .enumerate()
+            .map(|(depth, count)| depth as u64 * count)
+            .sum::<u64>();
+        let mean = match accesses {
+            0 => 0.0,
+            _ => hops as f64 / accesses as f64,
+        };
+
+        writeln!(f, "Environment stats:")?;
+        writeln!(f, "  environments allocated: {}", self.allocations)?;
+        writeln!(f, "  map probes: {}", self.probes)?;
+        writeln!(
+            f,
+            "  local accesses: {accesses}, walking {mean:.2} environments up on average"
+        )?;
+        for (depth, count) in self.depths.iter().enumerate() {
+            writeln!(f, "    {depth} up: {count}")?;
+        }
+        writeln!(f, "  lookups by name: {}", self.name_lookups)
+    }
+}
+
 #[derive(Debug)]
 pub struct Environment {
     pub enclosing: Option<EnvRef>,
-    values: HashMap<String, Value>,
+    values: HashMap<String, Slot>,
+    // whether new variables get shared cells, which blocks inherit from their enclosing scope
+    shares_slots: bool,
 }
 
 impl Environment {
     pub fn new_global() -> EnvRef {
+        EnvStats::allocation();
         Rc::new(RefCell::new(Environment {
             enclosing: None,
             values: HashMap::new(),
+            shares_slots: true,
         }))
     }
 
     pub fn new_local(enclosing: &EnvRef) -> EnvRef {
+        let shares_slots = enclosing.borrow().shares_slots;
+        Environment::new_frame(enclosing, shares_slots)
+    }
+
+    /// The environment of a call, keeping its variables inline unless `shares_slots`, which
+    /// calls that might create closures need.
+    pub fn new_frame(enclosing: &EnvRef, shares_slots: bool) -> EnvRef {
+        if let Some(environment) = pool::take_environment() {
+            let mut reused = environment.borrow_mut();
+            reused.enclosing = Some(enclosing.clone());
+            reused.shares_slots = shares_slots;
+            drop(reused);
+            return environment;
+        }
+
+        EnvStats::allocation();
         Rc::new(RefCell::new(Environment {
             enclosing: Some(enclosing.clone()),
             values: HashMap::new(),
+            shares_slots,
         }))
     }
 
-    pub fn define(&mut self, name: String, value: Value) {
-        self.values.insert(name, value);
+    // empties a finished environment for reuse, keeping the memory of its map
+    pub(crate) fn clear(&mut self) {
+        self.values.clear();
+        self.enclosing = None;
     }
 
-    pub fn get_at(&self, distance: usize, name: &str) -> Result<Value, Exception> {
-        if distance == 0 {
-            return Ok(self.values.get(name).unwrap().clone());
+    /// Builds the environment a closure keeps alive: only the slots of its upvalues, shared with
+    /// `environment`, sitting directly on top of the globals.
+    pub fn capture(environment: &EnvRef, globals: &EnvRef, upvalues: &[Upvalue]) -> EnvRef {
+        if upvalues.is_empty() {
+            return globals.clone();
         }
 
-        if let Some(enclosing) = &self.enclosing {
-            return enclosing.borrow().get_at(distance - 1, name);
+        let closure = Environment::new_local(globals);
+        {
+            let mut environment = environment.borrow_mut();
+            let mut closure = closure.borrow_mut();
+            for upvalue in upvalues {
+                let cell = environment.share_at(upvalue.distance, &upvalue.name);
+                EnvStats::probes(1);
+                closure
+                    .values
+                    .insert(upvalue.name.clone(), Slot::Shared(cell));
+            }
         }
 
-        panic!("Could not find local scope that variable belongs to!")
+        closure
+    }
+
+    pub fn define(&mut self, name: String, value: Value) {
+        EnvStats::probes(1);
+        self.values
+            .insert(name, Slot::new(value, self.shares_slots));
+    }
+
+    /// Every variable defined directly in this environment, sorted by name.
+    pub fn bindings(&self) -> Vec<(String, Value)> {
+        let mut bindings = self
+            .values
+            .iter()
+            .map(|(name, slot)| (name.clone(), slot.get()))
+            .collect::<Vec<(String, Value)>>();
+        bindings.sort_by(|(a, _), (b, _)| a.cmp(b));
+        bindings
+    }
+
+    pub(crate) fn get_at(&self, distance: usize, name: &str) -> Result<Value, Exception> {
+        EnvStats::probes(1);
+        EnvStats::access(distance);
+        let value = match distance {
+            0 => self.values.get(name).map(Slot::get),
+            _ => self
+                .ancestor(distance)
+                .borrow()
+                .values
+                .get(name)
+                .map(Slot::get),
+        };
+
+        Ok(value.expect("Resolved variable to be defined in its scope!"))
     }
 
     pub fn assign_at(&mut self, distance: usize, name: &Token, value: &Value) {
+        EnvStats::access(distance);
         if distance == 0 {
-            self.values.insert(name.lexeme.clone(), value.clone());
+            self.assign_local(name, value);
             return;
         }
 
-        if let Some(enclosing) = &self.enclosing {
-            enclosing.borrow_mut().assign_at(distance - 1, name, value);
-            return;
-        }
-
-        panic!("Could not find local scope that variable belongs to!")
+        let ancestor = self.ancestor(distance);
+        ancestor.borrow_mut().assign_local(name, value);
     }
 
-    pub fn get(&self, name: &Token) -> Result<Value, Exception> {
-        if let Some(value) = self.values.get(&name.lexeme) {
-            return Ok(value.clone());
+    pub(crate) fn get(&self, name: &Token) -> Result<Value, Exception> {
+        EnvStats::name_lookup();
+        EnvStats::probes(1);
+        if let Some(slot) = self.values.get(&name.lexeme) {
+            return Ok(slot.get());
         }
 
-        if let Some(enclosing) = &self.enclosing {
-            return enclosing.borrow().get(name);
+        let mut next = self.enclosing.clone();
+        while let Some(environment) = next {
+            let environment = environment.borrow();
+            EnvStats::probes(1);
+            if let Some(slot) = environment.values.get(&name.lexeme) {
+                return Ok(slot.get());
+            }
+            next = environment.enclosing.clone();
         }
 
         Exception::runtime_error(name.clone(), format!("Undefined variable {}.", name.lexeme))
     }
 
-    pub fn assign(&mut self, name: &Token, value: &Value) -> Result<(), Exception> {
-        if let Entry::Occupied(mut e) = self.values.entry(name.lexeme.clone()) {
-            e.insert(value.clone());
+    pub(crate) fn assign(&mut self, name: &Token, value: &Value) -> Result<(), Exception> {
+        EnvStats::name_lookup();
+        EnvStats::probes(1);
+        if let Some(slot) = self.values.get_mut(&name.lexeme) {
+            slot.set(value);
             return Ok(());
         }
 
-        if let Some(enclosing) = &mut self.enclosing {
-            return enclosing.borrow_mut().assign(name, value);
+        let mut next = self.enclosing.clone();
+        while let Some(environment) = next {
+            let mut environment = environment.borrow_mut();
+            EnvStats::probes(1);
+            if let Some(slot) = environment.values.get_mut(&name.lexeme) {
+                slot.set(value);
+                return Ok(());
+            }
+            next = environment.enclosing.clone();
         }
 
         Exception::runtime_error(name.clone(), format!("Undefined variable {}.", name.lexeme))
     }
+
+    fn share_at(&mut self, distance: usize, name: &str) -> Rc<RefCell<Value>> {
+        EnvStats::probes(1);
+        EnvStats::access(distance);
+        let cell = match distance {
+            0 => self.values.get_mut(name).map(Slot::share),
+            _ => self
+                .ancestor(distance)
+                .borrow_mut()
+                .values
+                .get_mut(name)
+                .map(Slot::share),
+        };
+
+        cell.expect("Captured variable to be defined in its scope!")
+    }
+
+    /// Walks `distance` hops up the chain iteratively, only borrowing one link at a time.
+    fn ancestor(&self, distance: usize) -> EnvRef {
+        let mut environment = self
+            .enclosing
+            .clone()
+            .expect("Could not find local scope that variable belongs to!");
+
+        for _ in 1..distance {
+            let enclosing = environment
+                .borrow()
+                .enclosing
+                .clone()
+                .expect("Could not find local scope that variable belongs to!");
+            environment = enclosing;
+        }
+
+        environment
+    }
+
+    fn assign_local(&mut self, name: &Token, value: &Value) {
+        // write through the existing slot so closures that captured it see the new value
+        EnvStats::probes(1);
+        match self.values.get_mut(&name.lexeme) {
+            Some(slot) => slot.set(value),
+            None => {
+                EnvStats::probes(1);
+                let slot = Slot::new(value.clone(), self.shares_slots);
+                self.values.insert(name.lexeme.clone(), slot);
+            }
+        }
+    }
 }
@@ -0,0 +1,278 @@
+use std::rc::Rc;
+
+use crate::{
+    environment::{EnvRef, Environment},
+    interpreter::Interpreter,
+    semantics::is_truthy,
+    syntax::{expr::Expr, stmt::Stmt, token::Token, value::Value},
+    Exception,
+};
+
+/// A statement owned either by a shared list of statements or by a shared branch or loop body.
+#[derive(Clone)]
+enum StmtRef {
+    InBlock(Rc<Vec<Stmt>>, usize),
+    Shared(Rc<Stmt>),
+}
+
+impl StmtRef {
+    fn get(&self) -> &Stmt {
+        match self {
+            StmtRef::InBlock(statements, index) => &statements[*index],
+            StmtRef::Shared(statement) => statement,
+        }
+    }
+}
+
+enum Frame {
+    // runs `statements[next..]`, then switches back to `previous` if the block has its own scope
+    Block {
+        statements: Rc<Vec<Stmt>>,
+        next: usize,
+        previous: Option<EnvRef>,
+    },
+    // re-checks the condition of a while statement before every iteration
+    Loop(StmtRef),
+    // binds the next item of a for-in loop in a fresh scope on top of `outer` and runs the body
+    Each {
+        name: Token,
+        items: std::vec::IntoIter<Value>,
+        body: Rc<Stmt>,
+        outer: EnvRef,
+    },
+    Statement(StmtRef),
+}
+
+/// Why a fiber handed control back to whoever resumed it.
+pub(crate) enum Suspension {
+    Yielded(Value),
+    OutOfSteps,
+    // ran off the end of its statements or returned, with the returned value
+    Finished(Value),
+}
+
+/// Executes statements from an explicit stack of frames rather than the Rust call stack, so it
+/// can stop between any two statements and carry on later. Blocks, ifs and loops are stepped
+/// through; other statements, including function calls and `try` blocks, run to completion.
+pub(crate) struct Fiber {
+    frames: Vec<Frame>,
+    // the environment the fiber's statements run in while it isn't running
+    environment: EnvRef,
+    // the statement suspended at a yield, which receives the value the fiber is resumed with
+    suspended_at: Option<StmtRef>,
+}
+
+impl Fiber {
+    pub(crate) fn new(statements: Rc<Vec<Stmt>>, environment: EnvRef) -> Fiber {
+        Fiber {
+            frames: vec![Frame::Block {
+                statements,
+                next: 0,
+                previous: None,
+            }],
+            environment,
+            suspended_at: None,
+        }
+    }
+
+    /// Runs until the fiber yields, the interpreter has taken `deadline` steps in total or nothing
+    /// is left to run, passing `sent` to the yield it was suspended at. The caller's environment is
+    /// restored whenever the fiber stops, and after an error the fiber is finished.
+    pub(crate) fn resume(
+        &mut self,
+        interpreter: &mut Interpreter,
+        sent: Value,
+        deadline: u64,
+    ) -> Result<Suspension, Exception> {
+        let caller = std::mem::replace(&mut interpreter.environment, self.environment.clone());
+        let result = self.run(interpreter, sent, deadline);
+        self.environment = std::mem::replace(&mut interpreter.environment, caller);
+
+        if result.is_err() {
+            self.frames.clear();
+        }
+        result
+    }
+
+    fn run(
+        &mut self,
+        interpreter: &mut Interpreter,
+        sent: Value,
+        deadline: u64,
+    ) -> Result<Suspension, Exception> {
+        if let Some(statement) = self.suspended_at.take() {
+            Fiber::receive(interpreter, statement.get(), sent)?;
+        }
+
+        while interpreter.steps < deadline {
+            let Some(frame) = self.frames.pop() else {
+                break;
+            };
+            // loops run here rather than through `execute`, so they need their own check
+            interpreter.check_interrupted()?;
+
+            match self.step(interpreter, frame) {
+                Ok(Some(value)) => return Ok(Suspension::Yielded(value)),
+                Ok(None) => {}
+                Err(Exception::Return(value)) => {
+                    self.frames.clear();
+                    return Ok(Suspension::Finished(value));
+                }
+                Err(exception) => return Err(exception),
+            }
+        }
+
+        match self.frames.is_empty() {
+            true => Ok(Suspension::Finished(Value::Nil)),
+            false => Ok(Suspension::OutOfSteps),
+        }
+    }
+
+    /// Finishes the statement a yield suspended, now that it's been resumed with `sent`.
+    fn receive(
+        interpreter: &mut Interpreter,
+        statement: &Stmt,
+        sent: Value,
+    ) -> Result<(), Exception> {
+        match statement {
+            Stmt::Var { name, .. } => {
+                interpreter
+                    .environment
+                    .borrow_mut()
+                    .define(name.lexeme.clone(), sent);
+            }
+            Stmt::Expression(assign @ Expr::Assign { name, .. }) => {
+                interpreter.assign_variable(name, &sent, assign)?;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Runs one frame, returning the value handed back if it reached a yield.
+    fn step(
+        &mut self,
+        interpreter: &mut Interpreter,
+        frame: Frame,
+    ) -> Result<Option<Value>, Exception> {
+        match frame {
+            Frame::Block {
+                statements,
+                next,
+                previous,
+            } => {
+                if next == statements.len() {
+                    if let Some(previous) = previous {
+                        interpreter.environment = previous;
+                    }
+                    return Ok(None);
+                }
+
+                let statement = StmtRef::InBlock(statements.clone(), next);
+                self.frames.push(Frame::Block {
+                    statements,
+                    next: next + 1,
+                    previous,
+                });
+                self.frames.push(Frame::Statement(statement));
+            }
+            Frame::Loop(statement) => {
+                let Stmt::While { condition, body } = statement.get() else {
+                    panic!("Loop frame does not hold a while statement!");
+                };
+
+                if is_truthy(&interpreter.evaluate(condition)?) {
+                    let body = StmtRef::Shared(body.clone());
+                    self.frames.push(Frame::Loop(statement));
+                    self.frames.push(Frame::Statement(body));
+                }
+            }
+            Frame::Each {
+                name,
+                mut items,
+                body,
+                outer,
+            } => match items.next() {
+                Some(item) => {
+                    let environment = Environment::new_local(&outer);
+                    environment.borrow_mut().define(name.lexeme.clone(), item);
+                    interpreter.environment = environment;
+
+                    let statement = StmtRef::Shared(body.clone());
+                    self.frames.push(Frame::Each {
+                        name,
+                        items,
+                        body,
+                        outer,
+                    });
+                    self.frames.push(Frame::Statement(statement));
+                }
+                None => interpreter.environment = outer,
+            },
+            Frame::Statement(statement) => match statement.get() {
+                Stmt::Block(statements) => {
+                    interpreter.steps += 1;
+
+                    let environment = Environment::new_local(&interpreter.environment);
+                    let previous = std::mem::replace(&mut interpreter.environment, environment);
+                    self.frames.push(Frame::Block {
+                        statements: statements.clone(),
+                        next: 0,
+                        previous: Some(previous),
+                    });
+                }
+                Stmt::While { .. } => {
+                    interpreter.steps += 1;
+                    self.frames.push(Frame::Loop(statement.clone()));
+                }
+                Stmt::ForIn {
+                    name,
+                    iterable,
+                    body,
+                } => {
+                    interpreter.steps += 1;
+
+                    let iterable = interpreter.evaluate(iterable)?;
+                    self.frames.push(Frame::Each {
+                        name: name.clone(),
+                        items: Interpreter::items(iterable, name)?.into_iter(),
+                        body: body.clone(),
+                        outer: interpreter.environment.clone(),
+                    });
+                }
+                Stmt::If {
+                    condition,
+                    then_branch,
+                    else_branch,
+                } => {
+                    interpreter.steps += 1;
+
+                    let branch = match is_truthy(&interpreter.evaluate(condition)?) {
+                        true => Some(then_branch),
+                        false => else_branch.as_ref(),
+                    };
+                    if let Some(branch) = branch {
+                        self.frames
+                            .push(Frame::Statement(StmtRef::Shared(branch.clone())));
+                    }
+                }
+                stmt => match stmt.yield_point() {
+                    Some(Expr::Yield { value, .. }) => {
+                        interpreter.steps += 1;
+
+                        let value = match value {
+                            Some(value) => interpreter.evaluate(value)?,
+                            None => Value::Nil,
+                        };
+                        self.suspended_at = Some(statement);
+                        return Ok(Some(value));
+                    }
+                    _ => interpreter.execute(stmt)?,
+                },
+            },
+        }
+
+        Ok(None)
+    }
+}
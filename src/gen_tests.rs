@@ -0,0 +1,259 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Where `gen-tests` writes fixtures unless told otherwise, which is where the golden test in
+/// tests/integration_test.rs looks for them.
+pub const GOLDEN_DIR: &str = "test_files/golden";
+
+/// The feature areas there are skeletons for, with what each covers.
+pub const FEATURES: &[(&str, &str)] = &[
+    ("classes", "fields, methods, initializers and inheritance"),
+    ("closures", "captured variables, counters and shadowing"),
+    ("control-flow", "if, while, for and for-in loops"),
+    ("strings", "concatenation, repetition and string methods"),
+    ("exceptions", "throw, try/catch/finally and runtime errors"),
+    ("traits", "trait methods and their precedence"),
+    ("coroutines", "yield, resume and status"),
+];
+
+/// A skeleton fixture for `feature`: a few working cases, each print annotated with the line
+/// it should print as `// expect: <line>`, and a place to add more.
+pub fn skeleton(feature: &str) -> Option<String> {
+    let (_, covers) = FEATURES.iter().find(|(name, _)| *name == feature)?;
+    let cases = match feature {
+        "classes" => CLASSES,
+        "closures" => CLOSURES,
+        "control-flow" => CONTROL_FLOW,
+        "strings" => STRINGS,
+        "exceptions" => EXCEPTIONS,
+        "traits" => TRAITS,
+        "coroutines" => COROUTINES,
+        _ => return None,
+    };
+
+    Some(format!(
+        "// Golden tests for {feature}: {covers}.\n\
+         // Each expect comment gives the next line the script should print.\n\
+         // Generated by `rlox gen-tests --feature {feature}`; edit and extend freely.\n\
+         \n\
+         {cases}\n\
+         // TODO: cover more of {feature} here.\n"
+    ))
+}
+
+/// Writes the skeleton for `feature` to `<dir>/<feature>.lox`, returning its path. An existing
+/// fixture is never overwritten.
+pub fn write(feature: &str, dir: &Path) -> Result<PathBuf, String> {
+    let skeleton = skeleton(feature).ok_or_else(|| unknown_feature(feature))?;
+    let path = dir.join(format!("{feature}.lox"));
+
+    fs::create_dir_all(dir)
+        .and_then(|_| OpenOptions::new().write(true).create_new(true).open(&path))
+        .and_then(|mut file| file.write_all(skeleton.as_bytes()))
+        .map_err(|error| format!("Can't write '{}': {error}.", path.display()))?;
+    Ok(path)
+}
+
+pub fn unknown_feature(feature: &str) -> String {
+    let features = FEATURES
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<&str>>();
+    format!(
+        "Unknown feature '{feature}'; expected one of {}.",
+        features.join(", ")
+    )
+}
+
+const CLASSES: &str = r#"class Point {
+  init(x, y) {
+    this.x = x;
+    this.y = y;
+  }
+
+  sum() {
+    return this.x + this.y;
+  }
+}
+
+var point = Point(1, 2);
+print point.x; // expect: 1
+print point.sum(); // expect: 3
+point.y = 10;
+print point.sum(); // expect: 11
+
+class Point3 < Point {
+  init(x, y, z) {
+    super.init(x, y);
+    this.z = z;
+  }
+
+  sum() {
+    return super.sum() + this.z;
+  }
+}
+
+print Point3(1, 2, 3).sum(); // expect: 6
+print Point3; // expect: Point3
+"#;
+
+const CLOSURES: &str = r#"fun makeCounter() {
+  var count = 0;
+  fun increment() {
+    count = count + 1;
+    return count;
+  }
+  return increment;
+}
+
+var counter = makeCounter();
+counter();
+print counter(); // expect: 2
+print makeCounter()(); // expect: 1
+
+var name = "global";
+{
+  fun show() {
+    return name;
+  }
+  var name = "block";
+  print show(); // expect: global
+  print name; // expect: block
+}
+"#;
+
+const CONTROL_FLOW: &str = r#"if (1 < 2) print "then"; else print "else"; // expect: then
+if (nil) print "then"; else print "else"; // expect: else
+
+var total = 0;
+var i = 0;
+while (i < 4) {
+  total = total + i;
+  i = i + 1;
+}
+print total; // expect: 6
+
+var product = 1;
+for (var j = 1; j <= 5; j = j + 1) {
+  product = product * j;
+}
+print product; // expect: 120
+
+for (var letter in "ab") {
+  print letter; // expect: a
+  // expect: b
+}
+"#;
+
+const STRINGS: &str = r#"print "con" + "cat"; // expect: concat
+print "ab" * 3; // expect: ababab
+print "Hello".length(); // expect: 5
+print "Hello".upper(); // expect: HELLO
+print "  padded  ".trim(); // expect: padded
+print "a,b".contains(","); // expect: true
+print 'say "hi"'; // expect: say "hi"
+"#;
+
+const EXCEPTIONS: &str = r#"try {
+  throw "thrown";
+} catch (error) {
+  print error; // expect: thrown
+}
+
+try {
+  nil();
+} catch (error) {
+  print error; // expect: Can only call functions and classes.
+}
+
+fun cleanup() {
+  try {
+    return "returned";
+  } finally {
+    print "finally"; // expect: finally
+  }
+}
+print cleanup(); // expect: returned
+"#;
+
+const TRAITS: &str = r#"trait Greets {
+  greet() {
+    return "hello from " + this.name();
+  }
+
+  name() {
+    return "trait";
+  }
+}
+
+class Robot with Greets {
+  name() {
+    return "robot";
+  }
+}
+
+print Robot().greet(); // expect: hello from robot
+"#;
+
+const COROUTINES: &str = r#"fun countTo(limit) {
+  for (var i = 1; i <= limit; i = i + 1) {
+    yield i;
+  }
+  return "done";
+}
+
+var counting = coroutine(countTo);
+print counting.status(); // expect: suspended
+print counting.resume(2); // expect: 1
+print counting.resume(); // expect: 2
+print counting.resume(); // expect: done
+print counting.status(); // expect: dead
+"#;
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, fmt::Arguments, rc::Rc};
+
+    use rlox::prelude::*;
+
+    use super::*;
+
+    struct Lines(Rc<RefCell<Vec<String>>>);
+
+    impl Logger for Lines {
+        fn print(&mut self, value: Arguments) {
+            self.0.borrow_mut().push(value.to_string());
+        }
+    }
+
+    #[test]
+    fn skeletons_print_what_they_expect() {
+        for (feature, _) in FEATURES {
+            let skeleton = skeleton(feature).unwrap();
+            let expected = skeleton
+                .lines()
+                .filter_map(|line| line.split_once("// expect: "))
+                .map(|(_, expected)| expected.to_string())
+                .collect::<Vec<String>>();
+            assert!(!expected.is_empty(), "{feature} expects nothing");
+
+            let lines = Rc::new(RefCell::new(vec![]));
+            let mut interpreter = Interpreter::new(InterpreterOptions {
+                logger: Some(Box::new(Lines(lines.clone()))),
+                ..Default::default()
+            });
+            interpreter.load(&skeleton).unwrap();
+            assert_eq!(interpreter.run_for(Steps(u64::MAX)), RunState::Finished);
+            assert_eq!(*lines.borrow(), expected, "{feature}");
+        }
+    }
+
+    #[test]
+    fn unknown_features_have_no_skeleton() {
+        assert_eq!(skeleton("macros"), None);
+        assert!(unknown_feature("macros").contains("classes, closures"));
+    }
+}
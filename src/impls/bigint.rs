@@ -0,0 +1,327 @@
+use std::{cmp::Ordering, fmt::Display, rc::Rc};
+
+use crate::syntax::value::Value;
+
+// each limb holds nine decimal digits, which keeps printing simple and products within a u64
+const BASE: u64 = 1_000_000_000;
+
+/// An integer too big for an i64, which integer arithmetic overflows into with the `bigint`
+/// feature. Results that fit back in an i64 become plain integers again, so a `Value::BigInt`
+/// is always outside the i64 range. Big integers are slower than i64s by an allocation per
+/// operation and a loop over their digits, which is why they're opt-in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BigInt {
+    negative: bool,
+    // base 10^9 digits, least significant first, without leading zeros, so zero has none
+    limbs: Vec<u32>,
+}
+
+impl BigInt {
+    pub fn from_i64(value: i64) -> BigInt {
+        let mut magnitude = value.unsigned_abs();
+        let mut limbs = vec![];
+        while magnitude > 0 {
+            limbs.push((magnitude % BASE) as u32);
+            magnitude /= BASE;
+        }
+
+        BigInt {
+            negative: value < 0,
+            limbs,
+        }
+    }
+
+    /// Reads an integer written in `base`, from 2 to 36, with an optional sign, or None if it
+    /// isn't one.
+    pub fn parse_radix(text: &str, base: u32) -> Option<BigInt> {
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        if digits.is_empty() {
+            return None;
+        }
+
+        let mut limbs = vec![];
+        for digit in digits.chars() {
+            mul_add_small(&mut limbs, base, digit.to_digit(base)?);
+        }
+        Some(BigInt::new(negative, limbs))
+    }
+
+    /// The integer written in `base`, from 2 to 36, with lowercase letters for digits past 9.
+    pub fn to_string_radix(&self, base: u32) -> String {
+        if self.limbs.is_empty() {
+            return "0".to_string();
+        }
+
+        let mut limbs = self.limbs.clone();
+        let mut digits = vec![];
+        while !limbs.is_empty() {
+            let digit = div_small(&mut limbs, base);
+            digits.push(char::from_digit(digit, base).expect("digit to be below the base"));
+            while limbs.last() == Some(&0) {
+                limbs.pop();
+            }
+        }
+        if self.negative {
+            digits.push('-');
+        }
+        digits.iter().rev().collect()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        let mut magnitude: i128 = 0;
+        for limb in self.limbs.iter().rev() {
+            magnitude = magnitude * BASE as i128 + *limb as i128;
+            if magnitude > i64::MAX as i128 + 1 {
+                return None;
+            }
+        }
+
+        i64::try_from(if self.negative { -magnitude } else { magnitude }).ok()
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let magnitude = self
+            .limbs
+            .iter()
+            .rev()
+            .fold(0.0, |total, limb| total * BASE as f64 + *limb as f64);
+        if self.negative {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+
+    /// The integer as a Lox value: a plain integer if it fits in one.
+    pub fn into_value(self) -> Value {
+        match self.to_i64() {
+            Some(value) => Value::Int(value),
+            None => Value::BigInt(Rc::new(self)),
+        }
+    }
+
+    pub fn add(&self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::new(self.negative, add_magnitudes(&self.limbs, &other.limbs));
+        }
+
+        // the sign of the larger magnitude wins
+        match compare_magnitudes(&self.limbs, &other.limbs) {
+            Ordering::Less => {
+                BigInt::new(other.negative, sub_magnitudes(&other.limbs, &self.limbs))
+            }
+            _ => BigInt::new(self.negative, sub_magnitudes(&self.limbs, &other.limbs)),
+        }
+    }
+
+    pub fn sub(&self, other: &BigInt) -> BigInt {
+        self.add(&other.negate())
+    }
+
+    pub fn mul(&self, other: &BigInt) -> BigInt {
+        let mut limbs = vec![0u64; self.limbs.len() + other.limbs.len()];
+        for (i, left) in self.limbs.iter().enumerate() {
+            let mut carry = 0;
+            for (j, right) in other.limbs.iter().enumerate() {
+                let total = limbs[i + j] + *left as u64 * *right as u64 + carry;
+                limbs[i + j] = total % BASE;
+                carry = total / BASE;
+            }
+            limbs[i + other.limbs.len()] += carry;
+        }
+
+        BigInt::new(
+            self.negative != other.negative,
+            limbs.into_iter().map(|limb| limb as u32).collect(),
+        )
+    }
+
+    pub fn negate(&self) -> BigInt {
+        BigInt::new(!self.negative, self.limbs.clone())
+    }
+
+    pub fn abs(&self) -> BigInt {
+        BigInt::new(false, self.limbs.clone())
+    }
+
+    // drops leading zeros, and the sign of zero
+    fn new(negative: bool, mut limbs: Vec<u32>) -> BigInt {
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+
+        BigInt {
+            negative: negative && !limbs.is_empty(),
+            limbs,
+        }
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => compare_magnitudes(&self.limbs, &other.limbs),
+            (true, true) => compare_magnitudes(&other.limbs, &self.limbs),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for BigInt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Some((most, rest)) = self.limbs.split_last() else {
+            return write!(f, "0");
+        };
+
+        if self.negative {
+            write!(f, "-")?;
+        }
+        write!(f, "{most}")?;
+        for limb in rest.iter().rev() {
+            write!(f, "{limb:09}")?;
+        }
+        Ok(())
+    }
+}
+
+fn add_magnitudes(left: &[u32], right: &[u32]) -> Vec<u32> {
+    let mut limbs = vec![];
+    let mut carry = 0;
+    for i in 0..left.len().max(right.len()) {
+        let total = *left.get(i).unwrap_or(&0) as u64 + *right.get(i).unwrap_or(&0) as u64 + carry;
+        limbs.push((total % BASE) as u32);
+        carry = total / BASE;
+    }
+    limbs.push(carry as u32);
+    limbs
+}
+
+// `larger` minus `smaller`, where `larger` has the larger magnitude
+fn sub_magnitudes(larger: &[u32], smaller: &[u32]) -> Vec<u32> {
+    let mut limbs = vec![];
+    let mut borrow = 0;
+    for (i, limb) in larger.iter().enumerate() {
+        let subtracted = *smaller.get(i).unwrap_or(&0) as i64 + borrow;
+        let mut difference = *limb as i64 - subtracted;
+        borrow = 0;
+        if difference < 0 {
+            difference += BASE as i64;
+            borrow = 1;
+        }
+        limbs.push(difference as u32);
+    }
+    limbs
+}
+
+// multiplies a magnitude by `factor` and adds `addend`, both below the base
+fn mul_add_small(limbs: &mut Vec<u32>, factor: u32, addend: u32) {
+    let mut carry = addend as u64;
+    for limb in limbs.iter_mut() {
+        let total = *limb as u64 * factor as u64 + carry;
+        *limb = (total % BASE) as u32;
+        carry = total / BASE;
+    }
+    if carry > 0 {
+        limbs.push(carry as u32);
+    }
+}
+
+// divides a magnitude by `divisor` in place, returning the remainder
+fn div_small(limbs: &mut [u32], divisor: u32) -> u32 {
+    let mut remainder = 0;
+    for limb in limbs.iter_mut().rev() {
+        let current = remainder * BASE + *limb as u64;
+        *limb = (current / divisor as u64) as u32;
+        remainder = current % divisor as u64;
+    }
+    remainder as u32
+}
+
+fn compare_magnitudes(left: &[u32], right: &[u32]) -> Ordering {
+    left.len()
+        .cmp(&right.len())
+        .then_with(|| left.iter().rev().cmp(right.iter().rev()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(value: i64) -> BigInt {
+        BigInt::from_i64(value)
+    }
+
+    #[test]
+    fn arithmetic_carries_across_limbs() {
+        let max = big(i64::MAX);
+        assert_eq!(max.add(&big(1)).to_string(), "9223372036854775808");
+        assert_eq!(
+            max.mul(&max).to_string(),
+            "85070591730234615847396907784232501249"
+        );
+        assert_eq!(
+            big(i64::MIN).sub(&big(1)).to_string(),
+            "-9223372036854775809"
+        );
+        assert_eq!(big(-5).add(&big(3)).to_string(), "-2");
+        assert_eq!(big(5).sub(&big(5)), big(0));
+        assert_eq!(big(1_000_000_000).mul(&big(-3)).to_string(), "-3000000000");
+    }
+
+    #[test]
+    fn results_that_fit_become_plain_integers() {
+        let past_max = big(i64::MAX).add(&big(1));
+        assert_eq!(past_max.to_i64(), None);
+        assert!(matches!(
+            past_max.sub(&big(1)).into_value(),
+            Value::Int(i64::MAX)
+        ));
+        assert_eq!(big(i64::MIN).to_i64(), Some(i64::MIN));
+        assert_eq!(past_max.to_f64(), 9223372036854775808.0);
+    }
+
+    #[test]
+    fn integers_read_and_write_in_any_base() {
+        let huge = big(i64::MAX).mul(&big(i64::MAX));
+        let decimal = "85070591730234615847396907784232501249";
+        assert_eq!(BigInt::parse_radix(decimal, 10), Some(huge.clone()));
+        assert_eq!(huge.to_string_radix(10), decimal);
+        assert_eq!(
+            huge.negate().to_string_radix(16),
+            "-3fffffffffffffff0000000000000001"
+        );
+        for base in 2..=36 {
+            assert_eq!(
+                BigInt::parse_radix(&huge.to_string_radix(base), base),
+                Some(huge.clone())
+            );
+        }
+        assert_eq!(big(0).to_string_radix(2), "0");
+        assert_eq!(BigInt::parse_radix("-0", 10), Some(big(0)));
+        assert_eq!(BigInt::parse_radix("12a", 10), None);
+        assert_eq!(BigInt::parse_radix("-", 10), None);
+    }
+
+    #[test]
+    fn ordering_accounts_for_sign_and_size() {
+        let huge = big(i64::MAX).mul(&big(10));
+        assert!(huge > big(i64::MAX));
+        assert!(huge.negate() < big(i64::MIN));
+        assert!(big(-2) < big(-1));
+        assert_eq!(big(0).negate(), big(0));
+    }
+}
@@ -6,9 +6,29 @@ use crate::{
 
 pub trait Callable {
     fn arity(&self) -> usize;
-    fn call(&self, interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, Exception>;
+    /// `paren` is the call site's closing parenthesis, used to locate any runtime error.
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        args: Vec<Value>,
+    ) -> Result<Value, Exception>;
+    /// Variadic callables accept any number of arguments beyond their arity.
+    fn is_variadic(&self) -> bool {
+        false
+    }
     fn check_arity(&self, args_len: usize, current_token: &Token) -> Result<(), Exception> {
-        if args_len > self.arity() {
+        if self.is_variadic() && args_len < self.arity() {
+            return Exception::runtime_error(
+                current_token.clone(),
+                format!(
+                    "Expected at least {} arguments but got {}.",
+                    self.arity(),
+                    args_len
+                ),
+            );
+        }
+        if !self.is_variadic() && args_len != self.arity() {
             return Exception::runtime_error(
                 current_token.clone(),
                 format!("Expected {} arguments but got {}.", self.arity(), args_len),
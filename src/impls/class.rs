@@ -1,41 +1,187 @@
-use std::{cell::RefCell, collections::HashMap, fmt::Display, rc::Rc};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    fmt::Display,
+    rc::Rc,
+};
 
 use crate::{
     interpreter::Interpreter,
+    pool,
     syntax::{token::Token, value::Value},
     Exception,
 };
 
-use super::function::{Callable, Function};
+use super::{callable::Callable, function::Function, traits::TraitRef};
+
+pub type ClassRef = Rc<Class>;
+
+thread_local! {
+    // how many methods have been patched, so flattened method tables know when to rebuild
+    static PATCHES: Cell<u64> = const { Cell::new(0) };
+}
 
-#[derive(Clone, Debug)]
+/// Methods can be patched at runtime with `Class.name = function;`. Every method call looks the
+/// method up in the live tables, so a patch takes effect on the next call through any instance,
+/// old or new, and through subclasses that don't override the method. Bound methods already
+/// taken from an instance keep calling the method they were bound to, and fields still shadow
+/// methods. Anything that caches method lookups has to be invalidated by `patch`.
+#[derive(Debug)]
 pub struct Class {
     name: String,
-    super_class: Option<Box<Class>>,
-    methods: HashMap<String, Function>,
+    doc: Option<String>,
+    super_class: Option<ClassRef>,
+    traits: Vec<TraitRef>,
+    methods: RefCell<HashMap<String, Function>>,
+    // declared without a body, for a subclass to implement
+    abstract_methods: Vec<String>,
+    setters: HashMap<String, Function>,
+    // every method `find_method` could find, flattened into one table for `super` calls to
+    // look up, and the patch count it was built at
+    flattened: RefCell<(u64, HashMap<String, Function>)>,
 }
 
 impl Class {
     pub fn new(
         name: String,
-        super_class: Option<Box<Class>>,
+        doc: Option<String>,
+        super_class: Option<ClassRef>,
+        traits: Vec<TraitRef>,
         methods: HashMap<String, Function>,
-    ) -> Class {
-        Class {
+        abstract_methods: Vec<String>,
+        setters: HashMap<String, Function>,
+    ) -> ClassRef {
+        let class = Rc::new(Class {
             name,
+            doc,
             super_class,
-            methods,
+            traits,
+            methods: RefCell::new(methods),
+            abstract_methods,
+            setters,
+            flattened: RefCell::new((PATCHES.get(), HashMap::new())),
+        });
+        *class.flattened.borrow_mut() = (PATCHES.get(), class.all_methods());
+        class
+    }
+
+    /// Adds or replaces the method `name`. A method taken from an instance is rebound to
+    /// whichever instance it's called on, while a plain function is called without a "this".
+    pub(crate) fn patch(&self, name: &Token, value: Value) -> Result<(), Exception> {
+        let Value::Function(function) = value else {
+            return Exception::runtime_error(
+                name.clone(),
+                String::from("Can only patch methods with functions."),
+            );
+        };
+        if name.lexeme == "init" {
+            return Exception::runtime_error(
+                name.clone(),
+                String::from("Can't patch an initializer."),
+            );
+        }
+
+        self.methods
+            .borrow_mut()
+            .insert(name.lexeme.clone(), function.unbind());
+        // the patched class may be an ancestor of any class
+        PATCHES.set(PATCHES.get() + 1);
+        Ok(())
+    }
+
+    pub fn doc(&self) -> Option<&str> {
+        self.doc.as_deref()
+    }
+
+    /// The initializer's parameters as written in its declaration.
+    pub fn param_list(&self) -> String {
+        match self.find_method("init") {
+            Some(Value::Function(initializer)) => initializer.param_list(),
+            _ => String::new(),
         }
     }
 
+    pub fn find_setter(&self, name: &str) -> Option<Function> {
+        self.setters.get(name).cloned().or_else(|| {
+            self.super_class
+                .as_ref()
+                .and_then(|super_class| super_class.find_setter(name))
+        })
+    }
+
+    /// Looks up a method on the class itself, then on its mixed-in traits, then up the superclass chain.
     pub fn find_method(&self, name: &str) -> Option<Value> {
-        self.methods
-            .get(name)
+        if let Some(method) = self.methods.borrow().get(name) {
+            return Some(Value::Function(method.clone()));
+        }
+
+        self.traits
+            .iter()
+            .find_map(|lox_trait| lox_trait.find_method(name))
             .map(|method| Value::Function(method.clone()))
-            .or(self
-                .super_class
-                .as_ref()
-                .and_then(|super_class| super_class.find_method(name)))
+            .or_else(|| {
+                self.super_class
+                    .as_ref()
+                    .and_then(|super_class| super_class.find_method(name))
+            })
+    }
+
+    /// The method `find_method` finds, in a single lookup. The class, its traits and its
+    /// superclasses are flattened into one table when the class is made, and again after any
+    /// class has been patched, so this suits hot paths like `super` calls.
+    pub fn find_flattened_method(&self, name: &str) -> Option<Function> {
+        let mut flattened = self.flattened.borrow_mut();
+        let (built_at, methods) = &mut *flattened;
+        if *built_at != PATCHES.get() {
+            *methods = self.all_methods();
+            *built_at = PATCHES.get();
+        }
+        methods.get(name).cloned()
+    }
+
+    // every method `find_method` could find, keyed by name
+    fn all_methods(&self) -> HashMap<String, Function> {
+        let mut methods = self
+            .super_class
+            .as_ref()
+            .map_or_else(HashMap::new, |super_class| super_class.all_methods());
+        // earlier traits win, as they do in `find_method`
+        for lox_trait in self.traits.iter().rev() {
+            for name in lox_trait.method_names() {
+                let method = lox_trait
+                    .find_method(name)
+                    .expect("trait to have its methods");
+                methods.insert(name.clone(), method.clone());
+            }
+        }
+        methods.extend(
+            self.methods
+                .borrow()
+                .iter()
+                .map(|(name, method)| (name.clone(), method.clone())),
+        );
+        methods
+    }
+
+    /// The abstract methods declared on the class or its superclasses that it has no
+    /// implementation of, in alphabetical order. Instances can only be made when there are none.
+    pub fn unimplemented_methods(&self) -> Vec<String> {
+        let mut unimplemented = vec![];
+        let mut class = Some(self);
+        while let Some(current) = class {
+            unimplemented.extend(
+                current
+                    .abstract_methods
+                    .iter()
+                    .filter(|method| self.find_method(method).is_none())
+                    .cloned(),
+            );
+            class = current.super_class.as_deref();
+        }
+
+        unimplemented.sort();
+        unimplemented.dedup();
+        unimplemented
     }
 }
 
@@ -46,7 +192,7 @@ impl Display for Class {
 }
 
 // class constructor
-impl Callable for Class {
+impl Callable for ClassRef {
     fn arity(&self) -> usize {
         if let Some(initializer) = self.find_method("init") {
             match initializer {
@@ -58,13 +204,59 @@ impl Callable for Class {
         0
     }
 
-    fn call(&self, interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, Exception> {
+    fn is_variadic(&self) -> bool {
+        match self.find_method("init") {
+            Some(Value::Function(initializer)) => initializer.is_variadic(),
+            _ => false,
+        }
+    }
+
+    fn check_arity(&self, args_len: usize, current_token: &Token) -> Result<(), Exception> {
+        let expected = match self.is_variadic() {
+            true if args_len >= self.arity() => return Ok(()),
+            true => format!("at least {}", self.arity()),
+            false if args_len == self.arity() => return Ok(()),
+            false => self.arity().to_string(),
+        };
+
+        Exception::runtime_error(
+            current_token.clone(),
+            format!(
+                "{}({}) expects {} arguments but got {}.",
+                self.name,
+                self.param_list(),
+                expected,
+                args_len
+            ),
+        )
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        args: Vec<Value>,
+    ) -> Result<Value, Exception> {
+        let unimplemented = self.unimplemented_methods();
+        if !unimplemented.is_empty() {
+            return Exception::runtime_error(
+                paren.clone(),
+                format!(
+                    "Can't instantiate {}, which doesn't implement {}.",
+                    self.name,
+                    unimplemented.join(", ")
+                ),
+            );
+        }
+
         let instance = ClassInstance::new(self.clone());
 
         if let Some(initializer) = self.find_method("init") {
             match initializer {
                 Value::Function(initializer) => {
-                    let _ = initializer.bind(instance.clone()).call(interpreter, args);
+                    initializer
+                        .bind(instance.clone())
+                        .call(interpreter, paren, args)?;
                 }
                 _ => panic!("initalizer is not a function!"),
             };
@@ -76,9 +268,11 @@ impl Callable for Class {
 
 pub type ClassInstanceRef = Rc<RefCell<ClassInstance>>;
 
-#[derive(Clone, Debug)]
+// Instances are only ever shared through a ClassInstanceRef, never copied, so every
+// alias observes the same fields.
+#[derive(Debug)]
 pub struct ClassInstance {
-    class: Class,
+    class: ClassRef,
     fields: HashMap<String, Value>,
 }
 
@@ -88,15 +282,25 @@ impl Display for ClassInstance {
     }
 }
 
+impl Drop for ClassInstance {
+    fn drop(&mut self) {
+        pool::recycle_field_map(std::mem::take(&mut self.fields));
+    }
+}
+
 impl ClassInstance {
-    pub fn new(class: Class) -> ClassInstanceRef {
+    pub fn new(class: ClassRef) -> ClassInstanceRef {
         Rc::new(RefCell::new(ClassInstance {
             class,
-            fields: HashMap::new(),
+            fields: pool::take_field_map(),
         }))
     }
 
-    pub fn get(&self, name: &Token, instance_ref: ClassInstanceRef) -> Result<Value, Exception> {
+    pub(crate) fn get(
+        &self,
+        name: &Token,
+        instance_ref: ClassInstanceRef,
+    ) -> Result<Value, Exception> {
         if let Some(field) = self.fields.get(&name.lexeme) {
             return Ok(field.clone());
         }
@@ -109,6 +313,41 @@ impl ClassInstance {
         Exception::runtime_error(name.clone(), format!("Undefined property {}.", name.lexeme))
     }
 
+    pub fn find_setter(&self, name: &str) -> Option<Function> {
+        self.class.find_setter(name)
+    }
+
+    /// The names of the instance's fields, in alphabetical order. Methods aren't fields, so
+    /// they're left out.
+    pub fn field_names(&self) -> Vec<String> {
+        let mut names = self.fields.keys().cloned().collect::<Vec<String>>();
+        names.sort();
+        names
+    }
+
+    /// The instance's fields, with each value replaced by what `copy` makes of it.
+    pub(crate) fn copy_fields<E>(
+        &self,
+        mut copy: impl FnMut(&Value) -> Result<Value, E>,
+    ) -> Result<HashMap<String, Value>, E> {
+        self.fields
+            .iter()
+            .map(|(name, value)| Ok((name.clone(), copy(value)?)))
+            .collect()
+    }
+
+    pub(crate) fn fields(&self) -> &HashMap<String, Value> {
+        &self.fields
+    }
+
+    pub(crate) fn set_fields(&mut self, fields: HashMap<String, Value>) {
+        self.fields = fields;
+    }
+
+    pub(crate) fn class(&self) -> &ClassRef {
+        &self.class
+    }
+
     pub fn set(&mut self, name: &Token, value: Value) {
         self.fields.insert(name.lexeme.clone(), value);
     }
@@ -0,0 +1,300 @@
+use std::cmp::Ordering;
+
+use crate::{
+    impls::list::new_list,
+    interpreter::Interpreter,
+    syntax::{token::Token, value::Value},
+    Exception,
+};
+
+// languages written in the Latin alphabet whose order the built-in collation gets right: the
+// common one, or one of the tailorings below
+#[cfg(not(feature = "icu"))]
+const LANGUAGES: &[&str] = &[
+    "ca", "da", "de", "en", "es", "fi", "fr", "ga", "id", "it", "ms", "nb", "nl", "nn", "no", "pt",
+    "sv", "sw",
+];
+
+// Latin letters and their accented variants
+#[cfg(not(feature = "icu"))]
+const VARIANTS: &[(char, &str)] = &[
+    ('a', "àáâãäåāăą"),
+    ('c', "çćč"),
+    ('d', "ď"),
+    ('e', "èéêëēęě"),
+    ('g', "ğ"),
+    ('i', "ìíîïīı"),
+    ('l', "ł"),
+    ('n', "ñńň"),
+    ('o', "òóôõöøōő"),
+    ('r', "ř"),
+    ('s', "śşš"),
+    ('t', "ť"),
+    ('u', "ùúûüūůű"),
+    ('y', "ýÿ"),
+    ('z', "źżž"),
+];
+
+#[cfg(not(feature = "icu"))]
+// letters that some languages sort as letters of their own, each right after the letter paired
+// with it, in the order they're listed
+const TAILORINGS: &[(&str, &[(char, char)])] = &[
+    ("da", &[('æ', 'z'), ('ø', 'z'), ('å', 'z')]),
+    ("nb", &[('æ', 'z'), ('ø', 'z'), ('å', 'z')]),
+    ("nn", &[('æ', 'z'), ('ø', 'z'), ('å', 'z')]),
+    ("no", &[('æ', 'z'), ('ø', 'z'), ('å', 'z')]),
+    ("sv", &[('å', 'z'), ('ä', 'z'), ('ö', 'z')]),
+    ("fi", &[('å', 'z'), ('ä', 'z'), ('ö', 'z')]),
+    ("es", &[('ñ', 'n')]),
+];
+
+/// Orders strings the way a dictionary in some language would, rather than by code point: case
+/// and accents only break ties between otherwise equal strings, so `"apple" < "Banana" <
+/// "cherry"` and `"resume" < "résumé" < "rope"`. Locales are language tags such as `"sv"` or
+/// `"es-MX"`, and the empty one sorts in the common order of every script.
+///
+/// With the `icu` feature this is the Unicode Collation Algorithm, using ICU4X's data for the
+/// tailorings of every language it knows, whatever its script. Without it, a small stand-in
+/// covers a fixed list of languages written in the Latin alphabet: Danish, Norwegian, Swedish
+/// and Finnish treat `å`, `æ`, `ä`, `ø` and `ö` as letters of their own after `z`, Spanish puts
+/// `ñ` after `n`, and the rest share the common Latin order. Characters it doesn't know, such as
+/// Cyrillic or Greek letters, sort by code point after the Latin ones. Either way, locales for
+/// other languages are rejected rather than quietly sorted in some other language's order.
+pub struct Collator {
+    #[cfg(feature = "icu")]
+    collator: icu_collator::Collator,
+    #[cfg(not(feature = "icu"))]
+    tailoring: &'static [(char, char)],
+}
+
+// how one string sorts: by its letters, then their accents, then their case
+#[cfg(not(feature = "icu"))]
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct SortKey {
+    letters: Vec<u64>,
+    accents: Vec<u32>,
+    cases: Vec<bool>,
+}
+
+impl Collator {
+    /// The collation for `locale`, or an error saying why there's none for it.
+    #[cfg(feature = "icu")]
+    pub fn new(locale: &str) -> Result<Collator, String> {
+        use icu_collator::CollatorOptions;
+        use icu_locid::Locale;
+        use icu_locid_transform::{LocaleExpander, TransformResult};
+
+        let unsupported = || format!("Unsupported locale '{locale}'.");
+        let parsed = match locale {
+            "" => Locale::UND,
+            _ => Locale::try_from_bytes(locale.replace('_', "-").as_bytes())
+                .map_err(|_| unsupported())?,
+        };
+        // a language with no likely script or region is one the Unicode data doesn't know
+        let mut language = Locale::UND;
+        language.id.language = parsed.id.language;
+        if LocaleExpander::new().maximize(&mut language) == TransformResult::Unmodified {
+            return Err(unsupported());
+        }
+
+        let collator = icu_collator::Collator::try_new(&(&parsed).into(), CollatorOptions::new())
+            .map_err(|_| unsupported())?;
+        Ok(Collator { collator })
+    }
+
+    /// The collation for `locale`, or an error saying why there's none for it.
+    #[cfg(not(feature = "icu"))]
+    pub fn new(locale: &str) -> Result<Collator, String> {
+        let language = locale.split(['-', '_']).next().unwrap_or("");
+        let known = LANGUAGES
+            .iter()
+            .any(|known| known.eq_ignore_ascii_case(language));
+        if !known && !locale.is_empty() {
+            return Err(format!(
+                "Unsupported locale '{locale}'; only Latin-script languages are supported without the icu feature."
+            ));
+        }
+
+        let tailoring = TAILORINGS
+            .iter()
+            .find(|(tag, _)| tag.eq_ignore_ascii_case(language))
+            .map_or(&[][..], |(_, tailoring)| tailoring);
+        Ok(Collator { tailoring })
+    }
+
+    /// How `left` sorts relative to `right`. Strings differing only in ways this collation
+    /// ignores fall back to code point order, so only identical strings are equal.
+    pub fn compare(&self, left: &str, right: &str) -> Ordering {
+        #[cfg(feature = "icu")]
+        let ordering = self.collator.compare(left, right);
+        #[cfg(not(feature = "icu"))]
+        let ordering = self.sort_key(left).cmp(&self.sort_key(right));
+        ordering.then_with(|| left.cmp(right))
+    }
+}
+
+#[cfg(not(feature = "icu"))]
+impl Collator {
+    fn sort_key(&self, string: &str) -> SortKey {
+        let mut key = SortKey {
+            letters: vec![],
+            accents: vec![],
+            cases: vec![],
+        };
+        for char in string.chars() {
+            let lower = char.to_lowercase().next().unwrap_or(char);
+            let (letter, accent) = self.letter(lower);
+            key.letters.push(letter);
+            key.accents.push(accent);
+            key.cases.push(lower != char);
+        }
+        key
+    }
+
+    // where a lowercase character sorts among letters, and among the variants of its letter
+    fn letter(&self, char: char) -> (u64, u32) {
+        let weight = |letter: char, position: usize| ((letter as u64) << 8) + position as u64;
+        if let Some(position) = self
+            .tailoring
+            .iter()
+            .position(|(tailored, _)| *tailored == char)
+        {
+            return (weight(self.tailoring[position].1, position + 1), 0);
+        }
+
+        match VARIANTS
+            .iter()
+            .find(|(_, variants)| variants.contains(char))
+        {
+            Some((letter, _)) => (weight(*letter, 0), char as u32),
+            None => (weight(char, 0), 0),
+        }
+    }
+}
+
+/// Defines `compareStrings` and `sortStrings`.
+pub(crate) fn define(interpreter: &mut Interpreter) {
+    interpreter.define_native(
+        "compareStrings",
+        3,
+        "compareStrings(a, b, locale) is -1, 0 or 1 as a sorts before, with or after b\n\
+         in the language of a locale like \"sv\", ignoring case and accents unless they're\n\
+         all that differ.",
+        |_, paren, args| match (&args[0], &args[1], &args[2]) {
+            (Value::String(left), Value::String(right), Value::String(locale)) => {
+                match Collator::new(locale) {
+                    Ok(collator) => Ok(Value::Int(collator.compare(left, right) as i64)),
+                    Err(message) => Exception::runtime_error(paren.clone(), message),
+                }
+            }
+            _ => Exception::runtime_error(
+                paren.clone(),
+                String::from("compareStrings() expects two strings and a locale string."),
+            ),
+        },
+    );
+    interpreter.define_native(
+        "sortStrings",
+        2,
+        "sortStrings(list, locale) is a new list of the strings in a list, sorted as\n\
+         compareStrings() orders them.",
+        |_, paren, args| sort(paren, &args[0], &args[1]),
+    );
+}
+
+fn sort(paren: &Token, list: &Value, locale: &Value) -> Result<Value, Exception> {
+    let (Value::List(list), Value::String(locale)) = (list, locale) else {
+        return expects_strings(paren);
+    };
+
+    let mut strings = vec![];
+    for item in list.borrow().iter() {
+        match item {
+            Value::String(string) => strings.push(string.clone()),
+            _ => return expects_strings(paren),
+        }
+    }
+
+    let collator = match Collator::new(locale) {
+        Ok(collator) => collator,
+        Err(message) => return Exception::runtime_error(paren.clone(), message),
+    };
+    strings.sort_by(|left, right| collator.compare(left, right));
+    Ok(Value::List(new_list(
+        strings.into_iter().map(Value::String).collect(),
+    )))
+}
+
+fn expects_strings<T>(paren: &Token) -> Result<T, Exception> {
+    Exception::runtime_error(
+        paren.clone(),
+        String::from("sortStrings() expects a list of strings and a locale string."),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(locale: &str, words: &[&str]) -> Vec<String> {
+        let collator = Collator::new(locale).unwrap();
+        let mut words = words
+            .iter()
+            .map(|word| word.to_string())
+            .collect::<Vec<String>>();
+        words.sort_by(|left, right| collator.compare(left, right));
+        words
+    }
+
+    #[test]
+    fn case_and_accents_only_break_ties() {
+        assert_eq!(
+            sorted("en", &["cherry", "Banana", "apple", "Apple"]),
+            ["apple", "Apple", "Banana", "cherry"]
+        );
+        assert_eq!(
+            sorted("de", &["rope", "résumé", "resume", "Äpfel", "Zebra"]),
+            ["Äpfel", "resume", "résumé", "rope", "Zebra"]
+        );
+        assert_eq!(
+            Collator::new("").unwrap().compare("same", "same"),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn some_languages_have_letters_of_their_own() {
+        let words = ["ö", "z", "å", "ä", "a"];
+        assert_eq!(sorted("sv-SE", &words), ["a", "z", "å", "ä", "ö"]);
+        assert_eq!(sorted("en_US", &["ö", "z", "å", "a"]), ["a", "å", "ö", "z"]);
+        assert_eq!(sorted("da", &["ø", "å", "æ"]), ["æ", "ø", "å"]);
+        assert_eq!(sorted("es", &["ñu", "oso", "nube"]), ["nube", "ñu", "oso"]);
+    }
+
+    #[test]
+    fn unknown_languages_are_rejected() {
+        for locale in ["xx-nonsense", "zz", "-"] {
+            assert!(Collator::new(locale).is_err(), "{locale}");
+        }
+    }
+
+    #[cfg(feature = "icu")]
+    #[test]
+    fn other_scripts_sort_by_their_alphabets() {
+        assert_eq!(
+            sorted("ru", &["яблоко", "Банан", "ель", "арбуз", "ёж"]),
+            ["арбуз", "Банан", "ёж", "ель", "яблоко"]
+        );
+        assert_eq!(
+            sorted("el", &["ωμέγα", "Άλφα", "βήτα"]),
+            ["Άλφα", "βήτα", "ωμέγα"]
+        );
+    }
+
+    #[cfg(not(feature = "icu"))]
+    #[test]
+    fn other_scripts_need_the_icu_feature() {
+        assert!(Collator::new("ru").is_err());
+        assert!(Collator::new("ja-JP").is_err());
+    }
+}
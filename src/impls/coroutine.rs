@@ -0,0 +1,127 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use crate::{
+    fiber::{Fiber, Suspension},
+    impls::{callable::Callable, function::Function, methods::BuiltinMethod},
+    interpreter::Interpreter,
+    syntax::{token::Token, value::Value},
+    Exception,
+};
+
+/// Coroutines are shared by reference, so every copy resumes the same body.
+pub type CoroutineRef = Rc<RefCell<Coroutine>>;
+
+enum State {
+    // not started yet, waiting for the arguments of the first resume
+    Created,
+    Suspended(Fiber),
+    Running,
+    Dead,
+}
+
+/// A function body that can suspend itself at a `yield`, handing a value back to whoever resumed
+/// it and carrying on from there when it's resumed again, like Lua's coroutines.
+pub struct Coroutine {
+    function: Function,
+    state: State,
+}
+
+impl Coroutine {
+    pub fn new(function: Function) -> CoroutineRef {
+        Rc::new(RefCell::new(Coroutine {
+            function,
+            state: State::Created,
+        }))
+    }
+
+    pub fn status(&self) -> &'static str {
+        match self.state {
+            State::Created | State::Suspended(_) => "suspended",
+            State::Running => "running",
+            State::Dead => "dead",
+        }
+    }
+
+    /// Runs the coroutine until it yields or returns, evaluating to the value it handed back.
+    /// The first resume passes its arguments to the function's parameters; later ones pass their
+    /// argument, if any, to the suspended `yield`.
+    fn resume(
+        coroutine: &CoroutineRef,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        args: Vec<Value>,
+    ) -> Result<Value, Exception> {
+        let state = std::mem::replace(&mut coroutine.borrow_mut().state, State::Running);
+        let (mut fiber, sent) = match state {
+            State::Created => {
+                let function = coroutine.borrow().function.clone();
+                if let Err(exception) = function.check_arity(args.len(), paren) {
+                    coroutine.borrow_mut().state = State::Created;
+                    return Err(exception);
+                }
+                (
+                    Fiber::new(function.body(), function.bind_arguments(interpreter, args)),
+                    Value::Nil,
+                )
+            }
+            State::Suspended(fiber) => (fiber, args.into_iter().next().unwrap_or(Value::Nil)),
+            state => {
+                let message = match state {
+                    State::Running => "Cannot resume a running coroutine.",
+                    _ => "Cannot resume a dead coroutine.",
+                };
+                coroutine.borrow_mut().state = state;
+                return Exception::runtime_error(paren.clone(), String::from(message));
+            }
+        };
+
+        let (state, result) = match interpreter
+            .enter_call(paren)
+            .and_then(|mut interpreter| fiber.resume(&mut interpreter, sent, u64::MAX))
+        {
+            Ok(Suspension::Yielded(value)) => (State::Suspended(fiber), Ok(value)),
+            Ok(Suspension::Finished(value)) => (State::Dead, Ok(value)),
+            Ok(Suspension::OutOfSteps) => panic!("Coroutine ran out of steps!"),
+            Err(exception) => (State::Dead, Err(exception)),
+        };
+        coroutine.borrow_mut().state = state;
+        result
+    }
+}
+
+pub(crate) const METHODS: &[BuiltinMethod] = &[
+    BuiltinMethod {
+        name: "resume",
+        arity: 0,
+        variadic: true,
+        doc:
+            "Runs the coroutine until it yields or returns, evaluating to the value it hands back.",
+        callable: |interpreter, paren, mut args| {
+            let coroutine = receiver(&mut args);
+            Coroutine::resume(&coroutine, interpreter, paren, args)
+        },
+    },
+    BuiltinMethod {
+        name: "status",
+        arity: 0,
+        variadic: false,
+        doc: "Whether the coroutine is \"suspended\", \"running\" or \"dead\".",
+        callable: |_, _, mut args| {
+            let status = receiver(&mut args).borrow().status();
+            Ok(Value::String(status.into()))
+        },
+    },
+];
+
+fn receiver(args: &mut Vec<Value>) -> CoroutineRef {
+    match args.remove(0) {
+        Value::Coroutine(coroutine) => coroutine,
+        _ => panic!("Coroutine method was not bound to a coroutine!"),
+    }
+}
+
+impl fmt::Debug for Coroutine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<coroutine {}>", self.status())
+    }
+}
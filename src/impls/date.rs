@@ -0,0 +1,355 @@
+use crate::{
+    impls::list::new_list,
+    interpreter::Interpreter,
+    syntax::{token::Token, value::Value},
+    Exception,
+};
+
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+// timestamps reach 100 million days either side of the epoch, as in JavaScript, which keeps
+// every date's fields, and the arithmetic for them, well within an i64
+const MAX_TIMESTAMP: i64 = 100_000_000 * MILLIS_PER_DAY;
+
+const WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// A moment in UTC, broken into the fields of the proleptic Gregorian calendar. Timestamps are
+/// milliseconds since the Unix epoch, as `clock()` returns them. There's no time zone database
+/// to read local time from, so everything is in UTC.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: i64,
+    pub month: i64,
+    pub day: i64,
+    pub hour: i64,
+    pub minute: i64,
+    pub second: i64,
+    pub millisecond: i64,
+}
+
+impl DateTime {
+    pub fn from_millis(timestamp: i64) -> DateTime {
+        let (days, millis) = (
+            timestamp.div_euclid(MILLIS_PER_DAY),
+            timestamp.rem_euclid(MILLIS_PER_DAY),
+        );
+        let (year, month, day) = civil_from_days(days);
+        DateTime {
+            year,
+            month,
+            day,
+            hour: millis / 3_600_000,
+            minute: millis / 60_000 % 60,
+            second: millis / 1000 % 60,
+            millisecond: millis % 1000,
+        }
+    }
+
+    pub fn to_millis(self) -> i64 {
+        days_from_civil(self.year, self.month, self.day) * MILLIS_PER_DAY
+            + self.hour * 3_600_000
+            + self.minute * 60_000
+            + self.second * 1000
+            + self.millisecond
+    }
+
+    /// The day of the week, from 0 for Sunday to 6 for Saturday.
+    pub fn weekday(self) -> i64 {
+        // the epoch was a Thursday
+        (days_from_civil(self.year, self.month, self.day) + 4).rem_euclid(7)
+    }
+
+    /// The date written out as `format` describes, strftime-style: `%Y` is the year, `%m`,
+    /// `%d`, `%H`, `%M` and `%S` the two-digit month, day, hour, minute and second, `%L` the
+    /// three-digit millisecond, `%a` and `%A` the short and full weekday name, `%b` and `%B` the
+    /// short and full month name, and `%%` a percent sign. Anything else is copied as it is.
+    pub fn format(self, format: &str) -> Result<String, String> {
+        let mut formatted = String::new();
+        let mut chars = format.chars();
+        while let Some(char) = chars.next() {
+            if char != '%' {
+                formatted.push(char);
+                continue;
+            }
+
+            let weekday = WEEKDAYS[self.weekday() as usize];
+            let month = MONTHS[self.month as usize - 1];
+            match chars.next() {
+                Some('Y') => formatted.push_str(&format!("{:04}", self.year)),
+                Some('m') => formatted.push_str(&format!("{:02}", self.month)),
+                Some('d') => formatted.push_str(&format!("{:02}", self.day)),
+                Some('H') => formatted.push_str(&format!("{:02}", self.hour)),
+                Some('M') => formatted.push_str(&format!("{:02}", self.minute)),
+                Some('S') => formatted.push_str(&format!("{:02}", self.second)),
+                Some('L') => formatted.push_str(&format!("{:03}", self.millisecond)),
+                Some('a') => formatted.push_str(&weekday[..3]),
+                Some('A') => formatted.push_str(weekday),
+                Some('b') => formatted.push_str(&month[..3]),
+                Some('B') => formatted.push_str(month),
+                Some('%') => formatted.push('%'),
+                Some(other) => return Err(format!("Unknown format specifier '%{other}'.")),
+                None => return Err(String::from("Format ends in a lone '%'.")),
+            }
+        }
+        Ok(formatted)
+    }
+
+    /// Reads a date written as `format` describes, with the same specifiers as
+    /// [`DateTime::format`] except the names of days and months. Fields the format leaves out
+    /// are those of midnight on the 1st of January 1970.
+    pub fn parse(text: &str, format: &str) -> Result<DateTime, String> {
+        let mut date = DateTime::from_millis(0);
+        let mut rest = text;
+        let mut chars = format.chars();
+        let mismatch = || format!("'{text}' doesn't match the format '{format}'.");
+        while let Some(char) = chars.next() {
+            if char != '%' {
+                rest = rest.strip_prefix(char).ok_or_else(mismatch)?;
+                continue;
+            }
+
+            let (field, width) = match chars.next() {
+                Some('Y') => (&mut date.year, 4),
+                Some('m') => (&mut date.month, 2),
+                Some('d') => (&mut date.day, 2),
+                Some('H') => (&mut date.hour, 2),
+                Some('M') => (&mut date.minute, 2),
+                Some('S') => (&mut date.second, 2),
+                Some('L') => (&mut date.millisecond, 3),
+                Some('%') => {
+                    rest = rest.strip_prefix('%').ok_or_else(mismatch)?;
+                    continue;
+                }
+                Some(other) => return Err(format!("Can't parse the format specifier '%{other}'.")),
+                None => return Err(String::from("Format ends in a lone '%'.")),
+            };
+
+            let digits = rest
+                .char_indices()
+                .take(width)
+                .take_while(|(_, char)| char.is_ascii_digit())
+                .count();
+            if digits != width {
+                return Err(mismatch());
+            }
+            *field = rest[..digits].parse().map_err(|_| mismatch())?;
+            rest = &rest[digits..];
+        }
+
+        if !rest.is_empty() {
+            return Err(mismatch());
+        }
+        match date.is_valid() {
+            true => Ok(date),
+            false => Err(format!("'{text}' is not a valid date.")),
+        }
+    }
+
+    fn is_valid(self) -> bool {
+        (1..=12).contains(&self.month)
+            && (1..=days_in_month(self.year, self.month)).contains(&self.day)
+            && self.hour < 24
+            && self.minute < 60
+            && self.second < 60
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// days since the epoch of a date, counting in 400-year eras of 146097 days that start in March
+// so leap days fall at the end of each year
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+// the date `days` after the epoch, the inverse of `days_from_civil`
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = (shifted_month + 2) % 12 + 1;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Defines `dateParts`, `formatTime` and `parseTime`.
+pub(crate) fn define(interpreter: &mut Interpreter) {
+    interpreter.define_native(
+        "dateParts",
+        1,
+        "dateParts(timestamp) is a list of the year, month, day, hour, minute, second,\n\
+         millisecond and weekday (0 for Sunday) of a timestamp in milliseconds, in UTC.",
+        |_, paren, args| {
+            let date = DateTime::from_millis(timestamp(paren, "dateParts", &args[0])?);
+            let parts = [
+                date.year,
+                date.month,
+                date.day,
+                date.hour,
+                date.minute,
+                date.second,
+                date.millisecond,
+                date.weekday(),
+            ];
+            Ok(Value::List(new_list(parts.map(Value::Int).to_vec())))
+        },
+    );
+    interpreter.define_native(
+        "formatTime",
+        2,
+        "formatTime(timestamp, format) writes out a timestamp in milliseconds in UTC,\n\
+         with %Y, %m, %d, %H, %M, %S and %L for its fields, as in \"%Y-%m-%d\".",
+        |_, paren, args| {
+            let date = DateTime::from_millis(timestamp(paren, "formatTime", &args[0])?);
+            let Value::String(format) = &args[1] else {
+                return Exception::runtime_error(
+                    paren.clone(),
+                    String::from("formatTime() expects a timestamp and a format string."),
+                );
+            };
+            match date.format(format) {
+                Ok(formatted) => Ok(Value::String(formatted.into())),
+                Err(message) => Exception::runtime_error(paren.clone(), message),
+            }
+        },
+    );
+    interpreter.define_native(
+        "parseTime",
+        2,
+        "parseTime(string, format) is the timestamp in milliseconds of a UTC date written\n\
+         as formatTime() writes it with the same format.",
+        |_, paren, args| match (&args[0], &args[1]) {
+            (Value::String(text), Value::String(format)) => match DateTime::parse(text, format) {
+                Ok(date) => Ok(Value::Int(date.to_millis())),
+                Err(message) => Exception::runtime_error(paren.clone(), message),
+            },
+            _ => Exception::runtime_error(
+                paren.clone(),
+                String::from("parseTime() expects a date string and a format string."),
+            ),
+        },
+    );
+}
+
+// a timestamp argument as whole milliseconds
+fn timestamp(paren: &Token, name: &str, value: &Value) -> Result<i64, Exception> {
+    match value.as_f64() {
+        Some(timestamp) if timestamp.abs() <= MAX_TIMESTAMP as f64 => Ok(timestamp.floor() as i64),
+        Some(_) => Exception::runtime_error(
+            paren.clone(),
+            format!("{name}() expects a timestamp within 100 million days of 1970."),
+        ),
+        None => Exception::runtime_error(
+            paren.clone(),
+            format!("{name}() expects a timestamp in milliseconds."),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_round_trip_through_dates() {
+        let date = DateTime::from_millis(951_827_696_789);
+        assert_eq!(
+            date,
+            DateTime {
+                year: 2000,
+                month: 2,
+                day: 29,
+                hour: 12,
+                minute: 34,
+                second: 56,
+                millisecond: 789,
+            }
+        );
+        assert_eq!(date.weekday(), 2);
+        assert_eq!(date.to_millis(), 951_827_696_789);
+
+        let before_epoch = DateTime::from_millis(-1);
+        assert_eq!(
+            (before_epoch.year, before_epoch.month, before_epoch.day),
+            (1969, 12, 31)
+        );
+        assert_eq!(before_epoch.millisecond, 999);
+        assert_eq!(before_epoch.to_millis(), -1);
+    }
+
+    #[test]
+    fn the_furthest_timestamps_have_dates() {
+        let first = DateTime::from_millis(-MAX_TIMESTAMP);
+        assert_eq!((first.year, first.month, first.day), (-271821, 4, 20));
+        let last = DateTime::from_millis(MAX_TIMESTAMP);
+        assert_eq!((last.year, last.month, last.day), (275760, 9, 13));
+        assert_eq!(last.to_millis(), MAX_TIMESTAMP);
+    }
+
+    #[test]
+    fn formats_and_parses_fields() {
+        let date = DateTime::from_millis(951_827_696_789);
+        assert_eq!(
+            date.format("%a %d %b %Y %H:%M:%S.%L 100%%").unwrap(),
+            "Tue 29 Feb 2000 12:34:56.789 100%"
+        );
+        assert_eq!(date.format("%A, %B").unwrap(), "Tuesday, February");
+        assert!(date.format("%q").is_err());
+
+        assert_eq!(
+            DateTime::parse("2000-02-29 12:34:56.789", "%Y-%m-%d %H:%M:%S.%L"),
+            Ok(date)
+        );
+        assert_eq!(
+            DateTime::parse("1999-12-31", "%Y-%m-%d").map(|date| date.to_millis()),
+            Ok(946_598_400_000)
+        );
+        assert!(DateTime::parse("1999-02-29", "%Y-%m-%d").is_err());
+        assert!(DateTime::parse("1999-1-31", "%Y-%m-%d").is_err());
+        assert!(DateTime::parse("1999-12-31 extra", "%Y-%m-%d").is_err());
+    }
+}
@@ -0,0 +1,533 @@
+use std::{cmp::Ordering, fmt::Display};
+
+/// The most digits a decimal keeps after its point. Products round to this many, and quotients
+/// that don't come out exact are carried to it.
+pub const MAX_SCALE: u32 = 18;
+
+// powers are worked out with this many limbs of nine digits after the point, far past
+// MAX_SCALE, so rounding the intermediate products can't reach the digits that are kept
+const POWER_FRACTION_LIMBS: usize = 7;
+const LIMB: u64 = 1_000_000_000;
+
+/// An exact decimal number, for sums of money and anything else binary floats round. It's
+/// `units` hundredths when `scale` is 2, thousandths when it's 3, and so on. The scale is kept
+/// as written, so `decimal("0.10")` prints as `0.10`, and sums keep the larger scale of the two
+/// sides. Decimals hold about 38 significant digits, and arithmetic that needs more fails rather
+/// than rounding.
+#[derive(Clone, Copy, Debug)]
+pub struct Decimal {
+    units: i128,
+    scale: u32,
+}
+
+impl Decimal {
+    pub fn from_i64(value: i64) -> Decimal {
+        Decimal {
+            units: value as i128,
+            scale: 0,
+        }
+    }
+
+    /// Reads a decimal written out in digits, like `-12.50`, or with an exponent, like `1.5e3`,
+    /// or None if it isn't one or has more than [`MAX_SCALE`] digits after its point. An exponent
+    /// moves the point, so `2.50e1` is `25.0` and `1e-3` is `0.001`.
+    pub fn parse(string: &str) -> Option<Decimal> {
+        let (string, exponent) = match string.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => {
+                let digits = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+                if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+                    return None;
+                }
+                (mantissa, exponent.parse::<i32>().ok()?)
+            }
+            None => (string, 0),
+        };
+        let (whole, fraction) = string.split_once('.').unwrap_or((string, ""));
+        let digits = whole.strip_prefix('-').unwrap_or(whole);
+        let is_digits = |part: &str| part.bytes().all(|byte| byte.is_ascii_digit());
+        if digits.is_empty() || !is_digits(digits) || !is_digits(fraction) {
+            return None;
+        }
+        if string.contains('.') && fraction.is_empty() {
+            return None;
+        }
+
+        let units: i128 = format!("{whole}{fraction}").parse().ok()?;
+        let scale = fraction.len() as i64 - i64::from(exponent);
+        match u32::try_from(scale) {
+            Ok(scale) if scale <= MAX_SCALE => Some(Decimal { units, scale }),
+            Ok(_) => None,
+            // the point moves past the last digit, adding zeros
+            Err(_) => Some(Decimal {
+                units: units.checked_mul(10i128.checked_pow(u32::try_from(-scale).ok()?)?)?,
+                scale: 0,
+            }),
+        }
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.units as f64 / 10f64.powi(self.scale as i32)
+    }
+
+    /// The decimal as an integer, if it has nothing after its point.
+    pub fn to_i64(self) -> Option<i64> {
+        match self.units % power(self.scale) {
+            0 => i64::try_from(self.units / power(self.scale)).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn checked_add(self, other: Decimal) -> Option<Decimal> {
+        let scale = self.scale.max(other.scale);
+        let units = self
+            .rescale(scale)?
+            .units
+            .checked_add(other.rescale(scale)?.units)?;
+        Some(Decimal { units, scale })
+    }
+
+    pub fn checked_sub(self, other: Decimal) -> Option<Decimal> {
+        self.checked_add(other.checked_neg()?)
+    }
+
+    pub fn checked_mul(self, other: Decimal) -> Option<Decimal> {
+        let scale = self.scale + other.scale;
+        match scale > MAX_SCALE {
+            // the product is rounded to fit, so only the rounded one has to fit in an i128
+            true => Some(Decimal {
+                units: mul_rounded(self.units, other.units, scale - MAX_SCALE)?,
+                scale: MAX_SCALE,
+            }),
+            false => Some(Decimal {
+                units: self.units.checked_mul(other.units)?,
+                scale,
+            }),
+        }
+    }
+
+    /// The decimal raised to a whole power, rounded to [`MAX_SCALE`] digits once at the end
+    /// rather than after each product, or None if the power is too big.
+    pub fn checked_pow(self, exponent: u64) -> Option<Decimal> {
+        // as many digits after the point as multiplying would keep, if they're needed
+        let scale = u64::from(self.scale)
+            .saturating_mul(exponent)
+            .min(u64::from(MAX_SCALE)) as u32;
+        let mut exponent = exponent;
+        // the digits of the power, and of the base squared over and over, times 10^63
+        let fraction_digits = POWER_FRACTION_LIMBS * 9;
+        let digits = format!(
+            "{}{}",
+            self.units.unsigned_abs(),
+            "0".repeat(fraction_digits - self.scale as usize)
+        );
+        let mut square = to_limbs(&digits);
+        let mut power = to_limbs(&format!("1{}", "0".repeat(fraction_digits)));
+        // the power is only ever bigger than the squares it's made of when they're over one,
+        // so squares past what fits can only make a power that doesn't fit
+        let max_limbs = POWER_FRACTION_LIMBS + 3;
+        let negative = self.units < 0 && exponent % 2 == 1;
+        while exponent > 0 {
+            if exponent % 2 == 1 {
+                power = mul_limbs(&power, &square);
+            }
+            exponent /= 2;
+            if exponent > 0 {
+                square = mul_limbs(&square, &square);
+            }
+            if power.len() > max_limbs || (exponent > 0 && square.len() > max_limbs) {
+                return None;
+            }
+        }
+
+        // rounded from the first digit past MAX_SCALE, which decides a rounding half away
+        // from zero
+        let digits = from_limbs(&power);
+        let digits = format!("{digits:0>width$}", width = fraction_digits + 1);
+        let kept = digits.len() - (fraction_digits - MAX_SCALE as usize);
+        let mut units: i128 = digits[..kept].parse().ok()?;
+        if digits.as_bytes()[kept] >= b'5' {
+            units = units.checked_add(1)?;
+        }
+        let power = Decimal {
+            units: if negative { -units } else { units },
+            scale: MAX_SCALE,
+        };
+        Some(power.trimmed(scale))
+    }
+
+    /// The quotient, rounded to [`MAX_SCALE`] digits if it doesn't come out exact, or None when
+    /// dividing by zero or the quotient is too big.
+    pub fn checked_div(self, other: Decimal) -> Option<Decimal> {
+        if other.units == 0 {
+            return None;
+        }
+
+        // scaled up so the quotient has MAX_SCALE digits after its point
+        let dividend = self
+            .units
+            .checked_mul(power(MAX_SCALE + other.scale - self.scale))?;
+        let quotient = Decimal::rounded(dividend, other.units, MAX_SCALE);
+        Some(quotient.trimmed(self.scale.max(other.scale)))
+    }
+
+    pub fn checked_neg(self) -> Option<Decimal> {
+        Some(Decimal {
+            units: self.units.checked_neg()?,
+            scale: self.scale,
+        })
+    }
+
+    pub fn checked_abs(self) -> Option<Decimal> {
+        Some(Decimal {
+            units: self.units.checked_abs()?,
+            scale: self.scale,
+        })
+    }
+
+    /// The largest whole number less than or equal to the decimal.
+    pub fn floor(self) -> Decimal {
+        let whole = self.units.div_euclid(power(self.scale));
+        Decimal::from_units(whole)
+    }
+
+    /// The smallest whole number greater than or equal to the decimal.
+    pub fn ceil(self) -> Decimal {
+        let whole = -(-self.units).div_euclid(power(self.scale));
+        Decimal::from_units(whole)
+    }
+
+    /// The nearest whole number, rounding halves away from zero.
+    pub fn round(self) -> Decimal {
+        Decimal::rounded(self.units, power(self.scale), 0)
+    }
+
+    /// The decimal rounded to `places` digits after its point, rounding halves away from zero.
+    /// One with no more digits than that is left as it is.
+    pub fn round_to(self, places: u32) -> Decimal {
+        match places < self.scale {
+            true => Decimal::rounded(self.units, power(self.scale - places), places),
+            false => self,
+        }
+    }
+
+    fn from_units(units: i128) -> Decimal {
+        Decimal { units, scale: 0 }
+    }
+
+    // `units / divisor` rounded half away from zero, as a decimal of the given scale
+    fn rounded(units: i128, divisor: i128, scale: u32) -> Decimal {
+        let (quotient, remainder) = (units / divisor, units % divisor);
+        let half_or_more = remainder.unsigned_abs() * 2 >= divisor.unsigned_abs();
+        let away_from_zero = if (units < 0) != (divisor < 0) { -1 } else { 1 };
+        Decimal {
+            units: quotient + if half_or_more { away_from_zero } else { 0 },
+            scale,
+        }
+    }
+
+    // the same number with more digits after its point, if they fit
+    fn rescale(self, scale: u32) -> Option<Decimal> {
+        Some(Decimal {
+            units: self.units.checked_mul(power(scale - self.scale))?,
+            scale,
+        })
+    }
+
+    // the same number without trailing zeros, keeping at least `scale` digits after its point
+    fn trimmed(mut self, scale: u32) -> Decimal {
+        while self.scale > scale && self.units % 10 == 0 {
+            self.units /= 10;
+            self.scale -= 1;
+        }
+        self
+    }
+}
+
+fn power(exponent: u32) -> i128 {
+    10i128.pow(exponent)
+}
+
+// the nine-digit limbs of a number written in decimal digits, least significant first
+fn to_limbs(digits: &str) -> Vec<u64> {
+    let mut limbs = digits
+        .as_bytes()
+        .rchunks(9)
+        .map(|chunk| {
+            std::str::from_utf8(chunk)
+                .expect("digits to be ASCII")
+                .parse()
+                .expect("chunks to be digits")
+        })
+        .collect::<Vec<u64>>();
+    while limbs.last() == Some(&0) {
+        limbs.pop();
+    }
+    limbs
+}
+
+fn from_limbs(limbs: &[u64]) -> String {
+    let Some((most, rest)) = limbs.split_last() else {
+        return String::from("0");
+    };
+    let mut digits = most.to_string();
+    for limb in rest.iter().rev() {
+        digits.push_str(&format!("{limb:09}"));
+    }
+    digits
+}
+
+// the product of two numbers in limbs that are both times 10^63, as one that is too, rounding
+// away the low limbs
+fn mul_limbs(left: &[u64], right: &[u64]) -> Vec<u64> {
+    let mut product = vec![0u64; left.len() + right.len()];
+    for (i, left) in left.iter().enumerate() {
+        let mut carry = 0;
+        for (j, right) in right.iter().enumerate() {
+            let total = product[i + j] + left * right + carry;
+            product[i + j] = total % LIMB;
+            carry = total / LIMB;
+        }
+        product[i + right.len()] += carry;
+    }
+
+    let round_up = product
+        .get(POWER_FRACTION_LIMBS - 1)
+        .is_some_and(|limb| *limb >= LIMB / 2);
+    let mut limbs = product.split_off(POWER_FRACTION_LIMBS.min(product.len()));
+    let mut carry = u64::from(round_up);
+    for limb in limbs.iter_mut() {
+        *limb += carry;
+        carry = *limb / LIMB;
+        *limb %= LIMB;
+    }
+    if carry > 0 {
+        limbs.push(carry);
+    }
+    while limbs.last() == Some(&0) {
+        limbs.pop();
+    }
+    limbs
+}
+
+// `left * right / 10^places`, rounded half away from zero, if it fits in an i128. The product
+// is worked out in 256 bits, as four u64 limbs with the least significant first.
+fn mul_rounded(left: i128, right: i128, places: u32) -> Option<i128> {
+    let halves = |value: u128| [value as u64, (value >> 64) as u64];
+    let (left_halves, right_halves) = (halves(left.unsigned_abs()), halves(right.unsigned_abs()));
+    let mut limbs = [0u64; 4];
+    for (i, left) in left_halves.into_iter().enumerate() {
+        let mut carry = 0u128;
+        for (j, right) in right_halves.into_iter().enumerate() {
+            let total = limbs[i + j] as u128 + left as u128 * right as u128 + carry;
+            limbs[i + j] = total as u64;
+            carry = total >> 64;
+        }
+        limbs[i + 2] = carry as u64;
+    }
+
+    // dividing by ten the last time leaves the first digit dropped, which alone decides which
+    // way a rounding half away from zero goes
+    let mut dropped = 0;
+    for _ in 0..places {
+        let mut remainder = 0u128;
+        for limb in limbs.iter_mut().rev() {
+            let current = (remainder << 64) | *limb as u128;
+            *limb = (current / 10) as u64;
+            remainder = current % 10;
+        }
+        dropped = remainder;
+    }
+    if limbs[2] != 0 || limbs[3] != 0 {
+        return None;
+    }
+
+    let magnitude = (limbs[1] as u128) << 64 | limbs[0] as u128;
+    let magnitude = i128::try_from(magnitude).ok()?;
+    let magnitude = match dropped >= 5 {
+        true => magnitude.checked_add(1)?,
+        false => magnitude,
+    };
+    match (left < 0) != (right < 0) {
+        true => Some(-magnitude),
+        false => Some(magnitude),
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let scale = self.scale.max(other.scale);
+        match (self.rescale(scale), other.rescale(scale)) {
+            (Some(left), Some(right)) => left.units.cmp(&right.units),
+            // too big to line up, so the sides differ by more than the digits that don't fit
+            _ => self.to_f64().total_cmp(&other.to_f64()),
+        }
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.units < 0 { "-" } else { "" };
+        let scale = self.scale as usize;
+        let digits = format!("{:0>width$}", self.units.unsigned_abs(), width = scale + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - scale);
+        match fraction.is_empty() {
+            true => write!(f, "{sign}{whole}"),
+            false => write!(f, "{sign}{whole}.{fraction}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(string: &str) -> Decimal {
+        Decimal::parse(string).unwrap()
+    }
+
+    #[test]
+    fn parsing_keeps_the_scale() {
+        assert_eq!(decimal("0.10").to_string(), "0.10");
+        assert_eq!(decimal("-0.05").to_string(), "-0.05");
+        assert_eq!(decimal("12").to_string(), "12");
+        assert_eq!(decimal("0.10"), decimal("0.1"));
+        for invalid in [
+            "",
+            "-",
+            "1.",
+            ".5",
+            "1e",
+            "1e+",
+            "1.2e3.4",
+            "1.2.3",
+            " 1",
+            "0.0000000000000000001",
+            "1e-19",
+            "1e99",
+        ] {
+            assert!(Decimal::parse(invalid).is_none(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn exponents_move_the_point() {
+        assert_eq!(decimal("1e5").to_string(), "100000");
+        assert_eq!(decimal("2.50E1").to_string(), "25.0");
+        assert_eq!(decimal("1.5e+3").to_string(), "1500");
+        assert_eq!(decimal("-1.5e-3").to_string(), "-0.0015");
+        assert_eq!(decimal("1e-18").to_string(), "0.000000000000000001");
+    }
+
+    #[test]
+    fn arithmetic_is_exact() {
+        let sum = decimal("0.1").checked_add(decimal("0.2")).unwrap();
+        assert_eq!(sum, decimal("0.3"));
+        assert_eq!(
+            decimal("0.10")
+                .checked_add(decimal("0.2"))
+                .unwrap()
+                .to_string(),
+            "0.30"
+        );
+        assert_eq!(
+            decimal("19.99")
+                .checked_mul(decimal("3"))
+                .unwrap()
+                .to_string(),
+            "59.97"
+        );
+        assert_eq!(
+            decimal("1")
+                .checked_sub(decimal("1.5"))
+                .unwrap()
+                .to_string(),
+            "-0.5"
+        );
+        // rounded products only have to fit once they're rounded
+        assert_eq!(
+            decimal("123456789.123456789123")
+                .checked_mul(decimal("987654321.987654321987"))
+                .unwrap()
+                .to_string(),
+            "121932631356500531.590536501581968601"
+        );
+        assert_eq!(
+            decimal("-0.000000000000000005")
+                .checked_mul(decimal("0.1"))
+                .unwrap()
+                .to_string(),
+            "-0.000000000000000001"
+        );
+        assert!(Decimal::from_i64(i64::MAX)
+            .checked_mul(Decimal::from_i64(i64::MAX))
+            .unwrap()
+            .checked_mul(decimal("10"))
+            .is_none());
+    }
+
+    #[test]
+    fn powers_round_once() {
+        let pow = |base, exponent| decimal(base).checked_pow(exponent).map(|p| p.to_string());
+        assert_eq!(
+            pow("1.1", 100),
+            Some(String::from("13780.612339822270184118"))
+        );
+        assert_eq!(pow("1.1", 2), Some(String::from("1.21")));
+        assert_eq!(pow("2.0", 3), Some(String::from("8.000")));
+        assert_eq!(pow("-0.5", 3), Some(String::from("-0.125")));
+        assert_eq!(pow("0.5", 0), Some(String::from("1")));
+        assert_eq!(pow("0.5", 1000), Some(String::from("0.000000000000000000")));
+        assert_eq!(
+            pow("0.999999999", 1_000_000_000),
+            Some(String::from("0.367879440987502601"))
+        );
+        assert_eq!(pow("10", 20), Some(String::from("100000000000000000000")));
+        assert_eq!(pow("10", 21), None);
+        assert_eq!(pow("1.1", u64::MAX), None);
+    }
+
+    #[test]
+    fn quotients_round_only_when_inexact() {
+        let divide = |left, right| {
+            decimal(left)
+                .checked_div(decimal(right))
+                .map(|q| q.to_string())
+        };
+        assert_eq!(divide("1", "4"), Some(String::from("0.25")));
+        assert_eq!(divide("10.00", "4"), Some(String::from("2.50")));
+        assert_eq!(divide("2", "3"), Some(String::from("0.666666666666666667")));
+        assert_eq!(
+            divide("-2", "3"),
+            Some(String::from("-0.666666666666666667"))
+        );
+        assert_eq!(divide("1", "0.00"), None);
+    }
+
+    #[test]
+    fn rounding_to_whole_numbers() {
+        let rounded =
+            |string, rounding: fn(Decimal) -> Decimal| rounding(decimal(string)).to_string();
+        assert_eq!(rounded("2.5", Decimal::round), "3");
+        assert_eq!(rounded("-2.5", Decimal::round), "-3");
+        assert_eq!(rounded("-2.5", Decimal::floor), "-3");
+        assert_eq!(rounded("-2.5", Decimal::ceil), "-2");
+        assert_eq!(rounded("2.01", Decimal::ceil), "3");
+        assert_eq!(rounded("7", Decimal::floor), "7");
+        assert_eq!(decimal("3.00").to_i64(), Some(3));
+        assert_eq!(decimal("3.01").to_i64(), None);
+    }
+}
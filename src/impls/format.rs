@@ -0,0 +1,355 @@
+use crate::{
+    impls::function::NativeFunction,
+    interpreter::Interpreter,
+    syntax::{token::Token, value::Value},
+    Exception,
+};
+
+// the widest width or precision a placeholder may give, well past any table but short of
+// what would exhaust memory or Rust's own formatting limits
+const MAX_WIDTH: usize = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+/// How a `{:...}` placeholder lays out its value: aligned within at least `width` characters
+/// padded with `fill`, or a space, with numbers padded with zeros after their sign if `zero` is
+/// set, and numbers rounded to `precision` decimal places or other values cut to `precision`
+/// characters.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Spec {
+    fill: Option<char>,
+    align: Option<Align>,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Spec {
+    // what follows the ':' in a placeholder, like "<8", "*^9" or "08.2"
+    fn parse(spec: &str) -> Option<Spec> {
+        let align = |c: Option<char>| match c {
+            Some('<') => Some(Align::Left),
+            Some('>') => Some(Align::Right),
+            Some('^') => Some(Align::Center),
+            _ => None,
+        };
+        let mut chars = spec.chars();
+        let (first, second) = (chars.next(), chars.next());
+        // a fill character comes before an alignment, as in "0>5"
+        let (fill, align, mut rest) = match (first, align(second)) {
+            (Some(fill), Some(align)) => (Some(fill), Some(align), &spec[fill.len_utf8() + 1..]),
+            _ => match align(first) {
+                Some(align) => (None, Some(align), &spec[1..]),
+                None => (None, None, spec),
+            },
+        };
+        let mut parsed = Spec {
+            fill,
+            align,
+            ..Spec::default()
+        };
+        if let Some(after_zero) = rest.strip_prefix('0') {
+            parsed.zero = true;
+            rest = after_zero;
+        }
+
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if digits > 0 {
+            parsed.width = rest[..digits]
+                .parse()
+                .ok()
+                .filter(|width| *width <= MAX_WIDTH)?;
+        }
+        rest = &rest[digits..];
+        if let Some(precision) = rest.strip_prefix('.') {
+            parsed.precision = Some(
+                precision
+                    .parse()
+                    .ok()
+                    .filter(|precision| *precision <= MAX_WIDTH)?,
+            );
+            rest = "";
+        }
+
+        rest.is_empty().then_some(parsed)
+    }
+}
+
+/// Fills the `{}` placeholders in `template` with `args` in order, showing each as `display`
+/// does. `{{` and `}}` stand for braces, and a placeholder may give a fill character and an
+/// alignment (`<`, `>` or `^`), a `0` to pad numbers with zeros, a width and a precision, as in
+/// `{:>8.2}` or `{:*^9}`.
+pub(crate) fn format(
+    template: &str,
+    args: &[Value],
+    display: impl Fn(&Value) -> String,
+) -> Result<String, String> {
+    let mut formatted = String::with_capacity(template.len());
+    let mut args = args.iter();
+    let mut chars = template.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '{' if chars.next_if(|(_, c)| *c == '{').is_some() => formatted.push('{'),
+            '}' if chars.next_if(|(_, c)| *c == '}').is_some() => formatted.push('}'),
+            '}' => return Err(String::from("The format string has a '}' with no '{'.")),
+            '{' => {
+                let Some(end) = template[start..].find('}').map(|end| start + end) else {
+                    return Err(String::from("The format string has a '{' with no '}'."));
+                };
+                let placeholder = &template[start..=end];
+                let spec = match &template[start + 1..end] {
+                    "" => Some(Spec::default()),
+                    inside => inside.strip_prefix(':').and_then(Spec::parse),
+                };
+                let Some(spec) = spec else {
+                    return Err(format!("'{placeholder}' isn't a valid placeholder."));
+                };
+
+                let Some(arg) = args.next() else {
+                    return Err(String::from(
+                        "The format string has more placeholders than values.",
+                    ));
+                };
+                if spec.zero && arg.as_f64().is_none() {
+                    return Err(format!(
+                        "'{placeholder}' pads with zeros, which only numbers can be; \
+                         give a fill character instead, as in '{{:0>5}}'."
+                    ));
+                }
+                render(&mut formatted, arg, spec, &display);
+                while chars.next_if(|(index, _)| *index <= end).is_some() {}
+            }
+            c => formatted.push(c),
+        }
+    }
+
+    match args.len() {
+        0 => Ok(formatted),
+        _ => Err(String::from(
+            "The format string has fewer placeholders than values.",
+        )),
+    }
+}
+
+fn render(out: &mut String, value: &Value, spec: Spec, display: &impl Fn(&Value) -> String) {
+    let numeric = value.as_f64().is_some();
+    // integers and decimals are exact, so they're rounded in decimal rather than as floats
+    let text = match (spec.precision, value) {
+        (Some(precision), Value::Int(int)) => with_fraction(int.to_string(), precision),
+        #[cfg(feature = "bigint")]
+        (Some(precision), Value::BigInt(int)) => with_fraction(int.to_string(), precision),
+        (Some(precision), Value::Decimal(decimal)) => {
+            with_fraction(decimal.round_to(precision as u32).to_string(), precision)
+        }
+        (Some(precision), _) if numeric => {
+            format!("{:.*}", precision, value.as_f64().unwrap_or_default())
+        }
+        (Some(precision), _) => display(value).chars().take(precision).collect(),
+        (None, _) => display(value),
+    };
+
+    let padding = spec.width.saturating_sub(text.chars().count());
+    if spec.zero && numeric {
+        // the zeros go between the sign and the digits
+        let digits = match text.strip_prefix('-') {
+            Some(digits) => {
+                out.push('-');
+                digits
+            }
+            None => &text,
+        };
+        out.extend(std::iter::repeat_n('0', padding));
+        out.push_str(digits);
+        return;
+    }
+
+    let align = match (spec.align, numeric) {
+        (Some(align), _) => align,
+        (None, true) => Align::Right,
+        (None, false) => Align::Left,
+    };
+    let (before, after) = match align {
+        Align::Left => (0, padding),
+        Align::Right => (padding, 0),
+        Align::Center => (padding / 2, padding - padding / 2),
+    };
+    let fill = spec.fill.unwrap_or(' ');
+    out.extend(std::iter::repeat_n(fill, before));
+    out.push_str(&text);
+    out.extend(std::iter::repeat_n(fill, after));
+}
+
+// an exact number written out in digits, with zeros added to give `precision` decimal places
+fn with_fraction(mut digits: String, precision: usize) -> String {
+    let places = digits
+        .split_once('.')
+        .map_or(0, |(_, fraction)| fraction.len());
+    if places == 0 && precision > 0 {
+        digits.push('.');
+    }
+    digits.extend(std::iter::repeat_n('0', precision.saturating_sub(places)));
+    digits
+}
+
+/// Defines `format` and `printf`, which take a format string and any number of values.
+pub(crate) fn define(interpreter: &mut Interpreter) {
+    define_variadic(
+        interpreter,
+        "format",
+        "format(template, ...) fills each {} in a string with the next value, as in\n\
+         format(\"{:<8}|{:>6.2}\", name, price). A placeholder may give a fill character and\n\
+         an alignment (<, > or ^), as in {:*^9}, a 0 to pad numbers with zeros, a width and a\n\
+         precision; {{ and }} are braces.",
+        |interpreter, paren, args| {
+            format_template(interpreter, paren, "format", &args)
+                .map(|formatted| Value::String(formatted.into()))
+        },
+    );
+    define_variadic(
+        interpreter,
+        "printf",
+        "printf(template, ...) prints a line filled in as format(template, ...) fills it.",
+        |interpreter, paren, args| {
+            let formatted = format_template(interpreter, paren, "printf", &args)?;
+            interpreter.logger.print(format_args!("{formatted}"));
+            Ok(Value::Nil)
+        },
+    );
+}
+
+fn define_variadic(
+    interpreter: &mut Interpreter,
+    name: &str,
+    doc: &'static str,
+    callable: fn(&mut Interpreter, &Token, Vec<Value>) -> Result<Value, Exception>,
+) {
+    let native = NativeFunction {
+        doc: Some(doc),
+        variadic: true,
+        ..NativeFunction::new(1, callable)
+    };
+    interpreter.define_constant(name, Value::NativeFunction(native));
+}
+
+fn format_template(
+    interpreter: &Interpreter,
+    paren: &Token,
+    name: &str,
+    args: &[Value],
+) -> Result<String, Exception> {
+    let Value::String(template) = &args[0] else {
+        return Exception::runtime_error(
+            paren.clone(),
+            format!("{name}() expects a format string and the values to fill it with."),
+        );
+    };
+    format(template, &args[1..], |value| {
+        interpreter.semantics.display(value)
+    })
+    .or_else(|message| Exception::runtime_error(paren.clone(), message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::impls::decimal::Decimal;
+
+    fn format_values(template: &str, args: &[Value]) -> Result<String, String> {
+        format(template, args, |value| value.to_string())
+    }
+
+    #[test]
+    fn placeholders_take_width_and_precision() {
+        let args = [
+            Value::String("tea".into()),
+            Value::Number(2.456),
+            Value::Int(-7),
+        ];
+        assert_eq!(
+            format_values("{}: {} x{}", &args).unwrap(),
+            "tea: 2.456 x-7"
+        );
+        assert_eq!(
+            format_values("[{:6}|{:8.2}|{:05}]", &args).unwrap(),
+            "[tea   |    2.46|-0007]"
+        );
+        assert_eq!(
+            format_values("[{:>6}|{:<8.1}|{:^6.2}]", &args).unwrap(),
+            "[   tea|2.5     |-7.00 ]"
+        );
+        assert_eq!(
+            format_values("{{{:.2}}}", &[Value::String("long".into())]).unwrap(),
+            "{lo}"
+        );
+    }
+
+    #[test]
+    fn mismatched_templates_are_errors() {
+        let one = [Value::Int(1)];
+        assert!(format_values("{} {}", &one)
+            .unwrap_err()
+            .contains("more placeholders"));
+        assert!(format_values("none", &one)
+            .unwrap_err()
+            .contains("fewer placeholders"));
+        assert!(format_values("{", &one).unwrap_err().contains("no '}'"));
+        assert!(format_values("}", &one).unwrap_err().contains("no '{'"));
+        assert_eq!(
+            format_values("{:x}", &one).unwrap_err(),
+            "'{:x}' isn't a valid placeholder."
+        );
+        assert!(format_values("{:.}", &one).is_err());
+        assert!(format_values("{:1024.1024}", &one).is_ok());
+        assert!(format_values("{:1025}", &one).is_err());
+        assert!(format_values("{:.70000}", &one).is_err());
+    }
+
+    #[test]
+    fn fills_pad_anything_and_zeros_only_numbers() {
+        let word = [Value::String("ab".into())];
+        assert_eq!(format_values("{:*^6}", &word).unwrap(), "**ab**");
+        assert_eq!(format_values("{:0>5}", &word).unwrap(), "000ab");
+        assert_eq!(format_values("{:<<4}", &word).unwrap(), "ab<<");
+        assert_eq!(
+            format_values("{:05}", &word).unwrap_err(),
+            "'{:05}' pads with zeros, which only numbers can be; \
+             give a fill character instead, as in '{:0>5}'."
+        );
+        assert_eq!(
+            format_values("{:0>5}|{:05}", &[Value::Int(-7), Value::Int(-7)]).unwrap(),
+            "000-7|-0007"
+        );
+    }
+
+    #[test]
+    fn exact_numbers_round_exactly() {
+        let decimal = |text| Value::Decimal(Decimal::parse(text).unwrap());
+        assert_eq!(format_values("{:.2}", &[decimal("2.675")]).unwrap(), "2.68");
+        assert_eq!(
+            format_values("{:.1}", &[decimal("12345678901234567.15")]).unwrap(),
+            "12345678901234567.2"
+        );
+        assert_eq!(
+            format_values("{:.3}|{:.0}", &[decimal("-1.5"), decimal("-1.5")]).unwrap(),
+            "-1.500|-2"
+        );
+
+        #[cfg(feature = "bigint")]
+        {
+            use crate::impls::bigint::BigInt;
+            let big = BigInt::from_i64(i64::MAX).mul(&BigInt::from_i64(10));
+            assert_eq!(
+                format_values("{:.2}", &[Value::BigInt(std::rc::Rc::new(big))]).unwrap(),
+                "92233720368547758070.00"
+            );
+        }
+    }
+}
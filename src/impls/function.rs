@@ -1,30 +1,40 @@
+use std::rc::Rc;
+
 use crate::{
     environment::{EnvRef, Environment},
-    impls::class::ClassInstanceRef,
+    impls::{callable::Callable, class::ClassInstanceRef, list::new_list},
     interpreter::Interpreter,
     syntax::{stmt::Stmt, token::Token, value::Value},
     Exception,
 };
 
-pub trait Callable {
-    fn arity(&self) -> usize;
-    fn call(&self, interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, Exception>;
-    fn check_arity(&self, args_len: usize, current_token: &Token) -> Result<(), Exception> {
-        if args_len > self.arity() {
-            return Exception::runtime_error(
-                current_token.clone(),
-                format!("Expected {} arguments but got {}.", self.arity(), args_len),
-            );
-        }
-
-        Ok(())
-    }
-}
+pub(crate) type NativeFn = fn(&mut Interpreter, &Token, Vec<Value>) -> Result<Value, Exception>;
 
 #[derive(Clone, Debug)]
 pub struct NativeFunction {
-    pub arity: usize,
-    pub callable: fn(&mut Interpreter, Vec<Value>) -> Value,
+    pub(crate) arity: usize,
+    pub(crate) variadic: bool,
+    pub(crate) callable: NativeFn,
+    // a native method's object, passed to `callable` ahead of the other arguments
+    pub(crate) receiver: Option<Box<Value>>,
+    pub(crate) doc: Option<&'static str>,
+}
+
+impl NativeFunction {
+    pub(crate) fn new(arity: usize, callable: NativeFn) -> NativeFunction {
+        NativeFunction {
+            arity,
+            variadic: false,
+            callable,
+            receiver: None,
+            doc: None,
+        }
+    }
+
+    pub(crate) fn bind(mut self, receiver: Value) -> NativeFunction {
+        self.receiver = Some(Box::new(receiver));
+        self
+    }
 }
 
 impl Callable for NativeFunction {
@@ -32,68 +42,194 @@ impl Callable for NativeFunction {
         self.arity
     }
 
-    fn call(&self, interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, Exception> {
-        Ok((self.callable)(interpreter, args))
+    fn is_variadic(&self) -> bool {
+        self.variadic
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        mut args: Vec<Value>,
+    ) -> Result<Value, Exception> {
+        if let Some(receiver) = &self.receiver {
+            args.insert(0, receiver.as_ref().clone());
+        }
+        (self.callable)(interpreter, paren, args)
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct Function {
-    declaration: Stmt,
+    declaration: Rc<Stmt>,
     closure: EnvRef,
     is_initializer: bool,
+    // declared in a class or trait body, so it runs with "this" bound to an instance
+    is_method: bool,
 }
 
 impl Function {
     pub fn new(declaration: Stmt, closure: EnvRef, is_initializer: bool) -> Function {
         match &declaration {
             Stmt::Function { .. } => Function {
-                declaration,
+                declaration: Rc::new(declaration),
                 closure,
                 is_initializer,
+                is_method: false,
             },
             _ => panic!("Function was not initialized with a function declaration!"),
         }
     }
 
+    pub fn method(declaration: Stmt, closure: EnvRef, is_initializer: bool) -> Function {
+        Function {
+            is_method: true,
+            ..Function::new(declaration, closure, is_initializer)
+        }
+    }
+
+    /// Two function values are the same if they share a declaration and closure,
+    /// which also makes a method bound twice to the same instance compare unequal.
+    pub fn is_same(&self, other: &Function) -> bool {
+        Rc::ptr_eq(&self.declaration, &other.declaration)
+            && Rc::ptr_eq(&self.closure, &other.closure)
+    }
+
+    /// Binds "this" to `instance`. Plain functions patched onto a class have no "this" to bind,
+    /// so they are called as they are.
     pub fn bind(&self, instance: ClassInstanceRef) -> Function {
+        if !self.is_method {
+            return self.clone();
+        }
+
         let environment = Environment::new_local(&self.closure);
         environment
             .borrow_mut()
             .define(String::from("this"), Value::ClassInstance(instance));
 
-        Function::new(self.declaration.clone(), environment, self.is_initializer)
+        Function {
+            closure: environment,
+            ..self.clone()
+        }
+    }
+
+    /// The method a bound method was made from, so it can be bound to another instance.
+    /// Methods only reach Lox code bound, so any method can be unbound.
+    pub(crate) fn unbind(&self) -> Function {
+        let closure = match self.is_method {
+            true => self.closure.borrow().enclosing.clone(),
+            false => None,
+        };
+
+        match closure {
+            Some(closure) => Function {
+                closure,
+                ..self.clone()
+            },
+            None => self.clone(),
+        }
+    }
+}
+
+impl Function {
+    /// Creates the environment the body runs in, with the arguments bound to the parameters.
+    pub(crate) fn bind_arguments(&self, interpreter: &Interpreter, args: Vec<Value>) -> EnvRef {
+        let Stmt::Function { uid, .. } = self.declaration.as_ref() else {
+            panic!("Function was not initialized with a function declaration!");
+        };
+        let environment = Environment::new_frame(&self.closure, interpreter.shares_frame(*uid));
+
+        let params = self.params();
+        let mut args = args.into_iter();
+        for param in params.iter().take(self.arity()) {
+            environment
+                .borrow_mut()
+                .define(param.lexeme.clone(), args.next().unwrap())
+        }
+        if let (true, Some(rest)) = (self.is_variadic(), params.last()) {
+            environment
+                .borrow_mut()
+                .define(rest.lexeme.clone(), Value::List(new_list(args.collect())));
+        }
+
+        environment
+    }
+
+    pub(crate) fn body(&self) -> Rc<Vec<Stmt>> {
+        if let Stmt::Function { body, .. } = self.declaration.as_ref() {
+            return body.clone();
+        }
+        panic!("Function was not initialized with a function declaration!");
+    }
+
+    pub fn params(&self) -> &[Token] {
+        if let Stmt::Function { params, .. } = self.declaration.as_ref() {
+            return params;
+        }
+        panic!("Function was not initialized with a function declaration!");
+    }
+
+    /// The parameters as written in the declaration, such as `prefix, ...tags`.
+    pub fn param_list(&self) -> String {
+        let mut params = self
+            .params()
+            .iter()
+            .map(|param| param.lexeme.clone())
+            .collect::<Vec<String>>();
+        if let (true, Some(last)) = (self.is_variadic(), params.last_mut()) {
+            last.insert_str(0, "...");
+        }
+        params.join(", ")
+    }
+
+    pub fn name(&self) -> &str {
+        if let Stmt::Function { name, .. } = self.declaration.as_ref() {
+            return &name.lexeme;
+        }
+        panic!("Function was not initialized with a function declaration!");
+    }
+
+    pub fn doc(&self) -> Option<&str> {
+        if let Stmt::Function { doc, .. } = self.declaration.as_ref() {
+            return doc.as_deref();
+        }
+        panic!("Function was not initialized with a function declaration!");
     }
 }
 
 impl Callable for Function {
     fn arity(&self) -> usize {
-        if let Stmt::Function { params, .. } = &self.declaration {
-            return params.len();
+        match self.is_variadic() {
+            true => self.params().len() - 1,
+            false => self.params().len(),
         }
-        panic!("Function was not initialized with a function declaration!");
     }
 
-    fn call(&self, interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, Exception> {
-        let environment = Environment::new_local(&self.closure);
+    fn is_variadic(&self) -> bool {
+        matches!(
+            self.declaration.as_ref(),
+            Stmt::Function { variadic: true, .. }
+        )
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        args: Vec<Value>,
+    ) -> Result<Value, Exception> {
+        let mut interpreter = interpreter.enter_call(paren)?;
+        let environment = self.bind_arguments(&interpreter, args);
 
-        if let Stmt::Function { params, body, .. } = &self.declaration {
-            for (i, param) in params.iter().enumerate() {
-                environment
-                    .borrow_mut()
-                    .define(param.lexeme.clone(), args.get(i).unwrap().clone())
-            }
-
-            if let Err(exception) = interpreter.execute_block(body, environment) {
-                return match exception {
-                    Exception::RuntimeError(e) => Err(Exception::RuntimeError(e)),
-                    Exception::Return(value) => match self.is_initializer {
-                        // initializers always return their instance AKA "this"
-                        true => self.closure.borrow().get_at(0, "this"),
-                        false => Ok(value),
-                    },
-                };
-            }
+        if let Err(exception) = interpreter.execute_block(&self.body(), environment) {
+            return match exception {
+                Exception::Return(value) => match self.is_initializer {
+                    // initializers always return their instance AKA "this"
+                    true => self.closure.borrow().get_at(0, "this"),
+                    false => Ok(value),
+                },
+                exception => Err(exception),
+            };
         }
 
         if self.is_initializer {
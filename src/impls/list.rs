@@ -0,0 +1,290 @@
+use std::{cell::RefCell, cmp::Ordering, rc::Rc};
+
+use crate::{
+    impls::methods::BuiltinMethod,
+    interpreter::Interpreter,
+    semantics::is_truthy,
+    syntax::{token::Token, value::Value},
+    Exception,
+};
+
+/// Lists are mutable and shared by reference, like class instances.
+pub type ListRef = Rc<RefCell<Vec<Value>>>;
+
+pub fn new_list(values: Vec<Value>) -> ListRef {
+    Rc::new(RefCell::new(values))
+}
+
+pub(crate) const METHODS: &[BuiltinMethod] = &[
+    BuiltinMethod {
+        name: "map",
+        arity: 1,
+        variadic: false,
+        doc: "A new list of the results of calling a function with each item.",
+        callable: |interpreter, paren, mut args| {
+            let (items, function) = (items(&mut args), args.remove(0));
+
+            let mut mapped = vec![];
+            for item in items {
+                mapped.push(interpreter.call(function.clone(), paren, vec![item])?);
+            }
+            Ok(Value::List(new_list(mapped)))
+        },
+    },
+    BuiltinMethod {
+        name: "filter",
+        arity: 1,
+        variadic: false,
+        doc: "A new list of the items a function returns a truthy value for.",
+        callable: |interpreter, paren, mut args| {
+            let (items, predicate) = (items(&mut args), args.remove(0));
+
+            let mut kept = vec![];
+            for item in items {
+                let keep = interpreter.call(predicate.clone(), paren, vec![item.clone()])?;
+                if is_truthy(&keep) {
+                    kept.push(item);
+                }
+            }
+            Ok(Value::List(new_list(kept)))
+        },
+    },
+    BuiltinMethod {
+        name: "reduce",
+        arity: 2,
+        variadic: false,
+        doc: "Combines the items into one value, starting from initial and calling a function\n\
+              with the value so far and each item in turn.",
+        callable: |interpreter, paren, mut args| {
+            let items = items(&mut args);
+            let (function, mut reduced) = (args.remove(0), args.remove(0));
+
+            for item in items {
+                reduced = interpreter.call(function.clone(), paren, vec![reduced, item])?;
+            }
+            Ok(reduced)
+        },
+    },
+    BuiltinMethod {
+        name: "sort",
+        arity: 0,
+        variadic: true,
+        doc: "A new list of the items in ascending order, which must be all numbers or all strings,\n\
+              or in the order a comparator function gives: negative if its first argument comes\n\
+              first, positive if its second does, and 0 if either can.",
+        callable: |interpreter, paren, mut args| {
+            let mut items = items(&mut args);
+            match args.len() {
+                0 => sort(&mut items, paren)?,
+                1 => sort_with(&mut items, interpreter, paren, args.remove(0))?,
+                _ => {
+                    return Exception::runtime_error(
+                        paren.clone(),
+                        String::from("sort() takes at most one comparator."),
+                    )
+                }
+            }
+            Ok(Value::List(new_list(items)))
+        },
+    },
+    BuiltinMethod {
+        name: "length",
+        arity: 0,
+        variadic: false,
+        doc: "The number of items in the list.",
+        callable: |_, _, mut args| Ok(Value::Int(list(&mut args).borrow().len() as i64)),
+    },
+    BuiltinMethod {
+        name: "push",
+        arity: 1,
+        variadic: false,
+        doc: "Adds an item to the end of the list.",
+        callable: |_, _, mut args| {
+            let list = list(&mut args);
+            list.borrow_mut().push(args.remove(0));
+            Ok(Value::Nil)
+        },
+    },
+    BuiltinMethod {
+        name: "pop",
+        arity: 0,
+        variadic: false,
+        doc: "Removes the last item from the list and returns it.",
+        callable: |_, paren, mut args| match list(&mut args).borrow_mut().pop() {
+            Some(item) => Ok(item),
+            None => Exception::runtime_error(
+                paren.clone(),
+                String::from("Can't pop from an empty list."),
+            ),
+        },
+    },
+    BuiltinMethod {
+        name: "insert",
+        arity: 2,
+        variadic: false,
+        doc: "Inserts an item at an index, moving the items from there on up one.",
+        callable: |_, paren, mut args| {
+            let list = list(&mut args);
+            let length = list.borrow().len();
+            let index = index(paren, "insert", &args[0], length + 1)?;
+            list.borrow_mut().insert(index, args.remove(1));
+            Ok(Value::Nil)
+        },
+    },
+    BuiltinMethod {
+        name: "removeAt",
+        arity: 1,
+        variadic: false,
+        doc: "Removes the item at an index and returns it, moving the items after it down one.",
+        callable: |_, paren, mut args| {
+            let list = list(&mut args);
+            let length = list.borrow().len();
+            let index = index(paren, "removeAt", &args[0], length)?;
+            let item = list.borrow_mut().remove(index);
+            Ok(item)
+        },
+    },
+    BuiltinMethod {
+        name: "slice",
+        arity: 2,
+        variadic: false,
+        doc: "A new list of the items from index start up to end.",
+        callable: |_, paren, mut args| {
+            let items = items(&mut args);
+            let (Some(start), Some(end)) = (args[0].as_integer(), args[1].as_integer()) else {
+                return Exception::runtime_error(
+                    paren.clone(),
+                    String::from("slice() expects two indices."),
+                );
+            };
+
+            match 0 <= start && start <= end && end <= items.len() as i64 {
+                true => Ok(Value::List(new_list(
+                    items[start as usize..end as usize].to_vec(),
+                ))),
+                false => Exception::runtime_error(
+                    paren.clone(),
+                    format!("Slice range {start}..{end} is out of bounds."),
+                ),
+            }
+        },
+    },
+    BuiltinMethod {
+        name: "concat",
+        arity: 1,
+        variadic: false,
+        doc: "A new list of the items of the list followed by those of another.",
+        callable: |_, paren, mut args| {
+            let mut items = items(&mut args);
+            match &args[0] {
+                Value::List(other) => {
+                    items.extend(other.borrow().iter().cloned());
+                    Ok(Value::List(new_list(items)))
+                }
+                _ => Exception::runtime_error(
+                    paren.clone(),
+                    String::from("concat() expects a list."),
+                ),
+            }
+        },
+    },
+];
+
+// the natural order of numbers or of strings
+fn sort(items: &mut [Value], paren: &Token) -> Result<(), Exception> {
+    let numbers = items.iter().all(|item| item.as_f64().is_some());
+    let strings = items.iter().all(|item| matches!(item, Value::String(_)));
+    if !numbers && !strings {
+        return Exception::runtime_error(
+            paren.clone(),
+            String::from("Can only sort lists of all numbers or all strings."),
+        );
+    }
+
+    items.sort_by(|a, b| match (a, b) {
+        (Value::Int(a), Value::Int(b)) => a.cmp(b),
+        (Value::String(a), Value::String(b)) => a.as_str().cmp(b.as_str()),
+        (a, b) => match (a.as_f64(), b.as_f64()) {
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            _ => Ordering::Equal,
+        },
+    });
+    Ok(())
+}
+
+// the order a Lox comparator gives, stopping at the first error it raises or number it
+// doesn't return. This is a merge sort rather than `sort_by`, which may panic given a
+// comparator that isn't a total order, and Lox code can't be trusted to give one.
+fn sort_with(
+    items: &mut [Value],
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    comparator: Value,
+) -> Result<(), Exception> {
+    merge_sort(items, &mut |a, b| {
+        let order = interpreter.call(comparator.clone(), paren, vec![a.clone(), b.clone()])?;
+        match order.as_f64() {
+            Some(order) => Ok(order.partial_cmp(&0.0).unwrap_or(Ordering::Equal)),
+            None => Exception::runtime_error(
+                paren.clone(),
+                String::from("A sort() comparator must return a number."),
+            ),
+        }
+    })
+}
+
+// a stable sort that compares each pair at most once, so whatever `compare` says, it ends
+fn merge_sort(
+    items: &mut [Value],
+    compare: &mut impl FnMut(&Value, &Value) -> Result<Ordering, Exception>,
+) -> Result<(), Exception> {
+    if items.len() < 2 {
+        return Ok(());
+    }
+    let middle = items.len() / 2;
+    merge_sort(&mut items[..middle], compare)?;
+    merge_sort(&mut items[middle..], compare)?;
+
+    let mut merged = Vec::with_capacity(items.len());
+    let (mut left, mut right) = (0, middle);
+    while left < middle && right < items.len() {
+        // the left item goes first unless it's greater, keeping equal items in order
+        if compare(&items[left], &items[right])? == Ordering::Greater {
+            merged.push(items[right].clone());
+            right += 1;
+        } else {
+            merged.push(items[left].clone());
+            left += 1;
+        }
+    }
+    merged.extend_from_slice(&items[left..middle]);
+    merged.extend_from_slice(&items[right..]);
+    items.clone_from_slice(&merged);
+    Ok(())
+}
+
+// an integer index below `end`, for the method `name`
+fn index(paren: &Token, name: &str, index: &Value, end: usize) -> Result<usize, Exception> {
+    match index.as_integer() {
+        Some(index) if 0 <= index && (index as usize) < end => Ok(index as usize),
+        Some(index) => Exception::runtime_error(
+            paren.clone(),
+            format!("Index {index} is out of bounds for {name}()."),
+        ),
+        None => {
+            Exception::runtime_error(paren.clone(), format!("{name}() expects an integer index."))
+        }
+    }
+}
+
+fn list(args: &mut Vec<Value>) -> ListRef {
+    match args.remove(0) {
+        Value::List(list) => list,
+        _ => panic!("List method was not bound to a list!"),
+    }
+}
+
+// a snapshot of the receiver's items, so callbacks can't invalidate the iteration
+fn items(args: &mut Vec<Value>) -> Vec<Value> {
+    list(args).borrow().clone()
+}
@@ -0,0 +1,196 @@
+use std::f64::consts;
+
+use crate::{
+    impls::number,
+    interpreter::Interpreter,
+    numeric::{self, Operation},
+    syntax::{token::Token, value::Value},
+    Exception,
+};
+
+/// Defines the math natives and the constants `PI` and `E`. Rounding and `abs` keep the kind of
+/// number they're given, as the number methods of the same names do, and `pow` keeps integers
+/// exact for whole exponents, and decimals exact to 18 places. Everything else works in floats,
+/// and raises an error for an argument outside its domain, such as `sqrt(-1)` or `log(0)`,
+/// rather than giving NaN or an infinity.
+pub(crate) fn define(interpreter: &mut Interpreter) {
+    interpreter.define_constant("PI", Value::Number(consts::PI));
+    interpreter.define_constant("E", Value::Number(consts::E));
+
+    interpreter.define_native(
+        "sqrt",
+        1,
+        "sqrt(x) is the square root of a number, which mustn't be negative.",
+        |_, paren, args| float(paren, "sqrt", &args[0], f64::sqrt),
+    );
+    interpreter.define_native(
+        "sin",
+        1,
+        "sin(x) is the sine of an angle in radians.",
+        |_, paren, args| float(paren, "sin", &args[0], f64::sin),
+    );
+    interpreter.define_native(
+        "cos",
+        1,
+        "cos(x) is the cosine of an angle in radians.",
+        |_, paren, args| float(paren, "cos", &args[0], f64::cos),
+    );
+    interpreter.define_native(
+        "log",
+        1,
+        "log(x) is the natural logarithm of a positive number.",
+        |_, paren, args| float(paren, "log", &args[0], f64::ln),
+    );
+    interpreter.define_native(
+        "abs",
+        1,
+        "abs(x) is a number without its sign.",
+        |interpreter, paren, args| method(interpreter, paren, "abs", args),
+    );
+    interpreter.define_native(
+        "floor",
+        1,
+        "floor(x) is the largest whole number less than or equal to a number.",
+        |interpreter, paren, args| method(interpreter, paren, "floor", args),
+    );
+    interpreter.define_native(
+        "ceil",
+        1,
+        "ceil(x) is the smallest whole number greater than or equal to a number.",
+        |interpreter, paren, args| method(interpreter, paren, "ceil", args),
+    );
+    interpreter.define_native(
+        "round",
+        1,
+        "round(x) is the nearest whole number, rounding halves away from zero.",
+        |interpreter, paren, args| method(interpreter, paren, "round", args),
+    );
+    interpreter.define_native(
+        "pow",
+        2,
+        "pow(base, exponent) raises a number to a power. Integers and decimals raised to\n\
+         whole powers stay exact, decimals up to 18 places, and anything else is computed\n\
+         in floats.",
+        |_, paren, args| pow(paren, &args[0], &args[1]),
+    );
+}
+
+// `function(x)` for a number of any kind, as a float. A finite `x` has to give a finite result,
+// so NaN and infinities only come out of ones that went in.
+fn float(
+    paren: &Token,
+    name: &str,
+    x: &Value,
+    function: fn(f64) -> f64,
+) -> Result<Value, Exception> {
+    match x.as_f64().map(|float| (float, function(float))) {
+        Some((float, result)) if float.is_finite() && !result.is_finite() => {
+            undefined(paren, name, x)
+        }
+        Some((float, result)) if result.is_nan() && !float.is_nan() => undefined(paren, name, x),
+        Some((_, result)) => Ok(Value::Number(result)),
+        None => expects_number(paren, name),
+    }
+}
+
+fn undefined(paren: &Token, name: &str, x: &Value) -> Result<Value, Exception> {
+    Exception::runtime_error(paren.clone(), format!("{name}() isn't defined for {x}."))
+}
+
+// calls the number method `name` on the argument
+fn method(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    name: &str,
+    args: Vec<Value>,
+) -> Result<Value, Exception> {
+    if args[0].as_f64().is_none() {
+        return expects_number(paren, name);
+    }
+
+    let method = number::METHODS
+        .iter()
+        .find(|method| method.name == name)
+        .expect("math native to have a number method");
+    (method.callable)(interpreter, paren, args)
+}
+
+fn pow(paren: &Token, base: &Value, exponent: &Value) -> Result<Value, Exception> {
+    let (Some(float_base), Some(float_exponent)) = (base.as_f64(), exponent.as_f64()) else {
+        return Exception::runtime_error(
+            paren.clone(),
+            String::from("pow() expects a base and an exponent that are numbers."),
+        );
+    };
+
+    let whole_exponent = match exponent {
+        Value::Int(exponent) => Some(*exponent),
+        _ => None,
+    };
+    match (base, whole_exponent) {
+        (Value::Number(_), _) | (_, None) => match float_base.powf(float_exponent) {
+            // as for a negative number to a fractional power
+            power if power.is_nan() && !float_base.is_nan() && !float_exponent.is_nan() => {
+                Exception::runtime_error(
+                    paren.clone(),
+                    format!("pow() isn't defined for {base} to the power {exponent}."),
+                )
+            }
+            power => Ok(Value::Number(power)),
+        },
+        (_, Some(exponent)) => {
+            let power = match base {
+                Value::Decimal(base) => match base.checked_pow(exponent.unsigned_abs()) {
+                    Some(power) => Value::Decimal(power),
+                    None => {
+                        return Exception::runtime_error(
+                            paren.clone(),
+                            String::from("Decimal overflow."),
+                        )
+                    }
+                },
+                _ => exact_power(paren, base, exponent.unsigned_abs())?,
+            };
+            match exponent < 0 {
+                true => result(
+                    paren,
+                    numeric::arithmetic(Operation::Divide, &Value::Int(1), &power),
+                ),
+                false => Ok(power),
+            }
+        }
+    }
+}
+
+// `base` multiplied by itself `exponent` times, by repeated squaring
+fn exact_power(paren: &Token, base: &Value, mut exponent: u64) -> Result<Value, Exception> {
+    let (mut power, mut square) = (Value::Int(1), base.clone());
+    while exponent > 0 {
+        if exponent % 2 == 1 {
+            power = result(
+                paren,
+                numeric::arithmetic(Operation::Multiply, &power, &square),
+            )?;
+        }
+        exponent /= 2;
+        if exponent > 0 {
+            square = result(
+                paren,
+                numeric::arithmetic(Operation::Multiply, &square, &square),
+            )?;
+        }
+    }
+
+    Ok(power)
+}
+
+fn result(paren: &Token, result: Option<Result<Value, String>>) -> Result<Value, Exception> {
+    match result.expect("powers to be of numbers") {
+        Ok(value) => Ok(value),
+        Err(message) => Exception::runtime_error(paren.clone(), message),
+    }
+}
+
+fn expects_number<T>(paren: &Token, name: &str) -> Result<T, Exception> {
+    Exception::runtime_error(paren.clone(), format!("{name}() expects a number."))
+}
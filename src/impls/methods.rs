@@ -0,0 +1,60 @@
+use crate::{
+    impls::{
+        coroutine,
+        function::{NativeFn, NativeFunction},
+        list, number, string,
+    },
+    syntax::{token::Token, value::Value},
+    Exception,
+};
+
+/// A method every value of a built-in type has, implemented natively. The receiver is passed to
+/// `callable` as its first argument. Methods that produce a collection return a new one, never
+/// nil, so calls can be chained like `list.filter(f).map(g).sort()`. Methods that change their
+/// receiver instead, like a list's `push`, return nil or the item they removed.
+pub(crate) struct BuiltinMethod {
+    pub name: &'static str,
+    pub arity: usize,
+    pub variadic: bool,
+    pub doc: &'static str,
+    pub callable: NativeFn,
+}
+
+/// Looks up a method on a value that isn't a class instance, bound to that value.
+pub(crate) fn get(receiver: &Value, name: &Token) -> Result<Value, Exception> {
+    let methods = match receiver {
+        Value::List(_) => list::METHODS,
+        Value::Coroutine(_) => coroutine::METHODS,
+        Value::String(_) => string::METHODS,
+        Value::Int(_) | Value::Number(_) | Value::Decimal(_) => number::METHODS,
+        #[cfg(feature = "bigint")]
+        Value::BigInt(_) => number::METHODS,
+        // name the method, since a nil in the middle of a chain is otherwise hard to track down
+        Value::Nil => {
+            return Exception::runtime_error(
+                name.clone(),
+                format!("Can't call '{}' on nil.", name.lexeme),
+            )
+        }
+        _ => {
+            return Exception::runtime_error(
+                name.clone(),
+                String::from("Only instances have properties."),
+            )
+        }
+    };
+
+    match methods.iter().find(|method| method.name == name.lexeme) {
+        Some(method) => {
+            let native = NativeFunction {
+                variadic: method.variadic,
+                doc: Some(method.doc),
+                ..NativeFunction::new(method.arity, method.callable)
+            };
+            Ok(Value::NativeFunction(native.bind(receiver.clone())))
+        }
+        None => {
+            Exception::runtime_error(name.clone(), format!("Undefined property {}.", name.lexeme))
+        }
+    }
+}
@@ -1,3 +1,19 @@
-mod callable;
+#[cfg(feature = "bigint")]
+pub mod bigint;
+pub mod callable;
 pub mod class;
+pub mod collation;
+pub mod coroutine;
+pub mod date;
+pub mod decimal;
+pub mod format;
 pub mod function;
+pub mod list;
+pub mod math;
+pub mod methods;
+pub mod net;
+pub mod number;
+pub mod numeral;
+pub mod string;
+pub mod traits;
+pub mod unicode;
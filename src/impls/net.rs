@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use crate::{
+    impls::class::{Class, ClassInstance},
+    interpreter::Interpreter,
+    syntax::{token::Token, value::Value},
+    utils::http::{self, Response},
+    Exception,
+};
+
+/// Defines `httpGet` and `httpPost`, which are in the `net` group and so only available where
+/// the network has been granted.
+pub(crate) fn define(interpreter: &mut Interpreter) {
+    interpreter.define_native(
+        "httpGet",
+        1,
+        "httpGet(url) fetches an http:// URL, returning a response with its status,\n\
+         headers and body.",
+        |interpreter, paren, args| match &args[0] {
+            Value::String(url) => request(interpreter, paren, "GET", url, None),
+            _ => Exception::runtime_error(
+                paren.clone(),
+                String::from("httpGet() expects a URL string."),
+            ),
+        },
+    );
+    interpreter.define_native(
+        "httpPost",
+        2,
+        "httpPost(url, body) sends a string to an http:// URL, returning a response with\n\
+         its status, headers and body.",
+        |interpreter, paren, args| match (&args[0], &args[1]) {
+            (Value::String(url), Value::String(body)) => {
+                request(interpreter, paren, "POST", url, Some(body))
+            }
+            _ => Exception::runtime_error(
+                paren.clone(),
+                String::from("httpPost() expects a URL string and a body string."),
+            ),
+        },
+    );
+}
+
+fn request(
+    interpreter: &Interpreter,
+    paren: &Token,
+    method: &str,
+    url: &str,
+    body: Option<&str>,
+) -> Result<Value, Exception> {
+    let name = match method {
+        "GET" => "httpGet",
+        _ => "httpPost",
+    };
+    if interpreter.is_deterministic() {
+        return Exception::runtime_error(
+            paren.clone(),
+            format!("{name}() is disabled in deterministic mode."),
+        );
+    }
+
+    // Ctrl-C and withTimeout stop a request stuck waiting on the server
+    let check = || {
+        interpreter.check_interrupted()?;
+        interpreter.check_deadlines()
+    };
+    match http::request(method, url, body, check)? {
+        Ok(response) => Ok(response_value(response)),
+        Err(message) => Exception::runtime_error(paren.clone(), message),
+    }
+}
+
+// an `HttpResponse` instance with `status`, `headers` and `body` fields, the headers being an
+// `HttpHeaders` instance with a field for each header, named in lowercase
+fn response_value(response: Response) -> Value {
+    let mut headers = HashMap::<String, Value>::new();
+    for (name, value) in response.headers {
+        let name = name.to_lowercase();
+        // repeated headers are combined, as HTTP allows
+        let value = match headers.remove(&name) {
+            Some(Value::String(earlier)) => format!("{earlier}, {value}"),
+            _ => value,
+        };
+        headers.insert(name, Value::String(value.into()));
+    }
+
+    let fields = HashMap::from([
+        (
+            String::from("status"),
+            Value::Int(i64::from(response.status)),
+        ),
+        (String::from("headers"), instance("HttpHeaders", headers)),
+        (String::from("body"), Value::String(response.body.into())),
+    ]);
+    instance("HttpResponse", fields)
+}
+
+fn instance(class: &str, fields: HashMap<String, Value>) -> Value {
+    let class = Class::new(
+        class.to_string(),
+        None,
+        None,
+        vec![],
+        HashMap::new(),
+        vec![],
+        HashMap::new(),
+    );
+    let instance = ClassInstance::new(class);
+    instance.borrow_mut().set_fields(fields);
+    Value::ClassInstance(instance)
+}
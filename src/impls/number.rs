@@ -0,0 +1,81 @@
+#[cfg(feature = "bigint")]
+use crate::impls::bigint::BigInt;
+use crate::{
+    impls::{decimal::Decimal, methods::BuiltinMethod},
+    syntax::{token::Token, value::Value},
+    Exception,
+};
+
+/// Methods of integers, floats and decimals. Rounding keeps the receiver's type, so an integer
+/// rounds to itself and a float to a float with no fractional part.
+pub(crate) const METHODS: &[BuiltinMethod] = &[
+    BuiltinMethod {
+        name: "floor",
+        arity: 0,
+        variadic: false,
+        doc: "The largest whole number less than or equal to the number.",
+        callable: |_, _, mut args| Ok(round(receiver(&mut args), f64::floor, Decimal::floor)),
+    },
+    BuiltinMethod {
+        name: "ceil",
+        arity: 0,
+        variadic: false,
+        doc: "The smallest whole number greater than or equal to the number.",
+        callable: |_, _, mut args| Ok(round(receiver(&mut args), f64::ceil, Decimal::ceil)),
+    },
+    BuiltinMethod {
+        name: "round",
+        arity: 0,
+        variadic: false,
+        doc: "The nearest whole number, rounding halves away from zero.",
+        callable: |_, _, mut args| Ok(round(receiver(&mut args), f64::round, Decimal::round)),
+    },
+    BuiltinMethod {
+        name: "abs",
+        arity: 0,
+        variadic: false,
+        doc: "The number without its sign.",
+        callable: |_, paren, mut args| match receiver(&mut args) {
+            Value::Int(value) => match value.checked_abs() {
+                Some(value) => Ok(Value::Int(value)),
+                None => overflowing_abs(paren, value),
+            },
+            #[cfg(feature = "bigint")]
+            Value::BigInt(value) => Ok(value.abs().into_value()),
+            Value::Number(value) => Ok(Value::Number(value.abs())),
+            Value::Decimal(value) => match value.checked_abs() {
+                Some(value) => Ok(Value::Decimal(value)),
+                None => Exception::runtime_error(paren.clone(), String::from("Decimal overflow.")),
+            },
+            _ => panic!("Number method was not bound to a number!"),
+        },
+    },
+];
+
+// the absolute value of `i64::MIN`, which is too big for an i64
+#[cfg(feature = "bigint")]
+fn overflowing_abs(_: &Token, value: i64) -> Result<Value, Exception> {
+    Ok(BigInt::from_i64(value).abs().into_value())
+}
+
+#[cfg(not(feature = "bigint"))]
+fn overflowing_abs(paren: &Token, _: i64) -> Result<Value, Exception> {
+    Exception::runtime_error(paren.clone(), String::from("Integer overflow."))
+}
+
+fn round(number: Value, floats: fn(f64) -> f64, decimals: fn(Decimal) -> Decimal) -> Value {
+    match number {
+        Value::Number(value) => Value::Number(floats(value)),
+        Value::Decimal(value) => Value::Decimal(decimals(value)),
+        integer => integer,
+    }
+}
+
+fn receiver(args: &mut Vec<Value>) -> Value {
+    match args.remove(0) {
+        number @ (Value::Int(_) | Value::Number(_) | Value::Decimal(_)) => number,
+        #[cfg(feature = "bigint")]
+        number @ Value::BigInt(_) => number,
+        _ => panic!("Number method was not bound to a number!"),
+    }
+}
@@ -0,0 +1,218 @@
+#[cfg(feature = "bigint")]
+use crate::impls::bigint::BigInt;
+use crate::{
+    interpreter::Interpreter,
+    syntax::{token::Token, value::Value},
+    Exception,
+};
+
+const DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+/// `value` written in `base`, from 2 to 36, with lowercase letters for digits past 9 and a `-`
+/// in front of negative numbers.
+pub(crate) fn to_string_base(value: i64, base: u32) -> String {
+    let mut magnitude = value.unsigned_abs();
+    let mut digits = vec![];
+    loop {
+        digits.push(DIGITS[(magnitude % u64::from(base)) as usize]);
+        magnitude /= u64::from(base);
+        if magnitude == 0 {
+            break;
+        }
+    }
+    if value < 0 {
+        digits.push(b'-');
+    }
+    digits.reverse();
+    String::from_utf8(digits).expect("digits to be ASCII")
+}
+
+/// The integer `text` spells out in `base`, in either case and with an optional sign, or None if
+/// it isn't one. The prefix for the base (`0x`, `0o` or `0b`) may come after the sign, so numbers
+/// other tools write with one read back as well. Integers that don't fit in 64 bits are big
+/// integers with the `bigint` feature, and None without it.
+pub(crate) fn parse_int_base(text: &str, base: u32) -> Option<Value> {
+    let text = text.trim();
+    let (sign, digits) = match text.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", text.strip_prefix('+').unwrap_or(text)),
+    };
+    let prefix = match base {
+        16 => Some(["0x", "0X"]),
+        8 => Some(["0o", "0O"]),
+        2 => Some(["0b", "0B"]),
+        _ => None,
+    };
+    let digits = prefix
+        .and_then(|prefixes| {
+            prefixes
+                .iter()
+                .find_map(|prefix| digits.strip_prefix(prefix))
+        })
+        .unwrap_or(digits);
+    // from_str_radix would take a second sign
+    if digits.is_empty() || digits.starts_with(['+', '-']) {
+        return None;
+    }
+
+    let text = format!("{sign}{digits}");
+    match i64::from_str_radix(&text, base) {
+        Ok(value) => Some(Value::Int(value)),
+        #[cfg(feature = "bigint")]
+        Err(_) => BigInt::parse_radix(&text, base).map(BigInt::into_value),
+        #[cfg(not(feature = "bigint"))]
+        Err(_) => None,
+    }
+}
+
+/// The number `text` spells out as an integer or float literal would, or with a `0x`, `0o` or
+/// `0b` prefix as a hex, octal or binary integer, ignoring surrounding whitespace.
+pub(crate) fn parse_number(text: &str) -> Option<Value> {
+    let text = text.trim();
+    let unsigned = text.trim_start_matches(['+', '-']);
+    for (prefix, base) in [("0x", 16), ("0o", 8), ("0b", 2)] {
+        if unsigned.len() > prefix.len() && unsigned[..prefix.len()].eq_ignore_ascii_case(prefix) {
+            return parse_int_base(text, base);
+        }
+    }
+
+    parse_base_ten(text)
+}
+
+/// The number `text` spells out as an integer or float literal would, without a prefix for
+/// another base, which is all `num` reads.
+pub(crate) fn parse_base_ten(text: &str) -> Option<Value> {
+    parse_int_base(text, 10).or_else(|| text.trim().parse().ok().map(Value::Number))
+}
+
+/// Defines `parseNumber`, `toStringBase` and `parseIntBase`, which read numbers from text
+/// without raising errors for text that isn't one, and write integers in other bases.
+pub(crate) fn define(interpreter: &mut Interpreter) {
+    interpreter.define_native(
+        "parseNumber",
+        1,
+        "parseNumber(string) is the number a string spells out, including hex, octal and\n\
+         binary integers like \"0xff\", or nil if it isn't one.",
+        |_, paren, args| match &args[0] {
+            Value::String(string) => Ok(parse_number(string).unwrap_or(Value::Nil)),
+            _ => Exception::runtime_error(
+                paren.clone(),
+                String::from("parseNumber() expects a string."),
+            ),
+        },
+    );
+    interpreter.define_native(
+        "toStringBase",
+        2,
+        "toStringBase(n, base) writes an integer in a base from 2 to 36, as in\n\
+         toStringBase(255, 16) == \"ff\".",
+        |_, paren, args| {
+            let integer = match &args[0] {
+                #[cfg(feature = "bigint")]
+                Value::BigInt(_) => true,
+                value => value.as_integer().is_some(),
+            };
+            if !integer {
+                return Exception::runtime_error(
+                    paren.clone(),
+                    String::from("toStringBase() expects an integer and a base."),
+                );
+            }
+            let base = base(paren, "toStringBase", &args[1])?;
+            let string = match &args[0] {
+                #[cfg(feature = "bigint")]
+                Value::BigInt(value) => value.to_string_radix(base),
+                value => to_string_base(value.as_integer().expect("an integer"), base),
+            };
+            Ok(Value::String(string.into()))
+        },
+    );
+    interpreter.define_native(
+        "parseIntBase",
+        2,
+        "parseIntBase(string, base) is the integer a string spells out in a base from 2\n\
+         to 36, or nil if it isn't one, so parseIntBase(\"ff\", 16) == 255.",
+        |_, paren, args| {
+            let Value::String(string) = &args[0] else {
+                return Exception::runtime_error(
+                    paren.clone(),
+                    String::from("parseIntBase() expects a string and a base."),
+                );
+            };
+            let base = base(paren, "parseIntBase", &args[1])?;
+            Ok(parse_int_base(string, base).unwrap_or(Value::Nil))
+        },
+    );
+}
+
+fn base(paren: &Token, name: &str, base: &Value) -> Result<u32, Exception> {
+    match base.as_integer() {
+        Some(base @ 2..=36) => Ok(base as u32),
+        _ => Exception::runtime_error(
+            paren.clone(),
+            format!("{name}() expects a base from 2 to 36."),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_int(text: &str, base: u32) -> Option<i64> {
+        parse_int_base(text, base).map(|value| value.as_integer().expect("a plain integer"))
+    }
+
+    #[test]
+    fn integers_round_trip_through_every_base() {
+        assert_eq!(to_string_base(255, 16), "ff");
+        assert_eq!(to_string_base(-5, 2), "-101");
+        assert_eq!(to_string_base(0, 36), "0");
+        assert_eq!(to_string_base(35, 36), "z");
+        for value in [0, 1, -1, 1234567, i64::MAX, i64::MIN] {
+            for base in 2..=36 {
+                assert_eq!(parse_int(&to_string_base(value, base), base), Some(value));
+            }
+        }
+
+        assert_eq!(parse_int("FF", 16), Some(255));
+        assert_eq!(parse_int("-0x10", 16), Some(-16));
+        assert_eq!(parse_int("0b11", 2), Some(3));
+        assert_eq!(parse_int("12", 2), None);
+        assert_eq!(parse_int("--1", 10), None);
+        assert_eq!(parse_int("-", 10), None);
+        #[cfg(not(feature = "bigint"))]
+        assert_eq!(parse_int("8000000000000000", 16), None);
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn integers_past_i64_stay_exact() {
+        let huge = "123456789012345678901234567890";
+        for text in [parse_number(huge), parse_base_ten(&format!(" {huge} "))] {
+            assert!(matches!(text, Some(Value::BigInt(value)) if value.to_string() == huge));
+        }
+        assert!(matches!(
+            parse_int_base("-0x8000000000000001", 16),
+            Some(Value::BigInt(value)) if value.to_string_radix(16) == "-8000000000000001"
+        ));
+        assert!(matches!(
+            parse_number("-9223372036854775808"),
+            Some(Value::Int(i64::MIN))
+        ));
+        assert!(matches!(parse_number("1e30"), Some(Value::Number(_))));
+    }
+
+    #[test]
+    fn numbers_parse_or_give_none() {
+        assert!(matches!(parse_number(" 42 "), Some(Value::Int(42))));
+        assert!(matches!(parse_number("-2.5e3"), Some(Value::Number(value)) if value == -2500.0));
+        assert!(matches!(parse_number("0xFF"), Some(Value::Int(255))));
+        assert!(matches!(parse_number("-0b101"), Some(Value::Int(-5))));
+        assert!(matches!(parse_number("0o17"), Some(Value::Int(15))));
+        assert!(parse_number("0x").is_none());
+        assert!(parse_number("0xfg").is_none());
+        assert!(parse_number("twelve").is_none());
+        assert!(parse_number("").is_none());
+    }
+}
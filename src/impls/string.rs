@@ -0,0 +1,314 @@
+use std::{fmt, ops::Deref, rc::Rc};
+
+use crate::{
+    impls::{list::new_list, methods::BuiltinMethod, unicode::graphemes},
+    syntax::{token::Token, value::Value},
+    Exception,
+};
+
+/// An immutable string that can share its buffer with the string it was sliced from, so taking a
+/// substring or splitting a string doesn't copy any text. Operations that build new text, such
+/// as concatenation, allocate a buffer of their own.
+#[derive(Clone)]
+pub struct LoxString {
+    buffer: Rc<str>,
+    // byte range of `buffer` this string covers
+    start: usize,
+    end: usize,
+}
+
+impl LoxString {
+    pub fn as_str(&self) -> &str {
+        &self.buffer[self.start..self.end]
+    }
+
+    /// The characters from index `start` up to but not including `end`, sharing this string's
+    /// buffer, or None if the range is out of bounds.
+    pub fn slice(&self, start: usize, end: usize) -> Option<LoxString> {
+        if start > end {
+            return None;
+        }
+
+        let mut boundaries = self
+            .as_str()
+            .char_indices()
+            .map(|(offset, _)| offset)
+            .chain([self.as_str().len()]);
+        let start_offset = boundaries.nth(start)?;
+        let end_offset = match end - start {
+            0 => start_offset,
+            length => boundaries.nth(length - 1)?,
+        };
+
+        Some(self.sub(start_offset, end_offset))
+    }
+
+    /// Every character as a string of its own, each sharing this string's buffer.
+    pub fn characters(&self) -> Vec<LoxString> {
+        self.as_str()
+            .char_indices()
+            .map(|(offset, character)| self.sub(offset, offset + character.len_utf8()))
+            .collect()
+    }
+
+    /// Every grapheme cluster, the characters a reader sees, as a string of its own sharing this
+    /// string's buffer.
+    pub fn graphemes(&self) -> Vec<LoxString> {
+        let text = self.as_str();
+        graphemes(text)
+            .into_iter()
+            .map(|cluster| {
+                let offset = cluster.as_ptr() as usize - text.as_ptr() as usize;
+                self.sub(offset, offset + cluster.len())
+            })
+            .collect()
+    }
+
+    /// The parts between each occurrence of `separator`, or the characters if it's empty, sharing
+    /// this string's buffer.
+    pub fn split(&self, separator: &str) -> Vec<LoxString> {
+        if separator.is_empty() {
+            return self.characters();
+        }
+
+        let mut parts = vec![];
+        let mut start = 0;
+        for (offset, _) in self.as_str().match_indices(separator) {
+            parts.push(self.sub(start, offset));
+            start = offset + separator.len();
+        }
+        parts.push(self.sub(start, self.as_str().len()));
+
+        parts
+    }
+
+    // byte offsets are relative to this string rather than the whole buffer
+    fn sub(&self, start: usize, end: usize) -> LoxString {
+        LoxString {
+            buffer: self.buffer.clone(),
+            start: self.start + start,
+            end: self.start + end,
+        }
+    }
+}
+
+/// The characters of `string` from index `start` up to `end`, or an error naming the range if it's
+/// out of bounds.
+pub(crate) fn substring(
+    string: &LoxString,
+    start: i64,
+    end: i64,
+    paren: &Token,
+) -> Result<Value, Exception> {
+    let slice = match 0 <= start && start <= end {
+        true => string.slice(start as usize, end as usize),
+        false => None,
+    };
+    match slice {
+        Some(slice) => Ok(Value::String(slice)),
+        None => Exception::runtime_error(
+            paren.clone(),
+            format!("Substring range {start}..{end} is out of bounds."),
+        ),
+    }
+}
+
+pub(crate) const METHODS: &[BuiltinMethod] = &[
+    BuiltinMethod {
+        name: "length",
+        arity: 0,
+        variadic: false,
+        doc: "The number of characters in the string.",
+        callable: |_, _, mut args| {
+            let length = receiver(&mut args).chars().count();
+            Ok(Value::Int(length as i64))
+        },
+    },
+    BuiltinMethod {
+        name: "split",
+        arity: 1,
+        variadic: false,
+        doc: "A list of the parts between every separator, or of the characters if the separator\n\
+              is empty.",
+        callable: |_, paren, mut args| {
+            let string = receiver(&mut args);
+            match &args[0] {
+                Value::String(separator) => Ok(Value::List(new_list(
+                    string
+                        .split(separator)
+                        .into_iter()
+                        .map(Value::String)
+                        .collect(),
+                ))),
+                _ => Exception::runtime_error(
+                    paren.clone(),
+                    String::from("split() expects a separator string."),
+                ),
+            }
+        },
+    },
+    BuiltinMethod {
+        name: "substring",
+        arity: 2,
+        variadic: false,
+        doc: "The characters from index start up to end.",
+        callable: |_, paren, mut args| {
+            let string = receiver(&mut args);
+            match (args[0].as_integer(), args[1].as_integer()) {
+                (Some(start), Some(end)) => substring(&string, start, end, paren),
+                _ => Exception::runtime_error(
+                    paren.clone(),
+                    String::from("substring() expects two character indices."),
+                ),
+            }
+        },
+    },
+    BuiltinMethod {
+        name: "contains",
+        arity: 1,
+        variadic: false,
+        doc: "Whether another string appears in the string.",
+        callable: |_, paren, mut args| {
+            let string = receiver(&mut args);
+            match &args[0] {
+                Value::String(part) => Ok(Value::Boolean(string.contains(part.as_str()))),
+                _ => Exception::runtime_error(
+                    paren.clone(),
+                    String::from("contains() expects a string."),
+                ),
+            }
+        },
+    },
+    BuiltinMethod {
+        name: "upper",
+        arity: 0,
+        variadic: false,
+        doc: "The string in upper case.",
+        callable: |_, _, mut args| Ok(Value::String(receiver(&mut args).to_uppercase().into())),
+    },
+    BuiltinMethod {
+        name: "lower",
+        arity: 0,
+        variadic: false,
+        doc: "The string in lower case.",
+        callable: |_, _, mut args| Ok(Value::String(receiver(&mut args).to_lowercase().into())),
+    },
+    BuiltinMethod {
+        name: "trim",
+        arity: 0,
+        variadic: false,
+        doc: "The string without whitespace at either end, sharing the string's memory.",
+        callable: |_, _, mut args| {
+            let string = receiver(&mut args);
+            let start = string.len() - string.trim_start().len();
+            let end = string.trim_end().len().max(start);
+            Ok(Value::String(string.sub(start, end)))
+        },
+    },
+];
+
+fn receiver(args: &mut Vec<Value>) -> LoxString {
+    match args.remove(0) {
+        Value::String(string) => string,
+        _ => panic!("String method was not bound to a string!"),
+    }
+}
+
+impl From<String> for LoxString {
+    fn from(value: String) -> Self {
+        let end = value.len();
+        LoxString {
+            buffer: Rc::from(value),
+            start: 0,
+            end,
+        }
+    }
+}
+
+impl From<&str> for LoxString {
+    fn from(value: &str) -> Self {
+        LoxString {
+            buffer: Rc::from(value),
+            start: 0,
+            end: value.len(),
+        }
+    }
+}
+
+impl Deref for LoxString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq for LoxString {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl fmt::Display for LoxString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for LoxString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slices_share_the_parent_buffer() {
+        let string = LoxString::from("héllo, world");
+
+        let slice = string.slice(1, 5).unwrap();
+        assert_eq!(slice.as_str(), "éllo");
+        assert!(Rc::ptr_eq(&slice.buffer, &string.buffer));
+
+        let nested = slice.slice(1, 3).unwrap();
+        assert_eq!(nested.as_str(), "ll");
+        assert!(Rc::ptr_eq(&nested.buffer, &string.buffer));
+    }
+
+    #[test]
+    fn slice_bounds() {
+        let string = LoxString::from("abc");
+
+        assert_eq!(string.slice(0, 3).unwrap().as_str(), "abc");
+        assert_eq!(string.slice(3, 3).unwrap().as_str(), "");
+        assert!(string.slice(2, 4).is_none());
+        assert!(string.slice(4, 4).is_none());
+        assert!(string.slice(2, 1).is_none());
+    }
+
+    #[test]
+    fn split_and_characters_share_the_parent_buffer() {
+        let string = LoxString::from("a,bc,");
+
+        let parts = string.split(",");
+        let parts = parts
+            .iter()
+            .map(|part| part.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(parts, ["a", "bc", ""]);
+
+        let characters = string.split(",")[1].characters();
+        assert_eq!(characters[1].as_str(), "c");
+        assert!(Rc::ptr_eq(&characters[1].buffer, &string.buffer));
+
+        let flags = LoxString::from("a🇫🇷e\u{301}").graphemes();
+        let flags = flags
+            .iter()
+            .map(|cluster| cluster.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(flags, ["a", "🇫🇷", "e\u{301}"]);
+    }
+}
@@ -0,0 +1,40 @@
+use std::{collections::HashMap, fmt::Display, rc::Rc};
+
+use super::function::Function;
+
+pub type TraitRef = Rc<Trait>;
+
+/// A named bundle of methods that classes can mix in with `with`.
+#[derive(Debug)]
+pub struct Trait {
+    name: String,
+    methods: HashMap<String, Function>,
+}
+
+impl Trait {
+    pub fn new(name: String, methods: HashMap<String, Function>) -> TraitRef {
+        Rc::new(Trait { name, methods })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn find_method(&self, name: &str) -> Option<&Function> {
+        self.methods.get(name)
+    }
+
+    /// The names of the trait's methods, in alphabetical order so that anything reported about
+    /// them comes out the same on every run.
+    pub fn method_names(&self) -> Vec<&String> {
+        let mut names = self.methods.keys().collect::<Vec<&String>>();
+        names.sort();
+        names
+    }
+}
+
+impl Display for Trait {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
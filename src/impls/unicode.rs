@@ -0,0 +1,147 @@
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    interpreter::Interpreter,
+    syntax::{token::Token, value::Value},
+    Exception,
+};
+
+/// Defines the Unicode natives: `normalize`, `caseFold`, `graphemeLength` and `graphemeReverse`.
+/// Normalization is full canonical (de)composition, Hangul syllables included, and grapheme
+/// clusters are the extended clusters of UAX #29, both from the Unicode data tables.
+pub(crate) fn define(interpreter: &mut Interpreter) {
+    interpreter.define_native(
+        "normalize",
+        2,
+        "normalize(string, form) is a string in normal form \"NFC\", with accents composed\n\
+         into the letters they go on, or \"NFD\", with them split out as combining marks.",
+        |_, paren, args| {
+            let (Value::String(string), Value::String(form)) = (&args[0], &args[1]) else {
+                return expects_string(paren, "normalize() expects a string and a form.");
+            };
+
+            match form.to_uppercase().as_str() {
+                "NFC" => Ok(Value::String(compose(string).into())),
+                "NFD" => Ok(Value::String(decompose(string).into())),
+                _ => Exception::runtime_error(
+                    paren.clone(),
+                    format!("Unknown normalization form '{form}'; expected \"NFC\" or \"NFD\"."),
+                ),
+            }
+        },
+    );
+    interpreter.define_native(
+        "caseFold",
+        1,
+        "caseFold(string) is a string with case differences erased, for comparing\n\
+         strings regardless of case: caseFold(\"STRASSE\") == caseFold(\"straße\").",
+        |_, paren, args| match &args[0] {
+            Value::String(string) => Ok(Value::String(case_fold(string).into())),
+            _ => expects_string(paren, "caseFold() expects a string."),
+        },
+    );
+    interpreter.define_native(
+        "graphemeLength",
+        1,
+        "graphemeLength(string) is the number of characters in a string as a reader would\n\
+         count them, so an accented letter or an emoji made of several code points is one.",
+        |_, paren, args| match &args[0] {
+            Value::String(string) => Ok(Value::Int(graphemes(string).len() as i64)),
+            _ => expects_string(paren, "graphemeLength() expects a string."),
+        },
+    );
+    interpreter.define_native(
+        "graphemeReverse",
+        1,
+        "graphemeReverse(string) is a string backwards, keeping accents on their letters\n\
+         and emoji in one piece.",
+        |_, paren, args| match &args[0] {
+            Value::String(string) => {
+                let reversed = graphemes(string).into_iter().rev().collect::<String>();
+                Ok(Value::String(reversed.into()))
+            }
+            _ => expects_string(paren, "graphemeReverse() expects a string."),
+        },
+    );
+}
+
+fn expects_string<T>(paren: &Token, message: &str) -> Result<T, Exception> {
+    Exception::runtime_error(paren.clone(), String::from(message))
+}
+
+/// The canonical decomposition of `string` (NFD): precomposed letters, Hangul syllables
+/// included, split into their parts, with runs of combining marks in canonical order.
+pub fn decompose(string: &str) -> String {
+    string.nfd().collect()
+}
+
+/// The canonical composition of `string` (NFC): decomposed, then each mark joined to the
+/// letter before it where Unicode has a letter for them.
+pub fn compose(string: &str) -> String {
+    string.nfc().collect()
+}
+
+/// `string` with case differences erased: lowercase, with `ß` as `ss` and final sigma as sigma.
+pub fn case_fold(string: &str) -> String {
+    string
+        .to_lowercase()
+        .replace(['ß', 'ẞ'], "ss")
+        .replace('ς', "σ")
+}
+
+/// The extended grapheme clusters of `string`, the characters a reader sees.
+pub fn graphemes(string: &str) -> Vec<&str> {
+    string.graphemes(true).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalization_composes_and_decomposes() {
+        let (composed, decomposed) = ("café", "cafe\u{301}");
+        assert_eq!(decompose(composed), decomposed);
+        assert_eq!(compose(decomposed), composed);
+        assert_eq!(compose(&decompose("Ångström")), "Ångström");
+
+        // Vietnamese letters carry two marks, which compose in canonical order
+        assert_eq!(decompose("ệ"), "e\u{323}\u{302}");
+        assert_eq!(compose(&decompose("e\u{302}\u{323}")), "ệ");
+        assert_eq!(compose("z\u{301}\u{301}"), "ź\u{301}");
+
+        // Cyrillic letters and Hangul syllables decompose too, the latter algorithmically
+        assert_eq!(decompose("й"), "и\u{306}");
+        assert_eq!(compose("и\u{306}"), "й");
+        assert_eq!(decompose("한"), "\u{1112}\u{1161}\u{11ab}");
+        assert_eq!(compose(&decompose("한국어")), "한국어");
+    }
+
+    #[test]
+    fn case_folding() {
+        assert_eq!(case_fold("STRASSE"), case_fold("straße"));
+        assert_eq!(case_fold("ΣΟΦΟΣ"), case_fold("σοφος"));
+    }
+
+    #[test]
+    fn grapheme_clusters() {
+        assert_eq!(graphemes("cafe\u{301}!"), ["c", "a", "f", "e\u{301}", "!"]);
+        assert_eq!(graphemes("a\r\nb"), ["a", "\r\n", "b"]);
+
+        let family = "👩\u{200d}👩\u{200d}👧";
+        let thumbs = "👍\u{1f3fd}";
+        let flags = "🇳🇿🇯🇵";
+        let text = format!("{family}{thumbs}{flags}");
+        assert_eq!(graphemes(&text), [family, thumbs, "🇳🇿", "🇯🇵"]);
+        assert!(graphemes("").is_empty());
+
+        // vowel signs and viramas stay with their consonants
+        assert_eq!(graphemes("नमस्ते"), ["न", "म", "स्ते"]);
+        assert_eq!(graphemes("и\u{306}ка"), ["и\u{306}", "к", "а"]);
+        assert_eq!(
+            graphemes("\u{1112}\u{1161}\u{11ab}글"),
+            ["\u{1112}\u{1161}\u{11ab}", "글"]
+        );
+    }
+}
@@ -1,106 +1,1215 @@
 use std::{
-    collections::HashMap,
-    time::{SystemTime, UNIX_EPOCH},
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    env, io,
+    ops::{Deref, DerefMut},
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering as AtomicOrdering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 use crate::{
-    environment::{EnvRef, Environment},
+    capabilities::{Capabilities, NativeGroup},
+    diagnostics::DiagnosticOptions,
+    environment::{EnvRef, Environment, Upvalue},
+    fiber::{Fiber, Suspension},
     impls::{
-        class::Class,
-        function::{Callable, Function, NativeFunction},
+        callable::Callable,
+        class::{Class, ClassInstanceRef, ClassRef},
+        collation,
+        coroutine::Coroutine,
+        date,
+        decimal::Decimal,
+        format,
+        function::{Function, NativeFunction},
+        list::new_list,
+        math, methods, net, numeral, string,
+        traits::{Trait, TraitRef},
+        unicode,
     },
+    numeric::{self, Operation},
+    parser::{Parser, ParserOptions},
+    perf_hints::PerfHints,
+    phase_times::{Phase, PhaseTimes},
+    pool,
+    render::{Diagnostic, ErrorFormat},
+    resolver::Resolver,
+    scanner::{ScanError, Scanner},
+    semantics::{is_truthy, Semantics},
     syntax::{
         expr::{self, Expr},
         stmt::{self, Stmt},
         token::{Literal, Token, TokenType},
         value::Value,
     },
-    utils::logger::{Logger, StdoutLogger},
-    Exception,
+    timers::{Clock, Timers},
+    utils::{
+        file_system::{FileSystem, StdFileSystem},
+        help,
+        import::{FileImportResolver, ImportResolver},
+        line_source::LineSource,
+        logger::{Logger, StdoutLogger},
+        module_header::ModuleHeader,
+    },
+    worker::{Mailbox, Message},
+    Exception, LoxError, RuntimeError,
 };
 
 type Result<T> = std::result::Result<T, Exception>;
 
+/// Swaps in a new environment for as long as the guard is alive and restores the
+/// previous one when it is dropped, regardless of how execution leaves the scope.
+struct EnvironmentGuard<'a> {
+    interpreter: &'a mut Interpreter,
+    previous: EnvRef,
+}
+
+impl<'a> EnvironmentGuard<'a> {
+    fn new(interpreter: &'a mut Interpreter, environment: EnvRef) -> EnvironmentGuard<'a> {
+        let previous = std::mem::replace(&mut interpreter.environment, environment);
+        EnvironmentGuard {
+            interpreter,
+            previous,
+        }
+    }
+}
+
+impl Deref for EnvironmentGuard<'_> {
+    type Target = Interpreter;
+
+    fn deref(&self) -> &Interpreter {
+        self.interpreter
+    }
+}
+
+impl DerefMut for EnvironmentGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Interpreter {
+        self.interpreter
+    }
+}
+
+impl Drop for EnvironmentGuard<'_> {
+    fn drop(&mut self) {
+        let finished = std::mem::replace(&mut self.interpreter.environment, self.previous.clone());
+        pool::recycle_environment(finished);
+    }
+}
+
+/// Counts a call for as long as the guard is alive, however the call ends, so the depth stays
+/// right when an exception unwinds through a native that called back into Lox.
+pub(crate) struct CallGuard<'a> {
+    interpreter: &'a mut Interpreter,
+}
+
+impl Deref for CallGuard<'_> {
+    type Target = Interpreter;
+
+    fn deref(&self) -> &Interpreter {
+        self.interpreter
+    }
+}
+
+impl DerefMut for CallGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Interpreter {
+        self.interpreter
+    }
+}
+
+impl Drop for CallGuard<'_> {
+    fn drop(&mut self) {
+        self.interpreter.call_depth -= 1;
+    }
+}
+
+/// The default limit on how deeply calls can nest, see [`InterpreterOptions::max_call_depth`].
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 1000;
+
+/// A stack size for threads running the interpreter that fits [`DEFAULT_MAX_CALL_DEPTH`] nested
+/// calls even in unoptimized builds, where every Lox call takes several kilobytes of native stack.
+pub const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+// the largest line buffer `print` keeps for the next line, so printing one huge value doesn't
+// hold on to its memory
+const MAX_PRINT_BUFFER: usize = 64 * 1024;
+
+// the longest string `*` makes, in bytes, so a huge count is an error rather than an
+// allocation failure that aborts the process
+const MAX_REPEATED_LENGTH: usize = 256 * 1024 * 1024;
+
+/// Settings an embedder can customize when creating an [`Interpreter`].
+#[derive(Default)]
+pub struct InterpreterOptions {
+    /// Where `print` output goes; defaults to stdout.
+    pub logger: Option<Box<dyn Logger>>,
+    /// Where `readLine()` reads from; defaults to stdin.
+    pub input: Option<Box<dyn LineSource>>,
+    /// Limits applied when parsing code run by this interpreter.
+    pub parser: ParserOptions,
+    /// Lets code redefine, assign to or shadow built-in globals such as `clock`.
+    pub allow_builtin_redefinition: bool,
+    /// Which warnings are silenced, reported, or promoted to errors.
+    pub diagnostics: DiagnosticOptions,
+    /// Where `import` finds modules; defaults to reading files from `file_system`.
+    pub import_resolver: Option<Box<dyn ImportResolver>>,
+    /// The files Lox code can reach, through `import` and the file natives; defaults to the real
+    /// filesystem.
+    pub file_system: Option<Rc<dyn FileSystem>>,
+    /// Which groups of natives each module may use; defaults to all of them everywhere.
+    pub capabilities: Capabilities,
+    /// How deeply Lox functions can call each other, directly or through natives, before a
+    /// "Stack overflow." error; defaults to [`DEFAULT_MAX_CALL_DEPTH`]. Every call also uses the
+    /// native stack, so the thread running the interpreter needs room for this many, as
+    /// [`STACK_SIZE`] gives.
+    pub max_call_depth: Option<usize>,
+    /// How equality and printing treat NaN and negative zero.
+    pub semantics: Semantics,
+    /// A handle made before the interpreter, such as one a signal handler already holds, for
+    /// [`Interpreter::interrupt_handle`] to share.
+    pub interrupt_handle: Option<InterruptHandle>,
+    /// Makes two runs of the same program print the same thing, for differential testing:
+    /// `clock()` reads a fake clock that starts at 0 and only moves when the event loop skips
+    /// ahead to the next timer, timers run without waiting, `withTimeout` is disabled, and
+    /// `getenv` only sees variables set with `setenv`.
+    pub deterministic: bool,
+    /// How [`run_file_with_options`](crate::run_file_with_options) prints the errors and
+    /// warnings of the script it runs. Embedders running code themselves get them from
+    /// [`Interpreter::diagnose`] and the results of loading and running.
+    pub error_format: ErrorFormat,
+    /// The command-line arguments given to the script, which it reads from the global `ARGV`.
+    pub args: Vec<String>,
+}
+
+/// How many steps [`Interpreter::run_for`] may take before pausing. Every statement executed and
+/// expression evaluated is one step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Steps(pub u64);
+
+/// Where a program loaded with [`Interpreter::load`] stands after a call to [`Interpreter::run_for`].
+#[derive(Debug, PartialEq)]
+pub enum RunState {
+    Paused,
+    Finished,
+    Failed(LoxError),
+    /// Stopped by an [`InterruptHandle`]; the rest of the program is abandoned.
+    Interrupted,
+}
+
+/// Stops an interpreter from another thread, or from a signal handler. The running program
+/// stops before its next statement, unwinding through `try` blocks without running their
+/// `catch` or `finally` blocks, and the interpreter is left ready to load another program.
+#[derive(Clone, Debug, Default)]
+pub struct InterruptHandle {
+    interrupted: Arc<AtomicBool>,
+}
+
+impl InterruptHandle {
+    /// A handle for an interpreter that hasn't been made yet, to pass in
+    /// [`InterpreterOptions::interrupt_handle`].
+    pub fn new() -> InterruptHandle {
+        InterruptHandle::default()
+    }
+
+    /// Asks the interpreter to stop. If nothing is running, the next run stops straight away,
+    /// unless a new program is loaded first.
+    pub fn interrupt(&self) {
+        self.interrupted.store(true, AtomicOrdering::Relaxed);
+    }
+}
+
 pub struct Interpreter {
-    pub globals: EnvRef,
-    environment: EnvRef,
-    locals: HashMap<Expr, usize>,
-    logger: Box<dyn Logger>,
+    pub(crate) globals: EnvRef,
+    pub(crate) environment: EnvRef,
+    // resolved scope distances keyed by expression uid
+    locals: HashMap<usize, usize>,
+    // distance to the "this" each super expression binds to, keyed by expression uid
+    receivers: HashMap<usize, usize>,
+    // variables each function declaration captures, keyed by declaration uid
+    upvalues: HashMap<usize, Vec<Upvalue>>,
+    // function declarations whose calls can't create closures, keeping their variables inline
+    inline_frames: HashSet<usize>,
+    pub(crate) logger: Box<dyn Logger>,
+    // the line `print` writes values into, kept between prints
+    print_buffer: String,
+    input: Box<dyn LineSource>,
+    pub(crate) parser_options: ParserOptions,
+    pub(crate) diagnostics: DiagnosticOptions,
+    // names of the natives defined before any user code runs
+    builtins: HashSet<String>,
+    allow_builtin_redefinition: bool,
+    // statements executed and expressions evaluated so far, used to budget `run_for`
+    pub(crate) steps: u64,
+    // the program loaded for time-sliced execution, if it hasn't finished yet
+    program: Option<Fiber>,
+    import_resolver: Box<dyn ImportResolver>,
+    // what the file natives read and write
+    file_system: Rc<dyn FileSystem>,
+    // variables set with `setenv`, which hide the process's own, as None where unset
+    env_vars: HashMap<String, Option<String>>,
+    // names of every module imported so far
+    modules: HashSet<String>,
+    // the module whose code is running or being resolved is last, under its importers
+    pub(crate) importers: Vec<String>,
+    capabilities: Capabilities,
+    // the channels to the host, if this interpreter is running a worker
+    pub(crate) mailbox: Option<Mailbox>,
+    // Lox calls in progress, including ones made by natives and coroutine resumes
+    call_depth: usize,
+    max_call_depth: usize,
+    pub(crate) semantics: Semantics,
+    // when each `withTimeout` call in progress runs out of time, outermost first
+    deadlines: Vec<Instant>,
+    // callbacks for the event loop to call once the program has finished
+    timers: Timers,
+    // what timers and, in deterministic mode, `clock()` tell the time by
+    clock: Clock,
+    // set by an InterruptHandle, and cleared once the interruption has been reported
+    interrupted: Arc<AtomicBool>,
 }
 
 impl Interpreter {
-    pub fn new(logger: Option<Box<dyn Logger>>) -> Interpreter {
+    pub fn new(options: InterpreterOptions) -> Interpreter {
         let globals = Environment::new_global();
-        globals.borrow_mut().define(
-            "clock".to_string(),
-            Value::NativeFunction(NativeFunction {
-                arity: 0,
-                callable: |_, _| {
-                    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
-                    Value::Number(timestamp.as_millis() as f64)
-                },
-            }),
-        );
 
-        let logger = match logger {
+        let file_system = options
+            .file_system
+            .unwrap_or_else(|| Rc::new(StdFileSystem));
+
+        let logger = match options.logger {
             Some(provided_logger) => provided_logger,
             None => Box::new(StdoutLogger),
         };
 
-        Interpreter {
+        let mut interpreter = Interpreter {
             environment: globals.clone(),
             globals,
             locals: HashMap::new(),
+            receivers: HashMap::new(),
+            upvalues: HashMap::new(),
+            inline_frames: HashSet::new(),
             logger,
+            print_buffer: String::new(),
+            input: options.input.unwrap_or_else(|| Box::new(io::stdin())),
+            parser_options: options.parser,
+            diagnostics: options.diagnostics,
+            builtins: HashSet::new(),
+            allow_builtin_redefinition: options.allow_builtin_redefinition,
+            steps: 0,
+            program: None,
+            import_resolver: options
+                .import_resolver
+                .unwrap_or_else(|| Box::new(FileImportResolver::new(file_system.clone()))),
+            file_system,
+            env_vars: HashMap::new(),
+            modules: HashSet::new(),
+            importers: vec![],
+            capabilities: options.capabilities,
+            mailbox: None,
+            call_depth: 0,
+            max_call_depth: options.max_call_depth.unwrap_or(DEFAULT_MAX_CALL_DEPTH),
+            semantics: options.semantics,
+            deadlines: vec![],
+            timers: Timers::default(),
+            clock: Clock::new(options.deterministic),
+            interrupted: options.interrupt_handle.unwrap_or_default().interrupted,
+        };
+
+        interpreter.define_native(
+            "clock",
+            0,
+            "Milliseconds since the Unix epoch, or in deterministic mode on a fake clock that\n\
+             starts at 0.",
+            |interpreter, _, _| {
+                let timestamp = match interpreter.clock {
+                    Clock::Real(_) => SystemTime::now().duration_since(UNIX_EPOCH).unwrap(),
+                    Clock::Fake(now) => now,
+                };
+                Ok(Value::Number(timestamp.as_millis() as f64))
+            },
+        );
+        interpreter.define_native(
+            "clockMonotonic",
+            0,
+            "Seconds since the interpreter started, to the nanosecond, on a clock that never\n\
+             jumps when the system time is adjusted, for timing code. In deterministic mode it\n\
+             reads the same fake clock as clock().",
+            |interpreter, _, _| Ok(Value::Number(interpreter.clock.now().as_secs_f64())),
+        );
+        date::define(&mut interpreter);
+        math::define(&mut interpreter);
+        numeral::define(&mut interpreter);
+        interpreter.define_native(
+            "split",
+            2,
+            "split(string, separator) breaks a string into a list at every separator,\n\
+             or into its characters if the separator is empty.",
+            |_, paren, args| match (&args[0], &args[1]) {
+                (Value::String(string), Value::String(separator)) => Ok(Value::List(new_list(
+                    string
+                        .split(separator)
+                        .into_iter()
+                        .map(Value::String)
+                        .collect(),
+                ))),
+                _ => Exception::runtime_error(
+                    paren.clone(),
+                    String::from("split() expects a string and a separator string."),
+                ),
+            },
+        );
+        interpreter.define_native(
+            "join",
+            2,
+            "join(list, separator) makes a string of a list's items with separators between them.",
+            |_, paren, args| match (&args[0], &args[1]) {
+                (Value::List(list), Value::String(separator)) => Ok(Value::String(
+                    list.borrow()
+                        .iter()
+                        .map(|item| item.to_string())
+                        .collect::<Vec<String>>()
+                        .join(separator)
+                        .into(),
+                )),
+                _ => Exception::runtime_error(
+                    paren.clone(),
+                    String::from("join() expects a list and a separator string."),
+                ),
+            },
+        );
+        interpreter.define_native(
+            "substring",
+            3,
+            "substring(string, start, end) takes the characters from index start up to end.",
+            |_, paren, args| {
+                let (Value::String(string), Some(start), Some(end)) =
+                    (&args[0], args[1].as_integer(), args[2].as_integer())
+                else {
+                    return Exception::runtime_error(
+                        paren.clone(),
+                        String::from("substring() expects a string and two character indices."),
+                    );
+                };
+
+                string::substring(string, start, end, paren)
+            },
+        );
+        collation::define(&mut interpreter);
+        format::define(&mut interpreter);
+        net::define(&mut interpreter);
+        unicode::define(&mut interpreter);
+        interpreter.define_native(
+            "assert",
+            2,
+            "assert(condition, message) raises a runtime error with the message if the\n\
+             condition is falsy, and does nothing otherwise.",
+            |interpreter, paren, args| match is_truthy(&args[0]) {
+                true => Ok(Value::Nil),
+                false => Exception::runtime_error(
+                    paren.clone(),
+                    format!(
+                        "Assertion failed: {}",
+                        interpreter.semantics.display(&args[1])
+                    ),
+                ),
+            },
+        );
+        interpreter.define_native(
+            "help",
+            1,
+            "help(value) is the documentation of a function, class or native, or nil.",
+            |_, _, args| match args[0].doc() {
+                Some(doc) => Ok(Value::String(doc.into())),
+                None => Ok(Value::Nil),
+            },
+        );
+        interpreter.define_native(
+            "deepCopy",
+            1,
+            "deepCopy(value) copies lists and instances all the way down, keeping shared\n\
+             parts shared and cycles intact.",
+            |_, paren, args| match args[0].deep_clone() {
+                Ok(copy) => Ok(copy),
+                Err(error) => Exception::runtime_error(paren.clone(), error),
+            },
+        );
+        interpreter.define_native(
+            "deepEquals",
+            2,
+            "deepEquals(a, b) compares lists by their items and instances by their class and\n\
+             fields, all the way down, where == compares them by identity.",
+            |interpreter, _, args| {
+                Ok(Value::Boolean(
+                    interpreter.semantics.is_deep_equal(&args[0], &args[1]),
+                ))
+            },
+        );
+        interpreter.define_native(
+            "str",
+            1,
+            "str(value) is the string print shows for a value.",
+            |interpreter, _, args| {
+                Ok(Value::String(
+                    interpreter.semantics.display(&args[0]).into(),
+                ))
+            },
+        );
+        interpreter.define_native(
+            "num",
+            1,
+            "num(string) is the number a string spells out, ignoring surrounding whitespace.",
+            |_, paren, args| match &args[0] {
+                Value::String(string) => match numeral::parse_base_ten(string) {
+                    Some(value) => Ok(value),
+                    None => Exception::runtime_error(
+                        paren.clone(),
+                        format!("Can't convert '{}' to a number.", string.trim()),
+                    ),
+                },
+                number if number.as_f64().is_some() => Ok(number.clone()),
+                _ => {
+                    Exception::runtime_error(paren.clone(), String::from("num() expects a string."))
+                }
+            },
+        );
+        interpreter.define_native(
+            "decimal",
+            1,
+            "decimal(value) is the exact decimal a string or number spells out, keeping the\n\
+             digits written after the point, so decimal(\"0.10\") prints as 0.10.",
+            |_, paren, args| {
+                let decimal = match &args[0] {
+                    Value::String(string) => Decimal::parse(string.trim()),
+                    Value::Decimal(value) => Some(*value),
+                    // floats print as the shortest digits that read back as them, so 0.1 is 0.1
+                    number if number.as_f64().is_some() => Decimal::parse(&number.to_string()),
+                    _ => {
+                        return Exception::runtime_error(
+                            paren.clone(),
+                            String::from("decimal() expects a string or a number."),
+                        )
+                    }
+                };
+
+                match decimal {
+                    Some(decimal) => Ok(Value::Decimal(decimal)),
+                    None => Exception::runtime_error(
+                        paren.clone(),
+                        format!("Can't convert '{}' to a decimal.", args[0]),
+                    ),
+                }
+            },
+        );
+        interpreter.define_native(
+            "instanceFields",
+            1,
+            "instanceFields(instance) lists the names of an instance's fields, alphabetically.",
+            |_, paren, args| match &args[0] {
+                Value::ClassInstance(instance) => Ok(Value::List(new_list(
+                    instance
+                        .borrow()
+                        .field_names()
+                        .into_iter()
+                        .map(|name| Value::String(name.into()))
+                        .collect(),
+                ))),
+                _ => Exception::runtime_error(
+                    paren.clone(),
+                    String::from("instanceFields() expects an instance."),
+                ),
+            },
+        );
+        interpreter.define_native(
+            "getattr",
+            2,
+            "getattr(value, name) is value.name, for a name only known when the program runs.",
+            |_, paren, args| match &args[1] {
+                Value::String(name) => {
+                    let name = Token {
+                        token_type: TokenType::Identifier,
+                        lexeme: name.to_string(),
+                        literal: Literal::None,
+                        line: paren.line,
+                    };
+                    Interpreter::property(args[0].clone(), &name)
+                }
+                _ => Exception::runtime_error(
+                    paren.clone(),
+                    String::from("getattr() expects a property name string."),
+                ),
+            },
+        );
+        interpreter.define_native(
+            "readLine",
+            0,
+            "readLine() is the next line of input without its line ending, or nil once the\n\
+             input has ended.",
+            |interpreter, _, _| match interpreter.input.read_line("") {
+                Some(line) => Ok(Value::String(line.into())),
+                None => Ok(Value::Nil),
+            },
+        );
+        interpreter.define_native(
+            "readFile",
+            1,
+            "readFile(path) is the contents of a file.",
+            |interpreter, paren, args| {
+                let path = Interpreter::path(paren, "readFile", &args[0])?;
+                match interpreter.file_system.read_to_string(path) {
+                    Ok(contents) => Ok(Value::String(contents.into())),
+                    Err(error) => Interpreter::file_error(paren, "read", path, error),
+                }
+            },
+        );
+        interpreter.define_native(
+            "writeFile",
+            2,
+            "writeFile(path, contents) creates a file, or replaces what it contains.",
+            |interpreter, paren, args| {
+                let path = Interpreter::path(paren, "writeFile", &args[0])?;
+                let contents = Interpreter::contents(paren, "writeFile", &args[1])?;
+                match interpreter.file_system.write(path, contents) {
+                    Ok(()) => Ok(Value::Nil),
+                    Err(error) => Interpreter::file_error(paren, "write", path, error),
+                }
+            },
+        );
+        interpreter.define_native(
+            "appendFile",
+            2,
+            "appendFile(path, contents) adds to the end of a file, creating it if need be.",
+            |interpreter, paren, args| {
+                let path = Interpreter::path(paren, "appendFile", &args[0])?;
+                let contents = Interpreter::contents(paren, "appendFile", &args[1])?;
+                match interpreter.file_system.append(path, contents) {
+                    Ok(()) => Ok(Value::Nil),
+                    Err(error) => Interpreter::file_error(paren, "append to", path, error),
+                }
+            },
+        );
+        interpreter.define_native(
+            "fileExists",
+            1,
+            "fileExists(path) is whether there's a file at a path.",
+            |interpreter, paren, args| {
+                let path = Interpreter::path(paren, "fileExists", &args[0])?;
+                Ok(Value::Boolean(interpreter.file_system.exists(path)))
+            },
+        );
+        interpreter.define_native(
+            "getenv",
+            1,
+            "getenv(name) is the value of an environment variable, or nil if it isn't set.",
+            |interpreter, paren, args| {
+                let Value::String(name) = &args[0] else {
+                    return Exception::runtime_error(
+                        paren.clone(),
+                        String::from("getenv() expects a variable name string."),
+                    );
+                };
+
+                let name: &str = name;
+                let value = match interpreter.env_vars.get(name) {
+                    Some(value) => value.clone(),
+                    // the process's variables differ from run to run
+                    None if interpreter.is_deterministic() => None,
+                    None => env::var(name).ok(),
+                };
+                Ok(value.map_or(Value::Nil, |value| Value::String(value.into())))
+            },
+        );
+        interpreter.define_native(
+            "setenv",
+            2,
+            "setenv(name, value) sets an environment variable for this interpreter's getenv(),\n\
+             or unsets it if the value is nil. The process's own environment is left alone.",
+            |interpreter, paren, args| {
+                let (name, value) = match (&args[0], &args[1]) {
+                    (Value::String(name), Value::String(value)) => (name, Some(value.to_string())),
+                    (Value::String(name), Value::Nil) => (name, None),
+                    _ => {
+                        return Exception::runtime_error(
+                            paren.clone(),
+                            String::from(
+                                "setenv() expects a variable name string and a string or nil.",
+                            ),
+                        )
+                    }
+                };
+
+                interpreter.env_vars.insert(name.to_string(), value);
+                Ok(Value::Nil)
+            },
+        );
+        let argv = options
+            .args
+            .into_iter()
+            .map(|arg| Value::String(arg.into()));
+        interpreter.define_constant("ARGV", Value::List(new_list(argv.collect())));
+        interpreter.define_native(
+            "withTimeout",
+            2,
+            "withTimeout(ms, function) calls a function with no arguments, failing with a\n\
+             catchable \"Timed out\" error if it hasn't returned within ms milliseconds.",
+            |interpreter, paren, mut args| {
+                if interpreter.is_deterministic() {
+                    return Exception::runtime_error(
+                        paren.clone(),
+                        String::from("withTimeout() is disabled in deterministic mode."),
+                    );
+                }
+
+                let callback = args.pop().expect("withTimeout to have two arguments");
+                match args[0].as_f64() {
+                    Some(ms) if ms >= 0.0 && ms.is_finite() => {
+                        let limit = Duration::from_secs_f64(ms / 1000.0);
+                        interpreter.call_with_timeout(limit, callback, paren)
+                    }
+                    _ => Exception::runtime_error(
+                        paren.clone(),
+                        String::from(
+                            "withTimeout() expects a non-negative number of milliseconds.",
+                        ),
+                    ),
+                }
+            },
+        );
+        interpreter.define_native(
+            "setTimeout",
+            2,
+            "setTimeout(function, ms) calls a function with no arguments once ms milliseconds\n\
+             have passed and the program has finished, returning an id for clearTimeout.",
+            |interpreter, paren, args| interpreter.set_timer(args, false, paren),
+        );
+        interpreter.define_native(
+            "setInterval",
+            2,
+            "setInterval(function, ms) calls a function with no arguments every ms milliseconds\n\
+             once the program has finished, returning an id for clearInterval.",
+            |interpreter, paren, args| interpreter.set_timer(args, true, paren),
+        );
+        interpreter.define_native(
+            "clearTimeout",
+            1,
+            "clearTimeout(id) cancels a callback scheduled with setTimeout.",
+            |interpreter, paren, args| interpreter.clear_timer(&args[0], paren),
+        );
+        interpreter.define_native(
+            "clearInterval",
+            1,
+            "clearInterval(id) stops the calls scheduled with setInterval.",
+            |interpreter, paren, args| interpreter.clear_timer(&args[0], paren),
+        );
+        interpreter.define_native(
+            "coroutine",
+            1,
+            "coroutine(function) makes a coroutine that runs the function when resumed.",
+            |_, paren, args| match &args[0] {
+                Value::Function(function) => Ok(Value::Coroutine(Coroutine::new(function.clone()))),
+                _ => Exception::runtime_error(
+                    paren.clone(),
+                    String::from("Can only make a coroutine from a function."),
+                ),
+            },
+        );
+        interpreter.define_native(
+            "post",
+            1,
+            "post(message) sends nil, a boolean, number, string or list of them to the host\n\
+             of this worker.",
+            |interpreter, paren, args| {
+                let message = Message::from_value(&args[0]);
+                let mailbox = interpreter.mailbox(paren)?;
+                match message {
+                    // a host that stopped listening has no use for the message
+                    Ok(message) => {
+                        let _ = mailbox.outbox.send(message);
+                        Ok(Value::Nil)
+                    }
+                    Err(error) => Exception::runtime_error(paren.clone(), error),
+                }
+            },
+        );
+        interpreter.define_native(
+            "receive",
+            0,
+            "receive() waits for the next message from the host of this worker, or is nil\n\
+             once the host won't send any more.",
+            |interpreter, paren, _| {
+                let mailbox = interpreter.mailbox(paren)?;
+                match mailbox.inbox.recv() {
+                    Ok(message) => Ok(message.into_value()),
+                    Err(_) => Ok(Value::Nil),
+                }
+            },
+        );
+
+        interpreter
+    }
+
+    fn mailbox(&self, paren: &Token) -> Result<&Mailbox> {
+        match &self.mailbox {
+            Some(mailbox) => Ok(mailbox),
+            None => Exception::runtime_error(
+                paren.clone(),
+                String::from("Only workers have a host to send messages to."),
+            ),
         }
     }
 
-    pub fn interpret(&mut self, statements: Vec<Stmt>) {
+    pub(crate) fn define_native(
+        &mut self,
+        name: &str,
+        arity: usize,
+        doc: &'static str,
+        callable: fn(&mut Interpreter, &Token, Vec<Value>) -> Result<Value>,
+    ) {
+        let native = NativeFunction {
+            doc: Some(doc),
+            ..NativeFunction::new(arity, callable)
+        };
+        self.define_constant(name, Value::NativeFunction(native));
+    }
+
+    /// Defines a built-in global, protected from redefinition like the natives.
+    pub(crate) fn define_constant(&mut self, name: &str, value: Value) {
+        self.globals.borrow_mut().define(name.to_string(), value);
+        self.builtins.insert(name.to_string());
+    }
+
+    pub(crate) fn is_deterministic(&self) -> bool {
+        matches!(self.clock, Clock::Fake(_))
+    }
+
+    // the path argument of a file native
+    fn path<'a>(paren: &Token, native: &str, path: &'a Value) -> Result<&'a str> {
+        match path {
+            Value::String(path) => Ok(path),
+            _ => Exception::runtime_error(
+                paren.clone(),
+                format!("{native}() expects a path string."),
+            ),
+        }
+    }
+
+    // the contents argument of a file native
+    fn contents<'a>(paren: &Token, native: &str, contents: &'a Value) -> Result<&'a str> {
+        match contents {
+            Value::String(contents) => Ok(contents),
+            _ => Exception::runtime_error(
+                paren.clone(),
+                format!("{native}() expects the contents to be a string."),
+            ),
+        }
+    }
+
+    fn file_error<T>(paren: &Token, action: &str, path: &str, error: io::Error) -> Result<T> {
+        Exception::runtime_error(paren.clone(), format!("Can't {action} '{path}': {error}"))
+    }
+
+    /// Describes the global function, class or native called `name`, including its documentation.
+    pub fn help(&self, name: &str) -> Option<String> {
+        let bindings = self.globals.borrow().bindings();
+        let (name, value) = bindings.iter().find(|(global, _)| global == name)?;
+        help::describe(name, value)
+    }
+
+    /// Lists every global function, class and native with a summary of its documentation.
+    pub fn help_index(&self) -> String {
+        help::index(&self.globals.borrow().bindings())
+    }
+
+    /// The group of the built-in native `name` if the current module hasn't been granted it.
+    pub(crate) fn withheld_group(&self, name: &str) -> Option<NativeGroup> {
+        let group = NativeGroup::of(name).filter(|_| self.builtins.contains(name))?;
+        let module = self.importers.last().map(String::as_str);
+        match self.capabilities.allows(module, group) {
+            true => None,
+            false => Some(group),
+        }
+    }
+
+    /// Whether user code is forbidden from redefining, assigning to or shadowing `name`.
+    pub(crate) fn is_protected_builtin(&self, name: &str) -> bool {
+        !self.allow_builtin_redefinition && self.builtins.contains(name)
+    }
+
+    /// Executes the statements in order, stopping at the first runtime error or interruption.
+    pub fn interpret(&mut self, statements: Vec<Stmt>) -> std::result::Result<(), LoxError> {
         for statement in statements {
-            match self.execute(&statement) {
-                Ok(_) => (),
-                Err(e) => match e {
-                    Exception::RuntimeError(e) => e.error(),
-                    Exception::Return(_) => panic!("Return statement not handled!"),
-                },
+            if let Err(exception) = self.execute(&statement) {
+                return Err(match self.stopped(exception) {
+                    RunState::Failed(error) => error,
+                    _ => LoxError {
+                        message: String::from("Interrupted."),
+                        line: 0,
+                    },
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Scans, parses and resolves `source`, replacing any previously loaded program, so it can
+    /// be executed a slice at a time with [`Interpreter::run_for`].
+    pub fn load(&mut self, source: &str) -> std::result::Result<(), LoxError> {
+        let statements = self.parse(source)?;
+        self.load_statements(statements)
+    }
+
+    /// Scans and parses `source` without resolving it, so the statements can be combined with
+    /// others, such as ones made with [`AstBuilder`](crate::AstBuilder), before loading.
+    pub fn parse(&self, source: &str) -> std::result::Result<Vec<Stmt>, LoxError> {
+        let mut scanner = Scanner::new(source.to_string());
+        let tokens = match PhaseTimes::time(Phase::Scanning, || scanner.scan_tokens()) {
+            Ok(tokens) => tokens,
+            Err(errors) => {
+                for error in errors {
+                    error.error();
+                }
+                return Err(LoxError::from(&errors[0]));
+            }
+        };
+
+        let mut parser = Parser::with_options(tokens, self.parser_options);
+        let statements = PhaseTimes::time(Phase::Parsing, || parser.parse());
+        if let Some(error) = parser.errors.into_iter().next() {
+            return Err(error);
+        }
+
+        Ok(statements)
+    }
+
+    /// Every error and warning in `source`, without loading or running it, for presenting with
+    /// [`render`](crate::render). Like [`Interpreter::load`], it stops after the first stage
+    /// that has errors, and what it finds is printed to stderr as well.
+    pub fn diagnose(&mut self, source: &str) -> Vec<Diagnostic> {
+        let mut scanner = Scanner::new(source.to_string());
+        let tokens = match scanner.scan_tokens() {
+            Ok(tokens) => tokens,
+            Err(errors) => {
+                for error in errors {
+                    error.error();
+                }
+                return errors.iter().map(ScanError::diagnostic).collect();
+            }
+        };
+
+        let mut parser = Parser::with_options(tokens, self.parser_options);
+        let statements = parser.parse();
+        if !parser.errors.is_empty() {
+            return parser.diagnostics;
+        }
+
+        let mut resolver = Resolver::new(self);
+        resolver.resolve_block(&statements);
+        resolver.diagnostics
+    }
+
+    /// Resolves `statements` and loads them in place of any previously loaded program, like
+    /// [`Interpreter::load`] does with parsed source.
+    pub fn load_statements(&mut self, statements: Vec<Stmt>) -> std::result::Result<(), LoxError> {
+        self.resolve_statements(&statements)?;
+
+        self.interrupted.store(false, AtomicOrdering::Relaxed);
+        self.program = Some(Fiber::new(Rc::new(statements), self.globals.clone()));
+        Ok(())
+    }
+
+    fn resolve_statements(&mut self, statements: &[Stmt]) -> std::result::Result<(), LoxError> {
+        let mut resolver = Resolver::new(self);
+        PhaseTimes::time(Phase::Resolving, || resolver.resolve_block(statements));
+        match resolver.errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    // parses and resolves a module, held to the capabilities and strictness its header asks for
+    fn load_module(&mut self, name: &str, source: &str) -> std::result::Result<Vec<Stmt>, String> {
+        let header = ModuleHeader::parse(source)?;
+        let withheld = header
+            .requires
+            .iter()
+            .find(|group| !self.capabilities.allows(Some(name), **group));
+        if let Some(group) = withheld {
+            return Err(format!(
+                "Module '{name}' requires '{group}' natives, which it hasn't been granted."
+            ));
+        }
+
+        let statements = self.parse(source).map_err(|error| error.to_string())?;
+        let diagnostics = self.diagnostics.clone();
+        if header.strict {
+            self.diagnostics = diagnostics.clone().deny_all();
+        }
+        let resolved = self.resolve_statements(&statements);
+        self.diagnostics = diagnostics;
+        resolved
+            .map(|_| statements)
+            .map_err(|error| error.to_string())
+    }
+
+    /// Runs the loaded program until it finishes, fails, or has used up `budget`, in which case it
+    /// pauses at the next statement boundary and carries on from there on the following call.
+    /// Statements inside function calls and `try` blocks always run to completion.
+    pub fn run_for(&mut self, budget: Steps) -> RunState {
+        let Some(mut program) = self.program.take() else {
+            return RunState::Finished;
+        };
+
+        let deadline = self.steps.saturating_add(budget.0);
+        let resumed = PhaseTimes::time(Phase::Interpreting, || {
+            program.resume(self, Value::Nil, deadline)
+        });
+        match resumed {
+            Ok(Suspension::Finished(_)) => RunState::Finished,
+            Ok(Suspension::OutOfSteps) => {
+                self.program = Some(program);
+                RunState::Paused
+            }
+            Ok(Suspension::Yielded(_)) => panic!("Top-level code yielded!"),
+            Err(exception) => self.stopped(exception),
+        }
+    }
+
+    /// A handle that stops whatever this interpreter is running, which can be sent to other
+    /// threads.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        InterruptHandle {
+            interrupted: self.interrupted.clone(),
+        }
+    }
+
+    // what became of a program an exception escaped from
+    fn stopped(&mut self, exception: Exception) -> RunState {
+        match exception {
+            Exception::Interrupted => {
+                self.interrupted.store(false, AtomicOrdering::Relaxed);
+                RunState::Interrupted
+            }
+            exception => RunState::Failed(Interpreter::report(exception)),
+        }
+    }
+
+    /// Fails if an [`InterruptHandle`] has asked for the program to stop.
+    pub(crate) fn check_interrupted(&self) -> Result<()> {
+        match self.interrupted.load(AtomicOrdering::Relaxed) {
+            true => Err(Exception::Interrupted),
+            false => Ok(()),
+        }
+    }
+
+    /// Calls the callbacks scheduled with `setTimeout` and `setInterval` as they come due,
+    /// sleeping in between, until none are left, one fails or the loop is interrupted. Hosts
+    /// run this once the program has finished, as [`run_file`](crate::run_file) does.
+    pub fn run_event_loop(&mut self) -> RunState {
+        while let Some(timer) = self.timers.next() {
+            if let Err(exception) = self.sleep_until(timer.due) {
+                return self.stopped(exception);
+            }
+
+            // an interval is rescheduled first, so its callback can cancel it
+            self.timers.repeat(&timer);
+            let called = PhaseTimes::time(Phase::Interpreting, || {
+                self.call(timer.callback, &timer.paren, vec![])
+            });
+            if let Err(exception) = called {
+                return self.stopped(exception);
+            }
+        }
+
+        RunState::Finished
+    }
+
+    // sleeps a little at a time, so an interrupt doesn't have to wait for the whole sleep. A
+    // fake clock skips straight to `due` instead.
+    fn sleep_until(&mut self, due: Duration) -> Result<()> {
+        if let Clock::Fake(now) = &mut self.clock {
+            *now = due.max(*now);
+            return self.check_interrupted();
+        }
+
+        loop {
+            self.check_interrupted()?;
+            let remaining = due.saturating_sub(self.clock.now());
+            if remaining.is_zero() {
+                return Ok(());
             }
+            thread::sleep(remaining.min(Duration::from_millis(10)));
         }
     }
 
-    fn evaluate(&mut self, expr: &Expr) -> Result<Value> {
+    /// Reports an exception that escaped to the top level.
+    fn report(exception: Exception) -> LoxError {
+        match exception {
+            Exception::RuntimeError(e) => {
+                e.error();
+                LoxError::from(&e)
+            }
+            Exception::Return(_) => panic!("Return statement not handled!"),
+            Exception::Timeout(_) => panic!("Timeout not handled!"),
+            Exception::Interrupted => panic!("Interruption not handled!"),
+            Exception::Throw(keyword, value) => {
+                let e = RuntimeError {
+                    token: keyword,
+                    message: format!("Uncaught error: {}", value),
+                };
+                e.error();
+                LoxError::from(&e)
+            }
+        }
+    }
+
+    pub(crate) fn evaluate(&mut self, expr: &Expr) -> Result<Value> {
+        self.steps += 1;
+        self.check_deadlines()?;
         expr::Visitor::visit_expr(self, expr)
     }
 
-    fn execute(&mut self, stmt: &Stmt) -> Result<()> {
+    pub(crate) fn execute(&mut self, stmt: &Stmt) -> Result<()> {
+        self.steps += 1;
+        self.check_interrupted()?;
+        self.check_deadlines()?;
         stmt::Visitor::visit_stmt(self, stmt)
     }
 
-    pub fn resolve(&mut self, expr: &Expr, depth: usize) {
-        self.locals.insert(expr.clone(), depth);
+    // fails with the outermost `withTimeout` whose time is up, if any
+    pub(crate) fn check_deadlines(&self) -> Result<()> {
+        if self.deadlines.is_empty() {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        match self.deadlines.iter().position(|deadline| *deadline <= now) {
+            Some(level) => Err(Exception::Timeout(level)),
+            None => Ok(()),
+        }
     }
 
-    pub fn execute_block(&mut self, statements: &Vec<Stmt>, environment: EnvRef) -> Result<()> {
-        let previous = self.environment.clone();
+    // schedules the callback in `args` for the event loop, after the delay in `args`
+    fn set_timer(&mut self, args: Vec<Value>, repeat: bool, paren: &Token) -> Result<Value> {
+        let mut args = args.into_iter();
+        let (Some(callback), Some(ms)) = (args.next(), args.next()) else {
+            panic!("Timers take a callback and a delay!");
+        };
 
-        self.environment = environment;
-        for statement in statements {
-            // If an exception occurs we still need to restore to the previous environment.
-            // This mimicks Java's try-finally logic
-            if let Err(e) = self.execute(statement) {
-                self.environment = previous;
-                return Err(e);
+        match ms.as_f64() {
+            Some(ms) if ms >= 0.0 && ms.is_finite() => {
+                let delay = Duration::from_secs_f64(ms / 1000.0);
+                let interval = repeat.then_some(delay);
+                let now = self.clock.now();
+                Ok(Value::Int(
+                    self.timers.schedule(callback, now, delay, interval, paren),
+                ))
             }
+            _ => Exception::runtime_error(
+                paren.clone(),
+                String::from("Expected a non-negative number of milliseconds."),
+            ),
+        }
+    }
+
+    fn clear_timer(&mut self, id: &Value, paren: &Token) -> Result<Value> {
+        match id {
+            Value::Int(id) => Ok(Value::Boolean(self.timers.cancel(*id))),
+            _ => Exception::runtime_error(paren.clone(), String::from("Expected a timer id.")),
+        }
+    }
+
+    /// Calls `callback` with no arguments, failing with a "Timed out" error at `paren` if it
+    /// hasn't returned within `limit`.
+    fn call_with_timeout(
+        &mut self,
+        limit: Duration,
+        callback: Value,
+        paren: &Token,
+    ) -> Result<Value> {
+        let level = self.deadlines.len();
+        self.deadlines.push(Instant::now() + limit);
+        let result = self.call(callback, paren, vec![]);
+        self.deadlines.truncate(level);
+
+        match result {
+            Err(Exception::Timeout(expired)) if expired == level => Exception::runtime_error(
+                paren.clone(),
+                format!("Timed out after {} ms.", limit.as_millis()),
+            ),
+            result => result,
+        }
+    }
+
+    pub(crate) fn resolve(&mut self, expr: &Expr, depth: usize) {
+        self.locals.insert(expr.uid(), depth);
+    }
+
+    pub(crate) fn resolve_receiver(&mut self, super_expr: &Expr, depth: usize) {
+        self.receivers.insert(super_expr.uid(), depth);
+    }
+
+    pub(crate) fn capture(&mut self, function_uid: usize, upvalues: Vec<Upvalue>) {
+        self.upvalues.insert(function_uid, upvalues);
+    }
+
+    pub(crate) fn inline_frame(&mut self, function_uid: usize) {
+        self.inline_frames.insert(function_uid);
+    }
+
+    /// Whether calls to a function declaration need variables closures can share.
+    pub(crate) fn shares_frame(&self, function_uid: usize) -> bool {
+        !self.inline_frames.contains(&function_uid)
+    }
+
+    /// Creates the closure for a function declaration, holding only the variables it captures.
+    fn close_over(&self, declaration: &Stmt) -> EnvRef {
+        let Stmt::Function { uid, .. } = declaration else {
+            panic!("Statement is not a function!");
+        };
+
+        match self.upvalues.get(uid) {
+            Some(upvalues) => Environment::capture(&self.environment, &self.globals, upvalues),
+            None => self.environment.clone(),
+        }
+    }
+
+    pub(crate) fn execute_block(
+        &mut self,
+        statements: &Vec<Stmt>,
+        environment: EnvRef,
+    ) -> Result<()> {
+        // the guard restores the previous environment even if a statement throws,
+        // mimicking Java's try-finally logic
+        let mut scope = self.enter_environment(environment);
+        for statement in statements {
+            scope.execute(statement)?;
         }
 
-        self.environment = previous;
         Ok(())
     }
 
+    fn enter_environment(&mut self, environment: EnvRef) -> EnvironmentGuard<'_> {
+        EnvironmentGuard::new(self, environment)
+    }
+
+    /// Counts a Lox call until the guard is dropped, or fails if calls are already nested as
+    /// deeply as allowed, so runaway recursion is an error rather than a crash.
+    pub(crate) fn enter_call(&mut self, paren: &Token) -> Result<CallGuard<'_>> {
+        if self.call_depth >= self.max_call_depth {
+            return Exception::runtime_error(paren.clone(), String::from("Stack overflow."));
+        }
+
+        self.call_depth += 1;
+        Ok(CallGuard { interpreter: self })
+    }
+
     fn evaluate_super_class(
         &mut self,
         class_name: &Token,
         super_class_expr: &Expr,
-    ) -> Result<Class> {
+    ) -> Result<ClassRef> {
         let evaluated = self.evaluate(super_class_expr)?;
         match evaluated {
             Value::Class(class) => Ok(class),
@@ -111,45 +1220,161 @@ impl Interpreter {
         }
     }
 
+    fn evaluate_traits(&mut self, methods: &[Stmt], trait_exprs: &[Expr]) -> Result<Vec<TraitRef>> {
+        let mut traits: Vec<TraitRef> = vec![];
+
+        for trait_expr in trait_exprs {
+            let trait_name = match trait_expr {
+                Expr::Variable { name, .. } => name,
+                _ => panic!("Trait is not a variable!"),
+            };
+
+            let lox_trait = match self.evaluate(trait_expr)? {
+                Value::Trait(lox_trait) => lox_trait,
+                _ => {
+                    return Exception::runtime_error(
+                        trait_name.clone(),
+                        String::from("Can only mix in traits."),
+                    )
+                }
+            };
+
+            // a method provided by two traits is ambiguous unless the class overrides it
+            for method_name in lox_trait.method_names() {
+                let overridden = methods.iter().any(|method| {
+                    matches!(method, Stmt::Function { name, .. } if name.lexeme == *method_name)
+                });
+                if overridden {
+                    continue;
+                }
+
+                if let Some(other) = traits
+                    .iter()
+                    .find(|other| other.find_method(method_name).is_some())
+                {
+                    return Exception::runtime_error(
+                        trait_name.clone(),
+                        format!(
+                            "Method '{}' is provided by both traits '{}' and '{}'.",
+                            method_name,
+                            other.name(),
+                            lox_trait.name()
+                        ),
+                    );
+                }
+            }
+
+            traits.push(lox_trait);
+        }
+
+        Ok(traits)
+    }
+
     fn visit_block_stmt(&mut self, statements: &Vec<Stmt>) -> Result<()> {
         let local_env = Environment::new_local(&self.environment);
         self.execute_block(statements, local_env)
     }
 
-    fn visit_class_stmt(
-        &mut self,
-        name: &Token,
-        super_class: &Option<Box<Expr>>,
-        methods: &Vec<Stmt>,
-    ) -> Result<()> {
+    fn visit_class_stmt(&mut self, class_stmt: &Stmt) -> Result<()> {
+        let Stmt::Class {
+            name,
+            doc,
+            super_class,
+            traits,
+            methods,
+            abstract_methods,
+            setters,
+        } = class_stmt
+        else {
+            panic!("Statement is not a class!");
+        };
+
         let super_class = match super_class {
-            Some(expr) => {
-                let class = self.evaluate_super_class(name, expr)?;
-                Some(Box::new(class))
-            }
+            Some(expr) => Some(self.evaluate_super_class(name, expr)?),
             None => None,
         };
+        let traits = self.evaluate_traits(methods, traits)?;
 
         self.environment
             .borrow_mut()
             .define(name.lexeme.clone(), Value::Nil);
 
-        let prev_environment = self.environment.clone();
-        if let Some(super_class) = super_class.clone() {
-            self.environment = Environment::new_local(&self.environment);
-            self.environment
-                .borrow_mut()
-                .define(String::from("super"), Value::Class(*super_class));
+        let (runtime_methods, runtime_setters) = match &super_class {
+            Some(super_class) => {
+                let environment = Environment::new_local(&self.environment);
+                environment
+                    .borrow_mut()
+                    .define(String::from("super"), Value::Class(super_class.clone()));
+
+                let scope = self.enter_environment(environment);
+                (
+                    scope.create_methods(methods, true),
+                    scope.create_methods(setters, false),
+                )
+            }
+            None => (
+                self.create_methods(methods, true),
+                self.create_methods(setters, false),
+            ),
+        };
+
+        let class = Class::new(
+            name.lexeme.clone(),
+            doc.clone(),
+            super_class,
+            traits,
+            runtime_methods,
+            abstract_methods
+                .iter()
+                .map(|method| method.lexeme.clone())
+                .collect(),
+            runtime_setters,
+        );
+
+        // a decorated method is replaced by what its decorators make of it, as a patch would
+        for method in methods {
+            let Stmt::Function { name, .. } = method else {
+                panic!("Statement is not a method!");
+            };
+            let decorators = self.evaluate_decorators(method)?;
+            if decorators.is_empty() {
+                continue;
+            }
+
+            let function = class
+                .find_method(&name.lexeme)
+                .expect("class to have its own methods");
+            match self.decorate(decorators, name, function)? {
+                decorated @ Value::Function(_) => class.patch(name, decorated)?,
+                _ => {
+                    return Exception::runtime_error(
+                        name.clone(),
+                        String::from("A method's decorators must make a function."),
+                    )
+                }
+            }
         }
 
+        self.environment
+            .borrow_mut()
+            .assign(name, &Value::Class(class))?;
+
+        Ok(())
+    }
+
+    fn create_methods(
+        &self,
+        methods: &Vec<Stmt>,
+        allow_initializer: bool,
+    ) -> HashMap<String, Function> {
         let mut runtime_methods = HashMap::new();
         for method in methods {
             match method {
                 Stmt::Function { name, .. } => {
-                    let function = Function::new(
+                    let function = Function::method(
                         method.clone(),
-                        self.environment.clone(),
-                        name.lexeme == "init",
+                        self.close_over(method),
+                        allow_initializer && name.lexeme == "init",
                     );
                     runtime_methods.insert(name.lexeme.clone(), function);
                 }
@@ -157,15 +1382,14 @@ impl Interpreter {
             }
         }
 
-        let class = Class::new(name.lexeme.clone(), super_class.clone(), runtime_methods);
-
-        if super_class.is_some() {
-            self.environment = prev_environment;
-        }
+        runtime_methods
+    }
 
+    fn visit_trait_stmt(&mut self, name: &Token, methods: &Vec<Stmt>) -> Result<()> {
+        let lox_trait = Trait::new(name.lexeme.clone(), self.create_methods(methods, false));
         self.environment
             .borrow_mut()
-            .assign(name, &Value::Class(class))?;
+            .define(name.lexeme.clone(), Value::Trait(lox_trait));
 
         Ok(())
     }
@@ -175,20 +1399,48 @@ impl Interpreter {
     }
 
     fn visit_function_stmt(&mut self, name: &Token, function_stmt: &Stmt) -> Result<()> {
-        let function = Function::new(function_stmt.clone(), self.environment.clone(), false);
+        let decorators = self.evaluate_decorators(function_stmt)?;
+
+        // define the name first so a recursive function can capture its own slot
         self.environment
             .borrow_mut()
-            .define(name.lexeme.clone(), Value::Function(function));
+            .define(name.lexeme.clone(), Value::Nil);
+
+        let function = Function::new(function_stmt.clone(), self.close_over(function_stmt), false);
+        let function = self.decorate(decorators, name, Value::Function(function))?;
+        self.environment.borrow_mut().assign_at(0, name, &function);
         Ok(())
     }
 
+    // the values of a declaration's decorators, evaluated top to bottom
+    fn evaluate_decorators(&mut self, function_stmt: &Stmt) -> Result<Vec<Value>> {
+        let Stmt::Function { decorators, .. } = function_stmt else {
+            panic!("Statement is not a function!");
+        };
+
+        decorators
+            .iter()
+            .map(|decorator| self.evaluate(decorator))
+            .collect()
+    }
+
+    // passes a function through its decorators, the one written closest to it first
+    fn decorate(&mut self, decorators: Vec<Value>, name: &Token, function: Value) -> Result<Value> {
+        decorators
+            .into_iter()
+            .rev()
+            .try_fold(function, |function, decorator| {
+                self.call(decorator, name, vec![function])
+            })
+    }
+
     fn visit_if_stmt(
         &mut self,
         condition: &Expr,
         then_branch: &Stmt,
-        else_branch: &Option<Box<Stmt>>,
+        else_branch: &Option<Rc<Stmt>>,
     ) -> Result<()> {
-        if Interpreter::is_truthy(&self.evaluate(condition)?) {
+        if is_truthy(&self.evaluate(condition)?) {
             return self.execute(then_branch);
         }
 
@@ -198,9 +1450,28 @@ impl Interpreter {
         }
     }
 
-    fn visit_print_stmt(&mut self, expr: &Expr) -> Result<()> {
-        let value = self.evaluate(expr)?;
-        self.logger.print(format_args!("{}", value));
+    fn visit_print_stmt(&mut self, exprs: &Vec<Expr>) -> Result<()> {
+        let mut values = Vec::with_capacity(exprs.len());
+        for expr in exprs {
+            values.push(self.evaluate(expr)?);
+        }
+
+        // the line is written into a buffer kept between prints, so printing allocates nothing
+        // once the buffer has grown to fit the longest line
+        let mut line = std::mem::take(&mut self.print_buffer);
+        line.clear();
+        for (index, value) in values.iter().enumerate() {
+            if index > 0 {
+                line.push(' ');
+            }
+            self.semantics
+                .write_display(&mut line, value)
+                .expect("writing to a String to succeed");
+        }
+        self.logger.print(format_args!("{line}"));
+        if line.capacity() <= MAX_PRINT_BUFFER {
+            self.print_buffer = line;
+        }
 
         Ok(())
     }
@@ -212,6 +1483,78 @@ impl Interpreter {
         }
     }
 
+    fn visit_try_stmt(
+        &mut self,
+        body: &Vec<Stmt>,
+        catch: &Option<(Token, Vec<Stmt>)>,
+        finally: &Option<Vec<Stmt>>,
+    ) -> Result<()> {
+        let mut result = self.visit_block_stmt(body);
+
+        // only errors are catchable, returns unwind straight through to the finally block
+        if let Some((name, catch_body)) = catch {
+            let caught = match &result {
+                Err(Exception::RuntimeError(error)) => {
+                    Some(Value::String(error.message.as_str().into()))
+                }
+                Err(Exception::Throw(_, value)) => Some(value.clone()),
+                _ => None,
+            };
+
+            if let Some(caught) = caught {
+                let environment = Environment::new_local(&self.environment);
+                environment.borrow_mut().define(name.lexeme.clone(), caught);
+                result = self.execute_block(catch_body, environment);
+            }
+        }
+
+        if let Some(finally) = finally {
+            // an exception raised by the finally block replaces the one in flight
+            self.visit_block_stmt(finally)?;
+        }
+
+        result
+    }
+
+    fn visit_throw_stmt(&mut self, keyword: &Token, value: &Expr) -> Result<()> {
+        let value = self.evaluate(value)?;
+        Err(Exception::Throw(keyword.clone(), value))
+    }
+
+    fn visit_import_stmt(&mut self, keyword: &Token, path: &Token) -> Result<()> {
+        let Literal::String(path) = &path.literal else {
+            panic!("Module path is not a string!");
+        };
+        let importer = self.importers.last().cloned();
+        let name = self.import_resolver.module_name(path, importer.as_deref());
+        if self.modules.contains(&name) {
+            return Ok(());
+        }
+
+        // the module is resolved as the current module, so it's held to its own grant
+        self.importers.push(name.clone());
+        let loaded = self
+            .import_resolver
+            .resolve(path, importer.as_deref())
+            .and_then(|source| self.load_module(&name, &source));
+        let statements = match loaded {
+            Ok(statements) => statements,
+            Err(message) => {
+                self.importers.pop();
+                return Exception::runtime_error(
+                    keyword.clone(),
+                    format!("Can't import '{path}': {message}"),
+                );
+            }
+        };
+
+        // marked before running so an import cycle doesn't run a module twice
+        self.modules.insert(name);
+        let result = self.execute_block(&statements, self.globals.clone());
+        self.importers.pop();
+        result
+    }
+
     fn visit_var_stmt(&mut self, name: &Token, initializer: &Option<Expr>) -> Result<()> {
         let mut value = Value::Nil;
         if let Some(expr) = initializer {
@@ -225,26 +1568,63 @@ impl Interpreter {
     }
 
     fn visit_while_stmt(&mut self, condition: &Expr, body: &Stmt) -> Result<()> {
-        while Interpreter::is_truthy(&self.evaluate(condition)?) {
+        while is_truthy(&self.evaluate(condition)?) {
             self.execute(body)?;
         }
 
         Ok(())
     }
 
+    fn visit_for_in_stmt(&mut self, name: &Token, iterable: &Expr, body: &Stmt) -> Result<()> {
+        let iterable = self.evaluate(iterable)?;
+        for item in Interpreter::items(iterable, name)? {
+            let environment = Environment::new_local(&self.environment);
+            environment.borrow_mut().define(name.lexeme.clone(), item);
+
+            let mut scope = self.enter_environment(environment);
+            scope.execute(body)?;
+        }
+
+        Ok(())
+    }
+
+    /// What a for-in loop visits: the items of a list as they are when the loop starts, or the
+    /// grapheme clusters of a string.
+    pub(crate) fn items(iterable: Value, name: &Token) -> Result<Vec<Value>> {
+        match iterable {
+            Value::List(list) => Ok(list.borrow().clone()),
+            Value::String(string) => {
+                Ok(string.graphemes().into_iter().map(Value::String).collect())
+            }
+            _ => Exception::runtime_error(
+                name.clone(),
+                String::from("Can only iterate over lists and strings."),
+            ),
+        }
+    }
+
     fn visit_assign_expr(&mut self, name: &Token, value: &Expr, expr: &Expr) -> Result<Value> {
         let value = self.evaluate(value)?;
+        self.assign_variable(name, &value, expr)?;
+        Ok(value)
+    }
 
-        let distance = self.locals.get(expr);
+    pub(crate) fn assign_variable(
+        &mut self,
+        name: &Token,
+        value: &Value,
+        expr: &Expr,
+    ) -> Result<()> {
+        let distance = self.locals.get(&expr.uid());
         match distance {
             Some(distance) => self
                 .environment
                 .borrow_mut()
-                .assign_at(*distance, name, &value),
-            None => self.globals.borrow_mut().assign(name, &value)?,
+                .assign_at(*distance, name, value),
+            None => self.globals.borrow_mut().assign(name, value)?,
         };
 
-        Ok(value)
+        Ok(())
     }
 
     fn visit_binary_expr(&mut self, left: &Expr, operator: &Token, right: &Expr) -> Result<Value> {
@@ -253,49 +1633,34 @@ impl Interpreter {
 
         match operator.token_type {
             // arithmetic
-            TokenType::Minus => match (left, right) {
-                (Value::Number(left), Value::Number(right)) => Ok(Value::Number(left - right)),
-                _ => Interpreter::number_operands_error(operator),
-            },
-            TokenType::Slash => match (left, right) {
-                (Value::Number(left), Value::Number(right)) => Ok(Value::Number(left / right)),
-                _ => Interpreter::number_operands_error(operator),
-            },
+            TokenType::Minus => {
+                Interpreter::arithmetic(operator, Operation::Subtract, &left, &right)
+            }
+            TokenType::Slash => Interpreter::arithmetic(operator, Operation::Divide, &left, &right),
             TokenType::Star => match (left, right) {
-                (Value::Number(left), Value::Number(right)) => Ok(Value::Number(left * right)),
-                _ => Interpreter::number_operands_error(operator),
-            },
-            TokenType::Plus => match (left, right) {
-                (Value::Number(left), Value::Number(right)) => Ok(Value::Number(left + right)),
-                (Value::String(left), Value::String(right)) => {
-                    let mut res = left.to_owned();
-                    res.push_str(&right);
-                    Ok(Value::String(res))
+                (Value::String(string), count) | (count, Value::String(string)) => {
+                    Interpreter::repeat(operator, &string, &count)
                 }
-                _ => Interpreter::number_operands_error(operator),
+                (left, right) => {
+                    Interpreter::arithmetic(operator, Operation::Multiply, &left, &right)
+                }
+            },
+            TokenType::Plus => match self.semantics.concatenate(&left, &right) {
+                Some(string) => Ok(string),
+                None => Interpreter::arithmetic(operator, Operation::Add, &left, &right),
             },
 
             // comparison
-            TokenType::Greater => match (left, right) {
-                (Value::Number(left), Value::Number(right)) => Ok(Value::Boolean(left > right)),
-                _ => Interpreter::number_operands_error(operator),
-            },
-            TokenType::GreaterEqual => match (left, right) {
-                (Value::Number(left), Value::Number(right)) => Ok(Value::Boolean(left >= right)),
-                _ => Interpreter::number_operands_error(operator),
-            },
-            TokenType::Less => match (left, right) {
-                (Value::Number(left), Value::Number(right)) => Ok(Value::Boolean(left < right)),
-                _ => Interpreter::number_operands_error(operator),
-            },
-            TokenType::LessEqual => match (left, right) {
-                (Value::Number(left), Value::Number(right)) => Ok(Value::Boolean(left <= right)),
-                _ => Interpreter::number_operands_error(operator),
-            },
+            TokenType::Greater => Interpreter::comparison(operator, left, right, Ordering::is_gt),
+            TokenType::GreaterEqual => {
+                Interpreter::comparison(operator, left, right, Ordering::is_ge)
+            }
+            TokenType::Less => Interpreter::comparison(operator, left, right, Ordering::is_lt),
+            TokenType::LessEqual => Interpreter::comparison(operator, left, right, Ordering::is_le),
 
             // equality
-            TokenType::BangEqual => Ok(Value::Boolean(!Interpreter::is_equal(left, right))),
-            TokenType::Equal => Ok(Value::Boolean(Interpreter::is_equal(left, right))),
+            TokenType::BangEqual => Ok(Value::Boolean(!self.semantics.is_equal(&left, &right))),
+            TokenType::EqualEqual => Ok(Value::Boolean(self.semantics.is_equal(&left, &right))),
 
             _ => panic!("unexpected operator for binary expression"),
         }
@@ -309,16 +1674,24 @@ impl Interpreter {
             evaluated_args.push(self.evaluate(arg)?);
         }
 
+        self.call(callee, paren, evaluated_args)
+    }
+
+    /// Calls any callable value, such as a Lox callback passed to a native.
+    pub(crate) fn call(&mut self, callee: Value, paren: &Token, args: Vec<Value>) -> Result<Value> {
         match callee {
             Value::Function(callee) => {
-                callee.check_arity(evaluated_args.len(), paren)?;
-                callee.call(self, evaluated_args)
+                callee.check_arity(args.len(), paren)?;
+                callee.call(self, paren, args)
             }
             Value::NativeFunction(callee) => {
-                callee.check_arity(evaluated_args.len(), paren)?;
-                callee.call(self, evaluated_args)
+                callee.check_arity(args.len(), paren)?;
+                callee.call(self, paren, args)
+            }
+            Value::Class(callee) => {
+                callee.check_arity(args.len(), paren)?;
+                callee.call(self, paren, args)
             }
-            Value::Class(callee) => callee.call(self, vec![]),
             _ => Exception::runtime_error(
                 paren.clone(),
                 String::from("Can only call functions and classes."),
@@ -326,25 +1699,33 @@ impl Interpreter {
         }
     }
 
-    fn visit_get_expr(&mut self, object: &Expr, name: &Token) -> Result<Value> {
+    fn visit_get_expr(&mut self, uid: usize, object: &Expr, name: &Token) -> Result<Value> {
         let object = self.evaluate(object)?;
+        if let Value::ClassInstance(instance) = &object {
+            PerfHints::read(uid, name, || instance.borrow().class().to_string());
+        }
+        Interpreter::property(object, name)
+    }
+
+    // `object.name`, which is a field or bound method of an instance, or a built-in method
+    fn property(object: Value, name: &Token) -> Result<Value> {
         match object {
             Value::ClassInstance(instance) => {
                 // pass instance_ref in case .get() needs to bind a method to 'this'
                 let instance_ref = instance.clone();
                 instance.borrow().get(name, instance_ref)
             }
-            _ => Exception::runtime_error(
-                name.clone(),
-                String::from("Only instances have properties."),
-            ),
+            object => methods::get(&object, name),
         }
     }
 
     fn visit_literal_expr(&self, literal: &Literal) -> Value {
         match literal {
-            Literal::String(value) => Value::String(value.clone()),
-            Literal::Number(value) => Value::Number(*value),
+            Literal::String(value) => Value::String(value.as_str().into()),
+            Literal::Int(value) => Value::Int(*value),
+            #[cfg(feature = "bigint")]
+            Literal::BigInt(value) => Value::BigInt(Rc::clone(value)),
+            Literal::Number(value) => self.semantics.number(*value),
             Literal::Bool(value) => Value::Boolean(*value),
             Literal::None => Value::Nil,
         }
@@ -354,10 +1735,10 @@ impl Interpreter {
         let left = self.evaluate(left)?;
 
         if operator.token_type == TokenType::Or {
-            if Interpreter::is_truthy(&left) {
+            if is_truthy(&left) {
                 return Ok(left);
             }
-        } else if !Interpreter::is_truthy(&left) {
+        } else if !is_truthy(&left) {
             return Ok(left);
         }
 
@@ -365,11 +1746,16 @@ impl Interpreter {
     }
 
     fn visit_set_expr(&mut self, object: &Expr, name: &Token, value: &Expr) -> Result<Value> {
+        // both sides are evaluated before either is checked, like the operands of a binary
         let object = self.evaluate(object)?;
+        let value = self.evaluate(value)?;
         match object {
             Value::ClassInstance(instance) => {
-                let value = self.evaluate(value)?;
-                instance.borrow_mut().set(name, value.clone());
+                self.set_property(&instance, name, &value)?;
+                Ok(value)
+            }
+            Value::Class(class) => {
+                class.patch(name, value.clone())?;
                 Ok(value)
             }
             _ => {
@@ -378,10 +1764,29 @@ impl Interpreter {
         }
     }
 
+    fn set_property(
+        &mut self,
+        instance: &ClassInstanceRef,
+        name: &Token,
+        value: &Value,
+    ) -> Result<()> {
+        let setter = instance.borrow().find_setter(&name.lexeme);
+        match setter {
+            Some(setter) => {
+                setter
+                    .bind(instance.clone())
+                    .call(self, name, vec![value.clone()])?;
+            }
+            None => instance.borrow_mut().set(name, value.clone()),
+        }
+
+        Ok(())
+    }
+
     fn visit_super_expr(&mut self, expr: &Expr, method: &Token) -> Result<Value> {
         let distance = self
             .locals
-            .get(expr)
+            .get(&expr.uid())
             .expect("Super class to have been resolved");
 
         let super_class = self
@@ -394,28 +1799,26 @@ impl Interpreter {
             _ => panic!("Expected superclass to be a class!"),
         };
 
+        let receiver = self
+            .receivers
+            .get(&expr.uid())
+            .expect("Receiver of super to have been resolved");
         let this = self
             .environment
             .borrow()
-            // "this" is always right inside where "super" is stored
-            .get_at(*distance - 1, "this")
+            .get_at(*receiver, "this")
             .expect("'this' to have been resolved");
         let this = match this {
             Value::ClassInstance(instance) => instance,
             _ => panic!("Expected 'this' to be a class instance!"),
         };
 
-        let method = super_class.find_method(&method.lexeme).ok_or_else(|| {
-            Exception::runtime_error::<()>(
+        match super_class.find_flattened_method(&method.lexeme) {
+            Some(method) => Ok(Value::Function(method.bind(this))),
+            None => Exception::runtime_error(
                 method.clone(),
                 format!("Undefined property {}.", method.lexeme),
-            )
-            .unwrap_err()
-        })?;
-
-        match method {
-            Value::Function(method) => Ok(Value::Function(method.bind(this))),
-            _ => panic!("Expected method to be a function!"),
+            ),
         }
     }
 
@@ -427,21 +1830,68 @@ impl Interpreter {
         let right_expr = self.evaluate(right)?;
 
         match operator.token_type {
-            TokenType::Minus => match right_expr {
-                Value::Number(value) => Ok(Value::Number(-value)),
-                _ => Interpreter::number_operand_error(operator),
+            TokenType::Minus => match numeric::negate(&right_expr) {
+                Some(Ok(value)) => Ok(value),
+                Some(Err(message)) => Exception::runtime_error(operator.clone(), message),
+                None => Interpreter::number_operand_error(operator),
             },
-            TokenType::Bang => Ok(Value::Boolean(!Interpreter::is_truthy(&right_expr))),
+            TokenType::Bang => Ok(Value::Boolean(!is_truthy(&right_expr))),
             _ => Interpreter::number_operand_error(operator),
         }
     }
 
+    /// Writes the incremented or decremented number back to the variable or field, producing
+    /// the new value for the prefix form and the original one for the postfix form.
+    fn visit_update_expr(
+        &mut self,
+        target: &Expr,
+        operator: &Token,
+        prefix: bool,
+    ) -> Result<Value> {
+        let delta = match operator.token_type {
+            TokenType::PlusPlus => 1,
+            _ => -1,
+        };
+
+        let (old, new) = match target {
+            Expr::Variable { name, .. } => {
+                let old = self.lookup_variable(name, target)?;
+                let new = Interpreter::add_delta(&old, delta, operator)?;
+                self.assign_variable(name, &new, target)?;
+                (old, new)
+            }
+            Expr::Get { object, name, .. } => {
+                let Value::ClassInstance(instance) = self.evaluate(object)? else {
+                    return Exception::runtime_error(
+                        name.clone(),
+                        String::from("Only instances have fields."),
+                    );
+                };
+                let old = instance.borrow().get(name, instance.clone())?;
+                let new = Interpreter::add_delta(&old, delta, operator)?;
+                self.set_property(&instance, name, &new)?;
+                (old, new)
+            }
+            _ => panic!("Update target is not a variable or field!"),
+        };
+
+        Ok(if prefix { new } else { old })
+    }
+
+    fn visit_yield_expr(&mut self, keyword: &Token) -> Result<Value> {
+        // coroutines suspend at yields before evaluating them, so this one wasn't in a coroutine
+        Exception::runtime_error(
+            keyword.clone(),
+            String::from("Can only yield from the body of a coroutine."),
+        )
+    }
+
     fn visit_var_expr(&self, name: &Token, expr: &Expr) -> Result<Value> {
         self.lookup_variable(name, expr)
     }
 
     fn lookup_variable(&self, name: &Token, expr: &Expr) -> Result<Value> {
-        let distance = self.locals.get(expr);
+        let distance = self.locals.get(&expr.uid());
 
         if let Some(distance) = distance {
             self.environment.borrow().get_at(*distance, &name.lexeme)
@@ -450,31 +1900,76 @@ impl Interpreter {
         }
     }
 
-    fn number_operand_error<T>(operator: &Token) -> Result<T> {
-        Exception::runtime_error(operator.clone(), String::from("Operands must be a number."))
+    fn arithmetic(
+        operator: &Token,
+        operation: Operation,
+        left: &Value,
+        right: &Value,
+    ) -> Result<Value> {
+        match numeric::arithmetic(operation, left, right) {
+            Some(Ok(value)) => Ok(value),
+            Some(Err(message)) => Exception::runtime_error(operator.clone(), message),
+            None => Interpreter::number_operands_error(operator),
+        }
     }
 
-    fn number_operands_error<T>(operator: &Token) -> Result<T> {
-        Exception::runtime_error(operator.clone(), String::from("Operands must be numbers."))
+    // `string * count`, written either way round
+    fn repeat(operator: &Token, string: &str, count: &Value) -> Result<Value> {
+        let message = match count.as_integer() {
+            Some(count) if count >= 0 => match string.len().checked_mul(count as usize) {
+                Some(length) if length <= MAX_REPEATED_LENGTH => {
+                    let mut repeated = String::new();
+                    if repeated.try_reserve_exact(length).is_ok() {
+                        (0..count).for_each(|_| repeated.push_str(string));
+                        return Ok(Value::String(repeated.into()));
+                    }
+                    "Repeated string is too long."
+                }
+                _ => "Repeated string is too long.",
+            },
+            Some(_) => "Can't repeat a string a negative number of times.",
+            // a count past i64 is whole, just far too many times
+            #[cfg(feature = "bigint")]
+            None if matches!(count, Value::BigInt(count) if count.is_negative()) => {
+                "Can't repeat a string a negative number of times."
+            }
+            #[cfg(feature = "bigint")]
+            None if matches!(count, Value::BigInt(_)) => "Repeated string is too long.",
+            None => "Can only repeat a string a whole number of times.",
+        };
+
+        Exception::runtime_error(operator.clone(), String::from(message))
     }
 
-    fn is_truthy(value: &Value) -> bool {
-        match value {
-            Value::Nil => false,
-            Value::Boolean(value) => *value,
-            _ => true,
+    fn comparison(
+        operator: &Token,
+        left: Value,
+        right: Value,
+        holds: fn(Ordering) -> bool,
+    ) -> Result<Value> {
+        match numeric::compare(&left, &right) {
+            // comparisons with NaN are always false
+            Some(ordering) => Ok(Value::Boolean(ordering.is_some_and(holds))),
+            None => Interpreter::number_operands_error(operator),
         }
     }
 
-    fn is_equal(left: Value, right: Value) -> bool {
-        match (left, right) {
-            (Value::Nil, Value::Nil) => true,
-            (Value::Number(left), Value::Number(right)) => left == right,
-            (Value::String(left), Value::String(right)) => left == right,
-            (Value::Boolean(left), Value::Boolean(right)) => left == right,
-            _ => false,
+    // the number `++` or `--` makes of `value`
+    fn add_delta(value: &Value, delta: i64, operator: &Token) -> Result<Value> {
+        match numeric::arithmetic(Operation::Add, value, &Value::Int(delta)) {
+            Some(Ok(value)) => Ok(value),
+            Some(Err(message)) => Exception::runtime_error(operator.clone(), message),
+            None => Interpreter::number_operand_error(operator),
         }
     }
+
+    fn number_operand_error<T>(operator: &Token) -> Result<T> {
+        Exception::runtime_error(operator.clone(), String::from("Operands must be a number."))
+    }
+
+    fn number_operands_error<T>(operator: &Token) -> Result<T> {
+        Exception::runtime_error(operator.clone(), String::from("Operands must be numbers."))
+    }
 }
 
 impl expr::Visitor<Result<Value>> for Interpreter {
@@ -505,7 +2000,7 @@ impl expr::Visitor<Result<Value>> for Interpreter {
                 args,
                 ..
             } => self.visit_call_expr(callee, paren, args),
-            Expr::Get { object, name, .. } => self.visit_get_expr(object, name),
+            Expr::Get { uid, object, name } => self.visit_get_expr(*uid, object, name),
             Expr::Set {
                 object,
                 name,
@@ -514,6 +2009,13 @@ impl expr::Visitor<Result<Value>> for Interpreter {
             } => self.visit_set_expr(object, name, value),
             Expr::This { keyword, .. } => self.visit_this_expr(expr, keyword),
             Expr::Super { method, .. } => self.visit_super_expr(expr, method),
+            Expr::Update {
+                target,
+                operator,
+                prefix,
+                ..
+            } => self.visit_update_expr(target, operator, *prefix),
+            Expr::Yield { keyword, .. } => self.visit_yield_expr(keyword),
         }
     }
 }
@@ -522,7 +2024,7 @@ impl stmt::Visitor<Result<()>> for Interpreter {
     fn visit_stmt(&mut self, stmt: &Stmt) -> Result<()> {
         match stmt {
             Stmt::Expression(expr) => self.visit_expr_stmt(expr),
-            Stmt::Print(expr) => self.visit_print_stmt(expr),
+            Stmt::Print(exprs) => self.visit_print_stmt(exprs),
             Stmt::Var { name, initializer } => self.visit_var_stmt(name, initializer),
             Stmt::Block(statements) => self.visit_block_stmt(statements),
             Stmt::If {
@@ -531,13 +2033,345 @@ impl stmt::Visitor<Result<()>> for Interpreter {
                 else_branch,
             } => self.visit_if_stmt(condition, then_branch, else_branch),
             Stmt::While { condition, body } => self.visit_while_stmt(condition, body),
+            Stmt::ForIn {
+                name,
+                iterable,
+                body,
+            } => self.visit_for_in_stmt(name, iterable, body),
             Stmt::Function { name, .. } => self.visit_function_stmt(name, stmt),
             Stmt::Return { value, .. } => self.visit_return_stmt(value),
-            Stmt::Class {
-                name,
-                super_class,
-                methods,
-            } => self.visit_class_stmt(name, super_class, methods),
+            Stmt::Throw { keyword, value } => self.visit_throw_stmt(keyword, value),
+            Stmt::Import { keyword, path } => self.visit_import_stmt(keyword, path),
+            Stmt::Class { .. } => self.visit_class_stmt(stmt),
+            Stmt::Trait { name, methods } => self.visit_trait_stmt(name, methods),
+            Stmt::Try {
+                body,
+                catch,
+                finally,
+            } => self.visit_try_stmt(body, catch, finally),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        parser::Parser, resolver::Resolver, scanner::Scanner, utils::file_system::MemoryFileSystem,
+    };
+
+    use super::*;
+
+    fn interpret_code(
+        interpreter: &mut Interpreter,
+        lox_code: &str,
+    ) -> std::result::Result<(), LoxError> {
+        let mut scanner = Scanner::new(lox_code.to_string());
+        let tokens = scanner.scan_tokens().unwrap();
+
+        let mut parser = Parser::new(tokens);
+        let statements = parser.parse();
+
+        let mut resolver = Resolver::new(interpreter);
+        resolver.resolve_block(&statements);
+
+        interpreter.interpret(statements)
+    }
+
+    fn assert_in_global_scope(interpreter: &Interpreter) {
+        assert!(Rc::ptr_eq(&interpreter.environment, &interpreter.globals));
+    }
+
+    #[test]
+    fn error_in_nested_blocks_restores_environment() {
+        let mut interpreter = Interpreter::new(InterpreterOptions::default());
+        let _ = interpret_code(
+            &mut interpreter,
+            "{ var a = 1; { var b = 2; { var c = -\"oops\"; } } }",
+        );
+
+        assert_in_global_scope(&interpreter);
+    }
+
+    #[test]
+    fn call_depth_is_limited_and_unwinds() {
+        let mut interpreter = Interpreter::new(InterpreterOptions {
+            max_call_depth: Some(10),
+            ..Default::default()
+        });
+        let recursion = "fun f(n) { if (n > 0) f(n - 1); }";
+        interpret_code(&mut interpreter, recursion).unwrap();
+
+        assert!(interpret_code(&mut interpreter, "f(9);").is_ok());
+        assert_eq!(
+            interpret_code(&mut interpreter, "f(10);"),
+            Err(LoxError {
+                message: String::from("Stack overflow."),
+                line: 1,
+            })
+        );
+        assert_eq!(interpreter.call_depth, 0);
+        assert!(interpret_code(&mut interpreter, "f(9);").is_ok());
+    }
+
+    #[test]
+    fn error_in_nested_function_call_restores_environment() {
+        let mut interpreter = Interpreter::new(InterpreterOptions::default());
+        let _ = interpret_code(
+            &mut interpreter,
+            "fun fail() { { { return nil + 1; } } } { var a = 1; { fail(); } }",
+        );
+
+        assert_in_global_scope(&interpreter);
+    }
+
+    #[test]
+    fn return_from_nested_blocks_restores_environment() {
+        let mut interpreter = Interpreter::new(InterpreterOptions::default());
+        let _ = interpret_code(
+            &mut interpreter,
+            "fun early() { { { return 1; } } } { var a = early(); }",
+        );
+
+        assert_in_global_scope(&interpreter);
+    }
+
+    #[test]
+    fn error_in_subclass_method_restores_environment() {
+        let mut interpreter = Interpreter::new(InterpreterOptions::default());
+        let _ = interpret_code(
+            &mut interpreter,
+            "class A { f() { return 1; } } class B < A { g() { return super.f() + \"x\"; } } B().g();",
+        );
+
+        assert_in_global_scope(&interpreter);
+    }
+
+    #[test]
+    fn native_function_can_raise_runtime_error() {
+        let mut interpreter = Interpreter::new(InterpreterOptions::default());
+        interpreter.define_native("fail", 0, "Always fails.", |_, paren, _| {
+            Exception::runtime_error(paren.clone(), String::from("Native failure."))
+        });
+
+        let result = interpret_code(&mut interpreter, "var a = 1;\nfail();");
+        assert_eq!(
+            result,
+            Err(LoxError {
+                message: String::from("Native failure."),
+                line: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn builtins_are_protected_unless_allowed() {
+        let interpreter = Interpreter::new(InterpreterOptions::default());
+        assert!(interpreter.is_protected_builtin("clock"));
+        assert!(!interpreter.is_protected_builtin("print_time"));
+
+        let mut interpreter = Interpreter::new(InterpreterOptions {
+            allow_builtin_redefinition: true,
+            ..Default::default()
+        });
+        assert!(!interpreter.is_protected_builtin("clock"));
+
+        interpret_code(&mut interpreter, "var clock = 1;\nclock = clock + 1;").unwrap();
+        assert!(matches!(
+            interpreter.globals.borrow().get_at(0, "clock").unwrap(),
+            Value::Int(2)
+        ));
+    }
+
+    #[test]
+    fn modules_only_use_granted_natives() {
+        let file_system = MemoryFileSystem::new()
+            .with_file(
+                "text.lox",
+                "fun shout(text) { return join(split(text, \"\"), \"!\"); }",
+            )
+            .with_file("timer.lox", "var started = clock();");
+        let mut interpreter = Interpreter::new(InterpreterOptions {
+            file_system: Some(Rc::new(file_system)),
+            capabilities: Capabilities::default()
+                .grant_by_default(&[NativeGroup::Strings])
+                .grant("main.lox", &NativeGroup::ALL),
+            ..Default::default()
+        });
+
+        interpreter.load("import \"text.lox\";").unwrap();
+        assert_eq!(interpreter.run_for(Steps(u64::MAX)), RunState::Finished);
+
+        interpreter.load("import \"timer.lox\";").unwrap();
+        assert_eq!(
+            interpreter.run_for(Steps(u64::MAX)),
+            RunState::Failed(LoxError {
+                message: String::from(
+                    "Can't import 'timer.lox': 'clock' is a 'time' native, which module \
+                     'timer.lox' hasn't been granted.\n[line 1]"
+                ),
+                line: 1,
+            })
+        );
+
+        let error = interpreter.load("print clock();").unwrap_err();
+        assert_eq!(
+            error.message,
+            "'clock' is a 'time' native, which this program hasn't been granted."
+        );
+
+        interpreter.importers.push(String::from("main.lox"));
+        interpreter.load("print clock() > 0;").unwrap();
+    }
+
+    #[test]
+    fn module_headers_are_checked_on_import() {
+        let file_system = MemoryFileSystem::new()
+            .with_file("fetch.lox", "//! requires strings, net\nvar a = 1;")
+            .with_file("text.lox", "//! requires strings\nvar b = 2;")
+            .with_file("strict.lox", "//! strict\nfun f() { var unused = 1; }")
+            .with_file("typo.lox", "//! requries io\n");
+        let mut interpreter = Interpreter::new(InterpreterOptions {
+            file_system: Some(Rc::new(file_system)),
+            capabilities: Capabilities::default().grant_by_default(&[NativeGroup::Strings]),
+            ..Default::default()
+        });
+        let import = |interpreter: &mut Interpreter, path: &str| {
+            interpreter.load(&format!("import \"{path}\";")).unwrap();
+            interpreter.run_for(Steps(u64::MAX))
+        };
+
+        assert_eq!(import(&mut interpreter, "text.lox"), RunState::Finished);
+        for (path, message) in [
+            (
+                "fetch.lox",
+                "Can't import 'fetch.lox': Module 'fetch.lox' requires 'net' natives, which it \
+                 hasn't been granted.",
+            ),
+            (
+                "strict.lox",
+                "Can't import 'strict.lox': Variable 'unused' is never read. \
+                 [unused_variable]\n[line 2]",
+            ),
+            (
+                "typo.lox",
+                "Can't import 'typo.lox': Unknown module header '//! requries io'.",
+            ),
+        ] {
+            assert_eq!(
+                import(&mut interpreter, path),
+                RunState::Failed(LoxError {
+                    message: String::from(message),
+                    line: 1,
+                })
+            );
+        }
+        // the strict module's warnings are only errors inside it
+        assert_eq!(interpreter.diagnostics, DiagnosticOptions::default());
+    }
+
+    #[test]
+    fn help_describes_globals() {
+        let mut interpreter = Interpreter::new(InterpreterOptions::default());
+        interpret_code(
+            &mut interpreter,
+            "/// Logs things.\n/// Tags are optional.\nfun log(message, ...tags) {}\nvar answer = 42;",
+        )
+        .unwrap();
+
+        assert_eq!(
+            interpreter.help("log").unwrap(),
+            "fun log(message, ...tags)\n    Logs things.\n    Tags are optional."
+        );
+        assert_eq!(interpreter.help("answer"), None);
+        assert_eq!(interpreter.help("missing"), None);
+
+        let index = interpreter.help_index();
+        assert!(index.contains("native clock/0"));
+        assert!(index.contains("Logs things."));
+        assert!(!index.contains("Tags are optional."));
+        assert!(!index.contains("answer"));
+    }
+
+    fn global_number(interpreter: &Interpreter, name: &str) -> f64 {
+        let value = interpreter.globals.borrow().get_at(0, name).unwrap();
+        match value.as_f64() {
+            Some(value) => value,
+            None => panic!("{name} is not a number: {value}"),
         }
     }
+
+    #[test]
+    fn run_for_pauses_and_resumes_inside_loops() {
+        let mut interpreter = Interpreter::new(InterpreterOptions::default());
+        interpreter
+            .load(
+                "var total = 0;
+                 {
+                   var i = 0;
+                   while (i < 100) {
+                     i = i + 1;
+                     if (i > 50) total = total + 1;
+                   }
+                 }",
+            )
+            .unwrap();
+
+        assert_eq!(interpreter.run_for(Steps(20)), RunState::Paused);
+        assert_eq!(global_number(&interpreter, "total"), 0.0);
+
+        let mut slices = 1;
+        while interpreter.run_for(Steps(20)) == RunState::Paused {
+            slices += 1;
+        }
+
+        assert!(slices > 10);
+        assert_eq!(global_number(&interpreter, "total"), 50.0);
+        assert_in_global_scope(&interpreter);
+        assert_eq!(interpreter.run_for(Steps(20)), RunState::Finished);
+    }
+
+    #[test]
+    fn run_for_reports_errors_and_restores_environment() {
+        let mut interpreter = Interpreter::new(InterpreterOptions::default());
+        interpreter
+            .load("var i = 0;\n{ while (true) { i = i + 1; if (i == 30) i = -\"x\"; } }")
+            .unwrap();
+
+        let state = loop {
+            match interpreter.run_for(Steps(10)) {
+                RunState::Paused => continue,
+                state => break state,
+            }
+        };
+
+        assert_eq!(
+            state,
+            RunState::Failed(LoxError {
+                message: String::from("Operands must be a number."),
+                line: 2,
+            })
+        );
+        assert_in_global_scope(&interpreter);
+    }
+
+    #[test]
+    fn load_rejects_invalid_programs() {
+        let mut interpreter = Interpreter::new(InterpreterOptions::default());
+
+        assert_eq!(
+            interpreter.load("var = 1;"),
+            Err(LoxError {
+                message: String::from("Expect variable name."),
+                line: 1,
+            })
+        );
+        assert_eq!(
+            interpreter.load("return 1;"),
+            Err(LoxError {
+                message: String::from("Can't return from top-level code"),
+                line: 1,
+            })
+        );
+        assert_eq!(interpreter.run_for(Steps(10)), RunState::Finished);
+    }
 }
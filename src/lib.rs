@@ -1,27 +1,93 @@
-use std::{fs, io, process};
+use std::{fmt::Display, fs, io};
 
-use interpreter::Interpreter;
+pub use capabilities::{Capabilities, NativeGroup};
+pub use diagnostics::{DiagnosticOptions, Level, Warning};
+pub use environment::EnvStats;
+pub use interpreter::{
+    Interpreter, InterpreterOptions, InterruptHandle, RunState, Steps, DEFAULT_MAX_CALL_DEPTH,
+    STACK_SIZE,
+};
 use parser::Parser;
-use resolver::Resolver;
-use scanner::Scanner;
-use syntax::{token::Token, value::Value};
-pub use utils::logger::Logger;
+pub use parser::ParserOptions;
+pub use perf_hints::{PerfHints, MEGAMORPHIC_CLASSES};
+pub use phase_times::{Phase, PhaseTimes};
+pub use pool::PoolStats;
+pub use render::{render, Diagnostic, ErrorFormat, Format, Severity};
+use resolver::{Resolution, Resolver};
+use scanner::{ScanError, Scanner};
+pub use semantics::Semantics;
+pub use syntax::{
+    builder::AstBuilder,
+    expr::Expr,
+    stmt::Stmt,
+    token::{Literal, Token, TokenType},
+    value::Value,
+};
+use utils::logger::StdoutLogger;
+pub use utils::{
+    ast_printer::pretty_print,
+    file_system::{FileSystem, MemoryFileSystem, StdFileSystem},
+    import::{FileImportResolver, ImportResolver},
+    line_source::LineSource,
+    logger::Logger,
+};
+pub use worker::{Message, Worker};
 
+/// The types most embedders need, importable in one line with `use rlox::prelude::*`.
+pub mod prelude {
+    pub use crate::{
+        AstBuilder, Capabilities, DiagnosticOptions, FileSystem, ImportResolver, Interpreter,
+        InterpreterOptions, InterruptHandle, Level, LineSource, Logger, LoxError, NativeGroup,
+        ParserOptions, RunState, Semantics, Steps, Value, Warning,
+    };
+}
+
+mod capabilities;
+mod diagnostics;
 mod environment;
+mod fiber;
 mod impls;
 mod interpreter;
+mod numeric;
 mod parser;
+mod perf_hints;
+mod phase_times;
+mod pool;
+mod render;
 mod resolver;
 mod scanner;
-mod syntax;
+mod semantics;
+pub mod syntax;
+mod timers;
 mod utils;
+mod worker;
 
-static mut HAD_RUNTIME_ERROR: bool = false;
+/// How running or inspecting a script ended, for the binary to turn into an exit code. The
+/// library never exits the process itself.
+#[must_use]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Success,
+    /// The script couldn't be read.
+    UnreadableFile,
+    /// The script didn't scan, parse or resolve, so none of it ran.
+    StaticError,
+    /// The script failed while running.
+    RuntimeError,
+    /// The script was stopped through an [`InterruptHandle`].
+    Interrupted,
+}
 
 #[derive(Debug)]
 enum Exception {
     RuntimeError(RuntimeError),
     Return(Value),
+    Throw(Token, Value),
+    // the deadline of the `withTimeout` call this many levels deep passed; it unwinds through
+    // `try` blocks, whose `finally` blocks can't run either, so timed code can't outlast it
+    Timeout(usize),
+    // an `InterruptHandle` asked the running program to stop; nothing in Lox can catch it
+    Interrupted,
 }
 
 impl Exception {
@@ -36,80 +102,356 @@ struct RuntimeError {
     message: String,
 }
 
+/// A runtime error reported back to the code driving the interpreter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoxError {
+    pub message: String,
+    pub line: usize,
+}
+
+impl From<&RuntimeError> for LoxError {
+    fn from(error: &RuntimeError) -> LoxError {
+        LoxError {
+            message: error.message.clone(),
+            line: error.token.line,
+        }
+    }
+}
+
+impl From<&ScanError> for LoxError {
+    fn from(error: &ScanError) -> LoxError {
+        LoxError {
+            message: error.message.clone(),
+            line: error.line,
+        }
+    }
+}
+
+impl Display for LoxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}\n[line {}]", self.message, self.line)
+    }
+}
+
+impl std::error::Error for LoxError {}
+
 impl RuntimeError {
     fn error(&self) {
-        println!("{}", self.message);
-        println!("[line {}]", self.token.line);
+        let diagnostic = Diagnostic::error(
+            self.token.line,
+            Some(self.token.lexeme.clone()),
+            self.message.clone(),
+        );
+        if render::collect(diagnostic).is_some() {
+            println!("{}", self.message);
+            println!("[line {}]", self.token.line);
+        }
+    }
+}
+
+pub fn run_file(path: &str, logger: Option<Box<dyn Logger>>) -> Outcome {
+    run_file_with_options(
+        path,
+        InterpreterOptions {
+            logger,
+            ..Default::default()
+        },
+    )
+}
+
+pub fn run_file_with_options(path: &str, options: InterpreterOptions) -> Outcome {
+    let Some(contents) = read_script(path) else {
+        return Outcome::UnreadableFile;
+    };
+    if options.error_format == ErrorFormat::Human {
+        return run_script(path, &contents, options);
+    }
+
+    let (outcome, mut diagnostics) = render::collecting(|| run_script(path, &contents, options));
+    for diagnostic in &mut diagnostics {
+        diagnostic.file = Some(path.to_string());
+    }
+    eprint!("{}", render(Format::Json, &contents, &diagnostics));
+    outcome
+}
 
-        unsafe { HAD_RUNTIME_ERROR = true }
+fn run_script(path: &str, contents: &str, options: InterpreterOptions) -> Outcome {
+    let mut interpreter = Interpreter::new(options);
+    interpreter.importers.push(path.to_string());
+    if interpreter.load(contents).is_err() {
+        return Outcome::StaticError;
+    }
+
+    let state = match interpreter.run_for(Steps(u64::MAX)) {
+        RunState::Finished => interpreter.run_event_loop(),
+        state => state,
+    };
+    match state {
+        RunState::Interrupted => Outcome::Interrupted,
+        RunState::Failed(_) => Outcome::RuntimeError,
+        _ => Outcome::Success,
     }
 }
 
-pub fn runtime_error() -> bool {
-    unsafe { HAD_RUNTIME_ERROR }
+/// Prints the syntax tree of the file, parsed with `parser`.
+pub fn print_ast(path: &str, parser: ParserOptions) -> Outcome {
+    let Some(contents) = read_script(path) else {
+        return Outcome::UnreadableFile;
+    };
+    let Some(statements) = parse_script(&contents, parser) else {
+        return Outcome::StaticError;
+    };
+
+    println!("{}", pretty_print(&statements));
+    Outcome::Success
 }
 
-fn check_runtime_error() {
-    unsafe {
-        if HAD_RUNTIME_ERROR {
-            process::exit(70)
-        }
+/// Prints, for every variable, `this` and `super` expression in the file, the scope the resolver
+/// found it in, ordered by line. The file is parsed with `parser`.
+pub fn explain_resolution(path: &str, parser: ParserOptions) -> Outcome {
+    let Some(contents) = read_script(path) else {
+        return Outcome::UnreadableFile;
+    };
+    let Some(statements) = parse_script(&contents, parser) else {
+        return Outcome::StaticError;
+    };
+
+    let mut interpreter = Interpreter::new(InterpreterOptions {
+        parser,
+        ..Default::default()
+    });
+    let mut resolver = Resolver::new(&mut interpreter);
+    resolver.explain();
+    resolver.resolve_block(&statements);
+    if !resolver.errors.is_empty() {
+        return Outcome::StaticError;
     }
+
+    print_resolutions(
+        &mut StdoutLogger,
+        resolver.explanations.unwrap_or_default(),
+        "",
+    );
+    Outcome::Success
 }
 
-pub fn run_file(path: &str, logger: Option<Box<dyn Logger>>) {
-    // let _bytes = fs::read(path).expect("file to be readable");
+/// Prints the file as syntax-highlighted HTML with its errors and warnings marked inline, as
+/// the web playground shows it. The warnings are the ones `diagnostics` lets through, and the
+/// file is parsed with `parser`.
+pub fn render_html(path: &str, diagnostics: DiagnosticOptions, parser: ParserOptions) -> Outcome {
+    let Some(contents) = read_script(path) else {
+        return Outcome::UnreadableFile;
+    };
 
-    let mut interpreter = Interpreter::new(logger);
-    let contents = fs::read_to_string(path).expect("file to be readable");
-    run(contents, &mut interpreter);
+    let mut interpreter = Interpreter::new(InterpreterOptions {
+        diagnostics,
+        parser,
+        ..Default::default()
+    });
+    let found = interpreter.diagnose(&contents);
+    print!("{}", render(Format::Html, &contents, &found));
+    match found
+        .iter()
+        .any(|diagnostic| diagnostic.severity == Severity::Error)
+    {
+        true => Outcome::StaticError,
+        false => Outcome::Success,
+    }
+}
 
-    unsafe {
-        if HAD_RUNTIME_ERROR {
-            process::exit(70)
+fn read_script(path: &str) -> Option<String> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Some(contents),
+        Err(error) => {
+            eprintln!("Can't read '{path}': {error}");
+            None
         }
     }
 }
 
-pub fn run_prompt() {
-    let mut interpreter = Interpreter::new(None);
+// the script's statements, or None once its syntax errors have been reported
+fn parse_script(source: &str, options: ParserOptions) -> Option<Vec<Stmt>> {
+    let mut scanner = Scanner::new(source.to_string());
+    let tokens = match scanner.scan_tokens() {
+        Ok(tokens) => tokens,
+        Err(errors) => {
+            for error in errors {
+                error.error();
+            }
+            return None;
+        }
+    };
 
-    loop {
-        println!("> ");
+    let mut parser = Parser::with_options(tokens, options);
+    let statements = parser.parse();
+    match parser.errors.is_empty() {
+        true => Some(statements),
+        false => None,
+    }
+}
 
-        let mut user_input = String::new();
-        io::stdin()
-            .read_line(&mut user_input)
-            .expect("valid user input");
+fn print_resolutions(output: &mut dyn Logger, mut resolutions: Vec<Resolution>, indent: &str) {
+    resolutions.sort_by_key(|resolution| resolution.line);
+    for resolution in resolutions {
+        output.print(format_args!("{indent}{resolution}"));
+    }
+}
+
+/// Runs the REPL on the terminal.
+pub fn run_prompt(options: InterpreterOptions) {
+    repl(
+        &mut Interpreter::new(options),
+        io::stdin(),
+        &mut StdoutLogger,
+    );
+}
 
+/// Runs a REPL session in `interpreter`, reading input from `lines` until it ends or reads
+/// `exit`, and printing the output of REPL commands to `output`. What the session's code prints
+/// goes to the interpreter's logger, and errors are reported as usual; they end the input they
+/// happen in, not the session. So does an interruption, which is noted in `output`.
+pub fn repl(interpreter: &mut Interpreter, mut lines: impl LineSource, output: &mut dyn Logger) {
+    while let Some(user_input) = read_input(&mut lines) {
         let user_input = user_input.trim();
         if user_input == "exit" {
             break;
         }
 
-        run(user_input.to_string(), &mut interpreter);
+        if let Some(topic) = repl_command(user_input, ":help") {
+            match topic {
+                "" => output.print(format_args!("{}", interpreter.help_index())),
+                name => match interpreter.help(name) {
+                    Some(help) => output.print(format_args!("{help}")),
+                    None => {
+                        output.print(format_args!("No function, class or native named '{name}'."))
+                    }
+                },
+            }
+            continue;
+        }
+
+        let stages = [(":parse", false), (":resolve", true)]
+            .into_iter()
+            .find_map(|(command, resolve)| {
+                repl_command(user_input, command).map(|code| (command, code, resolve))
+            });
+        if let Some((command, code, resolve)) = stages {
+            match code {
+                "" => output.print(format_args!("Usage: {command} <code>")),
+                code => show_stages(code, interpreter, resolve, output),
+            }
+            continue;
+        }
+
+        // errors have been reported by the time these return
+        if interpreter.load(user_input).is_err() {
+            continue;
+        }
+        let state = match interpreter.run_for(Steps(u64::MAX)) {
+            RunState::Finished => interpreter.run_event_loop(),
+            state => state,
+        };
+        if state == RunState::Interrupted {
+            output.print(format_args!("Interrupted."));
+        }
     }
 }
 
-fn run(source: String, interpreter: &mut Interpreter) {
-    let mut scanner = Scanner::new(source);
-    let tokens = scanner.scan_tokens();
+/// Reads the next piece of input, carrying on over more lines while it has unclosed braces, or
+/// returns None once `lines` has ended.
+fn read_input(lines: &mut impl LineSource) -> Option<String> {
+    let mut source = lines.read_line("> ")?;
+    while unclosed_braces(&source) {
+        let Some(line) = lines.read_line("... ") else {
+            break;
+        };
+        source.push('\n');
+        source.push_str(&line);
+    }
+
+    Some(source)
+}
 
-    let mut parser = Parser::new(tokens);
+// source that doesn't scan is left for the error to be reported when it runs
+fn unclosed_braces(source: &str) -> bool {
+    let mut scanner = Scanner::new(source.to_string());
+    let Ok(tokens) = scanner.scan_tokens() else {
+        return false;
+    };
+
+    let depth = tokens
+        .iter()
+        .fold(0, |depth, token| match token.token_type {
+            TokenType::LeftBrace => depth + 1,
+            TokenType::RightBrace => depth - 1,
+            _ => depth,
+        });
+    depth > 0
+}
+
+// the argument of a REPL command such as `:help name`, if `input` is that command
+fn repl_command<'a>(input: &'a str, command: &str) -> Option<&'a str> {
+    input
+        .strip_prefix(command)
+        .filter(|argument| argument.is_empty() || argument.starts_with(' '))
+        .map(str::trim)
+}
+
+/// Prints the tokens, syntax tree and, if `resolve` is set, the resolution of every variable in
+/// `source`, without running it, stopping at the first stage with errors.
+fn show_stages(
+    source: &str,
+    interpreter: &mut Interpreter,
+    resolve: bool,
+    output: &mut dyn Logger,
+) {
+    let mut scanner = Scanner::new(source.to_string());
+    let tokens = match scanner.scan_tokens() {
+        Ok(tokens) => tokens,
+        Err(errors) => {
+            for error in errors {
+                error.error();
+            }
+            return;
+        }
+    };
+
+    output.print(format_args!("Tokens:"));
+    for token in tokens {
+        output.print(format_args!("    {token}"));
+    }
+
+    let mut parser = Parser::with_options(tokens, interpreter.parser_options);
     let statements = parser.parse();
+    if !parser.errors.is_empty() {
+        return;
+    }
+
+    output.print(format_args!("Syntax tree:"));
+    for line in pretty_print(&statements).lines() {
+        output.print(format_args!("    {line}"));
+    }
 
-    check_runtime_error();
+    if !resolve {
+        return;
+    }
 
     let mut resolver = Resolver::new(interpreter);
+    resolver.explain();
     resolver.resolve_block(&statements);
+    if !resolver.errors.is_empty() {
+        return;
+    }
 
-    check_runtime_error();
-
-    interpreter.interpret(statements);
+    output.print(format_args!("Resolution:"));
+    print_resolutions(output, resolver.explanations.unwrap_or_default(), "    ");
 }
 
 // calling code will throw error
 pub fn print_error(line: usize, location: String, message: &str) {
-    eprintln!("[line {line}] Error {location}: {message}");
-    unsafe { HAD_RUNTIME_ERROR = true }
+    render::report(Diagnostic::error(line, Some(location), message.to_string()));
+}
+
+pub fn print_warning(line: usize, location: String, message: &str) {
+    render::report(Diagnostic::warning(line, location, message.to_string()));
 }
@@ -1,23 +1,145 @@
-use std::{cmp::Ordering, env, process};
+use std::{env, path::Path, process, thread};
 
-use rlox::{run_file, run_prompt};
+use cli::Command;
+use rlox::{
+    explain_resolution, print_ast, render_html, run_file_with_options, run_prompt, Capabilities,
+    EnvStats, InterpreterOptions, InterruptHandle, NativeGroup, Outcome, ParserOptions, PerfHints,
+    PhaseTimes, PoolStats, STACK_SIZE,
+};
+
+mod cli;
+mod gen_tests;
+mod signals;
 
 fn main() {
-    env::set_var("RUST_BACKTRACE", "1");
+    let command = match cli::parse(env::args().skip(1)) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("{message}\n{}", cli::USAGE);
+            process::exit(64);
+        }
+    };
 
-    let args: Vec<String> = env::args().collect();
-    println!("{:?}", args);
+    // deep recursion needs more native stack than the main thread has
+    let interpreter = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || run(command))
+        .expect("interpreter thread to start");
+    let outcome = interpreter.join().expect("interpreter not to panic");
 
-    match args.len().cmp(&2) {
-        Ordering::Greater => {
-            println!("Usage: rlox [script]");
-            process::exit(64);
+    process::exit(exit_code(outcome));
+}
+
+fn run(command: Command) -> Outcome {
+    match command {
+        Command::Help => {
+            println!("{}", cli::help());
+            Outcome::Success
         }
-        Ordering::Equal => {
-            run_file(&args[1], None);
+        Command::Version => {
+            println!("{}", cli::version_and_features());
+            Outcome::Success
         }
-        _ => {
-            run_prompt();
+        Command::Ast {
+            script,
+            optional_semicolons,
+        } => print_ast(&script, parser_options(optional_semicolons)),
+        Command::ExplainResolution {
+            script,
+            optional_semicolons,
+        } => explain_resolution(&script, parser_options(optional_semicolons)),
+        Command::Html {
+            script,
+            diagnostics,
+            optional_semicolons,
+        } => render_html(&script, diagnostics, parser_options(optional_semicolons)),
+        Command::GenTests { feature, dir } => match gen_tests::write(&feature, Path::new(&dir)) {
+            Ok(path) => {
+                println!("Wrote {}", path.display());
+                Outcome::Success
+            }
+            Err(message) => {
+                eprintln!("{message}");
+                // the nearest exit code to a file that couldn't be created
+                Outcome::UnreadableFile
+            }
+        },
+        Command::Run {
+            script,
+            args,
+            diagnostics,
+            optional_semicolons,
+            deterministic,
+            env_stats,
+            perf_hints,
+            allow_net,
+            time,
+            error_format,
+        } => {
+            // Ctrl-C stops the running code, leaving the REPL or exiting once it has
+            let interrupt_handle = InterruptHandle::new();
+            signals::forward_interrupts(interrupt_handle.clone());
+            let options = InterpreterOptions {
+                diagnostics,
+                parser: parser_options(optional_semicolons),
+                interrupt_handle: Some(interrupt_handle),
+                deterministic,
+                error_format,
+                args,
+                capabilities: match allow_net {
+                    true => Capabilities::default().grant_by_default(&NativeGroup::ALL),
+                    false => Capabilities::default(),
+                },
+                ..Default::default()
+            };
+
+            if env_stats {
+                EnvStats::start();
+                PoolStats::reset();
+            }
+            if perf_hints {
+                PerfHints::start();
+            }
+            if time {
+                PhaseTimes::start();
+            }
+            let outcome = match script {
+                Some(script) => run_file_with_options(&script, options),
+                None => {
+                    println!("{} (type :help for help, exit to quit)", cli::version());
+                    run_prompt(options);
+                    Outcome::Success
+                }
+            };
+            if let Some(stats) = EnvStats::stop() {
+                eprint!("{stats}{}", PoolStats::current());
+            }
+            if let Some(hints) = PerfHints::stop() {
+                eprint!("{hints}");
+            }
+            if let Some(times) = PhaseTimes::stop() {
+                eprint!("{times}");
+            }
+            outcome
         }
     }
 }
+
+// the sysexits.h codes, as in the book
+fn parser_options(optional_semicolons: bool) -> ParserOptions {
+    ParserOptions {
+        optional_semicolons,
+        ..Default::default()
+    }
+}
+
+fn exit_code(outcome: Outcome) -> i32 {
+    match outcome {
+        Outcome::Success => 0,
+        Outcome::StaticError => 65,
+        Outcome::UnreadableFile => 66,
+        Outcome::RuntimeError => 70,
+        // killed by SIGINT, as shells report it
+        Outcome::Interrupted => 130,
+    }
+}
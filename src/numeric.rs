@@ -0,0 +1,273 @@
+use std::cmp::Ordering;
+#[cfg(feature = "bigint")]
+use std::rc::Rc;
+
+#[cfg(feature = "bigint")]
+use crate::impls::bigint::BigInt;
+use crate::{impls::decimal::Decimal, syntax::value::Value};
+
+/// The arithmetic operators.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Operation {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+/// A number of any kind. The kinds form a tower, from integers through big integers and
+/// decimals to floats, and an operation on two different kinds climbs to the higher one first:
+/// an integer and a decimal make a decimal, and anything and a float make a float.
+#[derive(Clone, Debug)]
+enum Number {
+    Int(i64),
+    #[cfg(feature = "bigint")]
+    Big(Rc<BigInt>),
+    Decimal(Decimal),
+    Float(f64),
+}
+
+// how far up the tower each kind is
+const INT: u8 = 0;
+#[cfg(feature = "bigint")]
+const BIG: u8 = 1;
+const DECIMAL: u8 = 2;
+const FLOAT: u8 = 3;
+
+impl Number {
+    fn from_value(value: &Value) -> Option<Number> {
+        match value {
+            Value::Int(value) => Some(Number::Int(*value)),
+            #[cfg(feature = "bigint")]
+            Value::BigInt(value) => Some(Number::Big(value.clone())),
+            Value::Decimal(value) => Some(Number::Decimal(*value)),
+            Value::Number(value) => Some(Number::Float(*value)),
+            _ => None,
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Number::Int(_) => INT,
+            #[cfg(feature = "bigint")]
+            Number::Big(_) => BIG,
+            Number::Decimal(_) => DECIMAL,
+            Number::Float(_) => FLOAT,
+        }
+    }
+
+    // the number as the kind at `rank`, or as a float if it's too big for that kind
+    fn climb(self, rank: u8) -> Number {
+        match self {
+            number if number.rank() >= rank => number,
+            #[cfg(feature = "bigint")]
+            Number::Int(value) if rank == BIG => Number::Big(Rc::new(BigInt::from_i64(value))),
+            Number::Int(value) if rank == DECIMAL => Number::Decimal(Decimal::from_i64(value)),
+            #[cfg(feature = "bigint")]
+            Number::Big(value) if rank == DECIMAL => match Decimal::parse(&value.to_string()) {
+                Some(decimal) => Number::Decimal(decimal),
+                None => Number::Float(value.to_f64()),
+            },
+            number => Number::Float(number.to_f64()),
+        }
+    }
+
+    fn to_f64(&self) -> f64 {
+        match self {
+            Number::Int(value) => *value as f64,
+            #[cfg(feature = "bigint")]
+            Number::Big(value) => value.to_f64(),
+            Number::Decimal(value) => value.to_f64(),
+            Number::Float(value) => *value,
+        }
+    }
+}
+
+/// `left operation right`, or None if either side isn't a number. Integers stay integers under
+/// `+`, `-` and `*` unless they overflow, which is an error, or makes a big integer with the
+/// `bigint` feature.
+pub(crate) fn arithmetic(
+    operation: Operation,
+    left: &Value,
+    right: &Value,
+) -> Option<Result<Value, String>> {
+    let (left, right) = (Number::from_value(left)?, Number::from_value(right)?);
+    let rank = match left.rank().max(right.rank()) {
+        // division makes a float of integers, so `1 / 2` is still 0.5
+        rank if operation == Operation::Divide && rank < DECIMAL => FLOAT,
+        rank => rank,
+    };
+
+    Some(match (left.climb(rank), right.climb(rank)) {
+        (Number::Int(left), Number::Int(right)) => integers(operation, left, right),
+        #[cfg(feature = "bigint")]
+        (Number::Big(left), Number::Big(right)) => Ok(big_integers(operation, &left, &right)),
+        (Number::Decimal(left), Number::Decimal(right)) => decimals(operation, left, right),
+        (left, right) => Ok(Value::Number(floats(
+            operation,
+            left.to_f64(),
+            right.to_f64(),
+        ))),
+    })
+}
+
+/// `-value`, or None if it isn't a number.
+pub(crate) fn negate(value: &Value) -> Option<Result<Value, String>> {
+    Some(match Number::from_value(value)? {
+        Number::Int(value) => integers(Operation::Subtract, 0, value),
+        #[cfg(feature = "bigint")]
+        Number::Big(value) => Ok(value.negate().into_value()),
+        Number::Decimal(value) => decimal_result(value.checked_neg()),
+        Number::Float(value) => Ok(Value::Number(-value)),
+    })
+}
+
+/// How two numbers compare, or None if either isn't a number. Numbers of different kinds
+/// compare by value, and NaN is unordered, comparing as None.
+pub(crate) fn compare(left: &Value, right: &Value) -> Option<Option<Ordering>> {
+    let (left, right) = (Number::from_value(left)?, Number::from_value(right)?);
+    let rank = left.rank().max(right.rank());
+
+    Some(match (left.climb(rank), right.climb(rank)) {
+        (Number::Int(left), Number::Int(right)) => Some(left.cmp(&right)),
+        #[cfg(feature = "bigint")]
+        (Number::Big(left), Number::Big(right)) => Some(left.cmp(&right)),
+        (Number::Decimal(left), Number::Decimal(right)) => Some(left.cmp(&right)),
+        (left, right) => left.to_f64().partial_cmp(&right.to_f64()),
+    })
+}
+
+fn integers(operation: Operation, left: i64, right: i64) -> Result<Value, String> {
+    let result = match operation {
+        Operation::Add => left.checked_add(right),
+        Operation::Subtract => left.checked_sub(right),
+        Operation::Multiply => left.checked_mul(right),
+        Operation::Divide => panic!("Integers are divided as floats!"),
+    };
+
+    match result {
+        Some(value) => Ok(Value::Int(value)),
+        #[cfg(feature = "bigint")]
+        None => Ok(big_integers(
+            operation,
+            &BigInt::from_i64(left),
+            &BigInt::from_i64(right),
+        )),
+        #[cfg(not(feature = "bigint"))]
+        None => Err(String::from("Integer overflow.")),
+    }
+}
+
+#[cfg(feature = "bigint")]
+fn big_integers(operation: Operation, left: &BigInt, right: &BigInt) -> Value {
+    match operation {
+        Operation::Add => left.add(right),
+        Operation::Subtract => left.sub(right),
+        Operation::Multiply => left.mul(right),
+        Operation::Divide => panic!("Integers are divided as floats!"),
+    }
+    .into_value()
+}
+
+fn decimals(operation: Operation, left: Decimal, right: Decimal) -> Result<Value, String> {
+    match operation {
+        Operation::Add => decimal_result(left.checked_add(right)),
+        Operation::Subtract => decimal_result(left.checked_sub(right)),
+        Operation::Multiply => decimal_result(left.checked_mul(right)),
+        Operation::Divide if right == Decimal::from_i64(0) => {
+            Err(String::from("Can't divide a decimal by zero."))
+        }
+        Operation::Divide => decimal_result(left.checked_div(right)),
+    }
+}
+
+fn decimal_result(decimal: Option<Decimal>) -> Result<Value, String> {
+    decimal
+        .map(Value::Decimal)
+        .ok_or_else(|| String::from("Decimal overflow."))
+}
+
+fn floats(operation: Operation, left: f64, right: f64) -> f64 {
+    match operation {
+        Operation::Add => left + right,
+        Operation::Subtract => left - right,
+        Operation::Multiply => left * right,
+        Operation::Divide => left / right,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(string: &str) -> Value {
+        Value::Decimal(Decimal::parse(string).unwrap())
+    }
+
+    fn apply(operation: Operation, left: &Value, right: &Value) -> String {
+        match arithmetic(operation, left, right) {
+            Some(Ok(value)) => value.to_string(),
+            Some(Err(message)) => message,
+            None => String::from("not numbers"),
+        }
+    }
+
+    #[test]
+    fn mixed_kinds_climb_the_tower() {
+        let (two, half) = (Value::Int(2), Value::Number(0.5));
+        assert_eq!(apply(Operation::Add, &two, &Value::Int(3)), "5");
+        assert_eq!(apply(Operation::Divide, &Value::Int(1), &two), "0.5");
+        assert_eq!(apply(Operation::Multiply, &two, &half), "1");
+        assert_eq!(apply(Operation::Add, &decimal("0.10"), &two), "2.10");
+        assert_eq!(
+            apply(Operation::Divide, &Value::Int(1), &decimal("4")),
+            "0.25"
+        );
+        assert_eq!(apply(Operation::Add, &decimal("0.1"), &half), "0.6");
+        assert_eq!(apply(Operation::Add, &two, &Value::Nil), "not numbers");
+    }
+
+    #[test]
+    fn decimal_errors() {
+        assert_eq!(
+            apply(Operation::Divide, &decimal("1"), &Value::Int(0)),
+            "Can't divide a decimal by zero."
+        );
+        let big = Value::Decimal(Decimal::from_i64(i64::MAX));
+        let product = arithmetic(Operation::Multiply, &big, &big)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            apply(Operation::Multiply, &product, &Value::Int(10)),
+            "Decimal overflow."
+        );
+    }
+
+    #[cfg(not(feature = "bigint"))]
+    #[test]
+    fn integer_overflow_is_an_error() {
+        let max = Value::Int(i64::MAX);
+        assert_eq!(
+            apply(Operation::Add, &max, &Value::Int(1)),
+            "Integer overflow."
+        );
+        assert!(matches!(negate(&Value::Int(i64::MIN)), Some(Err(_))));
+    }
+
+    #[test]
+    fn numbers_compare_by_value() {
+        assert_eq!(
+            compare(&Value::Int(1), &decimal("1.00")),
+            Some(Some(Ordering::Equal))
+        );
+        assert_eq!(
+            compare(&decimal("0.3"), &Value::Number(0.25)),
+            Some(Some(Ordering::Greater))
+        );
+        assert_eq!(
+            compare(&Value::Number(f64::NAN), &Value::Int(1)),
+            Some(None)
+        );
+        assert_eq!(compare(&Value::Int(1), &Value::Boolean(true)), None);
+    }
+}
@@ -1,41 +1,98 @@
+use std::{collections::HashMap, rc::Rc};
+
 use crate::{
     print_error,
+    render::Diagnostic,
     syntax::{
-        expr::Expr,
+        expr::{next_uid, Expr},
         stmt::Stmt,
         token::{Literal, Token, TokenType},
     },
+    LoxError,
 };
 
-static mut ID: u8 = 0;
-
-fn next_id() -> u8 {
-    unsafe {
-        ID += 1;
-        ID
-    }
-}
-
 #[derive(Debug)]
 struct ParseError;
 
 type Result<T> = std::result::Result<T, ParseError>;
 
-pub struct Parser<'a> {
-    tokens: &'a Vec<Token>,
+/// Limits the parser enforces on function declarations and calls, and on how many syntax errors
+/// it reports before giving up on the rest of the source, along with the optional-semicolon mode.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ParserOptions {
+    pub max_parameters: usize,
+    pub max_arguments: usize,
+    pub max_errors: usize,
+    /// Lets a line break, `}` or the end of the source stand in for the `;` that ends a
+    /// statement. Only a statement that would otherwise be complete ends there, so an expression
+    /// can still carry on over the next line.
+    pub optional_semicolons: bool,
+}
+
+impl Default for ParserOptions {
+    fn default() -> ParserOptions {
+        ParserOptions {
+            max_parameters: 255,
+            max_arguments: 255,
+            max_errors: 50,
+            optional_semicolons: false,
+        }
+    }
+}
+
+pub struct Parser {
+    tokens: Vec<Token>,
     current: usize,
+    options: ParserOptions,
+    // doc comments, keyed by the index of the token right after them
+    docs: HashMap<usize, String>,
+    pub(crate) errors: Vec<LoxError>,
+    // the errors again, with the token each is at, for rendering
+    pub(crate) diagnostics: Vec<Diagnostic>,
 }
 
-impl Parser<'_> {
-    pub fn new(tokens: &Vec<Token>) -> Parser {
-        Parser { tokens, current: 0 }
+impl Parser {
+    #[cfg(test)]
+    pub fn new(tokens: &[Token]) -> Parser {
+        Parser::with_options(tokens, ParserOptions::default())
     }
 
+    pub fn with_options(tokens: &[Token], options: ParserOptions) -> Parser {
+        // doc comments are set aside so the grammar never has to skip over them
+        let mut docs: HashMap<usize, String> = HashMap::new();
+        let mut code = vec![];
+        for token in tokens {
+            match (token.token_type == TokenType::DocComment, &token.literal) {
+                (true, Literal::String(line)) => {
+                    let doc = docs.entry(code.len()).or_default();
+                    if !doc.is_empty() {
+                        doc.push('\n');
+                    }
+                    doc.push_str(line);
+                }
+                _ => code.push(token.clone()),
+            }
+        }
+
+        Parser {
+            tokens: code,
+            current: 0,
+            options,
+            docs,
+            errors: vec![],
+            diagnostics: vec![],
+        }
+    }
+
+    /// Parses every declaration, dropping the ones with syntax errors after reporting them. Once
+    /// `max_errors` have been reported, the rest of the source is skipped.
     pub fn parse(&mut self) -> Vec<Stmt> {
         let mut statements = vec![];
 
-        while !self.is_at_end() {
-            statements.push(self.declaration().unwrap());
+        while !self.is_at_end() && !self.gave_up() {
+            if let Some(statement) = self.declaration() {
+                statements.push(statement);
+            }
         }
 
         statements
@@ -46,12 +103,18 @@ impl Parser<'_> {
     }
 
     fn declaration(&mut self) -> Option<Stmt> {
-        let res = if self.match_token(&[TokenType::Fun]) {
-            self.function("function")
+        let doc = self.docs.remove(&self.current);
+
+        let res = if self.check(&TokenType::At) {
+            self.decorated_function(doc)
+        } else if self.match_token(&[TokenType::Fun]) {
+            self.function("function", doc)
         } else if self.match_token(&[TokenType::Var]) {
             self.var_declaration()
         } else if self.match_token(&[TokenType::Class]) {
-            self.class_declaration()
+            self.class_declaration(doc)
+        } else if self.match_token(&[TokenType::Trait]) {
+            self.trait_declaration()
         } else {
             self.statement()
         };
@@ -65,35 +128,121 @@ impl Parser<'_> {
         }
     }
 
-    fn class_declaration(&mut self) -> Result<Stmt> {
+    fn class_declaration(&mut self, doc: Option<String>) -> Result<Stmt> {
         let name = self.consume(TokenType::Identifier, "Expect class name")?;
 
         let super_class = if self.match_token(&[TokenType::Less]) {
             self.consume(TokenType::Identifier, "Expect superclass name.")?;
             Some(Box::new(Expr::Variable {
-                uid: next_id(),
+                uid: next_uid(),
                 name: self.previous(),
             }))
         } else {
             None
         };
 
+        let mut traits = vec![];
+        if self.match_token(&[TokenType::With]) {
+            loop {
+                let name = self.consume(TokenType::Identifier, "Expect trait name.")?;
+                traits.push(Expr::Variable {
+                    uid: next_uid(),
+                    name,
+                });
+
+                if !self.match_token(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+
         self.consume(TokenType::LeftBrace, "Expect '{' before class body.")?;
 
         let mut methods = vec![];
+        let mut abstract_methods = vec![];
+        let mut setters = vec![];
         while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
-            methods.push(self.function("method")?);
+            let doc = self.docs.remove(&self.current);
+            let decorators = self.decorators()?;
+            if self.check_setter() {
+                if !decorators.is_empty() {
+                    return Err(self.error(self.peek().clone(), "A setter can't be decorated."));
+                }
+                self.advance();
+                setters.push(self.setter(doc)?);
+                continue;
+            }
+
+            // a method declared without a body is abstract
+            let (method, params, variadic) = self.signature("method")?;
+            if !self.match_token(&[TokenType::Semicolon]) {
+                if method.lexeme == "init" && !decorators.is_empty() {
+                    return Err(self.error(method, "An initializer can't be decorated."));
+                }
+                methods
+                    .push(self.function_body("method", doc, method, params, variadic, decorators)?);
+            } else if method.lexeme == "init" {
+                return Err(self.error(method, "An initializer can't be abstract."));
+            } else if !decorators.is_empty() {
+                return Err(self.error(method, "An abstract method can't be decorated."));
+            } else {
+                abstract_methods.push(method);
+            }
         }
 
         self.consume(TokenType::RightBrace, "Expect '}' after class body")?;
 
         Ok(Stmt::Class {
             name,
+            doc,
             super_class,
+            traits,
             methods,
+            abstract_methods,
+            setters,
         })
     }
 
+    fn trait_declaration(&mut self) -> Result<Stmt> {
+        let name = self.consume(TokenType::Identifier, "Expect trait name.")?;
+        self.consume(TokenType::LeftBrace, "Expect '{' before trait body.")?;
+
+        let mut methods = vec![];
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            let doc = self.docs.remove(&self.current);
+            methods.push(self.function("method", doc)?);
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after trait body.")?;
+
+        Ok(Stmt::Trait { name, methods })
+    }
+
+    fn check_setter(&self) -> bool {
+        // "set" is only a contextual keyword, so a method may still be named "set"
+        self.check(&TokenType::Identifier)
+            && self.peek().lexeme == "set"
+            && self.check_next(&TokenType::Identifier)
+    }
+
+    fn setter(&mut self, doc: Option<String>) -> Result<Stmt> {
+        let setter = self.function("setter", doc)?;
+
+        if let Stmt::Function {
+            name,
+            params,
+            variadic,
+            ..
+        } = &setter
+        {
+            if params.len() != 1 || *variadic {
+                return Err(self.error(name.clone(), "Setter must take exactly one parameter."));
+            }
+        }
+
+        Ok(setter)
+    }
+
     fn statement(&mut self) -> Result<Stmt> {
         if self.match_token(&[TokenType::For]) {
             return self.for_statement();
@@ -111,12 +260,24 @@ impl Parser<'_> {
             return self.return_statement();
         }
 
+        if self.match_token(&[TokenType::Throw]) {
+            return self.throw_statement();
+        }
+
+        if self.match_token(&[TokenType::Import]) {
+            return self.import_statement();
+        }
+
         if self.match_token(&[TokenType::While]) {
             return self.while_statement();
         }
 
+        if self.match_token(&[TokenType::Try]) {
+            return self.try_statement();
+        }
+
         if self.match_token(&[TokenType::LeftBrace]) {
-            return Ok(Stmt::Block(self.block()));
+            return Ok(Stmt::Block(Rc::new(self.block())));
         }
 
         self.expression_statement()
@@ -125,6 +286,17 @@ impl Parser<'_> {
     fn for_statement(&mut self) -> Result<Stmt> {
         self.consume(TokenType::LeftParen, "Expect '(' after 'for'.")?;
 
+        let is_for_in = matches!(
+            self.tokens.get(self.current + 2),
+            Some(Token {
+                token_type: TokenType::In,
+                ..
+            })
+        );
+        if is_for_in && self.match_token(&[TokenType::Var]) {
+            return self.for_in_statement();
+        }
+
         let initializer = if self.match_token(&[TokenType::Semicolon]) {
             None
         } else if self.match_token(&[TokenType::Var]) {
@@ -148,35 +320,49 @@ impl Parser<'_> {
         let mut body = self.statement()?;
 
         if let Some(increment) = increment {
-            body = Stmt::Block(vec![body, Stmt::Expression(increment)])
+            body = Stmt::Block(Rc::new(vec![body, Stmt::Expression(increment)]))
         }
 
         if condition.is_none() {
             condition.replace(Expr::Literal {
-                uid: next_id(),
+                uid: next_uid(),
                 value: Literal::Bool(true),
             });
         }
         body = Stmt::While {
             condition: Box::new(condition.unwrap()),
-            body: Box::new(body),
+            body: Rc::new(body),
         };
 
         if let Some(initializer) = initializer {
-            body = Stmt::Block(vec![initializer, body]);
+            body = Stmt::Block(Rc::new(vec![initializer, body]));
         }
 
         Ok(body)
     }
 
+    fn for_in_statement(&mut self) -> Result<Stmt> {
+        let name = self.consume(TokenType::Identifier, "Expect variable name.")?;
+        self.consume(TokenType::In, "Expect 'in' after loop variable.")?;
+        let iterable = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after for-in clauses")?;
+
+        let body = self.statement()?;
+        Ok(Stmt::ForIn {
+            name,
+            iterable,
+            body: Rc::new(body),
+        })
+    }
+
     fn if_statement(&mut self) -> Result<Stmt> {
         self.consume(TokenType::LeftParen, "Expect '(' after 'if'.")?;
         let condition = self.expression()?;
         self.consume(TokenType::RightParen, "Expect ')' after if condition")?;
 
-        let then_branch = Box::new(self.statement()?);
+        let then_branch = Rc::new(self.statement()?);
         let else_branch = match self.match_token(&[TokenType::Else]) {
-            true => Some(Box::new(self.statement()?)),
+            true => Some(Rc::new(self.statement()?)),
             false => None,
         };
 
@@ -188,27 +374,47 @@ impl Parser<'_> {
     }
 
     fn print_statement(&mut self) -> Result<Stmt> {
-        let value = self.expression()?;
-        self.consume(TokenType::Semicolon, "Expect ';' after value.")?;
-        Ok(Stmt::Print(value))
+        let mut values = vec![self.expression()?];
+        while self.match_token(&[TokenType::Comma]) {
+            values.push(self.expression()?);
+        }
+
+        self.end_statement("Expect ';' after value.")?;
+        Ok(Stmt::Print(values))
     }
 
     fn return_statement(&mut self) -> Result<Stmt> {
         let keyword = self.previous();
 
-        let value = if !self.check(&TokenType::Semicolon) {
+        let value = if !self.at_statement_end() {
             Some(self.expression()?)
         } else {
             None
         };
 
-        self.consume(TokenType::Semicolon, "Expect ';' after return value.")?;
+        self.end_statement("Expect ';' after return value.")?;
         Ok(Stmt::Return {
             name: keyword,
             value: value.map(Box::new),
         })
     }
 
+    fn throw_statement(&mut self) -> Result<Stmt> {
+        let keyword = self.previous();
+        let value = self.expression()?;
+        self.end_statement("Expect ';' after thrown value.")?;
+
+        Ok(Stmt::Throw { keyword, value })
+    }
+
+    fn import_statement(&mut self) -> Result<Stmt> {
+        let keyword = self.previous();
+        let path = self.consume(TokenType::String, "Expect module path after 'import'.")?;
+        self.end_statement("Expect ';' after module path.")?;
+
+        Ok(Stmt::Import { keyword, path })
+    }
+
     fn var_declaration(&mut self) -> Result<Stmt> {
         let name = self.consume(TokenType::Identifier, "Expect variable name.")?;
 
@@ -217,10 +423,7 @@ impl Parser<'_> {
             initializer = Some(self.expression()?);
         }
 
-        self.consume(
-            TokenType::Semicolon,
-            "Expect ';' after variable declaration",
-        )?;
+        self.end_statement("Expect ';' after variable declaration")?;
         Ok(Stmt::Var { name, initializer })
     }
 
@@ -232,17 +435,78 @@ impl Parser<'_> {
 
         Ok(Stmt::While {
             condition: Box::new(condition),
-            body: Box::new(body),
+            body: Rc::new(body),
+        })
+    }
+
+    fn try_statement(&mut self) -> Result<Stmt> {
+        let keyword = self.previous();
+        self.consume(TokenType::LeftBrace, "Expect '{' after 'try'.")?;
+        let body = self.block();
+
+        let catch = if self.match_token(&[TokenType::Catch]) {
+            self.consume(TokenType::LeftParen, "Expect '(' after 'catch'.")?;
+            let name = self.consume(TokenType::Identifier, "Expect error variable name.")?;
+            self.consume(TokenType::RightParen, "Expect ')' after error variable.")?;
+            self.consume(TokenType::LeftBrace, "Expect '{' before catch body.")?;
+            Some((name, self.block()))
+        } else {
+            None
+        };
+
+        let finally = if self.match_token(&[TokenType::Finally]) {
+            self.consume(TokenType::LeftBrace, "Expect '{' after 'finally'.")?;
+            Some(self.block())
+        } else {
+            None
+        };
+
+        if catch.is_none() && finally.is_none() {
+            return Err(self.error(keyword, "Expect 'catch' or 'finally' after try block."));
+        }
+
+        Ok(Stmt::Try {
+            body,
+            catch,
+            finally,
         })
     }
 
     fn expression_statement(&mut self) -> Result<Stmt> {
         let value = self.expression()?;
-        self.consume(TokenType::Semicolon, "Expect ';' after expression.")?;
+        self.end_statement("Expect ';' after expression.")?;
         Ok(Stmt::Expression(value))
     }
 
-    fn function(&mut self, kind: &str) -> Result<Stmt> {
+    fn function(&mut self, kind: &str, doc: Option<String>) -> Result<Stmt> {
+        let (name, params, variadic) = self.signature(kind)?;
+        self.function_body(kind, doc, name, params, variadic, vec![])
+    }
+
+    fn decorated_function(&mut self, doc: Option<String>) -> Result<Stmt> {
+        let decorators = self.decorators()?;
+        self.consume(
+            TokenType::Fun,
+            "Expect function declaration after decorators.",
+        )?;
+
+        let (name, params, variadic) = self.signature("function")?;
+        self.function_body("function", doc, name, params, variadic, decorators)
+    }
+
+    /// Any `@expression`s before a declaration, each a call expression like `@memoize` or
+    /// `@retry(3)`.
+    fn decorators(&mut self) -> Result<Vec<Expr>> {
+        let mut decorators = vec![];
+        while self.match_token(&[TokenType::At]) {
+            decorators.push(self.call()?);
+        }
+
+        Ok(decorators)
+    }
+
+    /// The name, parameters and whether the last parameter is variadic, up to the closing paren.
+    fn signature(&mut self, kind: &str) -> Result<(Token, Vec<Token>, bool)> {
         let name = self.consume(TokenType::Identifier, &format!("Expect {} name.", kind))?;
 
         self.consume(
@@ -251,15 +515,28 @@ impl Parser<'_> {
         )?;
 
         let mut params = vec![];
+        let mut variadic = false;
         if !self.check(&TokenType::RightParen) {
             loop {
-                if params.len() >= 255 {
-                    return Err(
-                        self.error(self.peek().clone(), "Can't have more than 255 parameters.")
-                    );
+                if params.len() >= self.options.max_parameters {
+                    return Err(self.error(
+                        self.peek().clone(),
+                        &format!(
+                            "Can't have more than {} parameters.",
+                            self.options.max_parameters
+                        ),
+                    ));
                 }
+
+                variadic = self.match_token(&[TokenType::Ellipsis]);
                 params.push(self.consume(TokenType::Identifier, "Expect parameter name.")?);
 
+                if variadic && self.check(&TokenType::Comma) {
+                    return Err(self.error(
+                        self.peek().clone(),
+                        "Variadic parameter must be the last parameter.",
+                    ));
+                }
                 if !self.match_token(&[TokenType::Comma]) {
                     break;
                 }
@@ -267,13 +544,33 @@ impl Parser<'_> {
         }
         self.consume(TokenType::RightParen, "Expect ')' after parameters.")?;
 
+        Ok((name, params, variadic))
+    }
+
+    fn function_body(
+        &mut self,
+        kind: &str,
+        doc: Option<String>,
+        name: Token,
+        params: Vec<Token>,
+        variadic: bool,
+        decorators: Vec<Expr>,
+    ) -> Result<Stmt> {
         self.consume(
             TokenType::LeftBrace,
             &format!("Expect '{{' before {} body.", kind),
         )?;
         let body = self.block();
 
-        Ok(Stmt::Function { name, params, body })
+        Ok(Stmt::Function {
+            uid: next_uid(),
+            name,
+            doc,
+            params,
+            variadic,
+            body: Rc::new(body),
+            decorators,
+        })
     }
 
     fn block(&mut self) -> Vec<Stmt> {
@@ -290,6 +587,10 @@ impl Parser<'_> {
     }
 
     fn assignment(&mut self) -> Result<Expr> {
+        if self.match_token(&[TokenType::Yield]) {
+            return self.yield_expr();
+        }
+
         let expr = self.or()?;
 
         if self.match_token(&[TokenType::Equal]) {
@@ -298,13 +599,13 @@ impl Parser<'_> {
 
             if let Expr::Variable { name, .. } = expr {
                 return Ok(Expr::Assign {
-                    uid: next_id(),
+                    uid: next_uid(),
                     name,
                     value: Box::new(value),
                 });
             } else if let Expr::Get { object, name, .. } = expr {
                 return Ok(Expr::Set {
-                    uid: next_id(),
+                    uid: next_uid(),
                     object,
                     name,
                     value: Box::new(value),
@@ -317,6 +618,20 @@ impl Parser<'_> {
         Ok(expr)
     }
 
+    fn yield_expr(&mut self) -> Result<Expr> {
+        let keyword = self.previous();
+        let value = match self.at_statement_end() || self.check(&TokenType::RightParen) {
+            true => None,
+            false => Some(Box::new(self.assignment()?)),
+        };
+
+        Ok(Expr::Yield {
+            uid: next_uid(),
+            keyword,
+            value,
+        })
+    }
+
     fn or(&mut self) -> Result<Expr> {
         self.parse_logical_op(&[TokenType::Or], |parser| parser.and())
     }
@@ -360,13 +675,48 @@ impl Parser<'_> {
             let operator = self.previous();
             let right = self.unary()?;
             return Ok(Expr::Unary {
-                uid: next_id(),
+                uid: next_uid(),
                 operator,
                 right: Box::new(right),
             });
         }
 
-        self.call()
+        if self.match_token(&[TokenType::PlusPlus, TokenType::MinusMinus]) {
+            let operator = self.previous();
+            let target = self.unary()?;
+            return self.update(target, operator, true);
+        }
+
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Result<Expr> {
+        let expr = self.call()?;
+
+        if self.match_token(&[TokenType::PlusPlus, TokenType::MinusMinus]) {
+            let operator = self.previous();
+            return self.update(expr, operator, false);
+        }
+
+        Ok(expr)
+    }
+
+    fn update(&mut self, target: Expr, operator: Token, prefix: bool) -> Result<Expr> {
+        match target {
+            Expr::Variable { .. } | Expr::Get { .. } => Ok(Expr::Update {
+                uid: next_uid(),
+                target: Box::new(target),
+                operator,
+                prefix,
+            }),
+            _ => {
+                let kind = match operator.token_type {
+                    TokenType::PlusPlus => "increment",
+                    _ => "decrement",
+                };
+                Err(self.error(operator, &format!("Invalid {kind} target.")))
+            }
+        }
     }
 
     fn finish_call(&mut self, callee: Expr) -> Result<Expr> {
@@ -374,8 +724,14 @@ impl Parser<'_> {
 
         if !self.check(&TokenType::RightParen) {
             loop {
-                if args.len() >= 255 {
-                    self.error(self.peek().clone(), "Can't have more than 255 arguments.");
+                if args.len() >= self.options.max_arguments {
+                    return Err(self.error(
+                        self.peek().clone(),
+                        &format!(
+                            "Can't have more than {} arguments.",
+                            self.options.max_arguments
+                        ),
+                    ));
                 }
                 args.push(self.expression()?);
 
@@ -387,7 +743,7 @@ impl Parser<'_> {
         let paren = self.consume(TokenType::RightParen, "Expect ')' after arguments.")?;
 
         Ok(Expr::Call {
-            uid: next_id(),
+            uid: next_uid(),
             callee: Box::new(callee),
             paren,
             args,
@@ -404,7 +760,7 @@ impl Parser<'_> {
                 let name =
                     self.consume(TokenType::Identifier, "Expect property name after '.'.")?;
                 expr = Expr::Get {
-                    uid: next_id(),
+                    uid: next_uid(),
                     object: Box::new(expr),
                     name,
                 }
@@ -419,26 +775,26 @@ impl Parser<'_> {
     fn primary(&mut self) -> Result<Expr> {
         if self.match_token(&[TokenType::False]) {
             return Ok(Expr::Literal {
-                uid: next_id(),
+                uid: next_uid(),
                 value: Literal::Bool(false),
             });
         }
         if self.match_token(&[TokenType::True]) {
             return Ok(Expr::Literal {
-                uid: next_id(),
+                uid: next_uid(),
                 value: Literal::Bool(true),
             });
         }
         if self.match_token(&[TokenType::Nil]) {
             return Ok(Expr::Literal {
-                uid: next_id(),
+                uid: next_uid(),
                 value: Literal::None,
             });
         }
 
         if self.match_token(&[TokenType::Number, TokenType::String]) {
             return Ok(Expr::Literal {
-                uid: next_id(),
+                uid: next_uid(),
                 value: self.previous().literal,
             });
         }
@@ -449,7 +805,7 @@ impl Parser<'_> {
             let method = self.consume(TokenType::Identifier, "Expect superclass method name.")?;
 
             return Ok(Expr::Super {
-                uid: next_id(),
+                uid: next_uid(),
                 keyword,
                 method,
             });
@@ -457,14 +813,14 @@ impl Parser<'_> {
 
         if self.match_token(&[TokenType::This]) {
             return Ok(Expr::This {
-                uid: next_id(),
+                uid: next_uid(),
                 keyword: self.previous(),
             });
         }
 
         if self.match_token(&[TokenType::Identifier]) {
             return Ok(Expr::Variable {
-                uid: next_id(),
+                uid: next_uid(),
                 name: self.previous(),
             });
         }
@@ -473,7 +829,7 @@ impl Parser<'_> {
             let expr = self.expression()?;
             self.consume(TokenType::RightParen, "Expect ')' after expression")?;
             return Ok(Expr::Grouping {
-                uid: next_id(),
+                uid: next_uid(),
                 expression: Box::new(expr),
             });
         }
@@ -495,7 +851,7 @@ impl Parser<'_> {
             let operator = self.previous();
             let right = parse_next_level(self)?;
             expr = Expr::Binary {
-                uid: next_id(),
+                uid: next_uid(),
                 left: Box::new(expr),
                 operator,
                 right: Box::new(right),
@@ -519,7 +875,7 @@ impl Parser<'_> {
             let operator = self.previous();
             let right = parse_next_level(self)?;
             expr = Expr::Logical {
-                uid: next_id(),
+                uid: next_uid(),
                 left: Box::new(expr),
                 operator,
                 right: Box::new(right),
@@ -555,6 +911,13 @@ impl Parser<'_> {
         self.peek().token_type == *token_type
     }
 
+    fn check_next(&self, token_type: &TokenType) -> bool {
+        match self.tokens.get(self.current + 1) {
+            Some(token) => token.token_type == *token_type,
+            None => false,
+        }
+    }
+
     fn advance(&mut self) -> Token {
         if !self.is_at_end() {
             self.current += 1;
@@ -574,11 +937,59 @@ impl Parser<'_> {
         self.tokens.get(self.current - 1).unwrap().clone()
     }
 
-    fn error(&self, token: Token, message: &str) -> ParseError {
-        print_error(token.line, token.lexeme, message);
+    fn error(&mut self, token: Token, message: &str) -> ParseError {
+        if self.gave_up() {
+            return ParseError {};
+        }
+
+        self.report(&token, message.to_string());
+        if self.errors.len() >= self.options.max_errors {
+            let message = format!("Too many errors, giving up after {}.", self.errors.len());
+            self.report(&token, message);
+        }
         ParseError {}
     }
 
+    fn report(&mut self, token: &Token, message: String) {
+        print_error(token.line, token.lexeme.clone(), &message);
+        self.diagnostics.push(Diagnostic::error(
+            token.line,
+            Some(token.lexeme.clone()),
+            message.clone(),
+        ));
+        self.errors.push(LoxError {
+            message,
+            line: token.line,
+        });
+    }
+
+    // the "too many errors" error is the one past the limit
+    /// Consumes the `;` ending a statement, or in optional-semicolon mode accepts whatever
+    /// [`Parser::at_statement_end`] does in its place.
+    fn end_statement(&mut self, message: &str) -> Result<()> {
+        if !self.match_token(&[TokenType::Semicolon]) && !self.at_statement_end() {
+            return Err(self.error(self.peek().clone(), message));
+        }
+
+        Ok(())
+    }
+
+    // whether the statement so far ends here, at a `;` or where one may be left out
+    fn at_statement_end(&self) -> bool {
+        if self.check(&TokenType::Semicolon) {
+            return true;
+        }
+
+        self.options.optional_semicolons
+            && (self.is_at_end()
+                || self.check(&TokenType::RightBrace)
+                || self.peek().line > self.previous().line)
+    }
+
+    fn gave_up(&self) -> bool {
+        self.errors.len() > self.options.max_errors
+    }
+
     fn synchronize(&mut self) {
         self.advance();
 
@@ -588,14 +999,20 @@ impl Parser<'_> {
             }
 
             match self.peek().token_type {
-                TokenType::Class
+                TokenType::At
+                | TokenType::Class
+                | TokenType::Trait
                 | TokenType::Fun
                 | TokenType::Var
                 | TokenType::For
                 | TokenType::If
                 | TokenType::While
+                | TokenType::Try
                 | TokenType::Print
-                | TokenType::Return => return,
+                | TokenType::Return
+                | TokenType::Throw
+                | TokenType::Import
+                | TokenType::Yield => return,
                 _ => {
                     self.advance();
                 }
@@ -603,3 +1020,211 @@ impl Parser<'_> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::scanner::Scanner;
+
+    use super::*;
+
+    #[test]
+    fn expression_uids_are_unique() {
+        let source = (0..300)
+            .map(|i| format!("print {i};"))
+            .collect::<Vec<String>>()
+            .join("\n");
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().unwrap();
+
+        let statements = Parser::new(tokens).parse();
+
+        let mut exprs = HashSet::new();
+        for statement in statements {
+            if let Stmt::Print(values) = statement {
+                exprs.extend(values);
+            }
+        }
+        assert_eq!(exprs.len(), 300);
+    }
+
+    fn parse_with(source: String, options: ParserOptions) -> Vec<Stmt> {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().unwrap();
+
+        Parser::with_options(tokens, options).parse()
+    }
+
+    fn names(prefix: &str, count: usize) -> String {
+        (0..count)
+            .map(|i| format!("{prefix}{i}"))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    #[test]
+    fn parameter_limit_boundary() {
+        let options = ParserOptions::default();
+
+        let at_limit = format!("fun f({}) {{}}", names("p", 255));
+        assert_eq!(parse_with(at_limit, options).len(), 1);
+
+        let over_limit = format!("fun f({}) {{}}", names("p", 256));
+        assert!(parse_with(over_limit, options).is_empty());
+    }
+
+    #[test]
+    fn argument_limit_boundary() {
+        let options = ParserOptions::default();
+
+        let at_limit = format!("f({});", names("a", 255));
+        assert_eq!(parse_with(at_limit, options).len(), 1);
+
+        let over_limit = format!("f({});", names("a", 256));
+        assert!(parse_with(over_limit, options).is_empty());
+    }
+
+    #[test]
+    fn configured_limits() {
+        let options = ParserOptions {
+            max_parameters: 2,
+            max_arguments: 1,
+            ..Default::default()
+        };
+
+        assert_eq!(parse_with(String::from("fun f(a, b) {}"), options).len(), 1);
+        assert!(parse_with(String::from("fun f(a, b, c) {}"), options).is_empty());
+        assert_eq!(parse_with(String::from("f(a);"), options).len(), 1);
+        assert!(parse_with(String::from("f(a, b);"), options).is_empty());
+        // the declaration after a rejected call still parses
+        assert_eq!(
+            parse_with(String::from("f(a, b); print 1;"), options).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn doc_comments_attach_to_the_next_declaration() {
+        let source = String::from(
+            "/// Greets.\n/// Politely.\nfun greet() {}\n/// Stray.\nprint 1;\nclass A {\n  /// Says hi.\n  hi() {}\n}",
+        );
+        let statements = parse_with(source, ParserOptions::default());
+        assert_eq!(statements.len(), 3);
+
+        let Stmt::Function { doc, .. } = &statements[0] else {
+            panic!("Expected a function declaration!");
+        };
+        assert_eq!(doc.as_deref(), Some("Greets.\nPolitely."));
+
+        let Stmt::Class { doc, methods, .. } = &statements[2] else {
+            panic!("Expected a class declaration!");
+        };
+        assert_eq!(*doc, None);
+        assert!(matches!(&methods[0], Stmt::Function { doc: Some(doc), .. } if doc == "Says hi."));
+    }
+
+    #[test]
+    fn methods_without_a_body_are_abstract() {
+        let source = String::from("class Shape { area(); describe() { return 1; } init(); }");
+        assert!(parse_with(source, ParserOptions::default()).is_empty());
+
+        let source = String::from("class Shape { area(); describe() { return 1; } }");
+        let statements = parse_with(source, ParserOptions::default());
+        let Stmt::Class {
+            methods,
+            abstract_methods,
+            ..
+        } = &statements[0]
+        else {
+            panic!("Expected a class declaration!");
+        };
+        assert_eq!(methods.len(), 1);
+        assert_eq!(abstract_methods[0].lexeme, "area");
+    }
+
+    #[test]
+    fn decorators_come_before_functions_and_methods() {
+        let source = String::from("/// Cached.\n@trace @memoize(10) fun f() {}");
+        let statements = parse_with(source, ParserOptions::default());
+        let Stmt::Function {
+            doc, decorators, ..
+        } = &statements[0]
+        else {
+            panic!("Expected a function declaration!");
+        };
+        assert_eq!(doc.as_deref(), Some("Cached."));
+        assert!(matches!(
+            &decorators[..],
+            [Expr::Variable { .. }, Expr::Call { .. }]
+        ));
+
+        for rejected in [
+            "@trace var a = 1;",
+            "class A { @trace init() {} }",
+            "class A { @trace area(); }",
+            "class A { @trace set size(value) {} }",
+        ] {
+            let statements = parse_with(String::from(rejected), ParserOptions::default());
+            assert!(statements.is_empty(), "{rejected}");
+        }
+    }
+
+    #[test]
+    fn line_breaks_can_end_statements() {
+        let source =
+            "var a = 1\nprint a +\n  2\nfun f() { return }\nfun g() {\n  return\n  a }\nf(); g()";
+        assert!(parse_with(String::from(source), ParserOptions::default()).len() < 6);
+
+        let options = ParserOptions {
+            optional_semicolons: true,
+            ..Default::default()
+        };
+        let statements = parse_with(String::from(source), options);
+        assert_eq!(statements.len(), 6);
+        assert!(matches!(&statements[1], Stmt::Print(values) if values.len() == 1));
+
+        // a return at the end of a line returns nothing, leaving `a` as a statement of its own
+        let Stmt::Function { body, .. } = &statements[3] else {
+            panic!("Expected a function declaration!");
+        };
+        assert!(matches!(
+            &body[..],
+            [Stmt::Return { value: None, .. }, Stmt::Expression(_)]
+        ));
+
+        // a line break only ends a statement that's complete
+        assert!(parse_with(String::from("print 1 2"), options).is_empty());
+        assert_eq!(
+            parse_with(String::from("print 1; print 2"), options).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn parsing_stops_after_max_errors() {
+        let source = "print +;\n".repeat(10) + "print 1;";
+        let tokens = Scanner::new(source).scan_tokens().unwrap().clone();
+
+        let mut parser = Parser::with_options(
+            &tokens,
+            ParserOptions {
+                max_errors: 3,
+                ..Default::default()
+            },
+        );
+        assert!(parser.parse().is_empty());
+        assert_eq!(parser.errors.len(), 4);
+        assert_eq!(
+            parser.errors[3],
+            LoxError {
+                message: String::from("Too many errors, giving up after 3."),
+                line: 3,
+            }
+        );
+
+        let mut parser = Parser::new(&tokens);
+        assert_eq!(parser.parse().len(), 1);
+        assert_eq!(parser.errors.len(), 10);
+    }
+}
@@ -0,0 +1,114 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+};
+
+use crate::syntax::token::Token;
+
+/// How many classes a property has to be read on at one site before it's reported.
+pub const MEGAMORPHIC_CLASSES: usize = 4;
+
+/// The classes of the instances each `object.property` site in the program read from while
+/// collection was on, to point out megamorphic sites: those that see so many classes that an
+/// inline cache would miss nearly every time. There are no caches to miss here, but such sites
+/// are a sign a hot loop mixes too many kinds of objects to stay fast elsewhere. Collection is
+/// per thread, counting every interpreter running on it, and costs nothing but a check while
+/// it's off.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PerfHints {
+    // by the uid of the get expression
+    sites: HashMap<usize, Site>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Site {
+    name: Token,
+    reads: u64,
+    // reads by class name, sorted so reports are stable
+    classes: BTreeMap<String, u64>,
+}
+
+thread_local! {
+    static HINTS: RefCell<Option<PerfHints>> = const { RefCell::new(None) };
+}
+
+impl PerfHints {
+    /// Starts collecting on this thread, from nothing.
+    pub fn start() {
+        HINTS.with(|hints| *hints.borrow_mut() = Some(PerfHints::default()));
+    }
+
+    /// Stops collecting on this thread, returning what was collected if collection was on.
+    pub fn stop() -> Option<PerfHints> {
+        HINTS.with(|hints| hints.borrow_mut().take())
+    }
+
+    /// The lines and property names of the megamorphic sites, most read first.
+    pub fn megamorphic(&self) -> Vec<(usize, &str)> {
+        self.megamorphic_sites()
+            .iter()
+            .map(|site| (site.name.line, site.name.lexeme.as_str()))
+            .collect()
+    }
+
+    fn megamorphic_sites(&self) -> Vec<&Site> {
+        let mut sites = self
+            .sites
+            .iter()
+            .filter(|(_, site)| site.classes.len() >= MEGAMORPHIC_CLASSES)
+            .collect::<Vec<(&usize, &Site)>>();
+        // sites are numbered in the order they're parsed, which breaks ties on the same line
+        sites.sort_by_key(|(uid, site)| (u64::MAX - site.reads, site.name.line, **uid));
+        sites.into_iter().map(|(_, site)| site).collect()
+    }
+
+    /// Records a read of the property `name` at the site `uid` on an instance of `class`.
+    pub(crate) fn read(uid: usize, name: &Token, class: impl FnOnce() -> String) {
+        HINTS.with(|hints| {
+            if let Some(hints) = hints.borrow_mut().as_mut() {
+                let site = hints.sites.entry(uid).or_insert_with(|| Site {
+                    name: name.clone(),
+                    reads: 0,
+                    classes: BTreeMap::new(),
+                });
+                site.reads += 1;
+                *site.classes.entry(class()).or_insert(0) += 1;
+            }
+        });
+    }
+}
+
+impl Display for PerfHints {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sites = self.megamorphic_sites();
+        if sites.is_empty() {
+            return writeln!(f, "Perf hints: no megamorphic property reads.");
+        }
+
+        writeln!(
+            f,
+            "Perf hints: megamorphic property reads, seeing {MEGAMORPHIC_CLASSES} or more classes:"
+        )?;
+        for site in sites {
+            let classes = site
+                .classes
+                .iter()
+                .map(|(class, reads)| format!("{class} ({reads})"))
+                .collect::<Vec<String>>();
+            writeln!(
+                f,
+                "  [line {}] .{} read {} times on {} classes: {}",
+                site.name.line,
+                site.name.lexeme,
+                site.reads,
+                site.classes.len(),
+                classes.join(", ")
+            )?;
+        }
+        writeln!(
+            f,
+            "Consider grouping objects by class so each hot loop sees only one or two."
+        )
+    }
+}
@@ -0,0 +1,153 @@
+use std::{
+    cell::RefCell,
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+/// A stage a program goes through on its way to running.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    Scanning,
+    Parsing,
+    Resolving,
+    Interpreting,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 4] = [
+        Phase::Scanning,
+        Phase::Parsing,
+        Phase::Resolving,
+        Phase::Interpreting,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Phase::Scanning => "scanning",
+            Phase::Parsing => "parsing",
+            Phase::Resolving => "resolving",
+            Phase::Interpreting => "interpreting",
+        }
+    }
+}
+
+/// The wall-clock time spent in each phase while timing was on. A phase that starts inside
+/// another, like scanning a module the program imports while interpreting it, counts towards
+/// its own phase only, so the times add up to the whole run. Timing is per thread, covering
+/// every interpreter running on it, and costs nothing but a check while it's off.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PhaseTimes {
+    times: [Duration; 4],
+    // the phases in progress, innermost last, with when each started and how long the phases
+    // inside it took
+    running: Vec<(Phase, Instant, Duration)>,
+}
+
+thread_local! {
+    static TIMES: RefCell<Option<PhaseTimes>> = const { RefCell::new(None) };
+}
+
+impl PhaseTimes {
+    /// Starts timing on this thread, from zero.
+    pub fn start() {
+        TIMES.with(|times| *times.borrow_mut() = Some(PhaseTimes::default()));
+    }
+
+    /// Stops timing on this thread, returning the times if timing was on.
+    pub fn stop() -> Option<PhaseTimes> {
+        TIMES.with(|times| times.borrow_mut().take())
+    }
+
+    /// The time spent in `phase`, not counting phases started inside it.
+    pub fn get(&self, phase: Phase) -> Duration {
+        self.times[phase as usize]
+    }
+
+    pub fn total(&self) -> Duration {
+        self.times.iter().sum()
+    }
+
+    /// Runs `work`, counting the time it takes towards `phase` if timing is on.
+    pub(crate) fn time<T>(phase: Phase, work: impl FnOnce() -> T) -> T {
+        let timing = TIMES.with(|times| match times.borrow_mut().as_mut() {
+            Some(times) => {
+                times.running.push((phase, Instant::now(), Duration::ZERO));
+                true
+            }
+            None => false,
+        });
+        if !timing {
+            return work();
+        }
+
+        let result = work();
+        TIMES.with(|times| {
+            let mut times = times.borrow_mut();
+            // timing may have been stopped and restarted while `work` ran
+            let Some(times) = times.as_mut() else {
+                return;
+            };
+            let Some((phase, started, nested)) = times.running.pop() else {
+                return;
+            };
+            let elapsed = started.elapsed();
+            times.times[phase as usize] += elapsed.saturating_sub(nested);
+            if let Some((_, _, enclosing)) = times.running.last_mut() {
+                *enclosing += elapsed;
+            }
+        });
+        result
+    }
+}
+
+impl Display for PhaseTimes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Time:")?;
+        for phase in Phase::ALL {
+            writeln!(
+                f,
+                "  {:<13} {:>10.3} ms",
+                format!("{}:", phase.name()),
+                self.get(phase).as_secs_f64() * 1000.0
+            )?;
+        }
+        writeln!(
+            f,
+            "  {:<13} {:>10.3} ms",
+            "total:",
+            self.total().as_secs_f64() * 1000.0
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn nested_phases_count_only_towards_their_own() {
+        PhaseTimes::time(Phase::Parsing, || {});
+        assert_eq!(PhaseTimes::stop(), None);
+
+        PhaseTimes::start();
+        let started = Instant::now();
+        PhaseTimes::time(Phase::Interpreting, || {
+            thread::sleep(Duration::from_millis(20));
+            PhaseTimes::time(Phase::Scanning, || thread::sleep(Duration::from_millis(20)));
+        });
+        let elapsed = started.elapsed();
+        let times = PhaseTimes::stop().unwrap();
+
+        let interpreting = times.get(Phase::Interpreting);
+        let scanning = times.get(Phase::Scanning);
+        assert!(interpreting >= Duration::from_millis(20));
+        assert!(scanning >= Duration::from_millis(20));
+        // the nested scan isn't counted twice
+        assert!(times.total() <= elapsed);
+        assert_eq!(times.get(Phase::Resolving), Duration::ZERO);
+        assert_eq!(times.total(), interpreting + scanning);
+        assert!(times.to_string().starts_with("Time:\n  scanning:"));
+    }
+}
@@ -0,0 +1,473 @@
+use std::{cell::RefCell, fmt::Display};
+
+use crate::scanner;
+
+/// Whether a diagnostic stopped the program from running.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// An error or warning, as the scanner, parser and resolver report it and as a program fails
+/// with. Its `Display` is the one line printed to the terminal, so every backend words it the
+/// same.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The code of the warning this is, or was before it was denied, if it's one.
+    pub code: Option<&'static str>,
+    /// The script it's in, where that's known.
+    pub file: Option<String>,
+    pub line: usize,
+    /// The lexeme the diagnostic is about, if it's about one.
+    pub location: Option<String>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(line: usize, location: Option<String>, message: String) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            code: None,
+            file: None,
+            line,
+            location,
+            message,
+        }
+    }
+
+    pub fn warning(line: usize, location: String, message: String) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            code: None,
+            file: None,
+            line,
+            location: Some(location),
+            message,
+        }
+    }
+
+    pub fn with_code(self, code: &'static str) -> Diagnostic {
+        Diagnostic {
+            code: Some(code),
+            ..self
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self.severity {
+            Severity::Error => "Error",
+            Severity::Warning => "Warning",
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.location {
+            Some(location) => write!(
+                f,
+                "[line {}] {} {location}: {}",
+                self.line,
+                self.kind(),
+                self.message
+            )?,
+            None => write!(f, "[line {}] {}: {}", self.line, self.kind(), self.message)?,
+        }
+        match self.code {
+            Some(code) => write!(f, " [{code}]"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// How errors and warnings are printed to stderr while a script runs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// Each as the terminal shows it, as soon as it's found.
+    #[default]
+    Human,
+    /// Each as a line of JSON, as [`Format::Json`] renders it, once the script has finished.
+    Json,
+}
+
+thread_local! {
+    // the diagnostics reported on this thread while `collecting` runs
+    static COLLECTED: RefCell<Option<Vec<Diagnostic>>> = const { RefCell::new(None) };
+}
+
+/// Runs `work`, keeping the diagnostics reported on this thread meanwhile rather than letting
+/// them be printed.
+pub(crate) fn collecting<T>(work: impl FnOnce() -> T) -> (T, Vec<Diagnostic>) {
+    let outer = COLLECTED.with(|collected| collected.borrow_mut().replace(vec![]));
+    let result = work();
+    let collected =
+        COLLECTED.with(|collected| std::mem::replace(&mut *collected.borrow_mut(), outer));
+    (result, collected.unwrap_or_default())
+}
+
+/// Keeps `diagnostic` if [`collecting`] is running, or gives it back to be printed.
+pub(crate) fn collect(diagnostic: Diagnostic) -> Option<Diagnostic> {
+    COLLECTED.with(|collected| match collected.borrow_mut().as_mut() {
+        Some(collected) => {
+            collected.push(diagnostic);
+            None
+        }
+        None => Some(diagnostic),
+    })
+}
+
+/// Prints `diagnostic` to stderr, unless it's being collected.
+pub(crate) fn report(diagnostic: Diagnostic) {
+    if let Some(diagnostic) = collect(diagnostic) {
+        eprintln!("{diagnostic}");
+    }
+}
+
+/// How [`render`] presents diagnostics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// One line per diagnostic, as printed to the terminal.
+    Text,
+    /// The source as an HTML `<pre>`, with its tokens in `<span>`s classed `keyword`,
+    /// `identifier`, `string`, `number` or `comment`, and each diagnostic in a
+    /// `<span class="diagnostic error">` or `<span class="diagnostic warning">` after its line.
+    /// Lines with diagnostics are classed `error` or `warning` too, as is the first token on
+    /// them matching a diagnostic's location, so a stylesheet can underline it.
+    Html,
+    /// A line of JSON per diagnostic, for editors and CI annotators:
+    /// `{"code":"unused_variable","severity":"warning","message":"Unused local variable 'x'.",
+    /// "file":"main.lox","span":{"line":2,"column":7,"end_column":8}}`. `code` is null for
+    /// anything but a warning, denied or not, and `file` where the script isn't known. Columns
+    /// count characters from 1, and are null where the location can't be found on the line.
+    Json,
+}
+
+/// Presents the diagnostics found in `source` in the given format.
+pub fn render(format: Format, source: &str, diagnostics: &[Diagnostic]) -> String {
+    match format {
+        Format::Text => diagnostics
+            .iter()
+            .map(|diagnostic| format!("{diagnostic}\n"))
+            .collect(),
+        Format::Html => html(source, diagnostics),
+        Format::Json => {
+            let lines = highlight(source);
+            diagnostics
+                .iter()
+                .map(|diagnostic| format!("{}\n", json(diagnostic, &lines)))
+                .collect()
+        }
+    }
+}
+
+fn json(diagnostic: &Diagnostic, lines: &[Vec<(Option<&'static str>, &str)>]) -> String {
+    let columns = diagnostic
+        .location
+        .as_deref()
+        .zip(lines.get(diagnostic.line.wrapping_sub(1)))
+        .and_then(|(location, spans)| columns(location, spans));
+    let (column, end_column) = match columns {
+        Some((column, end_column)) => (column.to_string(), end_column.to_string()),
+        None => (String::from("null"), String::from("null")),
+    };
+
+    format!(
+        "{{\"code\":{},\"severity\":\"{}\",\"message\":{},\"file\":{},\
+         \"span\":{{\"line\":{},\"column\":{column},\"end_column\":{end_column}}}}}",
+        diagnostic.code.map_or(String::from("null"), json_string),
+        class_of(diagnostic.severity),
+        json_string(&diagnostic.message),
+        diagnostic
+            .file
+            .as_deref()
+            .map_or(String::from("null"), json_string),
+        diagnostic.line,
+    )
+}
+
+// the columns `location` starts and ends at on a line, preferring a token that is exactly it, as
+// the HTML does, and otherwise the first place it appears
+fn columns(location: &str, spans: &[(Option<&'static str>, &str)]) -> Option<(usize, usize)> {
+    let length = location.chars().count();
+    if length == 0 {
+        return None;
+    }
+
+    let mut column = 1;
+    for (_, text) in spans {
+        if *text == location {
+            return Some((column, column + length));
+        }
+        column += text.chars().count();
+    }
+    let line = spans.iter().map(|(_, text)| *text).collect::<String>();
+    let start = line.find(location)?;
+    let column = line[..start].chars().count() + 1;
+    Some((column, column + length))
+}
+
+fn json_string(text: &str) -> String {
+    let mut json = String::from("\"");
+    for char in text.chars() {
+        match char {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            char if char.is_control() => json.push_str(&format!("\\u{:04x}", char as u32)),
+            char => json.push(char),
+        }
+    }
+    json.push('"');
+    json
+}
+
+fn html(source: &str, diagnostics: &[Diagnostic]) -> String {
+    let mut html = String::from("<pre class=\"lox\">");
+    for (index, spans) in highlight(source).iter().enumerate() {
+        let line = index + 1;
+        let on_line = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.line == line)
+            .collect::<Vec<&Diagnostic>>();
+        let severity = on_line
+            .iter()
+            .map(|diagnostic| diagnostic.severity)
+            .min_by_key(|severity| *severity == Severity::Warning)
+            .map(class_of);
+
+        let line_class = match severity {
+            Some(severity) => format!("line {severity}"),
+            None => String::from("line"),
+        };
+        html.push_str(&format!("<span class=\"{line_class}\" id=\"L{line}\">"));
+        html.push_str(&format!("<span class=\"line-number\">{line}</span>"));
+
+        let mut marked = on_line
+            .iter()
+            .filter_map(|diagnostic| Some((diagnostic.location.as_deref()?, diagnostic.severity)))
+            .collect::<Vec<(&str, Severity)>>();
+        for (class, text) in spans {
+            let mark = marked.iter().position(|(location, _)| location == text);
+            let class = match (class, mark) {
+                (Some(class), Some(mark)) => {
+                    let (_, severity) = marked.remove(mark);
+                    Some(format!("{class} {}", class_of(severity)))
+                }
+                (class, _) => class.map(String::from),
+            };
+            match class {
+                Some(class) => {
+                    html.push_str(&format!("<span class=\"{class}\">{}</span>", escape(text)))
+                }
+                None => html.push_str(&escape(text)),
+            }
+        }
+        html.push_str("</span>\n");
+
+        for diagnostic in on_line {
+            html.push_str(&format!(
+                "<span class=\"diagnostic {}\">{}</span>\n",
+                class_of(diagnostic.severity),
+                escape(&diagnostic.to_string())
+            ));
+        }
+    }
+    html.push_str("</pre>\n");
+    html
+}
+
+fn class_of(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// the source split into lines of spans, each with the class of token it is if it's one worth
+// coloring. Strings and comments spanning lines are split into a span on each.
+fn highlight(source: &str) -> Vec<Vec<(Option<&'static str>, &str)>> {
+    let mut lines = vec![vec![]];
+    let mut rest = source;
+    while let Some(char) = rest.chars().next() {
+        let (class, length) = match char {
+            '\n' => {
+                lines.push(vec![]);
+                rest = &rest[1..];
+                continue;
+            }
+            '/' if rest.starts_with("//") => {
+                (Some("comment"), rest.find('\n').unwrap_or(rest.len()))
+            }
+            '/' if rest.starts_with("/*") => (Some("comment"), block_comment_length(rest)),
+            '"' => (
+                Some("string"),
+                rest[1..].find('"').map_or(rest.len(), |end| end + 2),
+            ),
+            char if char.is_ascii_digit() => (Some("number"), number_length(rest)),
+            char if char.is_alphabetic() || char == '_' => {
+                let length = rest
+                    .find(|char: char| !char.is_alphanumeric() && char != '_')
+                    .unwrap_or(rest.len());
+                match scanner::keyword(&rest[..length]) {
+                    Some(_) => (Some("keyword"), length),
+                    None => (Some("identifier"), length),
+                }
+            }
+            char => (None, char.len_utf8()),
+        };
+
+        let (token, after) = rest.split_at(length);
+        for (index, part) in token.split('\n').enumerate() {
+            if index > 0 {
+                lines.push(vec![]);
+            }
+            if !part.is_empty() {
+                lines.last_mut().unwrap().push((class, part));
+            }
+        }
+        rest = after;
+    }
+    // a final line break ends the last line rather than starting another
+    if source.ends_with('\n') {
+        lines.pop();
+    }
+    lines
+}
+
+// the length of the `/* ... */` comment `text` starts with, which may contain nested ones
+fn block_comment_length(text: &str) -> usize {
+    let mut depth = 0;
+    let mut index = 0;
+    while index < text.len() {
+        if text[index..].starts_with("/*") {
+            depth += 1;
+            index += 2;
+        } else if text[index..].starts_with("*/") {
+            depth -= 1;
+            index += 2;
+            if depth == 0 {
+                return index;
+            }
+        } else {
+            index += text[index..].chars().next().unwrap().len_utf8();
+        }
+    }
+    text.len()
+}
+
+fn number_length(text: &str) -> usize {
+    let digits = |text: &str| {
+        text.find(|char: char| !char.is_ascii_digit())
+            .unwrap_or(text.len())
+    };
+    let whole = digits(text);
+    match text[whole..].strip_prefix('.') {
+        Some(fraction) if fraction.starts_with(|char: char| char.is_ascii_digit()) => {
+            whole + 1 + digits(fraction)
+        }
+        _ => whole,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_is_what_the_terminal_shows() {
+        let diagnostics = [
+            Diagnostic::warning(2, String::from("x"), String::from("Unused."))
+                .with_code("unused_variable"),
+            Diagnostic::error(3, None, String::from("Unexpected character.")),
+        ];
+        assert_eq!(
+            render(Format::Text, "", &diagnostics),
+            "[line 2] Warning x: Unused. [unused_variable]\n[line 3] Error: Unexpected character.\n"
+        );
+    }
+
+    #[test]
+    fn html_classifies_tokens_and_marks_diagnostics() {
+        let source = "var s = \"a<b\"; // note\nprint 1.5 + y;";
+        let diagnostics = [Diagnostic::error(
+            2,
+            Some(String::from("y")),
+            String::from("Undefined variable 'y'."),
+        )];
+        assert_eq!(
+            render(Format::Html, source, &diagnostics),
+            "<pre class=\"lox\">\
+             <span class=\"line\" id=\"L1\"><span class=\"line-number\">1</span>\
+             <span class=\"keyword\">var</span> <span class=\"identifier\">s</span> = \
+             <span class=\"string\">&quot;a&lt;b&quot;</span>; \
+             <span class=\"comment\">// note</span></span>\n\
+             <span class=\"line error\" id=\"L2\"><span class=\"line-number\">2</span>\
+             <span class=\"keyword\">print</span> <span class=\"number\">1.5</span> + \
+             <span class=\"identifier error\">y</span>;</span>\n\
+             <span class=\"diagnostic error\">[line 2] Error y: Undefined variable 'y'.</span>\n\
+             </pre>\n"
+        );
+    }
+
+    #[test]
+    fn json_has_a_line_per_diagnostic_with_its_span() {
+        let source = "print a == \"\\\";\n  x;";
+        let mut quoted = Diagnostic::error(
+            1,
+            Some(String::from("==")),
+            String::from("Can't compare \"\\\"\n."),
+        );
+        quoted.file = Some(String::from("dir/main.lox"));
+        let diagnostics = [
+            quoted,
+            Diagnostic::warning(2, String::from("x"), String::from("Unused."))
+                .with_code("unused_variable"),
+            Diagnostic::error(9, None, String::from("Unterminated string.")),
+        ];
+        assert_eq!(
+            render(Format::Json, source, &diagnostics),
+            "{\"code\":null,\"severity\":\"error\",\"message\":\"Can't compare \\\"\\\\\\\"\\n.\",\
+             \"file\":\"dir/main.lox\",\"span\":{\"line\":1,\"column\":9,\"end_column\":11}}\n\
+             {\"code\":\"unused_variable\",\"severity\":\"warning\",\"message\":\"Unused.\",\
+             \"file\":null,\"span\":{\"line\":2,\"column\":3,\"end_column\":4}}\n\
+             {\"code\":null,\"severity\":\"error\",\"message\":\"Unterminated string.\",\
+             \"file\":null,\"span\":{\"line\":9,\"column\":null,\"end_column\":null}}\n"
+        );
+    }
+
+    #[test]
+    fn collected_diagnostics_are_not_printed() {
+        let error = Diagnostic::error(1, None, String::from("Collected."));
+        let ((), collected) = collecting(|| report(error.clone()));
+        assert_eq!(collected, std::slice::from_ref(&error));
+        assert_eq!(collect(error.clone()), Some(error));
+    }
+
+    #[test]
+    fn multiline_tokens_are_split_by_line() {
+        let lines = highlight("/* a\n b */ \"c\nd\"");
+        assert_eq!(
+            lines,
+            [
+                vec![(Some("comment"), "/* a")],
+                vec![
+                    (Some("comment"), " b */"),
+                    (None, " "),
+                    (Some("string"), "\"c")
+                ],
+                vec![(Some("string"), "d\"")],
+            ]
+        );
+    }
+}
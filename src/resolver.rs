@@ -1,14 +1,18 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt::Display, rc::Rc};
 
 use crate::{
+    diagnostics::{Level, Warning},
+    environment::Upvalue,
     interpreter::Interpreter,
     print_error,
+    render::{self, Diagnostic},
     syntax::{
         expr::{self, Expr},
         stmt::{self, Stmt},
         token::Token,
     },
-    RuntimeError,
+    utils::confusables,
+    LoxError, RuntimeError,
 };
 
 #[derive(Clone, Copy)]
@@ -24,32 +28,191 @@ enum ClassType {
     None,
     Class,
     Subclass,
+    Trait,
+}
+
+#[derive(Clone, Copy)]
+struct Local {
+    // false between a variable's declaration and the end of its initializer
+    defined: bool,
+    line: usize,
+}
+
+/// Where the resolver decided a variable, `this` or `super` expression finds its value.
+pub(crate) struct Resolution {
+    name: String,
+    pub(crate) line: usize,
+    binding: Binding,
+}
+
+enum Binding {
+    Local {
+        declared_on: usize,
+        // how many block, function or class scopes out from the expression it was declared
+        scopes_up: usize,
+        // declared outside the innermost function, so the function's closure holds it
+        captured: bool,
+    },
+    Global,
+}
+
+impl Display for Resolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[line {}] {}: ", self.line, self.name)?;
+        match self.binding {
+            Binding::Local {
+                declared_on,
+                scopes_up,
+                captured,
+            } => {
+                let kind = match captured {
+                    true => "captured from an enclosing function",
+                    false => "local",
+                };
+                let scopes = match scopes_up {
+                    1 => String::from("1 scope up"),
+                    n => format!("{n} scopes up"),
+                };
+                write!(f, "{kind}, declared on line {declared_on}, {scopes}")
+            }
+            Binding::Global => write!(f, "global"),
+        }
+    }
+}
+
+struct FunctionScope {
+    uid: usize,
+    // index of the scope holding the function's parameters
+    scope: usize,
+    // scopes right above the parameters that are supplied when the function is bound ("this")
+    bound: usize,
+    upvalues: Vec<Upvalue>,
+    // whether a function or method is declared in the body, which could capture its variables
+    declares_closures: bool,
 }
 
 pub struct Resolver<'a> {
     interpreter: &'a mut Interpreter,
-    scopes: Vec<HashMap<String, bool>>,
+    scopes: Vec<HashMap<String, Local>>,
+    // local variables of each scope that have not been read yet, in the order they were
+    // declared, so they're reported in that order
+    unused: Vec<Vec<Token>>,
+    functions: Vec<FunctionScope>,
     current_function: FunctionType,
     current_class: ClassType,
+    // the only yield the statement being resolved may contain, see `Stmt::yield_point`
+    yield_point: Option<usize>,
+    // coroutines can't suspend inside a try statement, which always runs to completion
+    in_try: bool,
+    // method names of every trait declared so far, used to detect mixin conflicts
+    trait_methods: HashMap<String, Vec<String>>,
+    // line of each global function and class declared so far, used to detect redefinitions
+    definitions: HashMap<String, usize>,
+    // the first identifier declared with each skeleton, used to detect confusable identifiers
+    skeletons: HashMap<String, Token>,
+    // every warning reported so far along with the level it was reported at
+    pub(crate) warnings: Vec<(Warning, Level)>,
+    pub(crate) errors: Vec<LoxError>,
+    // every error and printed warning, with the token each is at, for rendering
+    pub(crate) diagnostics: Vec<Diagnostic>,
+    // how every variable, `this` and `super` was resolved, if asked to explain
+    pub(crate) explanations: Option<Vec<Resolution>>,
 }
 
 impl Resolver<'_> {
-    pub fn new(interpreter: &mut Interpreter) -> Resolver {
+    pub fn new(interpreter: &mut Interpreter) -> Resolver<'_> {
         Resolver {
             interpreter,
             scopes: vec![],
+            unused: vec![],
+            functions: vec![],
             current_function: FunctionType::None,
             current_class: ClassType::None,
+            yield_point: None,
+            in_try: false,
+            trait_methods: HashMap::new(),
+            definitions: HashMap::new(),
+            skeletons: HashMap::new(),
+            warnings: vec![],
+            errors: vec![],
+            diagnostics: vec![],
+            explanations: None,
         }
     }
 
-    pub fn resolve_block(&mut self, statements: &Vec<Stmt>) {
-        for statement in statements {
+    /// Records how each variable, `this` and `super` expression resolves, in `explanations`.
+    pub fn explain(&mut self) {
+        self.explanations = Some(vec![]);
+    }
+
+    pub fn resolve_block(&mut self, statements: &[Stmt]) {
+        for (i, statement) in statements.iter().enumerate() {
             self.resolve_stmt(statement);
+
+            let exit = match statement {
+                Stmt::Return { name, .. } => name,
+                Stmt::Throw { keyword, .. } => keyword,
+                _ => continue,
+            };
+            if i + 1 < statements.len() {
+                self.warn(
+                    Warning::UnreachableCode,
+                    exit,
+                    format!("Unreachable code after '{}'.", exit.lexeme),
+                );
+            }
+        }
+    }
+
+    fn warn(&mut self, warning: Warning, token: &Token, message: String) {
+        let level = self.interpreter.diagnostics.level(warning);
+        let diagnostic = match level {
+            Level::Allow => return,
+            Level::Warn => Diagnostic::warning(token.line, token.lexeme.clone(), message),
+            Level::Deny => {
+                self.errors.push(LoxError {
+                    message: format!("{message} [{warning}]"),
+                    line: token.line,
+                });
+                Diagnostic::error(token.line, Some(token.lexeme.clone()), message)
+            }
         }
+        .with_code(warning.code());
+        render::report(diagnostic.clone());
+        self.diagnostics.push(diagnostic);
+
+        self.warnings.push((warning, level));
+    }
+
+    fn error(&mut self, token: &Token, message: String) {
+        let error = RuntimeError {
+            token: token.clone(),
+            message,
+        };
+        error.error();
+        self.diagnostics.push(Diagnostic::error(
+            token.line,
+            Some(token.lexeme.clone()),
+            error.message.clone(),
+        ));
+        self.errors.push(LoxError::from(&error));
+    }
+
+    fn token_error(&mut self, token: &Token, message: &str) {
+        print_error(token.line, token.lexeme.clone(), message);
+        self.diagnostics.push(Diagnostic::error(
+            token.line,
+            Some(token.lexeme.clone()),
+            message.to_string(),
+        ));
+        self.errors.push(LoxError {
+            message: message.to_string(),
+            line: token.line,
+        });
     }
 
     fn resolve_stmt(&mut self, stmt: &Stmt) {
+        self.yield_point = stmt.yield_point().map(Expr::uid);
         stmt::Visitor::visit_stmt(self, stmt);
     }
 
@@ -57,16 +220,33 @@ impl Resolver<'_> {
         expr::Visitor::visit_expr(self, expr);
     }
 
-    fn resolve_function(
-        &mut self,
-        params: &Vec<Token>,
-        body: &Vec<Stmt>,
-        function_type: FunctionType,
-    ) {
+    fn resolve_function(&mut self, function: &Stmt, function_type: FunctionType) {
+        let Stmt::Function {
+            uid, params, body, ..
+        } = function
+        else {
+            panic!("Function was not a function declaration!");
+        };
+
         let enclosing_function = self.current_function;
         self.current_function = function_type;
+        let enclosing_try = std::mem::replace(&mut self.in_try, false);
+
+        if let Some(enclosing) = self.functions.last_mut() {
+            enclosing.declares_closures = true;
+        }
 
         self.begin_scope();
+        self.functions.push(FunctionScope {
+            uid: *uid,
+            scope: self.scopes.len() - 1,
+            bound: match function_type {
+                FunctionType::Initializer | FunctionType::Method => 1,
+                _ => 0,
+            },
+            upvalues: vec![],
+            declares_closures: false,
+        });
 
         for param in params {
             self.declare(param);
@@ -74,34 +254,95 @@ impl Resolver<'_> {
         }
         self.resolve_block(body);
 
+        let function = self
+            .functions
+            .pop()
+            .expect("stack of functions to not be empty.");
+        self.interpreter.capture(function.uid, function.upvalues);
+        if !function.declares_closures {
+            self.interpreter.inline_frame(function.uid);
+        }
+
         self.end_scope();
         self.current_function = enclosing_function;
+        self.in_try = enclosing_try;
+    }
+
+    fn resolve_decorators(&mut self, function: &Stmt) {
+        if let Stmt::Function { decorators, .. } = function {
+            for decorator in decorators {
+                self.resolve_expr(decorator);
+            }
+        }
     }
 
     fn begin_scope(&mut self) {
-        self.scopes.push(HashMap::new())
+        self.scopes.push(HashMap::new());
+        self.unused.push(vec![]);
     }
 
     fn end_scope(&mut self) {
         self.scopes.pop().expect("stack of scopes to not be empty.");
+
+        let unused = self.unused.pop().expect("stack of scopes to not be empty.");
+        for name in unused {
+            self.warn(
+                Warning::UnusedVariable,
+                &name,
+                format!("Variable '{}' is never read.", name.lexeme),
+            );
+        }
+    }
+
+    fn mark_used(&mut self, name: &Token) {
+        if let Some(declared) = (0..self.scopes.len())
+            .rev()
+            .find(|i| self.scopes[*i].contains_key(&name.lexeme))
+        {
+            self.unused[declared].retain(|unused| unused.lexeme != name.lexeme);
+        }
     }
 
     fn declare(&mut self, name: &Token) {
+        self.check_builtin(name);
+        self.check_confusable(name);
+
         if self.scopes.is_empty() {
             return;
         }
 
-        let scope = self.peek_scopes_mut();
+        if self.peek_scopes_mut().contains_key(&name.lexeme) {
+            self.error(
+                name,
+                String::from("Already a variable with this name in this scope."),
+            );
+        }
+
+        let local = Local {
+            defined: false,
+            line: name.line,
+        };
+        self.peek_scopes_mut().insert(name.lexeme.clone(), local);
+    }
 
-        if scope.contains_key(&name.lexeme) {
-            RuntimeError {
-                token: name.clone(),
-                message: "Already a variable with this name in this scope.".to_string(),
-            }
-            .error();
+    // global functions and classes are replaced by a later declaration of the same name
+    fn check_redefinition(&mut self, name: &Token) {
+        if !self.scopes.is_empty() {
+            return;
         }
 
-        scope.insert(name.lexeme.clone(), false);
+        match self.definitions.get(&name.lexeme) {
+            Some(line) => {
+                let message = format!(
+                    "'{}' is already defined on line {line}, and this replaces it.",
+                    name.lexeme
+                );
+                self.warn(Warning::Redefinition, name, message);
+            }
+            None => {
+                self.definitions.insert(name.lexeme.clone(), name.line);
+            }
+        }
     }
 
     fn define(&mut self, name: &Token) {
@@ -109,20 +350,154 @@ impl Resolver<'_> {
             return;
         }
 
-        let scope = self.peek_scopes_mut();
-        scope.insert(name.lexeme.clone(), true);
+        let local = Local {
+            defined: true,
+            line: name.line,
+        };
+        self.peek_scopes_mut().insert(name.lexeme.clone(), local);
     }
 
-    fn resolve_local(&mut self, expr: &Expr, name: &Token) {
-        for i in (0..self.scopes.len()).rev() {
-            if self.scopes[i].contains_key(&name.lexeme) {
-                let hops_away = self.scopes.len() - 1 - i;
-                self.interpreter.resolve(expr, hops_away);
-                return;
+    // binds "this" or "super" in the innermost scope, as if declared by the class itself
+    fn define_implicit(&mut self, name: &str, class_name: &Token) {
+        let local = Local {
+            defined: true,
+            line: class_name.line,
+        };
+        self.peek_scopes_mut().insert(name.to_string(), local);
+    }
+
+    // identifiers that mix scripts, or look just like an earlier one, are easy to slip past review
+    fn check_confusable(&mut self, name: &Token) {
+        let level = self
+            .interpreter
+            .diagnostics
+            .level(Warning::ConfusableIdentifier);
+        if level == Level::Allow {
+            return;
+        }
+
+        if let Some((first, second)) = confusables::mixed_scripts(&name.lexeme) {
+            let message = format!(
+                "'{}' mixes {} and {} letters.",
+                name.lexeme,
+                first.name(),
+                second.name()
+            );
+            self.warn(Warning::ConfusableIdentifier, name, message);
+        }
+
+        let skeleton = confusables::skeleton(&name.lexeme);
+        match self.skeletons.get(&skeleton) {
+            Some(earlier) if earlier.lexeme != name.lexeme => {
+                let message = format!(
+                    "'{}' looks like '{}' declared on line {}, but is a different name.",
+                    name.lexeme, earlier.lexeme, earlier.line
+                );
+                self.warn(Warning::ConfusableIdentifier, name, message);
+            }
+            Some(_) => {}
+            None => {
+                self.skeletons.insert(skeleton, name.clone());
             }
         }
     }
 
+    fn check_builtin(&mut self, name: &Token) {
+        if self.interpreter.is_protected_builtin(&name.lexeme) {
+            self.error(name, format!("Cannot redefine built-in '{}'.", name.lexeme));
+        }
+    }
+
+    fn resolve_local(&mut self, expr: &Expr, name: &Token) {
+        self.record_resolution(name);
+
+        match self.hops_to(&name.lexeme) {
+            Some(hops_away) => self.interpreter.resolve(expr, hops_away),
+            None => self.check_capability(name),
+        }
+    }
+
+    fn check_capability(&mut self, name: &Token) {
+        let Some(group) = self.interpreter.withheld_group(&name.lexeme) else {
+            return;
+        };
+
+        let holder = match self.interpreter.importers.last() {
+            Some(module) => format!("module '{module}'"),
+            None => String::from("this program"),
+        };
+        self.error(
+            name,
+            format!(
+                "'{}' is a '{group}' native, which {holder} hasn't been granted.",
+                name.lexeme
+            ),
+        );
+    }
+
+    fn record_resolution(&mut self, name: &Token) {
+        if self.explanations.is_none() {
+            return;
+        }
+
+        let declared = (0..self.scopes.len())
+            .rev()
+            .find(|i| self.scopes[*i].contains_key(&name.lexeme));
+        let binding = match declared {
+            Some(declared) => Binding::Local {
+                declared_on: self.scopes[declared][&name.lexeme].line,
+                scopes_up: self.scopes.len() - 1 - declared,
+                captured: self
+                    .functions
+                    .last()
+                    .is_some_and(|function| declared + function.bound < function.scope),
+            },
+            None => Binding::Global,
+        };
+
+        if let Some(explanations) = &mut self.explanations {
+            explanations.push(Resolution {
+                name: name.lexeme.clone(),
+                line: name.line,
+                binding,
+            });
+        }
+    }
+
+    /// Finds how many environments up a local variable lives at runtime, or None for globals.
+    fn hops_to(&mut self, name: &str) -> Option<usize> {
+        let declared = (0..self.scopes.len())
+            .rev()
+            .find(|i| self.scopes[*i].contains_key(name))?;
+
+        Some(self.hops_from(name, declared, self.scopes.len() - 1, self.functions.len()))
+    }
+
+    /// Variables declared outside the innermost of the first `depth` functions are captured as
+    /// upvalues, which live in the environment just past the function's parameters (and "this").
+    /// Every function in between captures the variable too, so the chain of closures stays intact.
+    fn hops_from(&mut self, name: &str, declared: usize, current: usize, depth: usize) -> usize {
+        let Some(function) = depth.checked_sub(1).map(|i| &self.functions[i]) else {
+            return current - declared;
+        };
+        let (scope, bound) = (function.scope, function.bound);
+
+        if declared + bound >= scope {
+            return current - declared;
+        }
+
+        let distance = self.hops_from(name, declared, scope - 1 - bound, depth - 1);
+        let upvalues = &mut self.functions[depth - 1].upvalues;
+        if !upvalues.iter().any(|upvalue| upvalue.name == name) {
+            upvalues.push(Upvalue {
+                name: name.to_string(),
+                distance,
+            });
+        }
+
+        current - scope + 1 + bound
+    }
+
     fn resolve_super_class(&mut self, class_name: &Token, super_class_expr: &Expr) {
         match super_class_expr {
             Expr::Variable {
@@ -130,11 +505,10 @@ impl Resolver<'_> {
                 ..
             } => {
                 if class_name.lexeme == super_class_name.lexeme {
-                    RuntimeError {
-                        token: super_class_name.clone(),
-                        message: "A class can't inherit from itself.".to_string(),
-                    }
-                    .error()
+                    self.error(
+                        super_class_name,
+                        String::from("A class can't inherit from itself."),
+                    )
                 }
 
                 self.resolve_expr(super_class_expr);
@@ -143,7 +517,81 @@ impl Resolver<'_> {
         }
     }
 
-    fn visit_block_stmt(&mut self, statements: &Vec<Stmt>) {
+    fn resolve_traits(&mut self, class_name: &Token, traits: &[Expr], methods: &[Stmt]) {
+        let mut mixed_in: Vec<&Token> = vec![];
+
+        for trait_expr in traits {
+            let trait_name = match trait_expr {
+                Expr::Variable { name, .. } => name,
+                _ => panic!("Trait is not a variable!"),
+            };
+
+            if class_name.lexeme == trait_name.lexeme {
+                self.error(trait_name, String::from("A class can't mix in itself."));
+            }
+
+            if mixed_in
+                .iter()
+                .any(|other| other.lexeme == trait_name.lexeme)
+            {
+                self.error(
+                    trait_name,
+                    String::from("A class can't mix in the same trait twice."),
+                );
+            }
+
+            self.resolve_expr(trait_expr);
+            mixed_in.push(trait_name);
+        }
+
+        let mut provided_by: HashMap<String, &String> = HashMap::new();
+        for trait_name in mixed_in {
+            let Some(trait_methods) = self.trait_methods.get(&trait_name.lexeme).cloned() else {
+                continue;
+            };
+
+            for method_name in trait_methods {
+                let overridden = methods.iter().any(|method| {
+                    matches!(method, Stmt::Function { name, .. } if name.lexeme == method_name)
+                });
+                if overridden {
+                    continue;
+                }
+
+                match provided_by.get(&method_name) {
+                    Some(other) if **other != trait_name.lexeme => self.error(
+                        trait_name,
+                        format!(
+                            "Method '{}' is provided by both traits '{}' and '{}'.",
+                            method_name, other, trait_name.lexeme
+                        ),
+                    ),
+                    _ => {
+                        provided_by.insert(method_name, &trait_name.lexeme);
+                    }
+                }
+            }
+        }
+    }
+
+    fn resolve_methods(&mut self, methods: &Vec<Stmt>) {
+        for method in methods {
+            match method {
+                Stmt::Function { name, .. } => {
+                    let declaration = if name.lexeme == "init" {
+                        FunctionType::Initializer
+                    } else {
+                        FunctionType::Method
+                    };
+
+                    self.resolve_function(method, declaration)
+                }
+                _ => panic!("Method is not a function!"),
+            }
+        }
+    }
+
+    fn visit_block_stmt(&mut self, statements: &[Stmt]) {
         self.begin_scope();
         self.resolve_block(statements);
         self.end_scope();
@@ -153,37 +601,40 @@ impl Resolver<'_> {
         &mut self,
         name: &Token,
         super_class: &Option<Box<Expr>>,
+        traits: &[Expr],
         methods: &Vec<Stmt>,
+        setters: &Vec<Stmt>,
     ) {
         let enclosing_class = self.current_class;
         self.current_class = ClassType::Class;
 
+        self.check_redefinition(name);
         self.declare(name);
         self.define(name);
 
+        // decorators run outside the class, once it has been made
+        for method in methods {
+            self.resolve_decorators(method);
+        }
+        self.resolve_traits(name, traits, methods);
+
         if let Some(super_class) = super_class {
             self.current_class = ClassType::Subclass;
             self.resolve_super_class(name, super_class);
 
             self.begin_scope();
-            self.peek_scopes_mut().insert(String::from("super"), true);
+            self.define_implicit("super", name);
         }
 
         self.begin_scope();
-        self.peek_scopes_mut().insert(String::from("this"), true);
+        self.define_implicit("this", name);
 
-        for method in methods {
-            match method {
-                Stmt::Function { params, body, name } => {
-                    let declaration = if name.lexeme == "init" {
-                        FunctionType::Initializer
-                    } else {
-                        FunctionType::Method
-                    };
+        self.resolve_methods(methods);
 
-                    self.resolve_function(params, body, declaration)
-                }
-                _ => panic!("Method is not a function!"),
+        for setter in setters {
+            match setter {
+                Stmt::Function { .. } => self.resolve_function(setter, FunctionType::Method),
+                _ => panic!("Setter is not a function!"),
             }
         }
 
@@ -196,22 +647,54 @@ impl Resolver<'_> {
         self.current_class = enclosing_class;
     }
 
+    fn visit_trait_stmt(&mut self, name: &Token, methods: &Vec<Stmt>) {
+        let enclosing_class = self.current_class;
+        self.current_class = ClassType::Trait;
+
+        self.declare(name);
+        self.define(name);
+
+        let method_names = methods
+            .iter()
+            .filter_map(|method| match method {
+                Stmt::Function { name, .. } => Some(name.lexeme.clone()),
+                _ => None,
+            })
+            .collect();
+        self.trait_methods.insert(name.lexeme.clone(), method_names);
+
+        self.begin_scope();
+        self.define_implicit("this", name);
+
+        for method in methods {
+            match method {
+                Stmt::Function { .. } => self.resolve_function(method, FunctionType::Method),
+                _ => panic!("Method is not a function!"),
+            }
+        }
+
+        self.end_scope();
+        self.current_class = enclosing_class;
+    }
+
     fn visit_expr_stmt(&mut self, expr: &Expr) {
         self.resolve_expr(expr);
     }
 
-    fn visit_function_stmt(&mut self, name: &Token, params: &Vec<Token>, body: &Vec<Stmt>) {
+    fn visit_function_stmt(&mut self, name: &Token, function: &Stmt) {
+        self.resolve_decorators(function);
+        self.check_redefinition(name);
         self.declare(name);
         self.define(name);
 
-        self.resolve_function(params, body, FunctionType::Function);
+        self.resolve_function(function, FunctionType::Function);
     }
 
     fn visit_if_stmt(
         &mut self,
         condition: &Expr,
         then_branch: &Stmt,
-        else_branch: &Option<Box<Stmt>>,
+        else_branch: &Option<Rc<Stmt>>,
     ) {
         self.resolve_expr(condition);
         self.resolve_stmt(then_branch);
@@ -220,33 +703,76 @@ impl Resolver<'_> {
         }
     }
 
-    fn visit_print_stmt(&mut self, value: &Expr) {
-        self.resolve_expr(value);
+    fn visit_print_stmt(&mut self, values: &Vec<Expr>) {
+        for value in values {
+            self.resolve_expr(value);
+        }
     }
 
     fn visit_return_stmt(&mut self, name: &Token, value: &Option<Box<Expr>>) {
         if let FunctionType::None = self.current_function {
-            RuntimeError {
-                token: name.clone(),
-                message: "Can't return from top-level code".to_string(),
-            }
-            .error();
+            self.error(name, String::from("Can't return from top-level code"));
         }
 
         if let Some(value) = value {
             if let FunctionType::Initializer = self.current_function {
-                RuntimeError {
-                    token: name.clone(),
-                    message: "Can't return a value from an initializer.".to_string(),
-                }
-                .error()
+                self.error(
+                    name,
+                    String::from("Can't return a value from an initializer."),
+                )
             }
 
             self.resolve_expr(value);
         }
     }
 
+    fn visit_try_stmt(
+        &mut self,
+        body: &[Stmt],
+        catch: &Option<(Token, Vec<Stmt>)>,
+        finally: &Option<Vec<Stmt>>,
+    ) {
+        let enclosing_try = std::mem::replace(&mut self.in_try, true);
+        self.visit_block_stmt(body);
+
+        if let Some((name, catch_body)) = catch {
+            self.begin_scope();
+            self.declare(name);
+            self.define(name);
+            self.resolve_block(catch_body);
+            self.end_scope();
+        }
+
+        if let Some(finally) = finally {
+            self.visit_block_stmt(finally);
+        }
+        self.in_try = enclosing_try;
+    }
+
+    fn visit_throw_stmt(&mut self, value: &Expr) {
+        self.resolve_expr(value);
+    }
+
     fn visit_var_stmt(&mut self, name: &Token, initializer: &Option<Expr>) {
+        // variables prefixed with an underscore are intentionally unused or shadowing
+        let checked = !self.scopes.is_empty() && !name.lexeme.starts_with('_');
+
+        let enclosing = &self.scopes[..self.scopes.len().saturating_sub(1)];
+        if checked
+            && enclosing
+                .iter()
+                .any(|scope| scope.contains_key(&name.lexeme))
+        {
+            self.warn(
+                Warning::Shadowing,
+                name,
+                format!(
+                    "Variable '{}' shadows a variable in an enclosing scope.",
+                    name.lexeme
+                ),
+            );
+        }
+
         self.declare(name);
 
         if let Some(initializer) = initializer {
@@ -254,6 +780,13 @@ impl Resolver<'_> {
         }
 
         self.define(name);
+
+        if checked {
+            if let Some(unused) = self.unused.last_mut() {
+                unused.retain(|unused| unused.lexeme != name.lexeme);
+                unused.push(name.clone());
+            }
+        }
     }
 
     fn visit_while_stmt(&mut self, condition: &Expr, body: &Stmt) {
@@ -261,9 +794,25 @@ impl Resolver<'_> {
         self.resolve_stmt(body);
     }
 
+    fn visit_for_in_stmt(&mut self, name: &Token, iterable: &Expr, body: &Stmt) {
+        self.resolve_expr(iterable);
+
+        // each iteration binds the loop variable in a scope of its own, around the body
+        self.begin_scope();
+        self.declare(name);
+        self.define(name);
+        self.resolve_stmt(body);
+        self.end_scope();
+    }
+
     fn visit_assign_expr(&mut self, var_expr: &Expr, name: &Token, value: &Expr) {
         self.resolve_expr(value);
-        self.resolve_local(var_expr, name);
+        self.record_resolution(name);
+
+        match self.hops_to(&name.lexeme) {
+            Some(hops_away) => self.interpreter.resolve(var_expr, hops_away),
+            None => self.check_builtin(name),
+        }
     }
 
     fn visit_binary_expr(&mut self, left: &Expr, right: &Expr) {
@@ -301,29 +850,25 @@ impl Resolver<'_> {
 
     fn visit_super_expr(&mut self, expr: &Expr, keyword: &Token) {
         match self.current_class {
-            ClassType::None => print_error(
-                keyword.line,
-                keyword.lexeme.clone(),
-                "Can't use 'super' outside of a class.",
-            ),
-            ClassType::Class => print_error(
-                keyword.line,
-                keyword.lexeme.clone(),
-                "Can't use 'super' in a class with no superclass.",
-            ),
+            ClassType::None => self.token_error(keyword, "Can't use 'super' outside of a class."),
+            ClassType::Class => {
+                self.token_error(keyword, "Can't use 'super' in a class with no superclass.")
+            }
             ClassType::Subclass => {}
+            ClassType::Trait => self.token_error(keyword, "Can't use 'super' in a trait."),
         }
 
         self.resolve_local(expr, keyword);
+
+        // the method found on the superclass is bound to the innermost "this"
+        if let Some(hops_away) = self.hops_to("this") {
+            self.interpreter.resolve_receiver(expr, hops_away);
+        }
     }
 
     fn visit_this_expr(&mut self, expr: &Expr, keyword: &Token) {
         if let ClassType::None = self.current_class {
-            print_error(
-                keyword.line,
-                keyword.lexeme.clone(),
-                "Can't use 'this' outside of a class.",
-            );
+            self.token_error(keyword, "Can't use 'this' outside of a class.");
             return;
         }
 
@@ -334,21 +879,46 @@ impl Resolver<'_> {
         self.resolve_expr(right);
     }
 
+    fn visit_update_expr(&mut self, target: &Expr) {
+        self.resolve_expr(target);
+
+        // a local can only be named after a built-in when redefinition is allowed
+        if let Expr::Variable { name, .. } = target {
+            self.check_builtin(name);
+        }
+    }
+
+    fn visit_yield_expr(&mut self, expr: &Expr, keyword: &Token, value: &Option<Box<Expr>>) {
+        let message = match self.current_function {
+            FunctionType::None => Some("Can't yield from top-level code."),
+            FunctionType::Initializer => Some("Can't yield from an initializer."),
+            _ if self.in_try => Some("Can't yield inside a try statement."),
+            _ if self.yield_point != Some(expr.uid()) => Some(
+                "Can only yield as a whole statement, a variable initializer or an assigned value.",
+            ),
+            _ => None,
+        };
+        if let Some(message) = message {
+            self.error(keyword, String::from(message));
+        }
+
+        if let Some(value) = value {
+            self.resolve_expr(value);
+        }
+    }
+
     fn visit_var_expr(&mut self, var_expr: &Expr, name: &Token) {
         if let Some(scope) = self.scopes.last() {
-            if let Some(false) = scope.get(&name.lexeme) {
-                print_error(
-                    name.line,
-                    name.lexeme.clone(),
-                    "Can't read local variable in its own initializer.",
-                )
+            if let Some(Local { defined: false, .. }) = scope.get(&name.lexeme) {
+                self.token_error(name, "Can't read local variable in its own initializer.")
             }
         }
 
+        self.mark_used(name);
         self.resolve_local(var_expr, name)
     }
 
-    fn peek_scopes_mut(&mut self) -> &mut HashMap<String, bool> {
+    fn peek_scopes_mut(&mut self) -> &mut HashMap<String, Local> {
         self.scopes
             .last_mut()
             .expect("stack of scopes to be non-empty")
@@ -370,6 +940,8 @@ impl expr::Visitor<()> for Resolver<'_> {
             Expr::Set { object, value, .. } => self.visit_set_expr(object, value),
             Expr::This { keyword, .. } => self.visit_this_expr(expr, keyword),
             Expr::Super { keyword, .. } => self.visit_super_expr(expr, keyword),
+            Expr::Update { target, .. } => self.visit_update_expr(target),
+            Expr::Yield { keyword, value, .. } => self.visit_yield_expr(expr, keyword, value),
         }
     }
 }
@@ -378,7 +950,7 @@ impl stmt::Visitor<()> for Resolver<'_> {
     fn visit_stmt(&mut self, stmt: &Stmt) {
         match stmt {
             Stmt::Expression(expr) => self.visit_expr_stmt(expr),
-            Stmt::Print(value) => self.visit_print_stmt(value),
+            Stmt::Print(values) => self.visit_print_stmt(values),
             Stmt::Block(statements) => self.visit_block_stmt(statements),
             Stmt::Var { name, initializer } => self.visit_var_stmt(name, initializer),
             Stmt::If {
@@ -387,13 +959,30 @@ impl stmt::Visitor<()> for Resolver<'_> {
                 else_branch,
             } => self.visit_if_stmt(condition, then_branch, else_branch),
             Stmt::While { condition, body } => self.visit_while_stmt(condition, body),
-            Stmt::Function { name, params, body } => self.visit_function_stmt(name, params, body),
+            Stmt::ForIn {
+                name,
+                iterable,
+                body,
+            } => self.visit_for_in_stmt(name, iterable, body),
+            Stmt::Function { name, .. } => self.visit_function_stmt(name, stmt),
             Stmt::Return { name, value } => self.visit_return_stmt(name, value),
+            Stmt::Throw { value, .. } => self.visit_throw_stmt(value),
+            // modules are resolved on their own when they're imported
+            Stmt::Import { .. } => {}
             Stmt::Class {
                 name,
                 super_class,
+                traits,
                 methods,
-            } => self.visit_class_stmt(name, super_class, methods),
+                setters,
+                ..
+            } => self.visit_class_stmt(name, super_class, traits, methods, setters),
+            Stmt::Trait { name, methods } => self.visit_trait_stmt(name, methods),
+            Stmt::Try {
+                body,
+                catch,
+                finally,
+            } => self.visit_try_stmt(body, catch, finally),
         }
     }
 }
@@ -402,30 +991,229 @@ impl stmt::Visitor<()> for Resolver<'_> {
 mod tests {
     use std::{env, fs};
 
-    use crate::{interpreter::Interpreter, parser::Parser, runtime_error, scanner::Scanner};
+    use crate::{
+        diagnostics::DiagnosticOptions,
+        interpreter::{Interpreter, InterpreterOptions},
+        parser::Parser,
+        scanner::Scanner,
+    };
 
     use super::*;
 
     fn test_for_resolution_error(file_path: &str) {
         let lox_code = fs::read_to_string(file_path).expect("file to be readable");
-        resolve_code(lox_code);
-
-        assert!(runtime_error())
+        assert!(!resolve_code(lox_code).is_empty());
     }
 
-    fn resolve_code(lox_code: String) {
+    // the syntax and resolution errors in the code
+    fn resolve_code(lox_code: String) -> Vec<LoxError> {
         env::set_var("RUST_BACKTRACE", "1");
 
-        let mut interpreter = Interpreter::new(None);
+        let mut interpreter = Interpreter::new(InterpreterOptions::default());
 
         let mut scanner = Scanner::new(lox_code);
-        let tokens = scanner.scan_tokens();
+        let tokens = scanner.scan_tokens().unwrap();
 
         let mut parser = Parser::new(tokens);
         let statements = parser.parse();
 
         let mut resolver = Resolver::new(&mut interpreter);
         resolver.resolve_block(&statements);
+        parser.errors.into_iter().chain(resolver.errors).collect()
+    }
+
+    fn explain(lox_code: &str) -> Vec<String> {
+        let mut interpreter = Interpreter::new(InterpreterOptions::default());
+
+        let mut scanner = Scanner::new(lox_code.to_string());
+        let tokens = scanner.scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse();
+
+        let mut resolver = Resolver::new(&mut interpreter);
+        resolver.explain();
+        resolver.resolve_block(&statements);
+        resolver
+            .explanations
+            .unwrap()
+            .iter()
+            .map(|resolution| resolution.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn explains_resolution() {
+        let explanations = explain(
+            "fun makeCounter() {
+               var count = 0;
+               fun counter() {
+                 count = count + 1;
+                 {
+                   var step = count;
+                   print step;
+                 }
+               }
+               return counter;
+             }
+             class A {
+               name() { return this; }
+             }
+             class B < A {
+               name() { return super.name(); }
+             }
+             makeCounter();",
+        );
+
+        assert_eq!(
+            explanations,
+            [
+                "[line 4] count: captured from an enclosing function, declared on line 2, 1 scope up",
+                "[line 4] count: captured from an enclosing function, declared on line 2, 1 scope up",
+                "[line 6] count: captured from an enclosing function, declared on line 2, 2 scopes up",
+                "[line 7] step: local, declared on line 6, 0 scopes up",
+                "[line 10] counter: local, declared on line 3, 0 scopes up",
+                "[line 13] this: local, declared on line 12, 1 scope up",
+                "[line 15] A: global",
+                "[line 16] super: captured from an enclosing function, declared on line 15, 2 scopes up",
+                "[line 18] makeCounter: global",
+            ]
+        );
+    }
+
+    fn resolve_warnings(lox_code: &str, diagnostics: DiagnosticOptions) -> Vec<(Warning, Level)> {
+        let mut interpreter = Interpreter::new(InterpreterOptions {
+            diagnostics,
+            ..Default::default()
+        });
+
+        let mut scanner = Scanner::new(lox_code.to_string());
+        let tokens = scanner.scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse();
+
+        let mut resolver = Resolver::new(&mut interpreter);
+        resolver.resolve_block(&statements);
+        resolver.warnings
+    }
+
+    const SUSPICIOUS_CODE: &str = "
+        fun f(a) {
+          var unused = 1;
+          var _ignored = 2;
+          {
+            var a = 3;
+            print a;
+          }
+          return a;
+          print \"never\";
+        }
+    ";
+
+    #[test]
+    fn warnings_are_reported() {
+        assert_eq!(
+            resolve_warnings(SUSPICIOUS_CODE, DiagnosticOptions::default()),
+            [
+                (Warning::Shadowing, Level::Warn),
+                (Warning::UnreachableCode, Level::Warn),
+                (Warning::UnusedVariable, Level::Warn),
+            ]
+        );
+    }
+
+    #[test]
+    fn unused_variables_are_reported_in_declaration_order() {
+        let mut interpreter = Interpreter::new(InterpreterOptions::default());
+        let mut scanner = Scanner::new(String::from(
+            "{ var e = 1; var d = 2; var c = 3; var b = 4; var a = 5; }",
+        ));
+        let tokens = scanner.scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse();
+
+        let mut resolver = Resolver::new(&mut interpreter);
+        resolver.resolve_block(&statements);
+        let messages = resolver
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.clone())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            messages,
+            ["e", "d", "c", "b", "a"].map(|name| format!("Variable '{name}' is never read."))
+        );
+    }
+
+    #[test]
+    fn warnings_can_be_promoted_or_silenced() {
+        let diagnostics = DiagnosticOptions::default()
+            .deny_all()
+            .allow(Warning::Shadowing);
+
+        assert_eq!(
+            resolve_warnings(SUSPICIOUS_CODE, diagnostics),
+            [
+                (Warning::UnreachableCode, Level::Deny),
+                (Warning::UnusedVariable, Level::Deny),
+            ]
+        );
+    }
+
+    #[test]
+    fn redefining_a_global_function_or_class() {
+        let code = "fun greet() {}\nclass Greeter {}\nfun greet() {}\nclass Greeter {}\n{ fun greet() {} }";
+        assert_eq!(
+            resolve_warnings(code, DiagnosticOptions::default()),
+            [
+                (Warning::Redefinition, Level::Warn),
+                (Warning::Redefinition, Level::Warn),
+            ]
+        );
+
+        let mut interpreter = Interpreter::new(InterpreterOptions {
+            diagnostics: DiagnosticOptions::default().deny(Warning::Redefinition),
+            ..Default::default()
+        });
+        assert_eq!(
+            interpreter.load(code),
+            Err(LoxError {
+                message: String::from(
+                    "'greet' is already defined on line 1, and this replaces it. [redefinition]"
+                ),
+                line: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn confusable_identifiers_are_opt_in() {
+        // the second `price` starts with a Cyrillic `р`
+        let code = "var price = 1;\nvar рrice = 2;\nvar größe = 3;";
+        assert_eq!(resolve_warnings(code, DiagnosticOptions::default()), []);
+
+        let mut diagnostics = DiagnosticOptions::default();
+        diagnostics
+            .set("confusable_identifier", Level::Warn)
+            .unwrap();
+        // mixing scripts, then looking like `price`
+        assert_eq!(
+            resolve_warnings(code, diagnostics.clone()),
+            [(Warning::ConfusableIdentifier, Level::Warn); 2]
+        );
+
+        diagnostics
+            .set("confusable_identifier", Level::Deny)
+            .unwrap();
+        let mut interpreter = Interpreter::new(InterpreterOptions {
+            diagnostics,
+            ..Default::default()
+        });
+        assert_eq!(
+            interpreter.load(code),
+            Err(LoxError {
+                message: String::from(
+                    "'рrice' mixes Cyrillic and Latin letters. [confusable_identifier]"
+                ),
+                line: 2,
+            })
+        );
     }
 
     #[test]
@@ -457,4 +1245,39 @@ mod tests {
     fn top_level_super_use() {
         test_for_resolution_error("test_files/top_level_super.lox")
     }
+
+    #[test]
+    fn conflicting_trait_methods() {
+        test_for_resolution_error("test_files/conflicting_traits_error.lox")
+    }
+
+    #[test]
+    fn redefining_builtins() {
+        test_for_resolution_error("test_files/redefine_builtin_error.lox")
+    }
+
+    #[test]
+    fn yield_inside_expression() {
+        test_for_resolution_error("test_files/invalid_yield_error.lox")
+    }
+
+    #[test]
+    fn only_functions_declaring_closures_share_their_frames() {
+        let mut interpreter = Interpreter::new(InterpreterOptions::default());
+        let lox_code = "fun add(a, b) { var sum = a + b; return sum; }
+                        fun outer() { var x = 1; fun inner() { return x; } return inner; }";
+        let mut scanner = Scanner::new(lox_code.to_string());
+        let statements = Parser::new(scanner.scan_tokens().unwrap()).parse();
+        Resolver::new(&mut interpreter).resolve_block(&statements);
+
+        let uids = statements
+            .iter()
+            .map(|statement| match statement {
+                Stmt::Function { uid, .. } => *uid,
+                _ => panic!("Statement is not a function!"),
+            })
+            .collect::<Vec<usize>>();
+        assert!(!interpreter.shares_frame(uids[0]));
+        assert!(interpreter.shares_frame(uids[1]));
+    }
 }
@@ -1,6 +1,28 @@
-use std::collections::HashMap;
+#[cfg(feature = "bigint")]
+use std::rc::Rc;
 
-use crate::syntax::token::{Literal, Token, TokenType};
+#[cfg(feature = "bigint")]
+use crate::impls::bigint::BigInt;
+use crate::{
+    render::{self, Diagnostic},
+    syntax::token::{Literal, Token, TokenType},
+};
+
+#[derive(Debug, PartialEq)]
+pub struct ScanError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl ScanError {
+    pub fn error(&self) {
+        render::report(self.diagnostic());
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::error(self.line, None, self.message.clone())
+    }
+}
 
 pub struct Scanner {
     source: String,
@@ -8,7 +30,7 @@ pub struct Scanner {
     start: usize,
     current: usize,
     line: usize,
-    has_error: bool,
+    errors: Vec<ScanError>,
 }
 
 impl Scanner {
@@ -19,11 +41,12 @@ impl Scanner {
             start: 0,
             current: 0,
             line: 1,
-            has_error: false,
+            errors: vec![],
         }
     }
 
-    pub fn scan_tokens(&mut self) -> &Vec<Token> {
+    /// Scans the whole source, returning every lexical error encountered if there were any.
+    pub fn scan_tokens(&mut self) -> Result<&Vec<Token>, &Vec<ScanError>> {
         while !self.is_at_end() {
             self.start = self.current;
             self.scan_token();
@@ -35,12 +58,15 @@ impl Scanner {
             literal: Literal::None,
             line: self.line,
         });
-        &self.tokens
+
+        if !self.errors.is_empty() {
+            return Err(&self.errors);
+        }
+        Ok(&self.tokens)
     }
 
     fn scan_token(&mut self) {
-        let token = self.source.as_bytes()[self.current] as char;
-        self.current += 1;
+        let token = self.advance();
 
         match token {
             '(' => self.add_token(TokenType::LeftParen, Literal::None),
@@ -48,13 +74,33 @@ impl Scanner {
             '{' => self.add_token(TokenType::LeftBrace, Literal::None),
             '}' => self.add_token(TokenType::RightBrace, Literal::None),
             ',' => self.add_token(TokenType::Comma, Literal::None),
-            '.' => self.add_token(TokenType::Dot, Literal::None),
-            '-' => self.add_token(TokenType::Minus, Literal::None),
-            '+' => self.add_token(TokenType::Plus, Literal::None),
+            '.' => {
+                if self.peek() == '.' && self.peek_next() == '.' {
+                    self.current += 2;
+                    self.add_token(TokenType::Ellipsis, Literal::None);
+                } else {
+                    self.add_token(TokenType::Dot, Literal::None);
+                }
+            }
             ';' => self.add_token(TokenType::Semicolon, Literal::None),
             '*' => self.add_token(TokenType::Star, Literal::None),
+            '@' => self.add_token(TokenType::At, Literal::None),
 
             // single or double length operators
+            '-' => {
+                if self.match_next_token('-') {
+                    self.add_token(TokenType::MinusMinus, Literal::None);
+                } else {
+                    self.add_token(TokenType::Minus, Literal::None);
+                }
+            }
+            '+' => {
+                if self.match_next_token('+') {
+                    self.add_token(TokenType::PlusPlus, Literal::None);
+                } else {
+                    self.add_token(TokenType::Plus, Literal::None);
+                }
+            }
             '!' => {
                 if self.match_next_token('=') {
                     self.add_token(TokenType::BangEqual, Literal::None);
@@ -85,10 +131,17 @@ impl Scanner {
             }
             '/' => {
                 if self.match_next_token('/') {
-                    // comment goes until the end of the line
+                    // comment goes until the end of the line, and documents what follows if it
+                    // starts with exactly three slashes
+                    let is_doc = self.peek() == '/' && self.peek_next() != '/';
                     while self.peek() != '\n' && !self.is_at_end() {
-                        self.current += 1;
+                        self.advance();
                     }
+                    if is_doc {
+                        self.add_doc_comment();
+                    }
+                } else if self.match_next_token('*') {
+                    self.skip_block_comment();
                 } else {
                     self.add_token(TokenType::Slash, Literal::None);
                 }
@@ -100,8 +153,8 @@ impl Scanner {
             '\t' => {}
             '\n' => self.line += 1,
 
-            // string literals
-            '"' => self.add_string(),
+            // string literals, which can be quoted either way so they can contain the other quote
+            '"' | '\'' => self.add_string(token),
 
             _ => {
                 if token.is_ascii_digit() {
@@ -109,22 +162,18 @@ impl Scanner {
                 } else if token.is_alphabetic() || token == '_' {
                     self.add_identifier();
                 } else {
-                    eprintln!("{}: Unexpected character.", self.line);
-                    self.has_error = true;
+                    self.error("Unexpected character.");
                 }
             }
         }
     }
 
     fn match_next_token(&mut self, expected: char) -> bool {
-        if self.is_at_end() {
-            return false;
-        };
-        if self.source.as_bytes()[self.current] as char != expected {
+        if self.is_at_end() || self.peek() != expected {
             return false;
         }
 
-        self.current += 1;
+        self.advance();
         true
     }
 
@@ -138,20 +187,56 @@ impl Scanner {
         })
     }
 
-    fn add_string(&mut self) {
-        while self.peek() != '"' && !self.is_at_end() {
+    fn add_doc_comment(&mut self) {
+        let text = self.source[self.start + 3..self.current].trim_end();
+        let text = text.strip_prefix(' ').unwrap_or(text).to_string();
+        self.add_token(TokenType::DocComment, Literal::String(text));
+    }
+
+    /// Skips a `/* ... */` comment, which may contain nested block comments.
+    fn skip_block_comment(&mut self) {
+        let mut depth = 1;
+
+        while depth > 0 {
+            if self.is_at_end() {
+                self.error("Unterminated block comment.");
+                return;
+            }
+
+            match (self.peek(), self.peek_next()) {
+                ('/', '*') => {
+                    depth += 1;
+                    self.current += 2;
+                }
+                ('*', '/') => {
+                    depth -= 1;
+                    self.current += 2;
+                }
+                ('\n', _) => {
+                    self.line += 1;
+                    self.advance();
+                }
+                _ => {
+                    self.advance();
+                }
+            }
+        }
+    }
+
+    fn add_string(&mut self, quote: char) {
+        while self.peek() != quote && !self.is_at_end() {
             if self.peek() == '\n' {
                 self.line += 1;
             }
-            self.current += 1;
+            self.advance();
         }
 
         if self.is_at_end() {
-            eprintln!("{}: Unterminated string.", self.line);
-            self.has_error = true;
+            self.error("Unterminated string.");
+            return;
         }
 
-        // the closing "
+        // the closing quote
         self.current += 1;
 
         // Trim surrounding quotes
@@ -164,78 +249,156 @@ impl Scanner {
     }
 
     fn add_number(&mut self) {
-        while self.peek().is_ascii_digit() {
-            self.current += 1;
-        }
+        let mut well_placed = self.consume_digits();
 
-        // look for fractional part of number
-        if self.peek() == '.' && self.peek_next().is_ascii_digit() {
+        // look for fractional part of number, where a separator right after the '.' is misplaced
+        let fractional =
+            self.peek() == '.' && (self.peek_next().is_ascii_digit() || self.peek_next() == '_');
+        if fractional {
             // consume the '.'
             self.current += 1;
+            well_placed &= self.consume_digits();
         }
 
-        while self.peek().is_ascii_digit() {
+        // an exponent, as in 1e6 or 2.5e-3, also makes the number a float
+        let exponent = matches!(self.peek(), 'e' | 'E');
+        if exponent {
+            // consume the 'e' and the exponent's sign
             self.current += 1;
+            if matches!(self.peek(), '+' | '-') {
+                self.current += 1;
+            }
+
+            if !self.peek().is_ascii_digit() {
+                self.error("Expect digits in exponent.");
+                return;
+            }
+            well_placed &= self.consume_digits();
+        }
+
+        if !well_placed {
+            self.error("Separator '_' must be between two digits.");
+            return;
         }
 
-        let value: f64 = self
+        let digits = self
             .source
             .get(self.start..self.current)
             .unwrap()
-            .parse()
-            .unwrap();
-        self.add_token(TokenType::Number, Literal::Number(value))
+            .replace('_', "");
+
+        // numbers without a fractional part or exponent are integers
+        if fractional || exponent {
+            let literal = Literal::Number(digits.parse().unwrap());
+            self.add_token(TokenType::Number, literal);
+            return;
+        }
+        match digits.parse() {
+            Ok(value) => self.add_token(TokenType::Number, Literal::Int(value)),
+            #[cfg(feature = "bigint")]
+            Err(_) => {
+                let value = BigInt::parse_radix(&digits, 10).expect("literal to be digits");
+                self.add_token(TokenType::Number, Literal::BigInt(Rc::new(value)));
+            }
+            #[cfg(not(feature = "bigint"))]
+            Err(_) => self.error("Integer literal is too large."),
+        }
     }
 
-    fn add_identifier(&mut self) {
-        let mut keywords: HashMap<String, TokenType> = HashMap::new();
-        keywords.insert(String::from("and"), TokenType::And);
-        keywords.insert(String::from("class"), TokenType::Class);
-        keywords.insert(String::from("else"), TokenType::Else);
-        keywords.insert(String::from("false"), TokenType::False);
-        keywords.insert(String::from("for"), TokenType::For);
-        keywords.insert(String::from("fun"), TokenType::Fun);
-        keywords.insert(String::from("if"), TokenType::If);
-        keywords.insert(String::from("nil"), TokenType::Nil);
-        keywords.insert(String::from("or"), TokenType::Or);
-        keywords.insert(String::from("print"), TokenType::Print);
-        keywords.insert(String::from("return"), TokenType::Return);
-        keywords.insert(String::from("super"), TokenType::Super);
-        keywords.insert(String::from("this"), TokenType::This);
-        keywords.insert(String::from("true"), TokenType::True);
-        keywords.insert(String::from("var"), TokenType::Var);
-        keywords.insert(String::from("while"), TokenType::While);
+    /// Consumes a run of digits along with any `_` separators, returning whether every separator
+    /// sits between two digits.
+    fn consume_digits(&mut self) -> bool {
+        let mut well_placed = true;
+        while self.peek().is_ascii_digit() || self.peek() == '_' {
+            if self.peek() == '_' {
+                well_placed &=
+                    self.previous().is_ascii_digit() && self.peek_next().is_ascii_digit();
+            }
+            self.current += 1;
+        }
 
+        well_placed
+    }
+
+    fn add_identifier(&mut self) {
         while self.peek().is_alphanumeric() || self.peek() == '_' {
-            self.current += 1;
+            self.advance();
         }
 
         let text = self.source.get(self.start..self.current).unwrap();
-        match keywords.get(text) {
-            Some(token_type) => self.add_token(token_type.clone(), Literal::None),
+        match keyword(text) {
+            Some(token_type) => self.add_token(token_type, Literal::None),
             None => self.add_token(TokenType::Identifier, Literal::None),
         }
     }
 
+    fn error(&mut self, message: &str) {
+        self.errors.push(ScanError {
+            line: self.line,
+            message: message.to_string(),
+        });
+    }
+
     fn is_at_end(&self) -> bool {
         self.current >= self.source.len()
     }
 
+    // `current` is a byte offset that always sits on a character boundary, so moving past a
+    // character means moving past all of its UTF-8 bytes
+    fn advance(&mut self) -> char {
+        let character = self.peek();
+        self.current += character.len_utf8();
+        character
+    }
+
     fn peek(&self) -> char {
-        if self.is_at_end() {
-            return '\0';
-        }
-        self.source.as_bytes()[self.current] as char
+        self.source[self.current..].chars().next().unwrap_or('\0')
     }
 
     fn peek_next(&self) -> char {
-        if (self.current + 1) >= self.source.len() {
-            return '\0';
-        }
-        self.source.as_bytes()[self.current + 1] as char
+        self.source[self.current..].chars().nth(1).unwrap_or('\0')
+    }
+
+    fn previous(&self) -> char {
+        self.source[..self.current]
+            .chars()
+            .next_back()
+            .unwrap_or('\0')
     }
 }
 
+/// The keyword `text` is, if it's one.
+pub(crate) fn keyword(text: &str) -> Option<TokenType> {
+    Some(match text {
+        "and" => TokenType::And,
+        "catch" => TokenType::Catch,
+        "class" => TokenType::Class,
+        "else" => TokenType::Else,
+        "false" => TokenType::False,
+        "finally" => TokenType::Finally,
+        "for" => TokenType::For,
+        "fun" => TokenType::Fun,
+        "if" => TokenType::If,
+        "import" => TokenType::Import,
+        "in" => TokenType::In,
+        "nil" => TokenType::Nil,
+        "or" => TokenType::Or,
+        "print" => TokenType::Print,
+        "return" => TokenType::Return,
+        "super" => TokenType::Super,
+        "this" => TokenType::This,
+        "throw" => TokenType::Throw,
+        "trait" => TokenType::Trait,
+        "true" => TokenType::True,
+        "try" => TokenType::Try,
+        "var" => TokenType::Var,
+        "while" => TokenType::While,
+        "with" => TokenType::With,
+        "yield" => TokenType::Yield,
+        _ => return None,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -243,7 +406,7 @@ mod tests {
     #[test]
     fn identifiers() {
         let mut scanner = Scanner::new(String::from("andy formless fo _ _123 _abc ab123 \n abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ1234567890_"));
-        let tokens = scanner.scan_tokens();
+        let tokens = scanner.scan_tokens().unwrap();
 
         let expected_tokens = [
             Token {
@@ -313,9 +476,9 @@ mod tests {
     #[test]
     fn keywords() {
         let mut scanner = Scanner::new(String::from(
-            "and class else false for fun if nil or return super this true var while",
+            "and class else false for fun if nil or return super this trait true var while with",
         ));
-        let tokens = scanner.scan_tokens();
+        let tokens = scanner.scan_tokens().unwrap();
 
         let expected_tokens = [
             Token {
@@ -390,6 +553,12 @@ mod tests {
                 literal: Literal::None,
                 line: 1,
             },
+            Token {
+                token_type: TokenType::Trait,
+                lexeme: String::from("trait"),
+                literal: Literal::None,
+                line: 1,
+            },
             Token {
                 token_type: TokenType::True,
                 lexeme: String::from("true"),
@@ -408,6 +577,12 @@ mod tests {
                 literal: Literal::None,
                 line: 1,
             },
+            Token {
+                token_type: TokenType::With,
+                lexeme: String::from("with"),
+                literal: Literal::None,
+                line: 1,
+            },
             Token {
                 token_type: TokenType::Eof,
                 lexeme: String::new(),
@@ -425,13 +600,13 @@ mod tests {
     #[test]
     fn numbers() {
         let mut scanner = Scanner::new(String::from("123\n123.456\n.456\n123."));
-        let tokens = scanner.scan_tokens();
+        let tokens = scanner.scan_tokens().unwrap();
 
         let expected_tokens = [
             Token {
                 token_type: TokenType::Number,
                 lexeme: String::from("123"),
-                literal: Literal::Number(123.0),
+                literal: Literal::Int(123),
                 line: 1,
             },
             Token {
@@ -449,13 +624,13 @@ mod tests {
             Token {
                 token_type: TokenType::Number,
                 lexeme: String::from("456"),
-                literal: Literal::Number(456.0),
+                literal: Literal::Int(456),
                 line: 3,
             },
             Token {
                 token_type: TokenType::Number,
                 lexeme: String::from("123"),
-                literal: Literal::Number(123.0),
+                literal: Literal::Int(123),
                 line: 4,
             },
             Token {
@@ -481,7 +656,7 @@ mod tests {
     #[test]
     fn punctuators() {
         let mut scanner = Scanner::new(String::from("(){};,+-*!===<=>=!=<>/."));
-        let tokens = scanner.scan_tokens();
+        let tokens = scanner.scan_tokens().unwrap();
 
         let expected_tokens = [
             Token {
@@ -609,7 +784,7 @@ mod tests {
     #[test]
     fn strings() {
         let mut scanner = Scanner::new(String::from("\"\" \n \"string\""));
-        let tokens = scanner.scan_tokens();
+        let tokens = scanner.scan_tokens().unwrap();
 
         let expected_tokens = [
             Token {
@@ -648,7 +823,7 @@ mod tests {
 
         end",
         ));
-        let tokens = scanner.scan_tokens();
+        let tokens = scanner.scan_tokens().unwrap();
 
         let expected_tokens = [
             Token {
@@ -688,4 +863,283 @@ mod tests {
             assert_eq!(*token, expected_tokens[i]);
         }
     }
+
+    #[test]
+    fn unterminated_string() {
+        let mut scanner = Scanner::new(String::from("var a = \"abc\n"));
+
+        let errors = scanner.scan_tokens().unwrap_err();
+        assert_eq!(
+            *errors,
+            [ScanError {
+                line: 2,
+                message: String::from("Unterminated string."),
+            }]
+        );
+    }
+
+    #[test]
+    fn lone_quote_at_eof() {
+        let mut scanner = Scanner::new(String::from("\""));
+
+        let errors = scanner.scan_tokens().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Unterminated string.");
+    }
+
+    #[test]
+    fn unexpected_characters() {
+        let mut scanner = Scanner::new(String::from("var a = 1;\n^ # 2"));
+
+        let errors = scanner.scan_tokens().unwrap_err();
+        assert_eq!(
+            *errors,
+            [
+                ScanError {
+                    line: 2,
+                    message: String::from("Unexpected character."),
+                },
+                ScanError {
+                    line: 2,
+                    message: String::from("Unexpected character."),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn increment_and_decrement_operators() {
+        let mut scanner = Scanner::new(String::from("+ ++ - -- +++"));
+        let tokens = scanner.scan_tokens().unwrap();
+
+        let token_types = tokens
+            .iter()
+            .map(|token| token.token_type.clone())
+            .collect::<Vec<TokenType>>();
+        assert_eq!(
+            token_types,
+            [
+                TokenType::Plus,
+                TokenType::PlusPlus,
+                TokenType::Minus,
+                TokenType::MinusMinus,
+                TokenType::PlusPlus,
+                TokenType::Plus,
+                TokenType::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn block_comments() {
+        let mut scanner = Scanner::new(String::from(
+            "/* one line */ a /* spans\ntwo lines */ b\n/* outer /* inner\n */ still comment */ c",
+        ));
+        let tokens = scanner.scan_tokens().unwrap();
+
+        let identifiers = tokens
+            .iter()
+            .filter(|token| token.token_type == TokenType::Identifier)
+            .map(|token| (token.lexeme.as_str(), token.line))
+            .collect::<Vec<(&str, usize)>>();
+        assert_eq!(identifiers, [("a", 1), ("b", 2), ("c", 4)]);
+    }
+
+    #[test]
+    fn unterminated_block_comment() {
+        let mut scanner = Scanner::new(String::from("a /* outer /* inner */\n"));
+
+        let errors = scanner.scan_tokens().unwrap_err();
+        assert_eq!(
+            *errors,
+            [ScanError {
+                line: 2,
+                message: String::from("Unterminated block comment."),
+            }]
+        );
+    }
+
+    #[test]
+    fn numeric_separators() {
+        let mut scanner = Scanner::new(String::from("1_000_000\n3_141.592_65"));
+        let tokens = scanner.scan_tokens().unwrap();
+
+        let literals = tokens
+            .iter()
+            .map(|token| token.literal.clone())
+            .collect::<Vec<Literal>>();
+        assert_eq!(
+            literals,
+            [
+                Literal::Int(1_000_000),
+                Literal::Number(3_141.592_65),
+                Literal::None
+            ]
+        );
+        assert_eq!(tokens[0].lexeme, "1_000_000");
+    }
+
+    #[test]
+    fn misplaced_numeric_separators() {
+        for source in ["1_", "1__000", "1_.5", "1._5", "2.5_"] {
+            let mut scanner = Scanner::new(String::from(source));
+
+            let errors = scanner.scan_tokens().unwrap_err();
+            assert_eq!(
+                *errors,
+                [ScanError {
+                    line: 1,
+                    message: String::from("Separator '_' must be between two digits."),
+                }],
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn single_quoted_strings() {
+        let mut scanner = Scanner::new(String::from("'it' 'say \"hi\"' \"don't\" ''"));
+        let tokens = scanner.scan_tokens().unwrap();
+
+        let literals = tokens
+            .iter()
+            .map(|token| token.literal.clone())
+            .collect::<Vec<Literal>>();
+        assert_eq!(
+            literals,
+            [
+                Literal::String(String::from("it")),
+                Literal::String(String::from("say \"hi\"")),
+                Literal::String(String::from("don't")),
+                Literal::String(String::new()),
+                Literal::None
+            ]
+        );
+
+        let mut scanner = Scanner::new(String::from("'unterminated\""));
+        let errors = scanner.scan_tokens().unwrap_err();
+        assert_eq!(errors[0].message, "Unterminated string.");
+    }
+
+    #[test]
+    fn unicode_source() {
+        let mut scanner = Scanner::new(String::from(
+            "// café ☕\nvar größe = \"日本語 🎉\"; /* ünïcödé */ print größe;",
+        ));
+        let tokens = scanner.scan_tokens().unwrap();
+
+        let lexemes = tokens
+            .iter()
+            .map(|token| token.lexeme.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            lexemes,
+            [
+                "var",
+                "größe",
+                "=",
+                "\"日本語 🎉\"",
+                ";",
+                "print",
+                "größe",
+                ";",
+                ""
+            ]
+        );
+        assert_eq!(
+            tokens[3].literal,
+            Literal::String(String::from("日本語 🎉"))
+        );
+        assert_eq!(tokens[0].line, 2);
+    }
+
+    #[test]
+    fn unexpected_unicode_character() {
+        let mut scanner = Scanner::new(String::from("var price = 5€;"));
+
+        let errors = scanner.scan_tokens().unwrap_err();
+        assert_eq!(
+            *errors,
+            [ScanError {
+                line: 1,
+                message: String::from("Unexpected character."),
+            }]
+        );
+    }
+
+    #[test]
+    fn scientific_notation() {
+        let mut scanner = Scanner::new(String::from("1e6 2.5e-3 4E+2 1_000e1_0"));
+        let tokens = scanner.scan_tokens().unwrap();
+
+        let literals = tokens
+            .iter()
+            .map(|token| token.literal.clone())
+            .collect::<Vec<Literal>>();
+        assert_eq!(
+            literals,
+            [
+                Literal::Number(1e6),
+                Literal::Number(2.5e-3),
+                Literal::Number(4e2),
+                Literal::Number(1e13),
+                Literal::None
+            ]
+        );
+    }
+
+    #[test]
+    fn exponents_without_digits() {
+        for source in ["1e", "2.5e-", "3E+x", "4e_1"] {
+            let mut scanner = Scanner::new(String::from(source));
+
+            let errors = scanner.scan_tokens().unwrap_err();
+            assert_eq!(
+                *errors,
+                [ScanError {
+                    line: 1,
+                    message: String::from("Expect digits in exponent."),
+                }],
+                "{source}"
+            );
+        }
+    }
+
+    #[cfg(not(feature = "bigint"))]
+    #[test]
+    fn integer_literals_out_of_range() {
+        let mut scanner = Scanner::new(String::from("9223372036854775807 9223372036854775808"));
+
+        let errors = scanner.scan_tokens().unwrap_err();
+        assert_eq!(
+            *errors,
+            [ScanError {
+                line: 1,
+                message: String::from("Integer literal is too large."),
+            }]
+        );
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn integer_literals_past_i64_become_big_integers() {
+        let mut scanner = Scanner::new(String::from(
+            "9223372036854775807 99_999_999_999_999_999_999",
+        ));
+        let tokens = scanner.scan_tokens().unwrap();
+
+        let literals = tokens
+            .iter()
+            .map(|token| token.literal.clone())
+            .collect::<Vec<Literal>>();
+        let big = BigInt::parse_radix("99999999999999999999", 10).unwrap();
+        assert_eq!(
+            literals,
+            [
+                Literal::Int(i64::MAX),
+                Literal::BigInt(Rc::new(big)),
+                Literal::None
+            ]
+        );
+    }
 }
@@ -0,0 +1,294 @@
+use std::{cmp::Ordering, collections::HashSet, fmt, rc::Rc};
+
+use crate::{
+    impls::decimal::Decimal,
+    numeric,
+    syntax::value::{write_list, Value},
+};
+
+/// The edge cases of equality, printing, `+` and number literals where reasonable languages
+/// disagree, settled once for the whole interpreter. The defaults follow IEEE 754: NaN equals
+/// nothing, itself included, negative zero equals zero but prints as `-0`, and `0.1` is a
+/// float. `+` only joins two strings.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Semantics {
+    /// Makes NaN equal to itself, so `x == x` holds for every value.
+    pub nan_equals_itself: bool,
+    /// Prints negative zero as `0`, hiding the sign the two otherwise show.
+    pub unsigned_zero: bool,
+    /// Lets `+` join a string with any value, shown as `print` shows it, so `"count: " + 3`
+    /// is `"count: 3"` rather than an error.
+    pub string_coercion: bool,
+    /// Makes number literals with a fractional part, like `0.1`, exact decimals rather than
+    /// floats, so sums of money add up. Literals too long for a decimal stay floats.
+    pub decimal_literals: bool,
+}
+
+impl Semantics {
+    /// What `==` means. Numbers compare by value whatever their kind, strings by their
+    /// characters, and everything else that lives on the heap by identity.
+    pub fn is_equal(&self, left: &Value, right: &Value) -> bool {
+        if let Some(ordering) = numeric::compare(left, right) {
+            return ordering == Some(Ordering::Equal)
+                || (self.nan_equals_itself && is_nan(left) && is_nan(right));
+        }
+
+        match (left, right) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::String(left), Value::String(right)) => left == right,
+            (Value::Boolean(left), Value::Boolean(right)) => left == right,
+            // objects compare by identity
+            (Value::ClassInstance(left), Value::ClassInstance(right)) => Rc::ptr_eq(left, right),
+            (Value::Class(left), Value::Class(right)) => Rc::ptr_eq(left, right),
+            (Value::Trait(left), Value::Trait(right)) => Rc::ptr_eq(left, right),
+            (Value::List(left), Value::List(right)) => Rc::ptr_eq(left, right),
+            (Value::Coroutine(left), Value::Coroutine(right)) => Rc::ptr_eq(left, right),
+            (Value::Function(left), Value::Function(right)) => left.is_same(right),
+            _ => false,
+        }
+    }
+
+    /// What `deepEquals` means: lists are equal if their items are, in order, and instances if
+    /// they're of the same class and have the same fields with equal values, all the way down.
+    /// Everything else compares as `==` does. Structures with cycles are equal if they have the
+    /// same shape, however their cycles line up.
+    pub fn is_deep_equal(&self, left: &Value, right: &Value) -> bool {
+        self.deep_equal(left, right, &mut HashSet::new())
+    }
+
+    // `assumed` holds the pairs of lists and instances compared so far, taken to be equal when
+    // they come up again so comparing cycles ends. A pair that turns out unequal makes every
+    // comparison it's part of unequal, so the assumption never decides the result.
+    fn deep_equal(
+        &self,
+        left: &Value,
+        right: &Value,
+        assumed: &mut HashSet<(*const (), *const ())>,
+    ) -> bool {
+        match (left, right) {
+            (Value::List(left), Value::List(right)) => {
+                let pair = (
+                    Rc::as_ptr(left) as *const (),
+                    Rc::as_ptr(right) as *const (),
+                );
+                if Rc::ptr_eq(left, right) || !assumed.insert(pair) {
+                    return true;
+                }
+
+                let (left, right) = (left.borrow(), right.borrow());
+                left.len() == right.len()
+                    && left
+                        .iter()
+                        .zip(right.iter())
+                        .all(|(left, right)| self.deep_equal(left, right, assumed))
+            }
+            (Value::ClassInstance(left), Value::ClassInstance(right)) => {
+                let pair = (
+                    Rc::as_ptr(left) as *const (),
+                    Rc::as_ptr(right) as *const (),
+                );
+                if Rc::ptr_eq(left, right) || !assumed.insert(pair) {
+                    return true;
+                }
+
+                let (left, right) = (left.borrow(), right.borrow());
+                Rc::ptr_eq(left.class(), right.class())
+                    && left.fields().len() == right.fields().len()
+                    && left.fields().iter().all(|(name, left)| {
+                        right
+                            .fields()
+                            .get(name)
+                            .is_some_and(|right| self.deep_equal(left, right, assumed))
+                    })
+            }
+            _ => self.is_equal(left, right),
+        }
+    }
+
+    /// How `print` shows a value, which is its `Display` apart from the sign of zero.
+    pub fn display(&self, value: &Value) -> String {
+        let mut displayed = String::new();
+        self.write_display(&mut displayed, value)
+            .expect("writing to a String to succeed");
+        displayed
+    }
+
+    /// Writes what [`Semantics::display`] shows without building a string of its own, so
+    /// printing can reuse one buffer.
+    pub fn write_display(&self, out: &mut impl fmt::Write, value: &Value) -> fmt::Result {
+        self.write_display_in(out, value, &mut vec![])
+    }
+
+    // `lists` holds the lists being written, to catch a list that contains itself
+    fn write_display_in<W: fmt::Write>(
+        &self,
+        out: &mut W,
+        value: &Value,
+        lists: &mut Vec<*const ()>,
+    ) -> fmt::Result {
+        match value {
+            Value::Number(value) if self.unsigned_zero && *value == 0.0 => out.write_char('0'),
+            Value::List(list) if self.unsigned_zero => {
+                write_list(out, list, lists, |out, item, lists| {
+                    self.write_display_in(out, item, lists)
+                })
+            }
+            _ => write!(out, "{value}"),
+        }
+    }
+
+    /// The value of a number literal with a fractional part.
+    pub fn number(&self, value: f64) -> Value {
+        if !self.decimal_literals {
+            return Value::Number(value);
+        }
+
+        // a float prints as the shortest digits that read back as it, which are the literal's
+        match Decimal::parse(&value.to_string()) {
+            Some(decimal) => Value::Decimal(decimal),
+            None => Value::Number(value),
+        }
+    }
+
+    /// The string `+` makes of a string and another value, if it's allowed to.
+    pub fn concatenate(&self, left: &Value, right: &Value) -> Option<Value> {
+        match (left, right) {
+            (Value::String(left), Value::String(right)) => Some(format!("{left}{right}")),
+            (Value::String(_), _) | (_, Value::String(_)) if self.string_coercion => {
+                Some(self.display(left) + &self.display(right))
+            }
+            _ => None,
+        }
+        .map(|string| Value::String(string.into()))
+    }
+}
+
+fn is_nan(value: &Value) -> bool {
+    matches!(value, Value::Number(value) if value.is_nan())
+}
+
+/// Whether a condition holds: only `nil` and `false` are falsey, so `0` and `""` count as true.
+pub fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Nil => false,
+        Value::Boolean(value) => *value,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::impls::list::new_list;
+
+    #[test]
+    fn nan_is_unequal_to_itself_unless_asked() {
+        let nan = Value::Number(f64::NAN);
+        assert!(!Semantics::default().is_equal(&nan, &nan));
+
+        let semantics = Semantics {
+            nan_equals_itself: true,
+            ..Default::default()
+        };
+        assert!(semantics.is_equal(&nan, &nan));
+        assert!(!semantics.is_equal(&nan, &Value::Int(0)));
+    }
+
+    #[test]
+    fn numbers_compare_across_representations() {
+        let semantics = Semantics::default();
+        assert!(semantics.is_equal(&Value::Int(1), &Value::Number(1.0)));
+        assert!(semantics.is_equal(&Value::Number(-0.0), &Value::Int(0)));
+        assert!(!semantics.is_equal(&Value::Int(1), &Value::String("1".into())));
+
+        let list = Value::List(new_list(vec![]));
+        assert!(semantics.is_equal(&list, &list.clone()));
+        assert!(!semantics.is_equal(&list, &Value::List(new_list(vec![]))));
+    }
+
+    #[test]
+    fn deep_equality_compares_contents_and_cycles() {
+        let semantics = Semantics::default();
+        let list = |items| Value::List(new_list(items));
+        let nested = || list(vec![Value::Int(1), list(vec![Value::String("a".into())])]);
+        assert!(semantics.is_deep_equal(&nested(), &nested()));
+        assert!(!semantics.is_equal(&nested(), &nested()));
+        assert!(
+            semantics.is_deep_equal(&list(vec![Value::Int(1)]), &list(vec![Value::Number(1.0)]))
+        );
+        assert!(!semantics.is_deep_equal(&nested(), &list(vec![Value::Int(1)])));
+        assert!(!semantics.is_deep_equal(
+            &list(vec![Value::Number(f64::NAN)]),
+            &list(vec![Value::Number(f64::NAN)])
+        ));
+
+        // two lists that each contain themselves
+        let cycles = [new_list(vec![Value::Int(1)]), new_list(vec![Value::Int(1)])];
+        for cycle in &cycles {
+            cycle.borrow_mut().push(Value::List(cycle.clone()));
+        }
+        let [first, second] = cycles.clone().map(Value::List);
+        assert!(semantics.is_deep_equal(&first, &second));
+        cycles[1].borrow_mut()[0] = Value::Int(2);
+        assert!(!semantics.is_deep_equal(&first, &second));
+        for cycle in cycles {
+            cycle.borrow_mut().clear();
+        }
+    }
+
+    #[test]
+    fn negative_zero_prints_with_its_sign_unless_asked() {
+        let zeros = Value::List(new_list(vec![Value::Number(-0.0), Value::Number(0.0)]));
+        assert_eq!(Semantics::default().display(&zeros), "[-0, 0]");
+
+        let semantics = Semantics {
+            unsigned_zero: true,
+            ..Default::default()
+        };
+        assert_eq!(semantics.display(&zeros), "[0, 0]");
+        assert_eq!(semantics.display(&Value::Number(-1.5)), "-1.5");
+    }
+
+    #[test]
+    fn lists_inside_themselves_print_as_ellipses() {
+        let list = new_list(vec![Value::Number(-0.0)]);
+        list.borrow_mut().push(Value::List(list.clone()));
+        let list = Value::List(list);
+        assert_eq!(Semantics::default().display(&list), "[-0, [...]]");
+
+        let semantics = Semantics {
+            unsigned_zero: true,
+            ..Default::default()
+        };
+        assert_eq!(semantics.display(&list), "[0, [...]]");
+    }
+
+    #[test]
+    fn plus_coerces_to_string_only_when_asked() {
+        let (greeting, three) = (Value::String("count: ".into()), Value::Int(3));
+        assert!(Semantics::default()
+            .concatenate(&greeting, &three)
+            .is_none());
+        assert!(Semantics::default().concatenate(&three, &three).is_none());
+
+        let semantics = Semantics {
+            string_coercion: true,
+            ..Default::default()
+        };
+        let joined = |left, right| semantics.concatenate(left, right).map(|s| s.to_string());
+        assert_eq!(joined(&greeting, &three), Some(String::from("count: 3")));
+        assert_eq!(
+            joined(&Value::Nil, &greeting),
+            Some(String::from("nilcount: "))
+        );
+        assert_eq!(joined(&three, &three), None);
+    }
+
+    #[test]
+    fn only_nil_and_false_are_falsey() {
+        assert!(!is_truthy(&Value::Nil));
+        assert!(!is_truthy(&Value::Boolean(false)));
+        assert!(is_truthy(&Value::Int(0)));
+        assert!(is_truthy(&Value::String("".into())));
+    }
+}
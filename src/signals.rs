@@ -0,0 +1,38 @@
+use std::sync::OnceLock;
+
+use rlox::InterruptHandle;
+
+// the handle Ctrl-C interrupts, which the signal handler can't be given any other way
+static INTERRUPTS: OnceLock<InterruptHandle> = OnceLock::new();
+
+/// Makes Ctrl-C interrupt whatever the interpreter sharing `handle` is running, rather than
+/// killing the process. Only does anything on Unix.
+pub fn forward_interrupts(handle: InterruptHandle) {
+    if INTERRUPTS.set(handle).is_ok() {
+        install();
+    }
+}
+
+#[cfg(unix)]
+fn install() {
+    const SIGINT: i32 = 2;
+
+    extern "C" {
+        fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+    }
+
+    // only sets an atomic flag, which is safe to do from a signal handler
+    extern "C" fn on_interrupt(_: i32) {
+        if let Some(handle) = INTERRUPTS.get() {
+            handle.interrupt();
+        }
+    }
+
+    // SAFETY: the handler only reads an initialized OnceLock and stores to an atomic
+    unsafe {
+        signal(SIGINT, on_interrupt);
+    }
+}
+
+#[cfg(not(unix))]
+fn install() {}
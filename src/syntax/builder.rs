@@ -0,0 +1,221 @@
+use std::rc::Rc;
+
+use crate::scanner::Scanner;
+
+use super::{
+    expr::{next_uid, Expr},
+    stmt::Stmt,
+    token::{Literal, Token, TokenType},
+};
+
+/// Builds syntax trees from Rust without going through source text. Every node gets a fresh id,
+/// and every token the line set with [`AstBuilder::at_line`], so errors in generated code point
+/// somewhere sensible. The statements can be spliced into parsed ones and handed to
+/// [`Interpreter::load_statements`](crate::Interpreter::load_statements), which resolves them.
+pub struct AstBuilder {
+    line: usize,
+}
+
+impl Default for AstBuilder {
+    fn default() -> Self {
+        AstBuilder { line: 1 }
+    }
+}
+
+impl AstBuilder {
+    pub fn new() -> AstBuilder {
+        AstBuilder::default()
+    }
+
+    /// Attributes the nodes built from now on to `line`.
+    pub fn at_line(&mut self, line: usize) -> &mut AstBuilder {
+        self.line = line;
+        self
+    }
+
+    pub fn int(&self, value: i64) -> Expr {
+        self.literal(Literal::Int(value))
+    }
+
+    pub fn number(&self, value: f64) -> Expr {
+        self.literal(Literal::Number(value))
+    }
+
+    pub fn string(&self, value: &str) -> Expr {
+        self.literal(Literal::String(value.to_string()))
+    }
+
+    pub fn boolean(&self, value: bool) -> Expr {
+        self.literal(Literal::Bool(value))
+    }
+
+    pub fn nil(&self) -> Expr {
+        self.literal(Literal::None)
+    }
+
+    pub fn variable(&self, name: &str) -> Expr {
+        Expr::Variable {
+            uid: next_uid(),
+            name: self.identifier(name),
+        }
+    }
+
+    pub fn assign(&self, name: &str, value: Expr) -> Expr {
+        Expr::Assign {
+            uid: next_uid(),
+            name: self.identifier(name),
+            value: Box::new(value),
+        }
+    }
+
+    /// An arithmetic, comparison or equality operation, with `operator` written as in Lox.
+    pub fn binary(&self, left: Expr, operator: &str, right: Expr) -> Expr {
+        Expr::Binary {
+            uid: next_uid(),
+            left: Box::new(left),
+            operator: self.operator(operator),
+            right: Box::new(right),
+        }
+    }
+
+    /// An `and` or `or` expression.
+    pub fn logical(&self, left: Expr, operator: &str, right: Expr) -> Expr {
+        Expr::Logical {
+            uid: next_uid(),
+            left: Box::new(left),
+            operator: self.operator(operator),
+            right: Box::new(right),
+        }
+    }
+
+    /// A `!` or `-` expression.
+    pub fn unary(&self, operator: &str, right: Expr) -> Expr {
+        Expr::Unary {
+            uid: next_uid(),
+            operator: self.operator(operator),
+            right: Box::new(right),
+        }
+    }
+
+    pub fn grouping(&self, expression: Expr) -> Expr {
+        Expr::Grouping {
+            uid: next_uid(),
+            expression: Box::new(expression),
+        }
+    }
+
+    pub fn call(&self, callee: Expr, args: Vec<Expr>) -> Expr {
+        Expr::Call {
+            uid: next_uid(),
+            callee: Box::new(callee),
+            paren: self.token(TokenType::RightParen, ")"),
+            args,
+        }
+    }
+
+    pub fn get(&self, object: Expr, name: &str) -> Expr {
+        Expr::Get {
+            uid: next_uid(),
+            object: Box::new(object),
+            name: self.identifier(name),
+        }
+    }
+
+    pub fn set(&self, object: Expr, name: &str, value: Expr) -> Expr {
+        Expr::Set {
+            uid: next_uid(),
+            object: Box::new(object),
+            name: self.identifier(name),
+            value: Box::new(value),
+        }
+    }
+
+    pub fn this(&self) -> Expr {
+        Expr::This {
+            uid: next_uid(),
+            keyword: self.token(TokenType::This, "this"),
+        }
+    }
+
+    pub fn expression(&self, expression: Expr) -> Stmt {
+        Stmt::Expression(expression)
+    }
+
+    pub fn print(&self, values: Vec<Expr>) -> Stmt {
+        Stmt::Print(values)
+    }
+
+    pub fn var(&self, name: &str, initializer: Option<Expr>) -> Stmt {
+        Stmt::Var {
+            name: self.identifier(name),
+            initializer,
+        }
+    }
+
+    pub fn block(&self, statements: Vec<Stmt>) -> Stmt {
+        Stmt::Block(Rc::new(statements))
+    }
+
+    pub fn if_else(&self, condition: Expr, then_branch: Stmt, else_branch: Option<Stmt>) -> Stmt {
+        Stmt::If {
+            condition,
+            then_branch: Rc::new(then_branch),
+            else_branch: else_branch.map(Rc::new),
+        }
+    }
+
+    pub fn while_loop(&self, condition: Expr, body: Stmt) -> Stmt {
+        Stmt::While {
+            condition: Box::new(condition),
+            body: Rc::new(body),
+        }
+    }
+
+    pub fn function(&self, name: &str, params: &[&str], body: Vec<Stmt>) -> Stmt {
+        Stmt::Function {
+            uid: next_uid(),
+            name: self.identifier(name),
+            doc: None,
+            params: params.iter().map(|param| self.identifier(param)).collect(),
+            variadic: false,
+            body: Rc::new(body),
+            decorators: vec![],
+        }
+    }
+
+    pub fn return_value(&self, value: Option<Expr>) -> Stmt {
+        Stmt::Return {
+            name: self.token(TokenType::Return, "return"),
+            value: value.map(Box::new),
+        }
+    }
+
+    fn literal(&self, value: Literal) -> Expr {
+        Expr::Literal {
+            uid: next_uid(),
+            value,
+        }
+    }
+
+    fn identifier(&self, name: &str) -> Token {
+        self.token(TokenType::Identifier, name)
+    }
+
+    // scans the operator so it gets the same token type as it would in source code
+    fn operator(&self, operator: &str) -> Token {
+        let mut scanner = Scanner::new(operator.to_string());
+        match scanner.scan_tokens().map(|tokens| tokens.as_slice()) {
+            Ok([token, _eof]) => self.token(token.token_type.clone(), operator),
+            _ => panic!("'{operator}' is not a single operator!"),
+        }
+    }
+
+    fn token(&self, token_type: TokenType, lexeme: &str) -> Token {
+        Token {
+            token_type,
+            lexeme: lexeme.to_string(),
+            literal: Literal::None,
+            line: self.line,
+        }
+    }
+}
@@ -1,7 +1,17 @@
-use std::hash::Hash;
+use std::{
+    hash::Hash,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 use super::token::{Literal, Token};
 
+static NEXT_UID: AtomicUsize = AtomicUsize::new(0);
+
+/// Every expression gets a unique id so the resolver can key its results by node identity.
+pub fn next_uid() -> usize {
+    NEXT_UID.fetch_add(1, Ordering::Relaxed)
+}
+
 pub trait Visitor<T> {
     fn visit_expr(&mut self, expression: &Expr) -> T;
 }
@@ -9,69 +19,82 @@ pub trait Visitor<T> {
 #[derive(Clone, Debug)]
 pub enum Expr {
     Binary {
-        uid: u8,
+        uid: usize,
         left: Box<Expr>,
         operator: Token,
         right: Box<Expr>,
     },
     Grouping {
-        uid: u8,
+        uid: usize,
         expression: Box<Expr>,
     },
     Literal {
-        uid: u8,
+        uid: usize,
         value: Literal,
     },
     Unary {
-        uid: u8,
+        uid: usize,
         operator: Token,
         right: Box<Expr>,
     },
     Variable {
-        uid: u8,
+        uid: usize,
         name: Token,
     },
     Assign {
-        uid: u8,
+        uid: usize,
         name: Token,
         value: Box<Expr>,
     },
     Logical {
-        uid: u8,
+        uid: usize,
         left: Box<Expr>,
         operator: Token,
         right: Box<Expr>,
     },
     Call {
-        uid: u8,
+        uid: usize,
         callee: Box<Expr>,
         paren: Token,
         args: Vec<Expr>,
     },
     Get {
-        uid: u8,
+        uid: usize,
         object: Box<Expr>,
         name: Token,
     },
     Set {
-        uid: u8,
+        uid: usize,
         object: Box<Expr>,
         name: Token,
         value: Box<Expr>,
     },
     This {
-        uid: u8,
+        uid: usize,
         keyword: Token,
     },
     Super {
-        uid: u8,
+        uid: usize,
         keyword: Token,
         method: Token,
     },
+    // `++` or `--` on a variable or field, written before (prefix) or after the target
+    Update {
+        uid: usize,
+        target: Box<Expr>,
+        operator: Token,
+        prefix: bool,
+    },
+    // hands `value` to whoever resumed the running coroutine, evaluating to what it's resumed with
+    Yield {
+        uid: usize,
+        keyword: Token,
+        value: Option<Box<Expr>>,
+    },
 }
 
 impl Expr {
-    fn get_uid(&self) -> u8 {
+    pub fn uid(&self) -> usize {
         match self {
             Expr::Binary { uid, .. } => *uid,
             Expr::Grouping { uid, .. } => *uid,
@@ -85,13 +108,15 @@ impl Expr {
             Expr::Set { uid, .. } => *uid,
             Expr::This { uid, .. } => *uid,
             Expr::Super { uid, .. } => *uid,
+            Expr::Update { uid, .. } => *uid,
+            Expr::Yield { uid, .. } => *uid,
         }
     }
 }
 
 impl PartialEq for Expr {
     fn eq(&self, other: &Self) -> bool {
-        self.get_uid() == other.get_uid()
+        self.uid() == other.uid()
     }
 }
 
@@ -100,6 +125,6 @@ impl Eq for Expr {}
 impl Hash for Expr {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         // core::mem::discriminant(self).hash(state);
-        self.get_uid().hash(state);
+        self.uid().hash(state);
     }
 }
@@ -1,3 +1,4 @@
+pub mod builder;
 pub mod expr;
 pub mod stmt;
 pub mod token;
@@ -1,3 +1,5 @@
+use std::rc::Rc;
+
 use super::{expr::Expr, token::Token};
 
 pub trait Visitor<T> {
@@ -7,33 +9,92 @@ pub trait Visitor<T> {
 #[derive(Clone, Debug)]
 pub enum Stmt {
     Expression(Expr),
-    Print(Expr),
-    Block(Vec<Stmt>),
+    Print(Vec<Expr>),
+    // nested statements are shared so paused execution can hold on to them cheaply
+    Block(Rc<Vec<Stmt>>),
     Var {
         name: Token,
         initializer: Option<Expr>,
     },
     If {
         condition: Expr,
-        then_branch: Box<Stmt>,
-        else_branch: Option<Box<Stmt>>,
+        then_branch: Rc<Stmt>,
+        else_branch: Option<Rc<Stmt>>,
     },
     While {
         condition: Box<Expr>,
-        body: Box<Stmt>,
+        body: Rc<Stmt>,
+    },
+    // runs the body once for each item of a list or character of a string, bound to `name`
+    ForIn {
+        name: Token,
+        iterable: Expr,
+        body: Rc<Stmt>,
     },
     Function {
+        // keys the variables the resolver found this function captures
+        uid: usize,
         name: Token,
+        // the `///` comments written right before the declaration
+        doc: Option<String>,
         params: Vec<Token>,
-        body: Vec<Stmt>,
+        // the last parameter collects any extra arguments into a list
+        variadic: bool,
+        body: Rc<Vec<Stmt>>,
+        // the `@` expressions written before the declaration, outermost first
+        decorators: Vec<Expr>,
     },
     Return {
         name: Token,
         value: Option<Box<Expr>>,
     },
+    Throw {
+        keyword: Token,
+        value: Expr,
+    },
+    // runs a module's top-level code in the global scope, the first time the module is imported
+    Import {
+        keyword: Token,
+        path: Token,
+    },
     Class {
         name: Token,
+        doc: Option<String>,
         super_class: Option<Box<Expr>>,
+        traits: Vec<Expr>,
+        methods: Vec<Stmt>,
+        // names of the methods declared without a body
+        abstract_methods: Vec<Token>,
+        setters: Vec<Stmt>,
+    },
+    Trait {
+        name: Token,
         methods: Vec<Stmt>,
     },
+    Try {
+        body: Vec<Stmt>,
+        catch: Option<(Token, Vec<Stmt>)>,
+        finally: Option<Vec<Stmt>>,
+    },
+}
+
+impl Stmt {
+    /// The `yield` a coroutine can suspend at in this statement, which must be the whole
+    /// expression statement, a variable's initializer or the value of an assignment.
+    pub fn yield_point(&self) -> Option<&Expr> {
+        let expr = match self {
+            Stmt::Expression(Expr::Assign { value, .. }) => value,
+            Stmt::Expression(expr) => expr,
+            Stmt::Var {
+                initializer: Some(initializer),
+                ..
+            } => initializer,
+            _ => return None,
+        };
+
+        match expr {
+            Expr::Yield { .. } => Some(expr),
+            _ => None,
+        }
+    }
 }
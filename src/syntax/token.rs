@@ -1,8 +1,17 @@
 use std::fmt;
+#[cfg(feature = "bigint")]
+use std::rc::Rc;
+
+#[cfg(feature = "bigint")]
+use crate::impls::bigint::BigInt;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Literal {
     String(String),
+    Int(i64),
+    /// An integer literal outside the i64 range, with the `bigint` feature.
+    #[cfg(feature = "bigint")]
+    BigInt(Rc<BigInt>),
     Number(f64),
     Bool(bool),
     None,
@@ -17,15 +26,19 @@ pub enum TokenType {
     RightBrace,
     Comma,
     Dot,
+    Ellipsis,
     Minus,
     Plus,
     Semicolon,
     Slash,
     Star,
+    At,
 
     // One or two chracter tokens
     Bang,
     BangEqual,
+    MinusMinus,
+    PlusPlus,
     Equal,
     EqualEqual,
     Greater,
@@ -37,24 +50,35 @@ pub enum TokenType {
     Identifier,
     String,
     Number,
+    // a `///` comment documenting the declaration that follows it
+    DocComment,
 
     // Keywords
     And,
+    Catch,
     Class,
     Else,
     False,
+    Finally,
     Fun,
     For,
     Nil,
     If,
+    Import,
+    In,
     Print,
     Or,
     Return,
     Super,
     This,
+    Throw,
+    Trait,
     True,
+    Try,
     Var,
     While,
+    With,
+    Yield,
 
     Eof,
 }
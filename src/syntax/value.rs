@@ -1,41 +1,223 @@
-use std::fmt::Display;
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    rc::Rc,
+};
 
+#[cfg(feature = "bigint")]
+use crate::impls::bigint::BigInt;
 use crate::impls::{
-    class::{Class, ClassInstanceRef},
+    class::{ClassInstance, ClassInstanceRef, ClassRef},
+    coroutine::CoroutineRef,
+    decimal::Decimal,
     function::{Function, NativeFunction},
+    list::{new_list, ListRef},
+    string::LoxString,
+    traits::TraitRef,
 };
 
 #[derive(Clone, Debug)]
 pub enum Value {
     Boolean(bool),
+    Int(i64),
+    /// An integer outside the i64 range, with the `bigint` feature.
+    #[cfg(feature = "bigint")]
+    BigInt(Rc<BigInt>),
     Number(f64),
-    String(String),
+    /// An exact decimal, made by `decimal()` or by literals with the `decimal_literals` option.
+    Decimal(Decimal),
+    String(LoxString),
     Function(Function),
     NativeFunction(NativeFunction),
-    Class(Class),
+    Class(ClassRef),
     ClassInstance(ClassInstanceRef),
+    Trait(TraitRef),
+    List(ListRef),
+    Coroutine(CoroutineRef),
     Nil,
 }
 
-impl Display for Value {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let s = match self {
-            Value::Boolean(value) => value.to_string(),
-            Value::Number(value) => {
-                let mut res = value.to_string();
-                if res.ends_with(".0") {
-                    res = res.strip_suffix(".0").unwrap().to_string();
+impl Value {
+    /// The value of any kind of number as a float, or None for anything else.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Int(value) => Some(*value as f64),
+            #[cfg(feature = "bigint")]
+            Value::BigInt(value) => Some(value.to_f64()),
+            Value::Number(value) => Some(*value),
+            Value::Decimal(value) => Some(value.to_f64()),
+            _ => None,
+        }
+    }
+
+    /// The value of an integer, or of a float or decimal with no fractional part, as an integer.
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Value::Int(value) => Some(*value),
+            Value::Number(value) if value.fract() == 0.0 => Some(*value as i64),
+            Value::Decimal(value) => value.to_i64(),
+            _ => None,
+        }
+    }
+
+    /// A copy of the value that shares no mutable state with it. Lists and instances are copied
+    /// all the way down, and a list or instance reachable twice is copied once, so the copy has
+    /// the same shape as the original, cycles included. Strings, functions, natives, classes and
+    /// traits can't change, so the copy shares them. Coroutines can't be copied.
+    pub fn deep_clone(&self) -> Result<Value, String> {
+        self.copy(&mut HashMap::new())
+    }
+
+    // `copies` maps each list and instance copied so far to its copy
+    fn copy(&self, copies: &mut HashMap<*const (), Value>) -> Result<Value, String> {
+        match self {
+            Value::List(list) => {
+                let address = Rc::as_ptr(list) as *const ();
+                if let Some(copy) = copies.get(&address) {
+                    return Ok(copy.clone());
+                }
+
+                // registered before its items are copied, so items can refer back to it
+                let copy = new_list(vec![]);
+                copies.insert(address, Value::List(copy.clone()));
+                let items = list
+                    .borrow()
+                    .iter()
+                    .map(|item| item.copy(copies))
+                    .collect::<Result<Vec<Value>, String>>()?;
+                *copy.borrow_mut() = items;
+                Ok(Value::List(copy))
+            }
+            Value::ClassInstance(instance) => {
+                let address = Rc::as_ptr(instance) as *const ();
+                if let Some(copy) = copies.get(&address) {
+                    return Ok(copy.clone());
                 }
-                res
+
+                let copy = ClassInstance::new(instance.borrow().class().clone());
+                copies.insert(address, Value::ClassInstance(copy.clone()));
+                let fields = instance.borrow().copy_fields(|value| value.copy(copies))?;
+                copy.borrow_mut().set_fields(fields);
+                Ok(Value::ClassInstance(copy))
             }
-            Value::String(value) => value.clone(),
-            Value::Nil => String::from("nil"),
-            Value::Function(_) => String::from("<fn>"),
-            Value::NativeFunction(_) => String::from("<native fn>"),
-            Value::Class(class) => class.to_string(),
-            Value::ClassInstance(instance) => instance.borrow().to_string(),
+            Value::Coroutine(_) => Err(String::from("Can't copy a coroutine.")),
+            _ => Ok(self.clone()),
+        }
+    }
+
+    /// The documentation of a function, class or native, if it has any.
+    pub fn doc(&self) -> Option<&str> {
+        match self {
+            Value::Function(function) => function.doc(),
+            Value::NativeFunction(native) => native.doc,
+            Value::Class(class) => class.doc(),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Value {
+    // writes straight to the formatter, so printing a number or a list of them doesn't build
+    // a string for each value
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, &mut vec![])
+    }
+}
+
+impl Value {
+    // `lists` holds the lists being written, to catch a list that contains itself
+    fn write(&self, f: &mut fmt::Formatter<'_>, lists: &mut Vec<*const ()>) -> fmt::Result {
+        match self {
+            Value::Boolean(value) => write!(f, "{value}"),
+            Value::Int(value) => write!(f, "{value}"),
+            #[cfg(feature = "bigint")]
+            Value::BigInt(value) => write!(f, "{value}"),
+            // floats with no fractional part already display without a ".0"
+            Value::Number(value) => write!(f, "{value}"),
+            Value::Decimal(value) => write!(f, "{value}"),
+            Value::String(value) => write!(f, "{value}"),
+            Value::Nil => f.write_str("nil"),
+            Value::Function(_) => f.write_str("<fn>"),
+            Value::NativeFunction(_) => f.write_str("<native fn>"),
+            Value::Class(class) => write!(f, "{class}"),
+            Value::ClassInstance(instance) => write!(f, "{}", instance.borrow()),
+            Value::Trait(lox_trait) => write!(f, "{lox_trait}"),
+            Value::Coroutine(_) => f.write_str("<coroutine>"),
+            Value::List(list) => write_list(f, list, lists, |f, item, lists| item.write(f, lists)),
+        }
+    }
+}
+
+/// Writes a list as `[a, b]`, each item as `write_item` writes it, with `[...]` standing for
+/// a list inside itself. `lists` holds the lists already being written.
+pub(crate) fn write_list<W: fmt::Write>(
+    out: &mut W,
+    list: &ListRef,
+    lists: &mut Vec<*const ()>,
+    mut write_item: impl FnMut(&mut W, &Value, &mut Vec<*const ()>) -> fmt::Result,
+) -> fmt::Result {
+    let address = Rc::as_ptr(list) as *const ();
+    if lists.contains(&address) {
+        return out.write_str("[...]");
+    }
+
+    lists.push(address);
+    out.write_char('[')?;
+    for (index, item) in list.borrow().iter().enumerate() {
+        if index > 0 {
+            out.write_str(", ")?;
+        }
+        write_item(out, item, lists)?;
+    }
+    lists.pop();
+    out.write_char(']')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deep_clones_keep_the_shape_of_the_original() {
+        let shared = new_list(vec![Value::Int(1)]);
+        let list = new_list(vec![
+            Value::List(shared.clone()),
+            Value::List(shared.clone()),
+        ]);
+        list.borrow_mut().push(Value::List(list.clone()));
+
+        let Ok(Value::List(copy)) = Value::List(list.clone()).deep_clone() else {
+            panic!("Expected a list!");
         };
+        let items = copy.borrow().clone();
+        let [Value::List(first), Value::List(second), Value::List(itself)] = items.as_slice()
+        else {
+            panic!("Expected three lists!");
+        };
+        assert!(!Rc::ptr_eq(first, &shared));
+        assert!(Rc::ptr_eq(first, second));
+        assert!(Rc::ptr_eq(itself, &copy));
+
+        first.borrow_mut().push(Value::Int(2));
+        assert_eq!(Value::List(shared).to_string(), "[1]");
+
+        list.borrow_mut().clear();
+        copy.borrow_mut().clear();
+    }
 
-        write!(f, "{}", s)
+    #[test]
+    fn numbers_display_without_a_trailing_zero() {
+        let values = new_list(vec![
+            Value::Number(3.0),
+            Value::Number(-0.0),
+            Value::Number(1e21),
+            Value::Number(0.1),
+            Value::Int(-7),
+            Value::Nil,
+        ]);
+        assert_eq!(
+            Value::List(values).to_string(),
+            "[3, -0, 1000000000000000000000, 0.1, -7, nil]"
+        );
     }
 }
@@ -0,0 +1,180 @@
+use std::time::{Duration, Instant};
+
+use crate::syntax::{token::Token, value::Value};
+
+/// The time the event loop schedules by: the real time since the interpreter was made, or in
+/// deterministic mode a fake time that only moves when the event loop skips ahead to a timer.
+pub(crate) enum Clock {
+    Real(Instant),
+    Fake(Duration),
+}
+
+impl Clock {
+    pub(crate) fn new(deterministic: bool) -> Clock {
+        match deterministic {
+            true => Clock::Fake(Duration::ZERO),
+            false => Clock::Real(Instant::now()),
+        }
+    }
+
+    pub(crate) fn now(&self) -> Duration {
+        match self {
+            Clock::Real(start) => start.elapsed(),
+            Clock::Fake(now) => *now,
+        }
+    }
+}
+
+/// A callback waiting in the event loop to be called.
+pub(crate) struct Timer {
+    pub(crate) id: i64,
+    // when it's due, as a time on the interpreter's `Clock`
+    pub(crate) due: Duration,
+    // how long to wait before calling it again, for `setInterval`
+    pub(crate) interval: Option<Duration>,
+    pub(crate) callback: Value,
+    // the call that scheduled it, where errors in the callback are reported
+    pub(crate) paren: Token,
+}
+
+/// The callbacks scheduled with `setTimeout` and `setInterval`. They're called in the order
+/// they come due, with ones due at the same time called in the order they were scheduled.
+#[derive(Default)]
+pub(crate) struct Timers {
+    pending: Vec<Timer>,
+    next_id: i64,
+}
+
+impl Timers {
+    /// Schedules `callback` to be called `delay` after `now`, and every `interval` after that if
+    /// there is one, returning the id that cancels it.
+    pub(crate) fn schedule(
+        &mut self,
+        callback: Value,
+        now: Duration,
+        delay: Duration,
+        interval: Option<Duration>,
+        paren: &Token,
+    ) -> i64 {
+        self.next_id += 1;
+        self.add(Timer {
+            id: self.next_id,
+            due: now + delay,
+            interval,
+            callback,
+            paren: paren.clone(),
+        });
+        self.next_id
+    }
+
+    /// Puts an interval's timer back, due one interval after it last was.
+    pub(crate) fn repeat(&mut self, timer: &Timer) {
+        if let Some(interval) = timer.interval {
+            self.add(Timer {
+                id: timer.id,
+                due: timer.due + interval,
+                interval: timer.interval,
+                callback: timer.callback.clone(),
+                paren: timer.paren.clone(),
+            });
+        }
+    }
+
+    /// Cancels the timer `id`, returning whether it was still pending.
+    pub(crate) fn cancel(&mut self, id: i64) -> bool {
+        let before = self.pending.len();
+        self.pending.retain(|timer| timer.id != id);
+        self.pending.len() < before
+    }
+
+    /// Takes the timer that comes due first.
+    pub(crate) fn next(&mut self) -> Option<Timer> {
+        match self.pending.is_empty() {
+            true => None,
+            false => Some(self.pending.remove(0)),
+        }
+    }
+
+    // keeps `pending` ordered by when each timer is due, after any due at the same time
+    fn add(&mut self, timer: Timer) {
+        let index = self
+            .pending
+            .partition_point(|pending| pending.due <= timer.due);
+        self.pending.insert(index, timer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::token::{Literal, TokenType};
+
+    fn paren() -> Token {
+        Token {
+            token_type: TokenType::RightParen,
+            lexeme: String::from(")"),
+            literal: Literal::None,
+            line: 1,
+        }
+    }
+
+    fn order(timers: &mut Timers) -> Vec<i64> {
+        std::iter::from_fn(|| timers.next())
+            .map(|timer| timer.id)
+            .collect()
+    }
+
+    #[test]
+    fn timers_come_due_in_order() {
+        let mut timers = Timers::default();
+        let later = timers.schedule(
+            Value::Nil,
+            Duration::ZERO,
+            Duration::from_secs(2),
+            None,
+            &paren(),
+        );
+        let sooner = timers.schedule(
+            Value::Nil,
+            Duration::ZERO,
+            Duration::from_secs(1),
+            None,
+            &paren(),
+        );
+        let cancelled = timers.schedule(Value::Nil, Duration::ZERO, Duration::ZERO, None, &paren());
+
+        assert!(timers.cancel(cancelled));
+        assert!(!timers.cancel(cancelled));
+        assert_eq!(order(&mut timers), [sooner, later]);
+    }
+
+    #[test]
+    fn intervals_repeat_until_cancelled() {
+        let mut timers = Timers::default();
+        let interval = Some(Duration::from_secs(1));
+        let id = timers.schedule(
+            Value::Nil,
+            Duration::ZERO,
+            Duration::ZERO,
+            interval,
+            &paren(),
+        );
+        let once = timers.schedule(
+            Value::Nil,
+            Duration::ZERO,
+            Duration::from_millis(1500),
+            None,
+            &paren(),
+        );
+
+        let first = timers.next().unwrap();
+        timers.repeat(&first);
+        let second = timers.next().unwrap();
+        assert_eq!((first.id, second.id), (id, id));
+        assert_eq!(second.due, first.due + Duration::from_secs(1));
+
+        timers.repeat(&second);
+        assert!(timers.cancel(id));
+        assert_eq!(order(&mut timers), [once]);
+    }
+}
@@ -1,13 +1,29 @@
-use crate::{
-    expr::{Expr, Visitor},
-    scanner::{Literal, Token},
+use crate::syntax::{
+    expr::{self, Expr},
+    stmt::{self, Stmt},
+    token::{Literal, Token},
+    value::Value,
 };
 
+/// Renders a whole program as parenthesized, Lisp-like text, one top-level statement per line.
+pub fn pretty_print(statements: &[Stmt]) -> String {
+    let mut printer = AstPrinter {};
+    statements
+        .iter()
+        .map(|statement| printer.print_stmt(statement))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
 pub struct AstPrinter {}
 
 impl AstPrinter {
     pub fn print(&mut self, expr: &Expr) -> String {
-        self.visit_expr(expr)
+        expr::Visitor::visit_expr(self, expr)
+    }
+
+    pub fn print_stmt(&mut self, stmt: &Stmt) -> String {
+        stmt::Visitor::visit_stmt(self, stmt)
     }
 
     fn parenthesize(&mut self, name: &str, exprs: Vec<&Expr>) -> String {
@@ -16,7 +32,91 @@ impl AstPrinter {
 
         for expr in exprs {
             string.push(' ');
-            string.push_str(&self.visit_expr(expr));
+            string.push_str(&self.print(expr));
+        }
+
+        string.push(')');
+        string
+    }
+
+    fn parenthesize_stmts(&mut self, name: &str, stmts: &[Stmt]) -> String {
+        let mut string = String::from("(");
+        string.push_str(name);
+
+        for stmt in stmts {
+            string.push(' ');
+            string.push_str(&self.print_stmt(stmt));
+        }
+
+        string.push(')');
+        string
+    }
+
+    fn function(
+        &mut self,
+        keyword: &str,
+        name: &Token,
+        params: &[Token],
+        variadic: bool,
+        body: &[Stmt],
+    ) -> String {
+        let mut params = params
+            .iter()
+            .map(|param| param.lexeme.clone())
+            .collect::<Vec<String>>();
+        if let (true, Some(last)) = (variadic, params.last_mut()) {
+            last.insert_str(0, "...");
+        }
+        let params = params.join(" ");
+
+        self.parenthesize_stmts(&format!("{keyword} {}({params})", name.lexeme), body)
+    }
+
+    fn class(
+        &mut self,
+        name: &Token,
+        super_class: &Option<Box<Expr>>,
+        traits: &[Expr],
+        methods: &[Stmt],
+        abstract_methods: &[Token],
+        setters: &[Stmt],
+    ) -> String {
+        let mut string = format!("(class {}", name.lexeme);
+
+        if let Some(super_class) = super_class {
+            string.push_str(&format!(" < {}", self.print(super_class)));
+        }
+
+        if !traits.is_empty() {
+            let traits = traits
+                .iter()
+                .map(|lox_trait| self.print(lox_trait))
+                .collect::<Vec<String>>()
+                .join(" ");
+            string.push_str(&format!(" (with {traits})"));
+        }
+
+        for method in methods {
+            string.push(' ');
+            string.push_str(&self.print_stmt(method));
+        }
+
+        for method in abstract_methods {
+            string.push_str(&format!(" (abstract {})", method.lexeme));
+        }
+
+        for setter in setters {
+            if let Stmt::Function {
+                name,
+                params,
+                variadic,
+                body,
+                ..
+            } = setter
+            {
+                string.push(' ');
+                string.push_str(&self.function("set", name, params, *variadic, body));
+            }
         }
 
         string.push(')');
@@ -24,14 +124,19 @@ impl AstPrinter {
     }
 }
 
-impl Visitor<String> for AstPrinter {
+impl expr::Visitor<String> for AstPrinter {
     fn visit_expr(&mut self, expr: &Expr) -> String {
         match expr {
-            Expr::Grouping { expression } => self.parenthesize("group", vec![expression]),
-            Expr::Unary { operator, right } => self.parenthesize(&operator.lexeme, vec![right]),
-            Expr::Literal { value } => match value {
-                Literal::Number(value) => value.to_string(),
-                Literal::String(value) => value.to_string(),
+            Expr::Grouping { expression, .. } => self.parenthesize("group", vec![expression]),
+            Expr::Unary {
+                operator, right, ..
+            } => self.parenthesize(&operator.lexeme, vec![right]),
+            Expr::Literal { value, .. } => match value {
+                Literal::Int(value) => Value::Int(*value).to_string(),
+                #[cfg(feature = "bigint")]
+                Literal::BigInt(value) => value.to_string(),
+                Literal::Number(value) => Value::Number(*value).to_string(),
+                Literal::String(value) => format!("\"{value}\""),
                 Literal::Bool(value) => value.to_string(),
                 Literal::None => String::from("nil"),
             },
@@ -39,39 +144,222 @@ impl Visitor<String> for AstPrinter {
                 left,
                 operator,
                 right,
+                ..
+            }
+            | Expr::Logical {
+                left,
+                operator,
+                right,
+                ..
             } => self.parenthesize(&operator.lexeme, vec![left, right]),
-            Expr::Variable { name } => todo!(),
-            Expr::Assign { name, value } => todo!(),
+            Expr::Variable { name, .. } => name.lexeme.clone(),
+            Expr::Assign { name, value, .. } => {
+                self.parenthesize(&format!("= {}", name.lexeme), vec![value])
+            }
+            Expr::Call { callee, args, .. } => {
+                let mut exprs = vec![callee.as_ref()];
+                exprs.extend(args);
+                self.parenthesize("call", exprs)
+            }
+            Expr::Get { object, name, .. } => {
+                format!("(. {} {})", self.print(object), name.lexeme)
+            }
+            Expr::Set {
+                object,
+                name,
+                value,
+                ..
+            } => format!(
+                "(= (. {} {}) {})",
+                self.print(object),
+                name.lexeme,
+                self.print(value)
+            ),
+            Expr::This { .. } => String::from("this"),
+            Expr::Super { method, .. } => format!("(super {})", method.lexeme),
+            Expr::Update {
+                target,
+                operator,
+                prefix,
+                ..
+            } => {
+                let fixity = if *prefix { "pre" } else { "post" };
+                self.parenthesize(&format!("{fixity}{}", operator.lexeme), vec![target])
+            }
+            Expr::Yield { value, .. } => {
+                self.parenthesize("yield", value.iter().map(|v| v.as_ref()).collect())
+            }
         }
     }
 }
 
-pub fn test_ast_print() {
-    let expression = Expr::Binary {
-        left: Box::new(Expr::Unary {
-            operator: Token {
-                token_type: crate::scanner::TokenType::Minus,
-                lexeme: String::from("-"),
-                literal: Literal::None,
-                line: 1,
+impl stmt::Visitor<String> for AstPrinter {
+    fn visit_stmt(&mut self, stmt: &Stmt) -> String {
+        match stmt {
+            Stmt::Expression(expr) => self.parenthesize(";", vec![expr]),
+            Stmt::Print(exprs) => self.parenthesize("print", exprs.iter().collect()),
+            Stmt::Var { name, initializer } => match initializer {
+                Some(initializer) => {
+                    self.parenthesize(&format!("var {} =", name.lexeme), vec![initializer])
+                }
+                None => format!("(var {})", name.lexeme),
+            },
+            Stmt::Block(statements) => self.parenthesize_stmts("block", statements),
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                let mut string = format!(
+                    "(if {} {}",
+                    self.print(condition),
+                    self.print_stmt(then_branch)
+                );
+                if let Some(else_branch) = else_branch {
+                    string.push_str(&format!(" {}", self.print_stmt(else_branch)));
+                }
+                string.push(')');
+                string
+            }
+            Stmt::While { condition, body } => format!(
+                "(while {} {})",
+                self.print(condition),
+                self.print_stmt(body)
+            ),
+            Stmt::ForIn {
+                name,
+                iterable,
+                body,
+            } => format!(
+                "(for {} in {} {})",
+                name.lexeme,
+                self.print(iterable),
+                self.print_stmt(body)
+            ),
+            Stmt::Function {
+                name,
+                params,
+                variadic,
+                body,
+                decorators,
+                ..
+            } => decorators.iter().rev().fold(
+                self.function("fun", name, params, *variadic, body),
+                |function, decorator| format!("(@ {} {})", self.print(decorator), function),
+            ),
+            Stmt::Return { value, .. } => match value {
+                Some(value) => self.parenthesize("return", vec![value]),
+                None => String::from("(return)"),
             },
-            right: Box::new(Expr::Literal {
-                value: Literal::Number(123.0),
-            }),
-        }),
-        operator: Token {
-            token_type: crate::scanner::TokenType::Star,
-            lexeme: String::from("*"),
-            literal: Literal::None,
-            line: 1,
-        },
-        right: Box::new(Expr::Grouping {
-            expression: Box::new(Expr::Literal {
-                value: Literal::Number(45.67),
-            }),
-        }),
-    };
+            Stmt::Throw { value, .. } => self.parenthesize("throw", vec![value]),
+            Stmt::Import { path, .. } => format!("(import {})", path.lexeme),
+            Stmt::Class {
+                name,
+                super_class,
+                traits,
+                methods,
+                abstract_methods,
+                setters,
+                ..
+            } => self.class(
+                name,
+                super_class,
+                traits,
+                methods,
+                abstract_methods,
+                setters,
+            ),
+            Stmt::Trait { name, methods } => {
+                self.parenthesize_stmts(&format!("trait {}", name.lexeme), methods)
+            }
+            Stmt::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                let mut string = format!("(try {}", self.parenthesize_stmts("block", body));
+                if let Some((name, catch_body)) = catch {
+                    string.push(' ');
+                    string.push_str(
+                        &self.parenthesize_stmts(&format!("catch {}", name.lexeme), catch_body),
+                    );
+                }
+                if let Some(finally) = finally {
+                    string.push(' ');
+                    string.push_str(&self.parenthesize_stmts("finally", finally));
+                }
+                string.push(')');
+                string
+            }
+        }
+    }
+}
 
-    let mut printer = AstPrinter {};
-    println!("{}", printer.print(&expression));
+#[cfg(test)]
+mod tests {
+    use crate::{parser::Parser, scanner::Scanner};
+
+    use super::*;
+
+    fn print_code(lox_code: &str) -> String {
+        let mut scanner = Scanner::new(lox_code.to_string());
+        let tokens = scanner.scan_tokens().unwrap();
+
+        let mut parser = Parser::new(tokens);
+        pretty_print(&parser.parse())
+    }
+
+    #[test]
+    fn expressions() {
+        assert_eq!(
+            print_code("-123 * (45.67);"),
+            "(; (* (- 123) (group 45.67)))"
+        );
+        assert_eq!(
+            print_code("a = b or !c and \"s\";"),
+            "(; (= a (or b (and (! c) \"s\"))))"
+        );
+        assert_eq!(
+            print_code("obj.field = f(1, nil).x;"),
+            "(; (= (. obj field) (. (call f 1 nil) x)))"
+        );
+        assert_eq!(
+            print_code("i++; --this.count;"),
+            "(; (post++ i))\n(; (pre-- (. this count)))"
+        );
+    }
+
+    #[test]
+    fn statements() {
+        assert_eq!(
+            print_code("var a; var b = 1; print a, b;"),
+            "(var a)\n(var b = 1)\n(print a b)"
+        );
+        assert_eq!(
+            print_code("if (a) { print 1; } else print 2; while (true) {}"),
+            "(if a (block (print 1)) (print 2))\n(while true (block))"
+        );
+    }
+
+    #[test]
+    fn functions_and_classes() {
+        assert_eq!(
+            print_code("fun add(a, b) { return a + b; }"),
+            "(fun add(a b) (return (+ a b)))"
+        );
+        assert_eq!(
+            print_code("fun log(level, ...args) {}"),
+            "(fun log(level ...args))"
+        );
+        assert_eq!(
+            print_code(
+                "class B < A { init() { return; } get() { return super.get(); } set x(v) { this.y = v; } }"
+            ),
+            "(class B < A (fun init() (return)) (fun get() (return (call (super get)))) (set x(v) (; (= (. this y) v))))"
+        );
+        assert_eq!(
+            print_code("trait T { f() {} } class C with T, U {}"),
+            "(trait T (fun f()))\n(class C (with T U))"
+        );
+    }
 }
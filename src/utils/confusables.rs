@@ -0,0 +1,135 @@
+/// The scripts whose letters are easily mistaken for one another.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+}
+
+impl Script {
+    pub fn of(char: char) -> Option<Script> {
+        match char {
+            'a'..='z' | 'A'..='Z' => Some(Script::Latin),
+            '\u{c0}'..='\u{24f}' | '\u{1e00}'..='\u{1eff}' if char.is_alphabetic() => {
+                Some(Script::Latin)
+            }
+            '\u{370}'..='\u{3ff}' | '\u{1f00}'..='\u{1fff}' if char.is_alphabetic() => {
+                Some(Script::Greek)
+            }
+            '\u{400}'..='\u{52f}' if char.is_alphabetic() => Some(Script::Cyrillic),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Script::Latin => "Latin",
+            Script::Greek => "Greek",
+            Script::Cyrillic => "Cyrillic",
+        }
+    }
+}
+
+// Greek and Cyrillic letters that look like a Latin one
+const LOOKALIKES: &[(char, char)] = &[
+    ('а', 'a'),
+    ('е', 'e'),
+    ('і', 'i'),
+    ('ј', 'j'),
+    ('к', 'k'),
+    ('о', 'o'),
+    ('р', 'p'),
+    ('с', 'c'),
+    ('ѕ', 's'),
+    ('у', 'y'),
+    ('х', 'x'),
+    ('ԁ', 'd'),
+    ('ԛ', 'q'),
+    ('ԝ', 'w'),
+    ('А', 'A'),
+    ('В', 'B'),
+    ('Е', 'E'),
+    ('І', 'I'),
+    ('Ј', 'J'),
+    ('К', 'K'),
+    ('М', 'M'),
+    ('Н', 'H'),
+    ('О', 'O'),
+    ('Р', 'P'),
+    ('С', 'C'),
+    ('Ѕ', 'S'),
+    ('Т', 'T'),
+    ('Х', 'X'),
+    ('Ү', 'Y'),
+    ('α', 'a'),
+    ('ι', 'i'),
+    ('κ', 'k'),
+    ('ν', 'v'),
+    ('ο', 'o'),
+    ('ρ', 'p'),
+    ('υ', 'u'),
+    ('Α', 'A'),
+    ('Β', 'B'),
+    ('Ε', 'E'),
+    ('Ζ', 'Z'),
+    ('Η', 'H'),
+    ('Ι', 'I'),
+    ('Κ', 'K'),
+    ('Μ', 'M'),
+    ('Ν', 'N'),
+    ('Ο', 'O'),
+    ('Ρ', 'P'),
+    ('Τ', 'T'),
+    ('Υ', 'Y'),
+    ('Χ', 'X'),
+];
+
+/// The Latin letter `char` looks like, if it's a Greek or Cyrillic lookalike of one.
+pub fn lookalike(char: char) -> Option<char> {
+    LOOKALIKES
+        .iter()
+        .find(|(lookalike, _)| *lookalike == char)
+        .map(|(_, latin)| *latin)
+}
+
+/// What an identifier looks like on screen: its lookalike letters replaced by the Latin letters
+/// they resemble, so two identifiers that can't be told apart have the same skeleton.
+pub fn skeleton(identifier: &str) -> String {
+    identifier
+        .chars()
+        .map(|char| lookalike(char).unwrap_or(char))
+        .collect()
+}
+
+/// The first two scripts an identifier mixes, such as a Latin name with a Cyrillic letter in it.
+pub fn mixed_scripts(identifier: &str) -> Option<(Script, Script)> {
+    let mut scripts = identifier.chars().filter_map(Script::of);
+    let first = scripts.next()?;
+    scripts
+        .find(|script| *script != first)
+        .map(|second| (first, second))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookalikes_share_a_skeleton() {
+        assert_eq!(skeleton("pаy"), "pay");
+        assert_eq!(skeleton("Ρорс"), "Popc");
+        assert_eq!(skeleton("größe"), "größe");
+    }
+
+    #[test]
+    fn scripts_are_mixed_by_letters_only() {
+        assert_eq!(
+            mixed_scripts("pаy"),
+            Some((Script::Latin, Script::Cyrillic))
+        );
+        assert_eq!(mixed_scripts("café_2"), None);
+        assert_eq!(mixed_scripts("привет"), None);
+        assert_eq!(mixed_scripts("λ_x"), Some((Script::Greek, Script::Latin)));
+        assert_eq!(mixed_scripts("名前x"), None);
+    }
+}
@@ -0,0 +1,113 @@
+use std::{cell::RefCell, collections::HashMap, fs, io, path::Path};
+
+/// The files Lox code can reach, such as the modules it imports. Embedders can swap in a
+/// sandbox, an in-memory store for tests, or browser storage where there is no `std::fs`.
+pub trait FileSystem {
+    fn read_to_string(&self, path: &str) -> io::Result<String>;
+
+    /// Creates the file or replaces its contents.
+    fn write(&self, path: &str, contents: &str) -> io::Result<()>;
+
+    /// Adds to the end of the file, creating it if it doesn't exist.
+    fn append(&self, path: &str, contents: &str) -> io::Result<()>;
+
+    fn exists(&self, path: &str) -> bool;
+}
+
+/// The real filesystem, through `std::fs`.
+pub struct StdFileSystem;
+impl FileSystem for StdFileSystem {
+    fn read_to_string(&self, path: &str) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+
+    fn write(&self, path: &str, contents: &str) -> io::Result<()> {
+        fs::write(path, contents)
+    }
+
+    fn append(&self, path: &str, contents: &str) -> io::Result<()> {
+        use io::Write;
+
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?
+            .write_all(contents.as_bytes())
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        Path::new(path).exists()
+    }
+}
+
+/// Files kept in memory and keyed by their exact path, which never touches the disk.
+#[derive(Default)]
+pub struct MemoryFileSystem {
+    files: RefCell<HashMap<String, String>>,
+}
+
+impl MemoryFileSystem {
+    pub fn new() -> MemoryFileSystem {
+        MemoryFileSystem::default()
+    }
+
+    /// Adds a file, for setting up the files a program expects to find.
+    pub fn with_file(self, path: &str, contents: &str) -> MemoryFileSystem {
+        self.files
+            .borrow_mut()
+            .insert(path.to_string(), contents.to_string());
+        self
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn read_to_string(&self, path: &str) -> io::Result<String> {
+        self.files
+            .borrow()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No such file"))
+    }
+
+    fn write(&self, path: &str, contents: &str) -> io::Result<()> {
+        self.files
+            .borrow_mut()
+            .insert(path.to_string(), contents.to_string());
+        Ok(())
+    }
+
+    fn append(&self, path: &str, contents: &str) -> io::Result<()> {
+        self.files
+            .borrow_mut()
+            .entry(path.to_string())
+            .or_default()
+            .push_str(contents);
+        Ok(())
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.files.borrow().contains_key(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_file_system() {
+        let file_system = MemoryFileSystem::new().with_file("notes.txt", "one");
+
+        file_system.append("notes.txt", ", two").unwrap();
+        file_system.append("log.txt", "started").unwrap();
+        assert_eq!(file_system.read_to_string("notes.txt").unwrap(), "one, two");
+        assert_eq!(file_system.read_to_string("log.txt").unwrap(), "started");
+
+        file_system.write("notes.txt", "three").unwrap();
+        assert_eq!(file_system.read_to_string("notes.txt").unwrap(), "three");
+
+        assert!(!file_system.exists("missing.txt"));
+        let error = file_system.read_to_string("missing.txt").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,39 @@
+use crate::syntax::value::Value;
+
+/// How help introduces a global function, class or native bound to `name`, or None for any other
+/// value.
+pub fn signature(name: &str, value: &Value) -> Option<String> {
+    match value {
+        Value::Function(function) => Some(format!("fun {name}({})", function.param_list())),
+        Value::Class(class) => Some(format!("class {name}({})", class.param_list())),
+        Value::NativeFunction(native) => Some(format!("native {name}/{}", native.arity)),
+        _ => None,
+    }
+}
+
+/// The signature followed by the full documentation, indented underneath it.
+pub fn describe(name: &str, value: &Value) -> Option<String> {
+    let mut help = signature(name, value)?;
+    for line in value.doc().unwrap_or("No documentation.").lines() {
+        help.push_str("\n    ");
+        help.push_str(line);
+    }
+
+    Some(help)
+}
+
+/// One line per callable binding: its signature and the first line of its documentation.
+pub fn index(bindings: &[(String, Value)]) -> String {
+    let entries = bindings
+        .iter()
+        .filter_map(|(name, value)| {
+            let signature = signature(name, value)?;
+            Some(match value.doc().and_then(|doc| doc.lines().next()) {
+                Some(summary) => format!("{signature:<32} {summary}"),
+                None => signature,
+            })
+        })
+        .collect::<Vec<String>>();
+
+    entries.join("\n")
+}
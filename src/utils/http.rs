@@ -0,0 +1,491 @@
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+// how long connecting, and each read or write after, may take before the request fails
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+// how long a read or write blocks before the request checks whether it should stop
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// the most a response, head and body, may take up, so a server can't exhaust memory
+const MAX_RESPONSE_SIZE: usize = 16 * 1024 * 1024;
+
+/// A response to an HTTP request, with its headers in the order they were sent.
+#[derive(Debug, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+/// Sends an HTTP/1.1 request over a plain TCP connection, with `body` if there is one, and
+/// waits for the whole response. Only `http://` URLs are supported, as there's no TLS without
+/// a dependency.
+///
+/// `check` is called between reads and writes, which block for only a short while each, and
+/// the request is abandoned with its error as soon as it returns one. Hosts pass a check for
+/// interruptions and timeouts, so those can stop a request to a server that never answers.
+/// A request that fails on its own gives the inner error.
+pub fn request<E>(
+    method: &str,
+    url: &str,
+    body: Option<&str>,
+    check: impl Fn() -> Result<(), E>,
+) -> Result<Result<Response, String>, E> {
+    let (host, port, path) = match parse_url(url) {
+        Ok(parts) => parts,
+        Err(message) => return Ok(Err(message)),
+    };
+
+    let address = match (host.as_str(), port).to_socket_addrs() {
+        Ok(mut addresses) => addresses.next(),
+        Err(error) => return Ok(Err(format!("Can't resolve '{host}': {error}."))),
+    };
+    let Some(address) = address else {
+        return Ok(Err(format!("Can't resolve '{host}'.")));
+    };
+    let host = host_header(&host, port);
+    let mut stream = match TcpStream::connect_timeout(&address, TIMEOUT) {
+        Ok(stream) => stream,
+        Err(error) => return Ok(Err(format!("Can't connect to '{address}': {error}."))),
+    };
+    if let Err(error) = stream
+        .set_read_timeout(Some(POLL_INTERVAL))
+        .and_then(|_| stream.set_write_timeout(Some(POLL_INTERVAL)))
+    {
+        return Ok(Err(error.to_string()));
+    }
+
+    let mut message = format!(
+        "{method} {path} HTTP/1.1\r\nHost: {host}\r\nUser-Agent: rlox\r\nConnection: close\r\n"
+    );
+    if let Some(body) = body {
+        message.push_str("Content-Type: text/plain; charset=utf-8\r\n");
+        message.push_str(&format!("Content-Length: {}\r\n\r\n{body}", body.len()));
+    } else {
+        message.push_str("\r\n");
+    }
+
+    let failed = |error: String| format!("Request to '{url}' failed: {error}.");
+    if let Err(error) = send(&mut stream, message.as_bytes(), &check)? {
+        return Ok(Err(failed(error)));
+    }
+    let response = match receive(&mut stream, &check)? {
+        Ok(response) => response,
+        Err(error) => return Ok(Err(failed(error))),
+    };
+    Ok(parse_response(&response))
+}
+
+// writes all of `message`, checking in whenever the stream blocks for a while
+fn send<E>(
+    stream: &mut TcpStream,
+    mut message: &[u8],
+    check: &impl Fn() -> Result<(), E>,
+) -> Result<Result<(), String>, E> {
+    let mut progress = Instant::now();
+    while !message.is_empty() {
+        check()?;
+        match stream.write(message) {
+            Ok(0) => return Ok(Err(String::from("the connection closed"))),
+            Ok(written) => {
+                message = &message[written..];
+                progress = Instant::now();
+            }
+            Err(error) if is_wait(&error) => {
+                if progress.elapsed() >= TIMEOUT {
+                    return Ok(Err(String::from("timed out")));
+                }
+            }
+            Err(error) => return Ok(Err(error.to_string())),
+        }
+    }
+    Ok(Ok(()))
+}
+
+// reads the response until the server closes the connection or it's complete by its own
+// framing, checking in whenever the stream blocks for a while
+fn receive<E>(
+    stream: &mut TcpStream,
+    check: &impl Fn() -> Result<(), E>,
+) -> Result<Result<Vec<u8>, String>, E> {
+    let too_large = || format!("the response is over {MAX_RESPONSE_SIZE} bytes");
+    let mut response = vec![];
+    let mut buffer = [0; 8192];
+    let mut progress = Instant::now();
+    // how long the whole response is, once its head says so
+    let mut expected = None;
+    loop {
+        check()?;
+        match stream.read(&mut buffer) {
+            Ok(0) => return Ok(Ok(response)),
+            Ok(read) => {
+                response.extend_from_slice(&buffer[..read]);
+                progress = Instant::now();
+            }
+            Err(error) if is_wait(&error) => {
+                if progress.elapsed() >= TIMEOUT {
+                    return Ok(Err(String::from("timed out")));
+                }
+                continue;
+            }
+            Err(error) => return Ok(Err(error.to_string())),
+        }
+
+        if expected.is_none() {
+            expected = expected_length(&response);
+        }
+        match expected {
+            Some(Framing::Length(length)) if length > MAX_RESPONSE_SIZE => {
+                return Ok(Err(too_large()))
+            }
+            Some(Framing::Length(length)) if response.len() >= length => return Ok(Ok(response)),
+            Some(Framing::Chunked) if response.ends_with(b"\r\n0\r\n\r\n") => {
+                return Ok(Ok(response))
+            }
+            _ if response.len() > MAX_RESPONSE_SIZE => return Ok(Err(too_large())),
+            _ => {}
+        }
+    }
+}
+
+// whether an error only means the stream had nothing to do within the poll interval
+fn is_wait(error: &std::io::Error) -> bool {
+    matches!(
+        error.kind(),
+        ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted
+    )
+}
+
+// how a response marks the end of its body
+enum Framing {
+    // the whole response, head included, is this long
+    Length(usize),
+    // the body ends with an empty chunk
+    Chunked,
+    // the body ends when the server closes the connection
+    Close,
+}
+
+// how the response ends, once its head has arrived
+fn expected_length(response: &[u8]) -> Option<Framing> {
+    let end = head_end(response)?;
+    let (_, headers) = parse_head(&response[..end])?;
+    if header(&headers, "Transfer-Encoding")
+        .is_some_and(|encoding| encoding.eq_ignore_ascii_case("chunked"))
+    {
+        return Some(Framing::Chunked);
+    }
+    match header(&headers, "Content-Length").and_then(|length| length.parse::<usize>().ok()) {
+        Some(length) => Some(Framing::Length((end + 4).saturating_add(length))),
+        None => Some(Framing::Close),
+    }
+}
+
+// the host, port and path of an http:// URL, without any fragment, and with the brackets
+// around an IPv6 address removed
+fn parse_url(url: &str) -> Result<(String, u16, String), String> {
+    // they'd end the request line or a header early, letting the URL add headers of its own
+    if url.contains(|c: char| c.is_whitespace() || c.is_control()) {
+        return Err(format!(
+            "'{}' contains whitespace or control characters.",
+            url.escape_debug()
+        ));
+    }
+    let Some(rest) = url.strip_prefix("http://") else {
+        return Err(match url.starts_with("https://") {
+            true => format!("Can't request '{url}': https isn't supported, only http."),
+            false => format!("'{url}' is not an http:// URL."),
+        });
+    };
+
+    // the fragment is only for the client, and never sent
+    let rest = rest.split('#').next().unwrap_or(rest);
+    let (authority, path) = rest.split_at(rest.find(['/', '?']).unwrap_or(rest.len()));
+    let path = match path.starts_with('/') {
+        true => path.to_string(),
+        false => format!("/{path}"),
+    };
+    let invalid_port = || format!("'{url}' has an invalid port.");
+    let (host, port) = match authority.strip_prefix('[') {
+        // an IPv6 address, which has colons of its own
+        Some(bracketed) => {
+            let (host, port) = bracketed
+                .split_once(']')
+                .ok_or_else(|| format!("'{url}' has an unclosed '['."))?;
+            match port {
+                "" => (host, 80),
+                _ => (
+                    host,
+                    port.strip_prefix(':')
+                        .and_then(|port| port.parse().ok())
+                        .ok_or_else(invalid_port)?,
+                ),
+            }
+        }
+        None => match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| invalid_port())?),
+            None => (authority, 80),
+        },
+    };
+    if host.is_empty() {
+        return Err(format!("'{url}' has no host."));
+    }
+
+    Ok((host.to_string(), port, path))
+}
+
+// the Host header for a request, which names the port unless it's http's default
+fn host_header(host: &str, port: u16) -> String {
+    let host = match host.contains(':') {
+        true => format!("[{host}]"),
+        false => host.to_string(),
+    };
+    match port {
+        80 => host,
+        _ => format!("{host}:{port}"),
+    }
+}
+
+// where the head of a response ends, before the blank line
+fn head_end(response: &[u8]) -> Option<usize> {
+    response.windows(4).position(|window| window == b"\r\n\r\n")
+}
+
+// the status and headers in the head of a response
+fn parse_head(head: &[u8]) -> Option<(u16, Vec<(String, String)>)> {
+    let head = String::from_utf8_lossy(head);
+    let mut lines = head.split("\r\n");
+
+    // "HTTP/1.1 200 OK"
+    let status = lines
+        .next()
+        .and_then(|line| line.split(' ').nth(1))
+        .and_then(|status| status.parse().ok())?;
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect::<Vec<(String, String)>>();
+    Some((status, headers))
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(header, _)| header.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+fn parse_response(response: &[u8]) -> Result<Response, String> {
+    let malformed = || String::from("The server sent a malformed response.");
+    let end = head_end(response).ok_or_else(malformed)?;
+    let (status, headers) = parse_head(&response[..end]).ok_or_else(malformed)?;
+
+    let mut body = &response[end + 4..];
+    let chunked;
+    if header(&headers, "Transfer-Encoding")
+        .is_some_and(|encoding| encoding.eq_ignore_ascii_case("chunked"))
+    {
+        chunked = dechunk(body).ok_or_else(malformed)?;
+        body = &chunked;
+    } else if let Some(length) =
+        header(&headers, "Content-Length").and_then(|length| length.parse().ok())
+    {
+        body = &body[..body.len().min(length)];
+    }
+
+    Ok(Response {
+        status,
+        headers,
+        body: String::from_utf8_lossy(body).into_owned(),
+    })
+}
+
+// the body of a response sent in chunks, each prefixed with its length in hex
+fn dechunk(mut body: &[u8]) -> Option<Vec<u8>> {
+    let mut dechunked = vec![];
+    loop {
+        let line_end = body.windows(2).position(|window| window == b"\r\n")?;
+        let size = std::str::from_utf8(&body[..line_end]).ok()?;
+        // chunk extensions after a ';' are ignored
+        let size = usize::from_str_radix(size.split(';').next()?.trim(), 16).ok()?;
+        body = &body[line_end + 2..];
+        if size == 0 {
+            return Some(dechunked);
+        }
+        dechunked.extend_from_slice(body.get(..size)?);
+        body = body.get(size + 2..)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, net::TcpListener, thread};
+
+    use super::*;
+
+    // serves one connection with `reply`, then holds it open until the client hangs up
+    fn serve(reply: &'static [u8]) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request);
+            let _ = stream.write_all(reply);
+            let _ = stream.read(&mut request);
+        });
+        port
+    }
+
+    fn never_stop() -> Result<(), ()> {
+        Ok(())
+    }
+
+    #[test]
+    fn urls_split_into_host_port_and_path() {
+        assert_eq!(
+            parse_url("http://example.com"),
+            Ok((String::from("example.com"), 80, String::from("/")))
+        );
+        assert_eq!(
+            parse_url("http://localhost:8080/a/b?c=d"),
+            Ok((String::from("localhost"), 8080, String::from("/a/b?c=d")))
+        );
+        assert_eq!(
+            parse_url("http://localhost?q=1"),
+            Ok((String::from("localhost"), 80, String::from("/?q=1")))
+        );
+        assert!(parse_url("https://example.com")
+            .unwrap_err()
+            .contains("https"));
+        assert!(parse_url("ftp://example.com").is_err());
+        assert!(parse_url("http://host:port/").is_err());
+    }
+
+    #[test]
+    fn fragments_are_dropped_and_ipv6_addresses_keep_their_colons() {
+        assert_eq!(
+            parse_url("http://example.com/page?q=1#section"),
+            Ok((String::from("example.com"), 80, String::from("/page?q=1")))
+        );
+        assert_eq!(
+            parse_url("http://example.com#top"),
+            Ok((String::from("example.com"), 80, String::from("/")))
+        );
+        assert_eq!(
+            parse_url("http://[::1]:8080/a"),
+            Ok((String::from("::1"), 8080, String::from("/a")))
+        );
+        assert_eq!(
+            parse_url("http://[fe80::1]"),
+            Ok((String::from("fe80::1"), 80, String::from("/")))
+        );
+        assert!(parse_url("http://[::1/").is_err());
+        assert!(parse_url("http://[::1]8080/").is_err());
+    }
+
+    #[test]
+    fn host_headers_name_ports_other_than_the_default() {
+        assert_eq!(host_header("example.com", 80), "example.com");
+        assert_eq!(host_header("localhost", 8080), "localhost:8080");
+        assert_eq!(host_header("::1", 80), "[::1]");
+        assert_eq!(host_header("::1", 8080), "[::1]:8080");
+    }
+
+    #[test]
+    fn responses_end_at_their_content_length_without_waiting_for_a_close() {
+        let port = serve(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nhi");
+        let response = request(
+            "GET",
+            &format!("http://127.0.0.1:{port}/"),
+            None,
+            never_stop,
+        );
+        assert_eq!(response.unwrap().unwrap().body, "hi");
+
+        let port =
+            serve(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nhi\r\n0\r\n\r\n");
+        let response = request(
+            "GET",
+            &format!("http://127.0.0.1:{port}/"),
+            None,
+            never_stop,
+        );
+        assert_eq!(response.unwrap().unwrap().body, "hi");
+    }
+
+    #[test]
+    fn oversized_responses_are_refused() {
+        let port = serve(b"HTTP/1.1 200 OK\r\nContent-Length: 999999999999\r\n\r\n");
+        let response = request(
+            "GET",
+            &format!("http://127.0.0.1:{port}/"),
+            None,
+            never_stop,
+        );
+        assert!(response
+            .unwrap()
+            .unwrap_err()
+            .contains("the response is over 16777216 bytes"));
+    }
+
+    #[test]
+    fn checks_can_stop_a_request_the_server_never_answers() {
+        let port = serve(b"");
+        let checks = Cell::new(0);
+        let check = || {
+            checks.set(checks.get() + 1);
+            match checks.get() {
+                5 => Err("stopped"),
+                _ => Ok(()),
+            }
+        };
+        let response = request("GET", &format!("http://127.0.0.1:{port}/"), None, check);
+        assert_eq!(response, Err("stopped"));
+    }
+
+    #[test]
+    fn urls_cant_inject_headers_or_requests() {
+        for url in [
+            "http://example.com/a b",
+            "http://example.com/\r\nX-Injected: 1",
+            "http://example.com\nHost: other.com/",
+            "http://example.com/\tpath",
+            "http://example.com/\0",
+        ] {
+            assert!(parse_url(url)
+                .unwrap_err()
+                .contains("whitespace or control characters"));
+        }
+    }
+
+    #[test]
+    fn responses_parse_with_either_body_framing() {
+        let response = parse_response(
+            b"HTTP/1.1 404 Not Found\r\nContent-Type: text/plain\r\nContent-Length: 5\r\n\r\nmissing",
+        )
+        .unwrap();
+        assert_eq!(
+            response,
+            Response {
+                status: 404,
+                headers: vec![
+                    (String::from("Content-Type"), String::from("text/plain")),
+                    (String::from("Content-Length"), String::from("5")),
+                ],
+                body: String::from("missi"),
+            }
+        );
+
+        let chunked = parse_response(
+            b"HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n4\r\nWiki\r\n6;x=y\r\npedia!\r\n0\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(chunked.body, "Wikipedia!");
+        assert!(parse_response(b"garbage").is_err());
+    }
+}
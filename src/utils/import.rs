@@ -0,0 +1,43 @@
+use std::{path::Path, rc::Rc};
+
+use super::file_system::FileSystem;
+
+/// Finds the source of the modules that Lox code loads with `import "path";`.
+pub trait ImportResolver {
+    /// The source of the module `path`, imported from the module named `importer` (None for
+    /// code that isn't in a module, such as the REPL), or a message saying why it can't be read.
+    fn resolve(&mut self, path: &str, importer: Option<&str>) -> Result<String, String>;
+
+    /// The name identifying the module `path` imported from `importer`. A module runs only the
+    /// first time its name is imported, and its name is the importer of its own imports.
+    fn module_name(&self, path: &str, importer: Option<&str>) -> String {
+        let _ = importer;
+        path.to_string()
+    }
+}
+
+/// Reads modules from files, with paths relative to the directory of the importing file.
+pub struct FileImportResolver {
+    file_system: Rc<dyn FileSystem>,
+}
+
+impl FileImportResolver {
+    pub fn new(file_system: Rc<dyn FileSystem>) -> FileImportResolver {
+        FileImportResolver { file_system }
+    }
+}
+
+impl ImportResolver for FileImportResolver {
+    fn resolve(&mut self, path: &str, importer: Option<&str>) -> Result<String, String> {
+        self.file_system
+            .read_to_string(&self.module_name(path, importer))
+            .map_err(|error| error.to_string())
+    }
+
+    fn module_name(&self, path: &str, importer: Option<&str>) -> String {
+        match importer.and_then(|importer| Path::new(importer).parent()) {
+            Some(directory) => directory.join(path).to_string_lossy().into_owned(),
+            None => path.to_string(),
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use std::io::{self, Write};
+
+/// Where the REPL gets its input from, a line at a time.
+pub trait LineSource {
+    /// Shows `prompt` if the source has somewhere to show it, and reads the next line without
+    /// its line ending, or returns None once the input has ended.
+    fn read_line(&mut self, prompt: &str) -> Option<String>;
+}
+
+impl LineSource for io::Stdin {
+    fn read_line(&mut self, prompt: &str) -> Option<String> {
+        print!("{prompt}");
+        io::stdout().flush().expect("stdout to be writable");
+
+        let mut line = String::new();
+        match io::Stdin::read_line(self, &mut line).expect("valid user input") {
+            0 => None,
+            _ => Some(line.trim_end_matches(['\n', '\r']).to_string()),
+        }
+    }
+}
@@ -1,2 +1,9 @@
-// pub mod ast_printer;
+pub mod ast_printer;
+pub mod confusables;
+pub mod file_system;
+pub mod help;
+pub mod http;
+pub mod import;
+pub mod line_source;
 pub mod logger;
+pub mod module_header;
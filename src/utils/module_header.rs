@@ -0,0 +1,75 @@
+use crate::capabilities::NativeGroup;
+
+/// What a module says about itself in `//!` lines at the top of its source, before any code:
+/// `//! requires io, net` names the groups of natives it needs, and `//! strict` makes its
+/// warnings errors. An importer checks both before running the module, so a module that needs
+/// more than it's been granted fails to import up front rather than partway through.
+#[derive(Debug, Default, PartialEq)]
+pub struct ModuleHeader {
+    pub requires: Vec<NativeGroup>,
+    pub strict: bool,
+}
+
+impl ModuleHeader {
+    /// Reads the header at the top of `source`, or says which line of it isn't understood.
+    pub fn parse(source: &str) -> Result<ModuleHeader, String> {
+        let mut header = ModuleHeader::default();
+        for line in source.lines().map(str::trim) {
+            if line.is_empty() {
+                continue;
+            }
+            let Some(directive) = line.strip_prefix("//!") else {
+                break;
+            };
+
+            let directive = directive.trim();
+            match directive.split_once(char::is_whitespace) {
+                _ if directive == "strict" => header.strict = true,
+                Some(("requires", groups)) => {
+                    for code in groups.split(',').map(str::trim) {
+                        let group = NativeGroup::from_code(code)
+                            .ok_or_else(|| format!("Unknown native group '{code}' in '{line}'."))?;
+                        header.requires.push(group);
+                    }
+                }
+                _ => return Err(format!("Unknown module header '{line}'.")),
+            }
+        }
+        Ok(header)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headers_come_before_any_code() {
+        assert_eq!(
+            ModuleHeader::parse("//! requires io, net\n\n//! strict\nvar a = 1;\n//! strict"),
+            Ok(ModuleHeader {
+                requires: vec![NativeGroup::Io, NativeGroup::Net],
+                strict: true,
+            })
+        );
+        assert_eq!(
+            ModuleHeader::parse("// a comment\n//! strict"),
+            Ok(ModuleHeader::default())
+        );
+        assert_eq!(ModuleHeader::parse(""), Ok(ModuleHeader::default()));
+    }
+
+    #[test]
+    fn unknown_headers_are_errors() {
+        assert_eq!(
+            ModuleHeader::parse("//! requires io, disk"),
+            Err(String::from(
+                "Unknown native group 'disk' in '//! requires io, disk'."
+            ))
+        );
+        assert_eq!(
+            ModuleHeader::parse("//! lenient"),
+            Err(String::from("Unknown module header '//! lenient'."))
+        );
+    }
+}
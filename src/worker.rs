@@ -0,0 +1,220 @@
+use std::{
+    rc::Rc,
+    sync::mpsc::{self, Receiver, Sender},
+    thread::{self, JoinHandle},
+};
+
+#[cfg(feature = "bigint")]
+use crate::impls::bigint::BigInt;
+use crate::{
+    impls::{decimal::Decimal, list::new_list},
+    interpreter::{Interpreter, InterpreterOptions, RunState, Steps, STACK_SIZE},
+    syntax::value::Value,
+    LoxError,
+};
+
+/// A value that can cross from one interpreter to another. Only plain data can be sent, and it's
+/// copied on the way, so interpreters never share mutable state.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    Nil,
+    Boolean(bool),
+    Int(i64),
+    #[cfg(feature = "bigint")]
+    BigInt(BigInt),
+    Number(f64),
+    Decimal(Decimal),
+    String(String),
+    List(Vec<Message>),
+}
+
+impl Message {
+    /// Copies `value` into a message, or says why it can't be sent.
+    pub fn from_value(value: &Value) -> Result<Message, String> {
+        Message::copy(value, &mut vec![])
+    }
+
+    // `lists` holds the lists being copied, to catch a list that contains itself
+    fn copy(value: &Value, lists: &mut Vec<*const ()>) -> Result<Message, String> {
+        match value {
+            Value::Nil => Ok(Message::Nil),
+            Value::Boolean(value) => Ok(Message::Boolean(*value)),
+            Value::Int(value) => Ok(Message::Int(*value)),
+            #[cfg(feature = "bigint")]
+            Value::BigInt(value) => Ok(Message::BigInt(BigInt::clone(value))),
+            Value::Number(value) => Ok(Message::Number(*value)),
+            Value::Decimal(value) => Ok(Message::Decimal(*value)),
+            Value::String(value) => Ok(Message::String(value.to_string())),
+            Value::List(list) => {
+                let address = Rc::as_ptr(list) as *const ();
+                if lists.contains(&address) {
+                    return Err(String::from("Can't send a list that contains itself."));
+                }
+
+                lists.push(address);
+                let items = list
+                    .borrow()
+                    .iter()
+                    .map(|item| Message::copy(item, lists))
+                    .collect::<Result<Vec<Message>, String>>();
+                lists.pop();
+                Ok(Message::List(items?))
+            }
+            _ => Err(String::from(
+                "Can only send nil, booleans, numbers, strings and lists of them.",
+            )),
+        }
+    }
+
+    pub fn into_value(self) -> Value {
+        match self {
+            Message::Nil => Value::Nil,
+            Message::Boolean(value) => Value::Boolean(value),
+            Message::Int(value) => Value::Int(value),
+            #[cfg(feature = "bigint")]
+            Message::BigInt(value) => Value::BigInt(Rc::new(value)),
+            Message::Number(value) => Value::Number(value),
+            Message::Decimal(value) => Value::Decimal(value),
+            Message::String(value) => Value::String(value.into()),
+            Message::List(items) => Value::List(new_list(
+                items.into_iter().map(Message::into_value).collect(),
+            )),
+        }
+    }
+}
+
+/// A worker's ends of the channels to its host, used by the `post` and `receive` natives.
+pub(crate) struct Mailbox {
+    pub(crate) outbox: Sender<Message>,
+    pub(crate) inbox: Receiver<Message>,
+}
+
+/// A Lox program running in an interpreter of its own on another thread. It talks to its host
+/// only through messages: `post(value)` in Lox arrives at [`Worker::receive`], and
+/// [`Worker::post`] arrives at `receive()`.
+pub struct Worker {
+    // dropped when joining, so a worker waiting in `receive()` sees its host is done
+    outbox: Option<Sender<Message>>,
+    inbox: Receiver<Message>,
+    handle: JoinHandle<Result<(), LoxError>>,
+}
+
+impl Worker {
+    /// Starts running `source` on a new thread, in an interpreter made with the options
+    /// `options` returns, since options can't be sent between threads themselves.
+    pub fn spawn<F>(source: String, options: F) -> Worker
+    where
+        F: FnOnce() -> InterpreterOptions + Send + 'static,
+    {
+        let (to_worker, from_host) = mpsc::channel();
+        let (to_host, from_worker) = mpsc::channel();
+
+        let thread = thread::Builder::new().stack_size(STACK_SIZE);
+        let handle = thread
+            .spawn(move || {
+                let mut interpreter = Interpreter::new(options());
+                interpreter.mailbox = Some(Mailbox {
+                    outbox: to_host,
+                    inbox: from_host,
+                });
+
+                interpreter.load(&source)?;
+                let state = match interpreter.run_for(Steps(u64::MAX)) {
+                    RunState::Finished => interpreter.run_event_loop(),
+                    state => state,
+                };
+                match state {
+                    RunState::Failed(error) => Err(error),
+                    _ => Ok(()),
+                }
+            })
+            .expect("worker thread to start");
+
+        Worker {
+            outbox: Some(to_worker),
+            inbox: from_worker,
+            handle,
+        }
+    }
+
+    /// Sends a message to the worker, returning false if it has already finished.
+    pub fn post(&self, message: Message) -> bool {
+        match &self.outbox {
+            Some(outbox) => outbox.send(message).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Waits for the worker's next message, or None once it has finished without sending one.
+    pub fn receive(&self) -> Option<Message> {
+        self.inbox.recv().ok()
+    }
+
+    /// Tells the worker no more messages are coming, so `receive()` returns nil once it has
+    /// taken the ones already sent.
+    pub fn close(&mut self) {
+        self.outbox = None;
+    }
+
+    /// Closes the worker's inbox and waits for it to finish.
+    pub fn join(mut self) -> Result<(), LoxError> {
+        self.close();
+        self.handle.join().expect("worker thread not to panic")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_copy_values() {
+        let inner = Value::List(new_list(vec![Value::Int(1), Value::String("two".into())]));
+        let value = Value::List(new_list(vec![inner, Value::Nil, Value::Number(0.5)]));
+
+        let message = Message::from_value(&value).unwrap();
+        assert_eq!(
+            message,
+            Message::List(vec![
+                Message::List(vec![Message::Int(1), Message::String(String::from("two"))]),
+                Message::Nil,
+                Message::Number(0.5),
+            ])
+        );
+        assert_eq!(message.into_value().to_string(), "[[1, two], nil, 0.5]");
+    }
+
+    #[test]
+    fn every_kind_of_number_is_sent() {
+        let decimal = Decimal::parse("0.10").unwrap();
+        let message = Message::from_value(&Value::Decimal(decimal)).unwrap();
+        assert_eq!(message, Message::Decimal(decimal));
+        assert_eq!(message.into_value().to_string(), "0.10");
+
+        #[cfg(feature = "bigint")]
+        {
+            let big = BigInt::from_i64(i64::MAX).add(&BigInt::from_i64(1));
+            let message = Message::from_value(&Value::BigInt(Rc::new(big.clone()))).unwrap();
+            assert_eq!(message, Message::BigInt(big));
+            assert_eq!(message.into_value().to_string(), "9223372036854775808");
+        }
+    }
+
+    #[test]
+    fn lists_that_contain_themselves_are_not_sent() {
+        let list = new_list(vec![]);
+        list.borrow_mut().push(Value::List(list.clone()));
+
+        assert_eq!(
+            Message::from_value(&Value::List(list.clone())),
+            Err(String::from("Can't send a list that contains itself."))
+        );
+
+        // the same list twice side by side is fine
+        let shared = Value::List(new_list(vec![]));
+        let value = Value::List(new_list(vec![shared.clone(), shared]));
+        assert!(Message::from_value(&value).is_ok());
+
+        list.borrow_mut().clear();
+    }
+}
@@ -1,6 +1,6 @@
 use std::{cell::RefCell, fmt::Arguments, rc::Rc, vec};
 
-use rlox::{run_file, Logger};
+use rlox::{run_file, Logger, Outcome};
 
 const TEST_FILE_DIR: &str = "test_files";
 
@@ -23,7 +23,10 @@ impl Logger for MockLogger {
 fn assert_prints(file_name: &str, expected_prints: &[String]) {
     let logger = Box::new(MockLogger::new());
     let logs = logger.logs.clone();
-    run_file(&format!("{TEST_FILE_DIR}/{file_name}"), Some(logger));
+    assert_eq!(
+        run_file(&format!("{TEST_FILE_DIR}/{file_name}"), Some(logger)),
+        Outcome::Success
+    );
 
     assert_eq!(expected_prints.len(), logs.borrow().len());
     for (index, log) in logs.borrow().iter().enumerate() {
@@ -31,6 +34,34 @@ fn assert_prints(file_name: &str, expected_prints: &[String]) {
     }
 }
 
+// the lines a golden test expects to print, from its `// expect: <line>` comments
+fn expected_prints(source: &str) -> Vec<String> {
+    source
+        .lines()
+        .filter_map(|line| line.split_once("// expect: "))
+        .map(|(_, expected)| expected.to_string())
+        .collect()
+}
+
+/// Runs every script in test_files/golden, which `rlox gen-tests` writes skeletons into,
+/// checking it prints what its comments expect.
+#[test]
+fn golden_files() {
+    let mut files = std::fs::read_dir(format!("{TEST_FILE_DIR}/golden"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "lox"))
+        .collect::<Vec<std::path::PathBuf>>();
+    files.sort();
+    assert!(!files.is_empty());
+
+    for path in files {
+        let source = std::fs::read_to_string(&path).unwrap();
+        let file_name = path.strip_prefix(TEST_FILE_DIR).unwrap();
+        assert_prints(file_name.to_str().unwrap(), &expected_prints(&source));
+    }
+}
+
 #[test]
 fn variable_declaration_and_assignment() {
     assert_prints(
@@ -44,6 +75,20 @@ fn expression_evaluation() {
     assert_prints("expression_eval.lox", &[String::from("15")])
 }
 
+#[test]
+fn equality() {
+    assert_prints(
+        "equality.lox",
+        &[
+            String::from("true"),
+            String::from("false"),
+            String::from("true"),
+            String::from("false"),
+            String::from("true"),
+        ],
+    )
+}
+
 #[test]
 fn variable_scoping() {
     assert_prints(
@@ -115,6 +160,45 @@ fn closures() {
     assert_prints("closures.lox", &[String::from("1")])
 }
 
+#[test]
+fn inline_frames() {
+    assert_prints("inline_frames.lox", &["10", "2", "610"].map(String::from))
+}
+
+#[test]
+fn date_time() {
+    assert_prints(
+        "date_time.lox",
+        &[
+            "951827696000",
+            "[2000, 2, 29, 12, 34, 56, 0, 2]",
+            "Tuesday 29 February 2000, 12:34",
+            "2000-03-01",
+            "1970-01-01T00:00:00.000Z",
+            "'2001-02-29' is not a valid date.",
+            "'29/02/2000' doesn't match the format '%Y-%m-%d'.",
+            "formatTime() expects a timestamp within 100 million days of 1970.",
+            "dateParts() expects a timestamp within 100 million days of 1970.",
+        ]
+        .map(String::from),
+    )
+}
+
+#[test]
+fn super_lookup() {
+    assert_prints(
+        "super_lookup.lox",
+        &[
+            "leaf of base",
+            "leaf named",
+            "leaf of patched base",
+            "leaf of middle",
+            "Undefined property missing.",
+        ]
+        .map(String::from),
+    )
+}
+
 #[test]
 fn print_class_name() {
     assert_prints("print_class_name.lox", &[String::from("DevonshireCream")])
@@ -178,3 +262,1520 @@ fn super_class_methods() {
         ],
     )
 }
+
+#[test]
+fn print_multiple_values() {
+    assert_prints(
+        "print_multiple.lox",
+        &[
+            String::from("1 two 3"),
+            String::from("single"),
+            String::from("nil true"),
+        ],
+    )
+}
+
+#[test]
+fn setters() {
+    assert_prints(
+        "setters.lox",
+        &[
+            String::from("100"),
+            String::from("32"),
+            String::from("0"),
+            String::from("plain method named set"),
+            String::from("name: Ada"),
+        ],
+    )
+}
+
+#[test]
+fn traits() {
+    assert_prints(
+        "traits.lox",
+        &[
+            String::from("Donald swims"),
+            String::from("Donald flies"),
+            String::from("waddle"),
+            String::from("quack"),
+            String::from("Swimmer"),
+        ],
+    )
+}
+
+#[test]
+fn prelude_exposes_embedding_types() {
+    use rlox::prelude::*;
+
+    let _interpreter = Interpreter::new(InterpreterOptions {
+        logger: Some(Box::new(MockLogger::new())),
+        ..Default::default()
+    });
+
+    let error = LoxError {
+        message: String::from("Operands must be numbers."),
+        line: 3,
+    };
+    assert_eq!(error.to_string(), "Operands must be numbers.\n[line 3]");
+    assert_eq!(Value::Number(2.0).to_string(), "2");
+    assert_eq!(ParserOptions::default().max_arguments, 255);
+}
+
+#[test]
+fn try_catch_finally() {
+    assert_prints(
+        "try_catch.lox",
+        &[
+            String::from("before"),
+            String::from("caught: Operands must be a number."),
+            String::from("body"),
+            String::from("finally"),
+            String::from("cleanup"),
+            String::from("returned"),
+            String::from("inner finally"),
+            String::from("Can only call functions and classes."),
+            String::from("after"),
+        ],
+    )
+}
+
+#[test]
+fn constructors() {
+    assert_prints(
+        "constructors.lox",
+        &[
+            String::from("3"),
+            String::from("1 2 3"),
+            String::from("6"),
+            String::from("Point(x, y) expects 2 arguments but got 1."),
+            String::from("Empty() expects 0 arguments but got 1."),
+            String::from("Expected 2 arguments but got 1."),
+        ],
+    )
+}
+
+#[test]
+fn throw_statements() {
+    assert_prints(
+        "throw.lox",
+        &[
+            String::from("2"),
+            String::from("caught division by zero"),
+            String::from("validated"),
+            String::from("ValidationError instance"),
+            String::from("name"),
+            String::from("2"),
+        ],
+    )
+}
+
+#[test]
+fn instance_aliasing() {
+    assert_prints(
+        "instance_aliasing.lox",
+        &[
+            String::from("1"),
+            String::from("2"),
+            String::from("true"),
+            String::from("false"),
+            String::from("true"),
+            String::from("13"),
+            String::from("14"),
+            String::from("true"),
+            String::from("true"),
+        ],
+    )
+}
+
+#[test]
+fn variadic_functions() {
+    assert_prints(
+        "variadic_functions.lox",
+        &[
+            String::from("info []"),
+            String::from("warn [1, two, nil]"),
+            String::from("[]"),
+            String::from("[1, 2, 3]"),
+            String::from("[a, b]"),
+            String::from("Expected at least 1 arguments but got 0."),
+            String::from("Logger(prefix, ...tags) expects at least 1 arguments but got 0."),
+        ],
+    )
+}
+
+#[test]
+fn upvalues() {
+    assert_prints(
+        "upvalues.lox",
+        &[
+            String::from("2"),
+            String::from("10"),
+            String::from("outer"),
+            String::from("done"),
+            String::from("derived of base"),
+        ],
+    )
+}
+
+#[test]
+fn increment_and_decrement() {
+    assert_prints(
+        "increment_decrement.lox",
+        &[
+            String::from("0"),
+            String::from("1"),
+            String::from("2"),
+            String::from("2"),
+            String::from("0"),
+            String::from("10"),
+            String::from("12"),
+            String::from("12"),
+            String::from("2"),
+            String::from("0"),
+            String::from("1"),
+            String::from("2"),
+            String::from("Operands must be a number."),
+        ],
+    )
+}
+
+#[test]
+fn coroutines() {
+    assert_prints(
+        "coroutines.lox",
+        &[
+            String::from("suspended"),
+            String::from("1"),
+            String::from("2"),
+            String::from("done"),
+            String::from("dead"),
+            String::from("Cannot resume a dead coroutine."),
+            String::from("15"),
+            String::from("ready"),
+            String::from("got hello"),
+            String::from("nil"),
+            String::from("dead"),
+            String::from("running"),
+            String::from("Cannot resume a running coroutine."),
+            String::from("broken"),
+            String::from("dead"),
+            String::from("Can only yield from the body of a coroutine."),
+        ],
+    )
+}
+
+#[test]
+fn string_splitting_and_iteration() {
+    assert_prints(
+        "strings.lox",
+        &[
+            String::from("[red, green, blue]"),
+            String::from("red | green | blue"),
+            String::from("a-b--c"),
+            String::from("red"),
+            String::from("green"),
+            String::from("blue"),
+            String::from("olléh"),
+            String::from("[a, b, c]"),
+            String::from("2"),
+            String::from("3 [न][म][स्ते]"),
+            String::from("1"),
+            String::from("x"),
+            String::from("o"),
+            String::from("k"),
+            String::from("héllo"),
+            String::from("or!"),
+            String::from("true"),
+            String::from("Substring range 5..13 is out of bounds."),
+            String::from("Can only iterate over lists and strings."),
+            String::from("join() expects a list and a separator string."),
+        ],
+    )
+}
+
+#[test]
+fn doc_comments() {
+    assert_prints(
+        "doc_comments.lox",
+        &[
+            String::from("Adds two numbers.\nBoth must be numbers."),
+            String::from("nil"),
+            String::from("A point on the plane."),
+            String::from("The distance from the origin, squared."),
+            String::from(
+                "Milliseconds since the Unix epoch, or in deterministic mode on a fake clock that\n\
+                 starts at 0.",
+            ),
+            String::from("nil"),
+        ],
+    )
+}
+
+#[test]
+fn list_methods() {
+    assert_prints(
+        "list_methods.lox",
+        &[
+            String::from("[10]"),
+            String::from("[6, 8, 10]"),
+            String::from("[1, 2, 3, 4, 5]"),
+            String::from("[5, 3, 1, 4, 2]"),
+            String::from("[apple, fig, pear]"),
+            String::from("[]"),
+            String::from("Can only sort lists of all numbers or all strings."),
+            String::from("Can't call 'map' on nil."),
+            String::from("Undefined property reverse."),
+            String::from("Can only call functions and classes."),
+            String::from("3 [1, 2] 2"),
+            String::from("[first, 1, 2, last]"),
+            String::from("1 [first, 2, last]"),
+            String::from("15"),
+            String::from("[5, 4, 3, 2, 1]"),
+            String::from("[fig, kiwi, banana]"),
+            String::from("[3, 1] []"),
+            String::from("[5, 3, 1, 4, 2, six] 5"),
+            String::from("Can't pop from an empty list."),
+            String::from("Index 5 is out of bounds for removeAt()."),
+            String::from("Slice range 3..9 is out of bounds."),
+            String::from("A sort() comparator must return a number."),
+            String::from("40 780"),
+            String::from("[1, [...]] 1-[1, [...]]"),
+        ],
+    )
+}
+
+#[test]
+fn built_statements_splice_into_parsed_ones() {
+    use rlox::prelude::*;
+
+    let logger = Box::new(MockLogger::new());
+    let logs = logger.logs.clone();
+    let mut interpreter = Interpreter::new(InterpreterOptions {
+        logger: Some(logger),
+        ..Default::default()
+    });
+
+    let mut statements = interpreter.parse("var x = 3;").unwrap();
+
+    let mut ast = AstBuilder::new();
+    ast.at_line(2);
+    statements.push(ast.function(
+        "square",
+        &["n"],
+        vec![ast.return_value(Some(ast.binary(ast.variable("n"), "*", ast.variable("n"))))],
+    ));
+    ast.at_line(3);
+    let call = ast.call(ast.variable("square"), vec![ast.variable("x")]);
+    statements.push(ast.print(vec![call]));
+    statements.push(ast.print(vec![ast.binary(ast.string("x = "), "+", ast.string("3"))]));
+
+    interpreter.load_statements(statements).unwrap();
+    assert_eq!(interpreter.run_for(Steps(u64::MAX)), RunState::Finished);
+    assert_eq!(*logs.borrow(), ["9", "x = 3"]);
+}
+
+// overflowing makes a big integer instead with the feature
+#[cfg(not(feature = "bigint"))]
+#[test]
+fn integers() {
+    assert_prints(
+        "integers.lox",
+        &[
+            String::from("10"),
+            String::from("21"),
+            String::from("3.5"),
+            String::from("7.5"),
+            String::from("5"),
+            String::from("true"),
+            String::from("true"),
+            String::from("-9223372036854775808"),
+            String::from("1"),
+            String::from("1.5"),
+            String::from("Integer overflow."),
+            String::from("Integer overflow."),
+            String::from("inte"),
+        ],
+    )
+}
+
+#[test]
+fn monkey_patching() {
+    assert_prints(
+        "monkey_patching.lox",
+        &[
+            String::from("Hello, Ada"),
+            String::from("Hello, Ada"),
+            String::from("HEY Ada"),
+            String::from("HEY Ada"),
+            String::from("HEY Bob"),
+            String::from("Hello, Ada"),
+            String::from("*waves*"),
+            String::from("HEY Ada"),
+            String::from("*waves*"),
+            String::from("Can only patch methods with functions."),
+            String::from("Can't patch an initializer."),
+        ],
+    )
+}
+
+#[test]
+fn imports() {
+    assert_prints(
+        "imports.lox",
+        &[
+            String::from("loading greeting"),
+            String::from("Hello, modules"),
+            String::from("9"),
+        ],
+    )
+}
+
+struct BundledModules(Vec<(&'static str, &'static str)>);
+impl rlox::ImportResolver for BundledModules {
+    fn resolve(&mut self, path: &str, _importer: Option<&str>) -> Result<String, String> {
+        match self.0.iter().find(|(name, _)| *name == path) {
+            Some((_, source)) => Ok(source.to_string()),
+            None => Err(String::from("not in the bundle.")),
+        }
+    }
+}
+
+#[test]
+fn imports_from_a_host_resolver() {
+    use rlox::prelude::*;
+
+    let logger = Box::new(MockLogger::new());
+    let logs = logger.logs.clone();
+    let mut interpreter = Interpreter::new(InterpreterOptions {
+        logger: Some(logger),
+        import_resolver: Some(Box::new(BundledModules(vec![
+            ("math", "fun square(n) { return n * n; }"),
+            ("app", "import \"math\"; print square(4);"),
+        ]))),
+        ..Default::default()
+    });
+
+    interpreter
+        .load(
+            "import \"app\";
+             import \"math\";
+             try {
+               import \"missing\";
+             } catch (error) {
+               print error;
+             }",
+        )
+        .unwrap();
+    assert_eq!(interpreter.run_for(Steps(u64::MAX)), RunState::Finished);
+    assert_eq!(
+        *logs.borrow(),
+        ["16", "Can't import 'missing': not in the bundle."]
+    );
+}
+
+#[test]
+fn imports_from_an_in_memory_file_system() {
+    use rlox::{prelude::*, MemoryFileSystem};
+
+    let file_system = MemoryFileSystem::new()
+        .with_file("lib/strings.lox", "import \"shout.lox\";")
+        .with_file("lib/shout.lox", "fun shout(text) { return text + \"!\"; }");
+
+    let logger = Box::new(MockLogger::new());
+    let logs = logger.logs.clone();
+    let mut interpreter = Interpreter::new(InterpreterOptions {
+        logger: Some(logger),
+        file_system: Some(Rc::new(file_system)),
+        ..Default::default()
+    });
+
+    interpreter
+        .load("import \"lib/strings.lox\"; print shout(\"hello\");")
+        .unwrap();
+    assert_eq!(interpreter.run_for(Steps(u64::MAX)), RunState::Finished);
+    assert_eq!(*logs.borrow(), ["hello!"]);
+}
+
+#[test]
+fn workers_exchange_messages() {
+    use rlox::{prelude::*, Message, Worker};
+
+    let mut worker = Worker::spawn(
+        String::from(
+            "fun list(...items) { return items; }
+             var message = receive();
+             while (message != nil) {
+               post(message * 2);
+               message = receive();
+             }
+             post(list(\"done\", 1.5));
+             try {
+               post(clock);
+             } catch (error) {
+               post(error);
+             }",
+        ),
+        InterpreterOptions::default,
+    );
+
+    for number in 1..=3 {
+        assert!(worker.post(Message::Int(number)));
+    }
+    for number in [2, 4, 6] {
+        assert_eq!(worker.receive(), Some(Message::Int(number)));
+    }
+
+    worker.close();
+    assert!(!worker.post(Message::Int(4)));
+    assert_eq!(
+        std::iter::from_fn(|| worker.receive()).collect::<Vec<Message>>(),
+        [
+            Message::List(vec![
+                Message::String(String::from("done")),
+                Message::Number(1.5)
+            ]),
+            Message::String(String::from(
+                "Can only send nil, booleans, numbers, strings and lists of them."
+            )),
+        ]
+    );
+    assert_eq!(worker.join(), Ok(()));
+}
+
+#[test]
+fn deep_copy() {
+    assert_prints(
+        "deep_copy.lox",
+        &[
+            String::from("1"),
+            String::from("3"),
+            String::from("3"),
+            String::from("a"),
+            String::from("b"),
+            String::from("Can't copy a coroutine."),
+        ],
+    );
+}
+
+#[test]
+fn abstract_methods() {
+    assert_prints(
+        "abstract_methods.lox",
+        &[
+            String::from("Can't instantiate Shape, which doesn't implement area, perimeter."),
+            String::from("Can't instantiate Square, which doesn't implement perimeter."),
+            String::from("shape"),
+            String::from("9"),
+            String::from("12"),
+            String::from("8"),
+        ],
+    );
+}
+
+#[test]
+fn failures_are_outcomes_rather_than_exits() {
+    let path = std::env::temp_dir().join("rlox_failing_script.lox");
+    let path = path.to_str().expect("temp path to be valid UTF-8");
+
+    std::fs::write(path, "print 1 +;").unwrap();
+    assert_eq!(run_file(path, None), Outcome::StaticError);
+    std::fs::write(path, "print nil + 1;").unwrap();
+    assert_eq!(run_file(path, None), Outcome::RuntimeError);
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(run_file(path, None), Outcome::UnreadableFile);
+}
+
+#[test]
+fn primitive_methods() {
+    assert_prints(
+        "primitive_methods.lox",
+        &[
+            "5",
+            "5",
+            "a b c",
+            "l-o-x",
+            "inter",
+            "padded|",
+            "LOXlox",
+            "true",
+            "3",
+            "4",
+            "-3",
+            "7",
+            "4",
+            "5",
+            "Only instances have properties.",
+        ]
+        .map(String::from),
+    );
+}
+
+#[test]
+fn natives_call_back_into_lox() {
+    // as deep as the default call depth goes, which needs the interpreter's stack size
+    let test = std::thread::Builder::new().stack_size(rlox::STACK_SIZE);
+    let test = test.spawn(|| {
+        assert_prints(
+            "reentrancy.lox",
+            &[
+                "900",
+                "Stack overflow.",
+                "900",
+                "[] [true] [] [true]",
+                "4",
+                "shape",
+            ]
+            .map(String::from),
+        )
+    });
+    test.unwrap().join().unwrap();
+}
+
+#[test]
+fn string_repetition() {
+    assert_prints(
+        "string_repetition.lox",
+        &[
+            "ababab",
+            "xyxy",
+            "|",
+            "==",
+            "Can't repeat a string a negative number of times.",
+            "Can only repeat a string a whole number of times.",
+            "Can only repeat a string a whole number of times.",
+            "Repeated string is too long.",
+        ]
+        .map(String::from),
+    );
+}
+
+#[test]
+fn equality_printing_and_plus_follow_the_semantics_options() {
+    use rlox::prelude::*;
+
+    let program = "var nan = 0 / 0;
+                   print nan == nan, -0.0 == 0, -0.0;
+                   try {
+                     print \"zero: \" + -0.0;
+                   } catch (error) {
+                     print error;
+                   }";
+    let run = |semantics: Semantics| {
+        let logger = Box::new(MockLogger::new());
+        let logs = logger.logs.clone();
+        let mut interpreter = Interpreter::new(InterpreterOptions {
+            logger: Some(logger),
+            semantics,
+            ..Default::default()
+        });
+        interpreter.load(program).unwrap();
+        assert_eq!(interpreter.run_for(Steps(u64::MAX)), RunState::Finished);
+        let printed = logs.borrow().join("\n");
+        printed
+    };
+
+    assert_eq!(
+        run(Semantics::default()),
+        "false true -0\nOperands must be numbers."
+    );
+    assert_eq!(
+        run(Semantics {
+            nan_equals_itself: true,
+            unsigned_zero: true,
+            string_coercion: true,
+            ..Default::default()
+        }),
+        "true true 0\nzero: 0"
+    );
+}
+
+#[test]
+fn decimals() {
+    assert_prints(
+        "decimals.lox",
+        &[
+            "0.30000000000000004",
+            "0.3",
+            "true",
+            "59.97",
+            "59.98",
+            "2.50",
+            "0.666666666666666667",
+            "true",
+            "3 -3 2.5",
+            "true true",
+            "-0.05",
+            "7",
+            "Can't divide a decimal by zero.",
+            "Can't convert 'ten' to a decimal.",
+            "100000 0.00250 -150",
+            "13780.612339822270184118",
+        ]
+        .map(String::from),
+    );
+}
+
+#[test]
+fn decimal_literals_make_decimals() {
+    use rlox::prelude::*;
+
+    let logger = Box::new(MockLogger::new());
+    let logs = logger.logs.clone();
+    let mut interpreter = Interpreter::new(InterpreterOptions {
+        logger: Some(logger),
+        semantics: Semantics {
+            decimal_literals: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    interpreter
+        .load("print 0.1 + 0.2, 1.10 * 3, 7 / 2;")
+        .unwrap();
+    assert_eq!(interpreter.run_for(Steps(u64::MAX)), RunState::Finished);
+    assert_eq!(logs.borrow().join("\n"), "0.3 3.3 3.5");
+}
+
+#[test]
+fn math() {
+    assert_prints(
+        "math.lox",
+        &[
+            "4 1.4142135623730951",
+            "3 2.5 1.50",
+            "2 3 -3 7",
+            "1024 0.25 1.4142135623730951 1.21",
+            "0 1 1",
+            "3.14",
+            "sqrt() expects a number.",
+            "pow() expects a base and an exponent that are numbers.",
+            "sqrt() isn't defined for -1.",
+            "log() isn't defined for 0.",
+            "pow() isn't defined for -8 to the power 0.5.",
+            "0 0 true",
+        ]
+        .map(String::from),
+    );
+}
+
+#[test]
+fn collation() {
+    assert_prints(
+        "collation.lox",
+        &[
+            "[Bo, Zoë, adam, Åsa, Émile, Ödön]",
+            "[adam, Åsa, Bo, Émile, Ödön, Zoë]",
+            "[adam, Bo, Émile, Zoë, Åsa, Ödön]",
+            "1 -1 0",
+            "sortStrings() expects a list of strings and a locale string.",
+            match cfg!(feature = "icu") {
+                true => "Unsupported locale 'xx-nonsense'.",
+                false => {
+                    "Unsupported locale 'xx-nonsense'; only Latin-script languages are supported without the icu feature."
+                }
+            },
+        ]
+        .map(String::from),
+    );
+}
+
+#[test]
+fn read_line_reads_from_the_input_option() {
+    use rlox::prelude::*;
+
+    struct Lines(Vec<&'static str>);
+    impl LineSource for Lines {
+        fn read_line(&mut self, _: &str) -> Option<String> {
+            match self.0.is_empty() {
+                true => None,
+                false => Some(String::from(self.0.remove(0))),
+            }
+        }
+    }
+
+    let logger = Box::new(MockLogger::new());
+    let logs = logger.logs.clone();
+    let mut interpreter = Interpreter::new(InterpreterOptions {
+        logger: Some(logger),
+        input: Some(Box::new(Lines(vec!["50", "25", "37"]))),
+        ..Default::default()
+    });
+    let program = "var secret = 37;
+                   var guess = readLine();
+                   while (guess != nil) {
+                     if (num(guess) < secret) print \"higher\";
+                     if (num(guess) > secret) print \"lower\";
+                     if (num(guess) == secret) print \"got it\";
+                     guess = readLine();
+                   }
+                   print readLine();";
+    interpreter.load(program).unwrap();
+    assert_eq!(interpreter.run_for(Steps(u64::MAX)), RunState::Finished);
+    assert_eq!(*logs.borrow(), ["lower", "higher", "got it", "nil"]);
+}
+
+#[test]
+fn file_natives_use_the_file_system_option() {
+    use rlox::{prelude::*, MemoryFileSystem};
+
+    let files = Rc::new(MemoryFileSystem::new().with_file("scores.txt", "3,5"));
+    let logger = Box::new(MockLogger::new());
+    let logs = logger.logs.clone();
+    let mut interpreter = Interpreter::new(InterpreterOptions {
+        logger: Some(logger),
+        file_system: Some(files.clone()),
+        ..Default::default()
+    });
+    let program = "var total = 0;
+                   for (var score in split(readFile(\"scores.txt\"), \",\")) {
+                     total = total + num(score);
+                   }
+                   writeFile(\"total.txt\", str(total));
+                   appendFile(\"total.txt\", \" points\");
+                   print readFile(\"total.txt\"), fileExists(\"total.txt\"), fileExists(\"nope\");
+                   try {
+                     readFile(\"nope\");
+                   } catch (error) {
+                     print error;
+                   }";
+    interpreter.load(program).unwrap();
+    assert_eq!(interpreter.run_for(Steps(u64::MAX)), RunState::Finished);
+    assert_eq!(
+        *logs.borrow(),
+        ["8 points true false", "Can't read 'nope': No such file"]
+    );
+    assert_eq!(files.read_to_string("total.txt").unwrap(), "8 points");
+}
+
+#[test]
+fn unicode() {
+    assert_prints(
+        "unicode.lox",
+        &[
+            "5 4",
+            "true false",
+            "true",
+            "4 4",
+            "true",
+            "3 6",
+            "4 true",
+            "6 2",
+            "Unknown normalization form 'NFKC'; expected \"NFC\" or \"NFD\".",
+        ]
+        .map(String::from),
+    );
+}
+
+#[test]
+fn instance_fields() {
+    assert_prints(
+        "instance_fields.lox",
+        &[
+            "[x, y]",
+            "1",
+            "3",
+            "ABC",
+            "[label, x, y]",
+            "{\"label\": {\"x\": 3, \"y\": 4}, \"x\": 1, \"y\": 2}",
+            "Undefined property missing.",
+            "instanceFields() expects an instance.",
+        ]
+        .map(String::from),
+    );
+}
+
+#[cfg(feature = "bigint")]
+#[test]
+fn bigint() {
+    assert_prints(
+        "bigint.lox",
+        &[
+            "2432902008176640000",
+            "15511210043330985984000000",
+            "0",
+            "9223372036854775808",
+            "true",
+            "-9223372036854775808",
+            "true",
+            "true",
+            "9223372036854775807",
+            "123456789012345678901234567891",
+            "true",
+            "true",
+            "4722366482869645213695",
+            "18ee90ff6c373e0ee4e3f0ad2",
+            "-byw97um9s91dlz68tsi",
+            "Repeated string is too long.",
+            "Can't repeat a string a negative number of times.",
+        ]
+        .map(String::from),
+    );
+}
+
+#[test]
+fn conversions() {
+    assert_prints(
+        "conversions.lox",
+        &[
+            "count: 3",
+            "1.5niltrue",
+            "43",
+            "5",
+            "1000",
+            "Operands must be numbers.",
+            "Can't convert 'twelve' to a number.",
+        ]
+        .map(String::from),
+    );
+}
+
+#[test]
+fn evaluation_order() {
+    assert_prints(
+        "evaluation_order.lox",
+        &[
+            "left",
+            "right",
+            "3",
+            "callee",
+            "first",
+            "second",
+            "3",
+            "a",
+            "b",
+            "1 2",
+            "object",
+            "value",
+            "target",
+            "6",
+            "left",
+            "right",
+            "Operands must be numbers.",
+            "callee",
+            "only",
+            "Expected 2 arguments but got 1.",
+            "object",
+            "value",
+            "Only instances have fields.",
+            "left",
+            "left",
+        ]
+        .map(String::from),
+    );
+}
+
+#[test]
+fn decorators() {
+    assert_prints(
+        "decorators.lox",
+        &[
+            "832040",
+            "31",
+            "a:b:1",
+            "hi from handler",
+            "1",
+            "A method's decorators must make a function.",
+        ]
+        .map(String::from),
+    );
+}
+
+#[test]
+fn timeouts() {
+    assert_prints(
+        "timeouts.lox",
+        &[
+            "done",
+            "Timed out after 20 ms.",
+            "inner: Timed out after 10 ms.",
+            "outer finished",
+            "outer: Timed out after 20 ms.",
+            "withTimeout() expects a non-negative number of milliseconds.",
+        ]
+        .map(String::from),
+    );
+}
+
+#[test]
+fn event_loop() {
+    assert_prints(
+        "event_loop.lox",
+        &[
+            "true",
+            "script finished",
+            "timeout after 0 ms",
+            "tick 1",
+            "tick 2",
+            "tick 3",
+            "timeout after 30 ms",
+        ]
+        .map(String::from),
+    );
+}
+
+#[test]
+fn interrupts_stop_a_running_program() {
+    use rlox::prelude::*;
+    use std::{thread, time::Duration};
+
+    let logger = Box::new(MockLogger::new());
+    let logs = logger.logs.clone();
+    let mut interpreter = Interpreter::new(InterpreterOptions {
+        logger: Some(logger),
+        ..Default::default()
+    });
+
+    let handle = interpreter.interrupt_handle();
+    let interrupter = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        handle.interrupt();
+    });
+
+    // neither the catch nor the finally block runs
+    interpreter
+        .load(
+            "print \"started\";
+             try {
+               while (true) {}
+             } catch (error) {
+               print error;
+             } finally {
+               print \"finally\";
+             }",
+        )
+        .unwrap();
+    assert_eq!(interpreter.run_for(Steps(u64::MAX)), RunState::Interrupted);
+    interrupter.join().unwrap();
+
+    // the interpreter carries on with the next program, and so does an interrupted event loop
+    interpreter
+        .load("fun tick() { print \"tick\"; } setInterval(tick, 5);")
+        .unwrap();
+    assert_eq!(interpreter.run_for(Steps(u64::MAX)), RunState::Finished);
+    let handle = interpreter.interrupt_handle();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(30));
+        handle.interrupt();
+    });
+    assert_eq!(interpreter.run_event_loop(), RunState::Interrupted);
+
+    assert_eq!(logs.borrow()[0], "started");
+    assert!(logs.borrow()[1..].iter().all(|log| log == "tick"));
+}
+
+#[test]
+fn diagnostics_render_as_text_html_or_json() {
+    use rlox::{prelude::*, render, Format, Severity};
+
+    let source = "fun f() {\n  var unused = 1;\n  return;\n  print \"never\";\n}";
+    let mut interpreter = Interpreter::new(InterpreterOptions::default());
+    let diagnostics = interpreter.diagnose(source);
+    assert!(diagnostics
+        .iter()
+        .all(|diagnostic| diagnostic.severity == Severity::Warning));
+
+    assert_eq!(
+        render(Format::Text, source, &diagnostics),
+        "[line 3] Warning return: Unreachable code after 'return'. [unreachable_code]\n\
+         [line 2] Warning unused: Variable 'unused' is never read. [unused_variable]\n"
+    );
+
+    let html = render(Format::Html, source, &diagnostics);
+    assert!(html.starts_with("<pre class=\"lox\">"));
+    assert!(html.contains(
+        "<span class=\"line warning\" id=\"L3\"><span class=\"line-number\">3</span>  \
+         <span class=\"keyword warning\">return</span>;</span>"
+    ));
+    assert!(html.contains(
+        "<span class=\"diagnostic warning\">[line 2] Warning unused: \
+         Variable 'unused' is never read. [unused_variable]</span>"
+    ));
+
+    assert_eq!(
+        render(Format::Json, source, &diagnostics),
+        "{\"code\":\"unreachable_code\",\"severity\":\"warning\",\
+         \"message\":\"Unreachable code after 'return'.\",\"file\":null,\
+         \"span\":{\"line\":3,\"column\":3,\"end_column\":9}}\n\
+         {\"code\":\"unused_variable\",\"severity\":\"warning\",\
+         \"message\":\"Variable 'unused' is never read.\",\"file\":null,\
+         \"span\":{\"line\":2,\"column\":7,\"end_column\":13}}\n"
+    );
+}
+
+#[test]
+fn assert() {
+    assert_prints(
+        "assert.lox",
+        &[
+            "passed",
+            "Assertion failed: 2 squared is 5",
+            "Assertion failed: 42",
+        ]
+        .map(String::from),
+    );
+
+    use rlox::prelude::*;
+    let mut interpreter = Interpreter::new(InterpreterOptions {
+        logger: Some(Box::new(MockLogger::new())),
+        ..Default::default()
+    });
+    interpreter
+        .load("var total = 1 + 1;\nassert(total == 3, \"sums add up\");")
+        .unwrap();
+    assert_eq!(
+        interpreter.run_for(Steps(u64::MAX)),
+        RunState::Failed(LoxError {
+            message: String::from("Assertion failed: sums add up"),
+            line: 2,
+        })
+    );
+}
+
+#[test]
+fn deterministic_runs_print_the_same() {
+    use rlox::prelude::*;
+
+    let program = "print clock();
+                   var ticks = 0;
+                   var id;
+                   fun tick() {
+                     ticks++;
+                     print \"tick\", clock();
+                     if (ticks == 3) clearInterval(id);
+                   }
+                   fun done() { print \"done\", clock(), clockMonotonic(); }
+                   id = setInterval(tick, 250);
+                   setTimeout(done, 10000);
+                   fun slow() { return 1; }
+                   try {
+                     withTimeout(100, slow);
+                   } catch (e) {
+                     print e;
+                   }";
+    let run = || {
+        let logger = Box::new(MockLogger::new());
+        let logs = logger.logs.clone();
+        let mut interpreter = Interpreter::new(InterpreterOptions {
+            logger: Some(logger),
+            deterministic: true,
+            ..Default::default()
+        });
+        interpreter.load(program).unwrap();
+        assert_eq!(interpreter.run_for(Steps(u64::MAX)), RunState::Finished);
+        assert_eq!(interpreter.run_event_loop(), RunState::Finished);
+        logs.take()
+    };
+
+    // the ten second timeout doesn't take ten seconds
+    let started = std::time::Instant::now();
+    let first = run();
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    assert_eq!(
+        first,
+        [
+            "0",
+            "withTimeout() is disabled in deterministic mode.",
+            "tick 250",
+            "tick 500",
+            "tick 750",
+            "done 10000 10",
+        ]
+    );
+    assert_eq!(run(), first);
+}
+
+#[test]
+fn environment_variable_natives() {
+    use rlox::prelude::*;
+
+    let logger = Box::new(MockLogger::new());
+    let logs = logger.logs.clone();
+    let mut interpreter = Interpreter::new(InterpreterOptions {
+        logger: Some(logger),
+        ..Default::default()
+    });
+    interpreter
+        .load(
+            "print getenv(\"RLOX_SURELY_UNSET\");
+             setenv(\"RLOX_MODE\", \"debug\");
+             print getenv(\"RLOX_MODE\");
+             setenv(\"RLOX_MODE\", nil);
+             print getenv(\"RLOX_MODE\");
+             setenv(\"PATH\", nil);
+             print getenv(\"PATH\");",
+        )
+        .unwrap();
+    assert_eq!(interpreter.run_for(Steps(u64::MAX)), RunState::Finished);
+    assert_eq!(*logs.borrow(), ["nil", "debug", "nil", "nil"]);
+    // the process's own environment is untouched
+    assert_eq!(std::env::var("RLOX_MODE").ok(), None);
+
+    let mut deterministic = Interpreter::new(InterpreterOptions {
+        logger: Some(Box::new(MockLogger::new())),
+        deterministic: true,
+        ..Default::default()
+    });
+    deterministic
+        .load("assert(getenv(\"PATH\") == nil, \"PATH is hidden\");")
+        .unwrap();
+    assert_eq!(deterministic.run_for(Steps(u64::MAX)), RunState::Finished);
+
+    let mut sandboxed = Interpreter::new(InterpreterOptions {
+        capabilities: Capabilities::default().grant_by_default(&[NativeGroup::Strings]),
+        ..Default::default()
+    });
+    assert_eq!(
+        sandboxed.load("print getenv(\"HOME\");"),
+        Err(LoxError {
+            message: String::from(
+                "'getenv' is a 'env' native, which this program hasn't been granted."
+            ),
+            line: 1,
+        })
+    );
+}
+
+#[test]
+fn http_natives_need_the_net_group() {
+    use rlox::prelude::*;
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+    };
+
+    // answers each request with its method and body
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = std::thread::spawn(move || {
+        for _ in 0..2 {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = vec![0; 1024];
+            let length = stream.read(&mut request).unwrap();
+            let request = String::from_utf8_lossy(&request[..length]).into_owned();
+            let method = request.split(' ').next().unwrap().to_string();
+            let body = request.split("\r\n\r\n").nth(1).unwrap_or("");
+            let reply = format!("{method} {body}");
+            write!(
+                stream,
+                "HTTP/1.1 201 Created\r\nContent-Type: text/plain\r\nX-Tag: a\r\nX-Tag: b\r\n\
+                 Content-Length: {}\r\n\r\n{reply}",
+                reply.len()
+            )
+            .unwrap();
+        }
+    });
+
+    let logger = Box::new(MockLogger::new());
+    let logs = logger.logs.clone();
+    let mut interpreter = Interpreter::new(InterpreterOptions {
+        logger: Some(logger),
+        capabilities: Capabilities::default().grant_by_default(&NativeGroup::ALL),
+        ..Default::default()
+    });
+    interpreter
+        .load(&format!(
+            "var response = httpGet(\"http://127.0.0.1:{port}/hello\");
+             print response.status, response.body;
+             print getattr(response.headers, \"content-type\"), response.headers;
+             print getattr(response.headers, \"x-tag\");
+             print httpPost(\"http://127.0.0.1:{port}/echo\", \"ping\").body;
+             try {{
+               httpGet(\"https://example.com\");
+             }} catch (e) {{
+               print e;
+             }}"
+        ))
+        .unwrap();
+    assert_eq!(interpreter.run_for(Steps(u64::MAX)), RunState::Finished);
+    server.join().unwrap();
+    assert_eq!(
+        *logs.borrow(),
+        [
+            "201 GET ",
+            "text/plain HttpHeaders instance",
+            "a, b",
+            "POST ping",
+            "Can't request 'https://example.com': https isn't supported, only http.",
+        ]
+    );
+
+    let mut sandboxed = Interpreter::new(InterpreterOptions::default());
+    assert_eq!(
+        sandboxed.load("httpGet(\"http://127.0.0.1/\");"),
+        Err(LoxError {
+            message: String::from(
+                "'httpGet' is a 'net' native, which this program hasn't been granted."
+            ),
+            line: 1,
+        })
+    );
+}
+
+#[test]
+fn timeouts_stop_http_requests_the_server_never_answers() {
+    use rlox::prelude::*;
+    use std::{io::Read, net::TcpListener};
+
+    // accepts the connection, then reads until the client gives up
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = vec![];
+        let _ = stream.read_to_end(&mut request);
+    });
+
+    let logger = Box::new(MockLogger::new());
+    let logs = logger.logs.clone();
+    let mut interpreter = Interpreter::new(InterpreterOptions {
+        logger: Some(logger),
+        capabilities: Capabilities::default().grant_by_default(&NativeGroup::ALL),
+        ..Default::default()
+    });
+    interpreter
+        .load(&format!(
+            "fun fetch() {{
+               httpGet(\"http://127.0.0.1:{port}/slow\");
+             }}
+             try {{
+               withTimeout(100, fetch);
+             }} catch (e) {{
+               print e;
+             }}"
+        ))
+        .unwrap();
+    assert_eq!(interpreter.run_for(Steps(u64::MAX)), RunState::Finished);
+    server.join().unwrap();
+    assert_eq!(*logs.borrow(), ["Timed out after 100 ms."]);
+}
+
+#[test]
+fn environment_stats_are_opt_in() {
+    use rlox::{prelude::*, EnvStats};
+
+    let run = |interpreter: &mut Interpreter| {
+        interpreter
+            .load(
+                "var total = 0;
+                 for (var i = 0; i < 3; i++) {
+                   { total = total + i; }
+                 }",
+            )
+            .unwrap();
+        assert_eq!(interpreter.run_for(Steps(u64::MAX)), RunState::Finished);
+    };
+    let mut interpreter = Interpreter::new(InterpreterOptions::default());
+
+    run(&mut interpreter);
+    assert_eq!(EnvStats::stop(), None);
+
+    EnvStats::start();
+    run(&mut interpreter);
+    let stats = EnvStats::stop().unwrap();
+    assert!(stats.allocations > 0);
+    assert!(stats.probes >= stats.local_accesses() + stats.name_lookups);
+    // `i` is read from inside the innermost block
+    assert!(stats.depths[1] > 0);
+    // the global `total` is read and assigned once per iteration
+    assert_eq!(stats.name_lookups, 6);
+    assert!(stats.to_string().starts_with("Environment stats:\n"));
+}
+
+#[test]
+fn finished_environments_and_instances_are_recycled() {
+    use rlox::{prelude::*, PoolStats};
+
+    let mut interpreter = Interpreter::new(InterpreterOptions::default());
+    interpreter
+        .load(
+            "class Point { init(x) { this.x = x; } }
+             fun twice(x) { return Point(x * 2).x; }
+             var total = 0;
+             for (var i = 0; i < 10; i++) total = total + twice(i);",
+        )
+        .unwrap();
+    PoolStats::reset();
+    assert_eq!(interpreter.run_for(Steps(u64::MAX)), RunState::Finished);
+
+    let stats = PoolStats::current();
+    // each call after the first runs in an environment an earlier one finished with
+    assert!(stats.environments_reused >= 9);
+    assert!(stats.environments_recycled >= stats.environments_reused);
+    assert!(stats.field_maps_reused >= 9);
+    assert!(stats.to_string().starts_with("Pool stats:\n"));
+}
+
+#[test]
+fn perf_hints_report_megamorphic_reads() {
+    use rlox::{prelude::*, PerfHints};
+
+    let program = std::fs::read_to_string("test_files/perf_hints.lox").unwrap();
+    let run = |interpreter: &mut Interpreter| {
+        interpreter.load(&program).unwrap();
+        assert_eq!(interpreter.run_for(Steps(u64::MAX)), RunState::Finished);
+    };
+    let mut interpreter = Interpreter::new(InterpreterOptions::default());
+
+    run(&mut interpreter);
+    assert_eq!(PerfHints::stop(), None);
+
+    PerfHints::start();
+    run(&mut interpreter);
+    let hints = PerfHints::stop().unwrap();
+    // `Circle().area()` only ever sees one class
+    assert_eq!(hints.megamorphic(), [(16, "area")]);
+    assert!(hints
+        .to_string()
+        .contains("[line 16] .area read 8 times on 4 classes: Circle (2), Hexagon (2)"));
+}
+
+#[test]
+fn time_is_split_between_the_phases() {
+    use rlox::{prelude::*, Phase, PhaseTimes};
+
+    let program = std::fs::read_to_string("test_files/inline_frames.lox").unwrap();
+    let run = |interpreter: &mut Interpreter| {
+        interpreter.load(&program).unwrap();
+        assert_eq!(interpreter.run_for(Steps(u64::MAX)), RunState::Finished);
+    };
+    let mut interpreter = Interpreter::new(InterpreterOptions::default());
+
+    run(&mut interpreter);
+    assert_eq!(PhaseTimes::stop(), None);
+
+    PhaseTimes::start();
+    run(&mut interpreter);
+    let times = PhaseTimes::stop().unwrap();
+    assert!(times.get(Phase::Interpreting) > std::time::Duration::ZERO);
+    assert_eq!(
+        times.total(),
+        Phase::ALL.iter().map(|phase| times.get(*phase)).sum()
+    );
+    assert!(times.to_string().starts_with("Time:"));
+}
+
+#[test]
+fn format() {
+    assert_prints(
+        "format.lox",
+        &[
+            "item    | qty|   price",
+            "tea     |   3|    2.50",
+            "biscuits|  12|    1.50",
+            "jam     |   1|    4.00",
+            "[  mid  ] [-0042] [tru] {nil}",
+            "true and Item",
+            "2.68 12345678901234567.2 [**mid**] [0007]",
+            "The format string has more placeholders than values.",
+            "'{:?}' isn't a valid placeholder.",
+            "'{:.70000}' isn't a valid placeholder.",
+            "'{:99999999999}' isn't a valid placeholder.",
+            "'{:05}' pads with zeros, which only numbers can be; give a fill character instead, as in '{:0>5}'.",
+        ]
+        .map(String::from),
+    )
+}
+
+#[test]
+fn numerals() {
+    assert_prints(
+        "numerals.lox",
+        &[
+            "43",
+            "2.5",
+            "255",
+            "-10",
+            "nil",
+            "ff",
+            "-1010",
+            "zz",
+            "255",
+            "255",
+            "35",
+            "nil",
+            "true",
+            "toStringBase() expects a base from 2 to 36.",
+            "toStringBase() expects an integer and a base.",
+        ]
+        .map(String::from),
+    )
+}
+
+#[test]
+fn deep_equals() {
+    assert_prints(
+        "deep_equals.lox",
+        &[
+            "false", "true", "false", "false", "false", "true", "false", "true", "true",
+        ]
+        .map(String::from),
+    )
+}
+
+#[test]
+fn scripts_read_their_arguments_from_argv() {
+    use rlox::prelude::*;
+
+    let logger = Box::new(MockLogger::new());
+    let logs = logger.logs.clone();
+    let mut interpreter = Interpreter::new(InterpreterOptions {
+        logger: Some(logger),
+        args: ["input.txt", "--verbose"].map(String::from).to_vec(),
+        ..Default::default()
+    });
+
+    interpreter
+        .load("print ARGV.length(); for (var arg in ARGV) print arg;")
+        .unwrap();
+    assert_eq!(interpreter.run_for(Steps(u64::MAX)), RunState::Finished);
+    assert_eq!(*logs.borrow(), ["2", "input.txt", "--verbose"]);
+}
+
+#[test]
+fn deterministic_runs_print_the_same_bytes() {
+    use std::process::Command;
+
+    let run = || {
+        Command::new(env!("CARGO_BIN_EXE_rlox"))
+            .args(["--deterministic", "--perf-hints"])
+            .arg(format!("{TEST_FILE_DIR}/deterministic_output.lox"))
+            .output()
+            .expect("rlox to run")
+    };
+    let first = run();
+    let stderr = String::from_utf8_lossy(&first.stderr);
+    assert!(stderr.contains("Variable 'e' is never read."));
+    assert!(stderr.contains("Perf hints: megamorphic"));
+
+    // hash maps are seeded differently in every process, so a few runs catch any order they leak
+    for _ in 0..4 {
+        let again = run();
+        assert_eq!(again.stdout, first.stdout);
+        assert_eq!(again.stderr, first.stderr);
+    }
+}
+
+#[test]
+fn inspections_parse_with_the_parser_options() {
+    use std::process::Command;
+
+    let inspect = |flags: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rlox"))
+            .args(flags)
+            .arg(format!("{TEST_FILE_DIR}/optional_semicolons.lox"))
+            .output()
+            .expect("rlox to run")
+    };
+    for inspection in ["--ast", "--explain-resolution", "--html"] {
+        assert_eq!(
+            inspect(&[inspection]).status.code(),
+            Some(65),
+            "{inspection}"
+        );
+        let output = inspect(&["--optional-semicolons", inspection]);
+        assert!(output.status.success(), "{inspection}");
+    }
+    let ast = inspect(&["--optional-semicolons", "--ast"]);
+    assert!(String::from_utf8_lossy(&ast.stdout).contains("greeting"));
+}
@@ -0,0 +1,122 @@
+use std::{cell::RefCell, fmt::Arguments, rc::Rc};
+
+use rlox::{repl, Interpreter, InterpreterOptions, LineSource, Logger};
+
+/// Everything the session shows, prompts and printed values alike, in the order it was shown.
+#[derive(Clone, Default)]
+struct Transcript {
+    text: Rc<RefCell<String>>,
+}
+impl Logger for Transcript {
+    fn print(&mut self, value: Arguments) {
+        self.text.borrow_mut().push_str(&format!("{value}\n"));
+    }
+}
+
+/// Input typed a line at a time, with each prompt shown in the transcript.
+struct Typed<'a> {
+    lines: std::str::Lines<'a>,
+    transcript: Transcript,
+}
+impl LineSource for Typed<'_> {
+    fn read_line(&mut self, prompt: &str) -> Option<String> {
+        self.transcript.text.borrow_mut().push_str(prompt);
+        self.lines.next().map(str::to_string)
+    }
+}
+
+// runs a REPL session on `input`, returning the transcript
+fn session(input: &str) -> String {
+    let mut transcript = Transcript::default();
+    let mut interpreter = Interpreter::new(InterpreterOptions {
+        logger: Some(Box::new(transcript.clone())),
+        ..Default::default()
+    });
+    let typed = Typed {
+        lines: input.lines(),
+        transcript: transcript.clone(),
+    };
+    repl(&mut interpreter, typed, &mut transcript);
+
+    let text = transcript.text.borrow().clone();
+    text
+}
+
+#[test]
+fn state_persists_between_inputs() {
+    assert_eq!(
+        session("var a = 1;\nfun double(n) { return n * 2; }\nprint double(a);\n"),
+        "> > > 2\n> "
+    );
+}
+
+#[test]
+fn unclosed_braces_continue_on_the_next_line() {
+    assert_eq!(
+        session("fun greet(name) {\n  if (name) {\n    print name;\n  }\n}\ngreet(\"hi\");\n"),
+        "> ... ... ... ... > hi\n> "
+    );
+}
+
+#[test]
+fn errors_end_the_input_not_the_session() {
+    assert_eq!(
+        session("var a = 1;\nprint missing;\nprint a +;\nprint a;\n"),
+        "> > > > 1\n> "
+    );
+}
+
+#[test]
+fn exit_ends_the_session() {
+    assert_eq!(session("print 1;\nexit\nprint 2;\n"), "> 1\n> ");
+}
+
+#[test]
+fn commands_write_to_the_output() {
+    assert_eq!(
+        session(":parse 1;\n:resolve\n"),
+        "> Tokens:\n    Number 1 Int(1)\n    Semicolon ; None\n    Eof  None\n\
+         Syntax tree:\n    (; 1)\n\
+         > Usage: :resolve <code>\n> "
+    );
+}
+
+#[test]
+fn sessions_carry_on_from_the_given_interpreter() {
+    let mut transcript = Transcript::default();
+    let mut interpreter = Interpreter::new(InterpreterOptions {
+        logger: Some(Box::new(transcript.clone())),
+        ..Default::default()
+    });
+    interpreter.load("var greeting = \"hi\";").unwrap();
+    interpreter.run_for(rlox::Steps(u64::MAX));
+
+    let typed = Typed {
+        lines: "print greeting;".lines(),
+        transcript: transcript.clone(),
+    };
+    repl(&mut interpreter, typed, &mut transcript);
+    assert_eq!(*transcript.text.borrow(), "> hi\n> ");
+}
+
+#[test]
+fn interrupts_end_the_input_not_the_session() {
+    let mut transcript = Transcript::default();
+    let mut interpreter = Interpreter::new(InterpreterOptions {
+        logger: Some(Box::new(transcript.clone())),
+        ..Default::default()
+    });
+
+    let handle = interpreter.interrupt_handle();
+    std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        handle.interrupt();
+    });
+
+    let typed = Typed {
+        lines: "while (true) {}\nprint 1;".lines(),
+        transcript: transcript.clone(),
+    };
+    repl(&mut interpreter, typed, &mut transcript);
+    assert_eq!(*transcript.text.borrow(), "> Interrupted.\n> 1\n> ");
+}